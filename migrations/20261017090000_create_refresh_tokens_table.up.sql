@@ -0,0 +1,14 @@
+CREATE TABLE IF NOT EXISTS refresh_token_families(
+    family_id TEXT NOT NULL PRIMARY KEY,
+    revoked BOOLEAN NOT NULL DEFAULT FALSE,
+    expires_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS refresh_tokens(
+    token_id TEXT NOT NULL PRIMARY KEY,
+    family_id TEXT NOT NULL REFERENCES refresh_token_families(family_id) ON DELETE CASCADE,
+    consumed BOOLEAN NOT NULL DEFAULT FALSE
+);
+
+CREATE INDEX IF NOT EXISTS refresh_tokens_family_id_idx ON refresh_tokens(family_id);
+CREATE INDEX IF NOT EXISTS refresh_token_families_expires_at_idx ON refresh_token_families(expires_at);
@@ -0,0 +1,5 @@
+CREATE TABLE IF NOT EXISTS service_credentials(
+    service_id TEXT NOT NULL PRIMARY KEY,
+    secret_hash TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
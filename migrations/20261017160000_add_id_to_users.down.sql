@@ -0,0 +1,15 @@
+ALTER TABLE totp_secrets DROP CONSTRAINT IF EXISTS totp_secrets_email_fkey;
+ALTER TABLE recovery_codes DROP CONSTRAINT IF EXISTS recovery_codes_email_fkey;
+ALTER TABLE passkey_credentials DROP CONSTRAINT IF EXISTS passkey_credentials_email_fkey;
+
+ALTER TABLE users DROP CONSTRAINT IF EXISTS users_pkey;
+ALTER TABLE users DROP CONSTRAINT IF EXISTS users_email_key;
+ALTER TABLE users ADD CONSTRAINT users_pkey PRIMARY KEY (email);
+ALTER TABLE users DROP COLUMN IF EXISTS id;
+
+ALTER TABLE totp_secrets ADD CONSTRAINT totp_secrets_email_fkey
+    FOREIGN KEY (email) REFERENCES users(email) ON DELETE CASCADE;
+ALTER TABLE recovery_codes ADD CONSTRAINT recovery_codes_email_fkey
+    FOREIGN KEY (email) REFERENCES users(email) ON DELETE CASCADE;
+ALTER TABLE passkey_credentials ADD CONSTRAINT passkey_credentials_email_fkey
+    FOREIGN KEY (email) REFERENCES users(email) ON DELETE CASCADE;
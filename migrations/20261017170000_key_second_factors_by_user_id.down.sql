@@ -0,0 +1,30 @@
+ALTER TABLE totp_secrets ADD COLUMN IF NOT EXISTS email TEXT;
+ALTER TABLE recovery_codes ADD COLUMN IF NOT EXISTS email TEXT;
+ALTER TABLE passkey_credentials ADD COLUMN IF NOT EXISTS email TEXT;
+
+UPDATE totp_secrets SET email = users.email FROM users WHERE users.id = totp_secrets.user_id;
+UPDATE recovery_codes SET email = users.email FROM users WHERE users.id = recovery_codes.user_id;
+UPDATE passkey_credentials SET email = users.email FROM users WHERE users.id = passkey_credentials.user_id;
+
+ALTER TABLE totp_secrets ALTER COLUMN email SET NOT NULL;
+ALTER TABLE recovery_codes ALTER COLUMN email SET NOT NULL;
+ALTER TABLE passkey_credentials ALTER COLUMN email SET NOT NULL;
+
+ALTER TABLE totp_secrets DROP CONSTRAINT IF EXISTS totp_secrets_pkey;
+ALTER TABLE totp_secrets ADD CONSTRAINT totp_secrets_pkey PRIMARY KEY (email);
+
+DROP INDEX IF EXISTS recovery_codes_user_id_idx;
+DROP INDEX IF EXISTS passkey_credentials_user_id_idx;
+CREATE INDEX IF NOT EXISTS recovery_codes_email_idx ON recovery_codes(email);
+CREATE INDEX IF NOT EXISTS passkey_credentials_email_idx ON passkey_credentials(email);
+
+ALTER TABLE totp_secrets ADD CONSTRAINT totp_secrets_email_fkey
+    FOREIGN KEY (email) REFERENCES users(email) ON DELETE CASCADE ON UPDATE CASCADE;
+ALTER TABLE recovery_codes ADD CONSTRAINT recovery_codes_email_fkey
+    FOREIGN KEY (email) REFERENCES users(email) ON DELETE CASCADE ON UPDATE CASCADE;
+ALTER TABLE passkey_credentials ADD CONSTRAINT passkey_credentials_email_fkey
+    FOREIGN KEY (email) REFERENCES users(email) ON DELETE CASCADE ON UPDATE CASCADE;
+
+ALTER TABLE totp_secrets DROP COLUMN IF EXISTS user_id;
+ALTER TABLE recovery_codes DROP COLUMN IF EXISTS user_id;
+ALTER TABLE passkey_credentials DROP COLUMN IF EXISTS user_id;
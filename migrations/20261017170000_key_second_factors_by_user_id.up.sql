@@ -0,0 +1,25 @@
+-- The second factors belong to the account, not to its address: they follow the user id and
+-- the email columns go, a change of email no longer has anything to carry over
+ALTER TABLE totp_secrets ADD COLUMN IF NOT EXISTS user_id UUID REFERENCES users(id) ON DELETE CASCADE;
+ALTER TABLE recovery_codes ADD COLUMN IF NOT EXISTS user_id UUID REFERENCES users(id) ON DELETE CASCADE;
+ALTER TABLE passkey_credentials ADD COLUMN IF NOT EXISTS user_id UUID REFERENCES users(id) ON DELETE CASCADE;
+
+UPDATE totp_secrets SET user_id = users.id FROM users WHERE users.email = totp_secrets.email;
+UPDATE recovery_codes SET user_id = users.id FROM users WHERE users.email = recovery_codes.email;
+UPDATE passkey_credentials SET user_id = users.id FROM users WHERE users.email = passkey_credentials.email;
+
+ALTER TABLE totp_secrets ALTER COLUMN user_id SET NOT NULL;
+ALTER TABLE recovery_codes ALTER COLUMN user_id SET NOT NULL;
+ALTER TABLE passkey_credentials ALTER COLUMN user_id SET NOT NULL;
+
+ALTER TABLE totp_secrets DROP CONSTRAINT IF EXISTS totp_secrets_pkey;
+ALTER TABLE totp_secrets ADD CONSTRAINT totp_secrets_pkey PRIMARY KEY (user_id);
+
+DROP INDEX IF EXISTS recovery_codes_email_idx;
+DROP INDEX IF EXISTS passkey_credentials_email_idx;
+CREATE INDEX IF NOT EXISTS recovery_codes_user_id_idx ON recovery_codes(user_id);
+CREATE INDEX IF NOT EXISTS passkey_credentials_user_id_idx ON passkey_credentials(user_id);
+
+ALTER TABLE totp_secrets DROP COLUMN IF EXISTS email;
+ALTER TABLE recovery_codes DROP COLUMN IF EXISTS email;
+ALTER TABLE passkey_credentials DROP COLUMN IF EXISTS email;
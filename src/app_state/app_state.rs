@@ -1,33 +1,73 @@
 use crate::domain::client::EmailClient;
-use crate::domain::data_stores::{BannedTokenStore, TwoFACodeStore, UserStore};
+use crate::domain::data_stores::{
+    BannedTokenStore, LoginLockoutStore, PasskeyChallengeStore, PasskeyStore, RateLimitStore, RecoveryCodeStore,
+    RefreshTokenStore, ServiceCredentialStore, SessionEpochStore, TotpStore, TwoFACodeStore, UserStore,
+};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
 pub type UserStoreType = Arc<RwLock<dyn UserStore>>;
 pub type BannedTokenStoreType = Arc<RwLock<dyn BannedTokenStore>>;
+pub type RefreshTokenStoreType = Arc<RwLock<dyn RefreshTokenStore>>;
+pub type SessionEpochStoreType = Arc<RwLock<dyn SessionEpochStore>>;
 pub type TwoFACodeStoreType = Arc<RwLock<dyn TwoFACodeStore>>;
 pub type EmailClientType = Arc<RwLock<dyn EmailClient>>;
+pub type ServiceCredentialStoreType = Arc<RwLock<dyn ServiceCredentialStore>>;
+pub type TotpStoreType = Arc<RwLock<dyn TotpStore>>;
+pub type RecoveryCodeStoreType = Arc<RwLock<dyn RecoveryCodeStore>>;
+pub type PasskeyStoreType = Arc<RwLock<dyn PasskeyStore>>;
+pub type PasskeyChallengeStoreType = Arc<RwLock<dyn PasskeyChallengeStore>>;
+pub type LoginLockoutStoreType = Arc<RwLock<dyn LoginLockoutStore>>;
+pub type RateLimitStoreType = Arc<RwLock<dyn RateLimitStore>>;
 
 #[derive(Clone)]
 pub struct AppState {
     pub user_store: UserStoreType,
     pub banned_token_store: BannedTokenStoreType,
+    pub refresh_token_store: RefreshTokenStoreType,
+    pub session_epoch_store: SessionEpochStoreType,
     pub two_fa_code_store: TwoFACodeStoreType,
     pub email_client: EmailClientType,
+    pub service_credential_store: ServiceCredentialStoreType,
+    pub totp_store: TotpStoreType,
+    pub recovery_code_store: RecoveryCodeStoreType,
+    pub passkey_store: PasskeyStoreType,
+    pub passkey_challenge_store: PasskeyChallengeStoreType,
+    pub login_lockout_store: LoginLockoutStoreType,
+    pub rate_limit_store: RateLimitStoreType,
 }
 
 impl AppState {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         user_store: UserStoreType,
         banned_token_store: BannedTokenStoreType,
+        refresh_token_store: RefreshTokenStoreType,
+        session_epoch_store: SessionEpochStoreType,
         two_fa_code_store: TwoFACodeStoreType,
         email_client: EmailClientType,
+        service_credential_store: ServiceCredentialStoreType,
+        totp_store: TotpStoreType,
+        recovery_code_store: RecoveryCodeStoreType,
+        passkey_store: PasskeyStoreType,
+        passkey_challenge_store: PasskeyChallengeStoreType,
+        login_lockout_store: LoginLockoutStoreType,
+        rate_limit_store: RateLimitStoreType,
     ) -> Self {
         Self {
             user_store,
             banned_token_store,
+            refresh_token_store,
+            session_epoch_store,
             two_fa_code_store,
             email_client,
+            service_credential_store,
+            totp_store,
+            recovery_code_store,
+            passkey_store,
+            passkey_challenge_store,
+            login_lockout_store,
+            rate_limit_store,
         }
     }
 }
@@ -26,4 +26,7 @@ pub trait EmailClient: Send + Sync {
         subject: &str,
         content: &str,
     ) -> Result<(), EmailClientError>;
+
+    // Whether the provider can be reached, reported by the gRPC health service
+    async fn check_health(&self) -> Result<(), EmailClientError>;
 }
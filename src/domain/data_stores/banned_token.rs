@@ -1,3 +1,7 @@
+use serde::{Deserialize, Serialize};
+use std::pin::Pin;
+use tokio_stream::Stream;
+
 #[cfg(test)]
 use mockall::automock;
 
@@ -7,9 +11,22 @@ pub enum BannedTokenStoreError {
     UnexpectedError,
 }
 
+// What downstream token caches are told about: a single banned token, or every token of a user
+// issued before the given session epoch
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Revocation {
+    Token { token: String },
+    UserSessions { subject: String, session_epoch: i64 },
+}
+
+// Ends when the subscriber falls too far behind, it has to resubscribe and drop what it cached
+pub type RevocationStream = Pin<Box<dyn Stream<Item = Revocation> + Send>>;
+
 #[cfg_attr(test, automock)]
 #[async_trait::async_trait]
 pub trait BannedTokenStore: Send + Sync {
+    // Also publishes the token to the revocation subscribers
     async fn store_token(
         &mut self,
         token: &str,
@@ -18,4 +35,9 @@ pub trait BannedTokenStore: Send + Sync {
         &self,
         token: &str,
     ) -> Result<bool, BannedTokenStoreError>;
+    async fn publish_revocation(
+        &self,
+        revocation: Revocation,
+    ) -> Result<(), BannedTokenStoreError>;
+    async fn watch_revocations(&self) -> Result<RevocationStream, BannedTokenStoreError>;
 }
@@ -0,0 +1,54 @@
+use crate::domain::LockoutSubject;
+use color_eyre::Report;
+use thiserror::Error;
+
+#[cfg(test)]
+use mockall::automock;
+
+#[derive(Debug, Error)]
+pub enum LoginLockoutStoreError {
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for LoginLockoutStoreError {
+    fn eq(
+        &self,
+        other: &Self,
+    ) -> bool {
+        matches!((self, other), (Self::UnexpectedError(_), Self::UnexpectedError(_)))
+    }
+}
+
+// Failed logins are counted for `LOCKOUT_FAILURE_WINDOW_SECONDS`. A lock expires on its own, the
+// lockouts are remembered for `LOCKOUT_RESET_SECONDS` so that the next one lasts longer.
+#[cfg_attr(test, automock)]
+#[async_trait::async_trait]
+pub trait LoginLockoutStore: Send + Sync {
+    // Seconds left before the subject can try again, `None` when it is not locked
+    async fn locked_for(
+        &self,
+        subject: &LockoutSubject,
+    ) -> Result<Option<u64>, LoginLockoutStoreError>;
+    // Counts a failed login and returns the failures of the current window
+    async fn record_failure(
+        &mut self,
+        subject: &LockoutSubject,
+    ) -> Result<u32, LoginLockoutStoreError>;
+    // Lockouts of the subject that are still remembered
+    async fn lockout_streak(
+        &self,
+        subject: &LockoutSubject,
+    ) -> Result<u32, LoginLockoutStoreError>;
+    // Locks the subject, clears its failures and extends the streak
+    async fn lock(
+        &mut self,
+        subject: &LockoutSubject,
+        seconds: u64,
+    ) -> Result<(), LoginLockoutStoreError>;
+    // Forgets the failures and the streak after a successful login, a running lock stays
+    async fn reset(
+        &mut self,
+        subject: &LockoutSubject,
+    ) -> Result<(), LoginLockoutStoreError>;
+}
@@ -1,7 +1,25 @@
 mod banned_token;
+mod login_lockout;
+mod passkey;
+mod passkey_challenge;
+mod rate_limit;
+mod recovery_code;
+mod refresh_token;
+mod service_credential;
+mod session_epoch;
+mod totp;
 mod two_fa_code;
 mod user;
 
 pub use banned_token::*;
+pub use login_lockout::*;
+pub use passkey::*;
+pub use passkey_challenge::*;
+pub use rate_limit::*;
+pub use recovery_code::*;
+pub use refresh_token::*;
+pub use service_credential::*;
+pub use session_epoch::*;
+pub use totp::*;
 pub use two_fa_code::*;
 pub use user::*;
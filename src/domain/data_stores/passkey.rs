@@ -0,0 +1,55 @@
+use crate::domain::{PasskeyCredential, UserId};
+use color_eyre::Report;
+use thiserror::Error;
+
+#[cfg(test)]
+use mockall::automock;
+
+#[derive(Debug, Error)]
+pub enum PasskeyStoreError {
+    #[error("Passkey credential not found")]
+    CredentialNotFound,
+
+    #[error("Passkey credential already registered")]
+    CredentialAlreadyExists,
+
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for PasskeyStoreError {
+    fn eq(
+        &self,
+        other: &Self,
+    ) -> bool {
+        matches!(
+            (self, other),
+            (Self::CredentialNotFound, Self::CredentialNotFound)
+                | (Self::CredentialAlreadyExists, Self::CredentialAlreadyExists)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+// The WebAuthn credentials of the users, looked up by credential id when an assertion comes in
+#[cfg_attr(test, automock)]
+#[async_trait::async_trait]
+pub trait PasskeyStore: Send + Sync {
+    async fn add_credential(
+        &mut self,
+        credential: PasskeyCredential,
+    ) -> Result<(), PasskeyStoreError>;
+    async fn get_credential(
+        &self,
+        credential_id: &str,
+    ) -> Result<PasskeyCredential, PasskeyStoreError>;
+    async fn get_credentials(
+        &self,
+        user_id: &UserId,
+    ) -> Result<Vec<PasskeyCredential>, PasskeyStoreError>;
+    async fn update_sign_count(
+        &mut self,
+        credential_id: &str,
+        sign_count: u32,
+    ) -> Result<(), PasskeyStoreError>;
+}
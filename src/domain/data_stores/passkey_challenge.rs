@@ -0,0 +1,42 @@
+use crate::domain::PasskeyChallenge;
+use color_eyre::Report;
+use thiserror::Error;
+
+#[cfg(test)]
+use mockall::automock;
+
+#[derive(Debug, Error)]
+pub enum PasskeyChallengeStoreError {
+    #[error("Passkey challenge not found")]
+    ChallengeNotFound,
+
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for PasskeyChallengeStoreError {
+    fn eq(
+        &self,
+        other: &Self,
+    ) -> bool {
+        matches!(
+            (self, other),
+            (Self::ChallengeNotFound, Self::ChallengeNotFound) | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+// Pending WebAuthn challenges by id. Taking a challenge removes it, a ceremony can't be finished twice.
+#[cfg_attr(test, automock)]
+#[async_trait::async_trait]
+pub trait PasskeyChallengeStore: Send + Sync {
+    async fn add_challenge(
+        &mut self,
+        challenge_id: &str,
+        challenge: PasskeyChallenge,
+    ) -> Result<(), PasskeyChallengeStoreError>;
+    async fn take_challenge(
+        &mut self,
+        challenge_id: &str,
+    ) -> Result<PasskeyChallenge, PasskeyChallengeStoreError>;
+}
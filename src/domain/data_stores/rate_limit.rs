@@ -0,0 +1,34 @@
+use crate::domain::RateLimitDecision;
+use color_eyre::Report;
+use thiserror::Error;
+
+#[cfg(test)]
+use mockall::automock;
+
+#[derive(Debug, Error)]
+pub enum RateLimitStoreError {
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for RateLimitStoreError {
+    fn eq(
+        &self,
+        other: &Self,
+    ) -> bool {
+        matches!((self, other), (Self::UnexpectedError(_), Self::UnexpectedError(_)))
+    }
+}
+
+// Token buckets, created full on their first request. Every request goes through this store, so
+// it only needs the read lock and implementations take care of their own synchronization.
+#[cfg_attr(test, automock)]
+#[async_trait::async_trait]
+pub trait RateLimitStore: Send + Sync {
+    async fn take_token(
+        &self,
+        key: &str,
+        capacity: u32,
+        refill_per_minute: u32,
+    ) -> Result<RateLimitDecision, RateLimitStoreError>;
+}
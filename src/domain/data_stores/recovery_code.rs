@@ -0,0 +1,49 @@
+use crate::domain::{RecoveryCode, UserId};
+use color_eyre::Report;
+use thiserror::Error;
+
+#[cfg(test)]
+use mockall::automock;
+
+#[derive(Debug, Error)]
+pub enum RecoveryCodeStoreError {
+    #[error("Invalid recovery code")]
+    InvalidCode,
+
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for RecoveryCodeStoreError {
+    fn eq(
+        &self,
+        other: &Self,
+    ) -> bool {
+        matches!(
+            (self, other),
+            (Self::InvalidCode, Self::InvalidCode) | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+// The recovery codes of a user, a code is consumed by a successful `use_code`.
+// An unknown, already used or someone else's code is `InvalidCode`.
+#[cfg_attr(test, automock)]
+#[async_trait::async_trait]
+pub trait RecoveryCodeStore: Send + Sync {
+    // Drops the previous set, the old codes stop working
+    async fn replace_codes(
+        &mut self,
+        user_id: &UserId,
+        codes: Vec<RecoveryCode>,
+    ) -> Result<(), RecoveryCodeStoreError>;
+    async fn use_code(
+        &mut self,
+        user_id: &UserId,
+        code: &RecoveryCode,
+    ) -> Result<(), RecoveryCodeStoreError>;
+    async fn remaining_codes(
+        &self,
+        user_id: &UserId,
+    ) -> Result<usize, RecoveryCodeStoreError>;
+}
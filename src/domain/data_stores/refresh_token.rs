@@ -0,0 +1,60 @@
+use color_eyre::Report;
+use thiserror::Error;
+
+#[cfg(test)]
+use mockall::automock;
+
+#[derive(Debug, Error)]
+pub enum RefreshTokenStoreError {
+    #[error("Refresh token not found")]
+    TokenNotFound,
+
+    #[error("Refresh token was already used, its family has been revoked")]
+    TokenReused,
+
+    #[error("Refresh token family revoked")]
+    FamilyRevoked,
+
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for RefreshTokenStoreError {
+    fn eq(
+        &self,
+        other: &Self,
+    ) -> bool {
+        matches!(
+            (self, other),
+            (Self::TokenNotFound, Self::TokenNotFound)
+                | (Self::TokenReused, Self::TokenReused)
+                | (Self::FamilyRevoked, Self::FamilyRevoked)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+// Every login starts a refresh token family. Each refresh consumes the presented token and adds
+// the next one to the same family, presenting an already consumed token revokes the whole family.
+#[cfg_attr(test, automock)]
+#[async_trait::async_trait]
+pub trait RefreshTokenStore: Send + Sync {
+    async fn add_token(
+        &mut self,
+        family_id: &str,
+        token_id: &str,
+    ) -> Result<(), RefreshTokenStoreError>;
+    async fn consume_token(
+        &mut self,
+        family_id: &str,
+        token_id: &str,
+    ) -> Result<(), RefreshTokenStoreError>;
+    async fn revoke_family(
+        &mut self,
+        family_id: &str,
+    ) -> Result<(), RefreshTokenStoreError>;
+    async fn is_family_revoked(
+        &self,
+        family_id: &str,
+    ) -> Result<bool, RefreshTokenStoreError>;
+}
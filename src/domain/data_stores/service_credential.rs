@@ -0,0 +1,49 @@
+use color_eyre::Report;
+use secrecy::SecretBox;
+use thiserror::Error;
+
+#[cfg(test)]
+use mockall::automock;
+
+#[derive(Debug, Error)]
+pub enum ServiceCredentialStoreError {
+    #[error("Service already registered")]
+    ServiceAlreadyExists,
+
+    #[error("Invalid service credentials")]
+    InvalidCredentials,
+
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for ServiceCredentialStoreError {
+    fn eq(
+        &self,
+        other: &Self,
+    ) -> bool {
+        matches!(
+            (self, other),
+            (Self::ServiceAlreadyExists, Self::ServiceAlreadyExists)
+                | (Self::InvalidCredentials, Self::InvalidCredentials)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+// Internal services authenticate with a client-credentials pair: a service id and a secret that
+// is only stored hashed. An unknown service and a wrong secret are both `InvalidCredentials`.
+#[cfg_attr(test, automock)]
+#[async_trait::async_trait]
+pub trait ServiceCredentialStore: Send + Sync {
+    async fn add_service(
+        &mut self,
+        service_id: &str,
+        secret: SecretBox<String>,
+    ) -> Result<(), ServiceCredentialStoreError>;
+    async fn validate_credentials(
+        &self,
+        service_id: &str,
+        secret: &SecretBox<String>,
+    ) -> Result<(), ServiceCredentialStoreError>;
+}
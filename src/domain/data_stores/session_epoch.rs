@@ -0,0 +1,42 @@
+use crate::domain::UserId;
+use color_eyre::Report;
+use thiserror::Error;
+
+#[cfg(test)]
+use mockall::automock;
+
+#[derive(Debug, Error)]
+pub enum SessionEpochStoreError {
+    #[error("User not found")]
+    UserNotFound,
+
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for SessionEpochStoreError {
+    fn eq(
+        &self,
+        other: &Self,
+    ) -> bool {
+        matches!(
+            (self, other),
+            (Self::UserNotFound, Self::UserNotFound) | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+// Every token carries the session epoch of its user at issue time. Incrementing the epoch
+// invalidates all the tokens issued before, which logs the user out everywhere.
+#[cfg_attr(test, automock)]
+#[async_trait::async_trait]
+pub trait SessionEpochStore: Send + Sync {
+    async fn get_epoch(
+        &self,
+        user_id: &UserId,
+    ) -> Result<i64, SessionEpochStoreError>;
+    async fn increment_epoch(
+        &mut self,
+        user_id: &UserId,
+    ) -> Result<i64, SessionEpochStoreError>;
+}
@@ -0,0 +1,73 @@
+use crate::domain::UserId;
+use color_eyre::Report;
+use thiserror::Error;
+
+#[cfg(test)]
+use mockall::automock;
+
+#[derive(Debug, Error)]
+pub enum TotpStoreError {
+    #[error("TOTP is not enrolled")]
+    NotEnrolled,
+
+    #[error("TOTP is already enabled")]
+    AlreadyEnabled,
+
+    #[error("TOTP time step already used")]
+    TimeStepAlreadyUsed,
+
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for TotpStoreError {
+    fn eq(
+        &self,
+        other: &Self,
+    ) -> bool {
+        matches!(
+            (self, other),
+            (Self::NotEnrolled, Self::NotEnrolled)
+                | (Self::AlreadyEnabled, Self::AlreadyEnabled)
+                | (Self::TimeStepAlreadyUsed, Self::TimeStepAlreadyUsed)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+// The secret stays pending until the user proves the authenticator works with a first code.
+// Stores only see the encrypted secret.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TotpEnrollment {
+    pub encrypted_secret: String,
+    pub confirmed: bool,
+}
+
+#[cfg_attr(test, automock)]
+#[async_trait::async_trait]
+pub trait TotpStore: Send + Sync {
+    // Starts over a pending enrollment, an enabled one can't be replaced
+    async fn set_pending_secret(
+        &mut self,
+        user_id: &UserId,
+        encrypted_secret: String,
+    ) -> Result<(), TotpStoreError>;
+    async fn get_enrollment(
+        &self,
+        user_id: &UserId,
+    ) -> Result<TotpEnrollment, TotpStoreError>;
+    async fn confirm(
+        &mut self,
+        user_id: &UserId,
+    ) -> Result<(), TotpStoreError>;
+    // Replay protection: a time step is accepted only if it is later than the last one used
+    async fn use_time_step(
+        &mut self,
+        user_id: &UserId,
+        time_step: i64,
+    ) -> Result<(), TotpStoreError>;
+    async fn remove(
+        &mut self,
+        user_id: &UserId,
+    ) -> Result<(), TotpStoreError>;
+}
@@ -51,4 +51,9 @@ pub trait TwoFACodeStore: Send + Sync {
         &self,
         email: &Email,
     ) -> Result<(LoginAttemptId, TwoFACode), TwoFACodeStoreError>;
+    // Counts a wrong answer to the pending code and returns the failures so far, `add_code` resets it
+    async fn record_failed_attempt(
+        &mut self,
+        email: &Email,
+    ) -> Result<u32, TwoFACodeStoreError>;
 }
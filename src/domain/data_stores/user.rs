@@ -1,4 +1,4 @@
-use crate::domain::{Email, Password, User};
+use crate::domain::{Email, Password, User, UserId};
 use color_eyre::Report;
 #[cfg(test)]
 use mockall::automock;
@@ -42,11 +42,32 @@ pub trait UserStore: Send + Sync {
         &self,
         email: &Email,
     ) -> Result<User, UserStoreError>;
+    async fn get_user_by_id(
+        &self,
+        id: &UserId,
+    ) -> Result<User, UserStoreError>;
     async fn validate_user(
         &self,
         email: &Email,
         password: &Password,
     ) -> Result<(), UserStoreError>;
+    async fn verify_email(
+        &mut self,
+        email: &Email,
+    ) -> Result<(), UserStoreError>;
+    // A new email starts verified, changing it takes a link sent to the new address. The TOTP secret
+    // is encrypted with the address, a secret re-encrypted for the new one is swapped in at the same time.
+    async fn update_email(
+        &mut self,
+        id: &UserId,
+        email: Email,
+        totp_secret: Option<String>,
+    ) -> Result<(), UserStoreError>;
+    async fn update_password(
+        &mut self,
+        email: &Email,
+        password: Password,
+    ) -> Result<(), UserStoreError>;
     async fn delete_account(
         &mut self,
         email: &Email,
@@ -1,5 +1,5 @@
 use crate::domain::{EmailError, PasswordError, UserError};
-use crate::utils::GenerateTokenError;
+use crate::utils::{GenerateTokenError, TokenRejection};
 use color_eyre::Report;
 
 #[derive(Debug, thiserror::Error)]
@@ -25,9 +25,18 @@ pub enum AuthAPIError {
     #[error("Token not valid")]
     TokenNotValid,
 
+    #[error("Forbidden")]
+    Forbidden,
+
+    #[error("Email not verified")]
+    EmailNotVerified,
+
     #[error("Password error")]
     PasswordError(#[from] PasswordError),
 
+    #[error("Password reused")]
+    PasswordReused,
+
     #[error("Email error")]
     EmailError(#[from] EmailError),
 
@@ -40,6 +49,48 @@ pub enum AuthAPIError {
     #[error("TwoFA malformed error")]
     TwoFAMalformedError,
 
+    #[error("Too many 2FA attempts")]
+    TooManyTwoFAAttempts,
+
+    // Seconds before the account can log in again
+    #[error("Account locked")]
+    AccountLocked(u64),
+
+    // Seconds before the source address can log in again
+    #[error("Too many login attempts")]
+    TooManyLoginAttempts(u64),
+
+    // Seconds before the next request is accepted
+    #[error("Rate limited")]
+    RateLimited(u64),
+
     #[error("Login attempt id malformed error")]
     LoginAttemptIdMalformedError,
+
+    #[error("TOTP already enabled")]
+    TotpAlreadyEnabled,
+
+    #[error("TOTP not enrolled")]
+    TotpNotEnrolled,
+
+    #[error("2FA not enabled")]
+    TwoFANotEnabled,
+
+    #[error("Invalid passkey response")]
+    InvalidPasskeyResponse,
+
+    #[error("Passkey already registered")]
+    PasskeyAlreadyRegistered,
+
+    #[error("No passkey registered")]
+    PasskeyNotRegistered,
+}
+
+impl From<TokenRejection> for AuthAPIError {
+    fn from(rejection: TokenRejection) -> Self {
+        match rejection {
+            TokenRejection::UnexpectedError(e) => AuthAPIError::UnexpectedError(e),
+            _ => AuthAPIError::TokenNotValid,
+        }
+    }
 }
@@ -0,0 +1,20 @@
+use crate::domain::Email;
+use secrecy::ExposeSecret;
+use std::net::IpAddr;
+
+// What failed logins are counted against: the targeted account, whoever tries it, and the
+// address they come from, whatever account they try
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum LockoutSubject {
+    Account(Email),
+    Ip(IpAddr),
+}
+
+impl LockoutSubject {
+    pub fn key(&self) -> String {
+        match self {
+            Self::Account(email) => format!("account:{}", email.as_ref().expose_secret()),
+            Self::Ip(ip) => format!("ip:{}", ip),
+        }
+    }
+}
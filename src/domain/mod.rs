@@ -2,14 +2,28 @@ pub mod client;
 pub mod data_stores;
 mod email;
 mod error;
+mod lockout;
 mod login_attempt;
+mod passkey;
 mod password;
+mod password_policy;
+mod rate_limit;
+mod recovery_code;
+mod totp;
 mod two_fa_code;
 mod user;
+mod user_id;
 
 pub use email::*;
 pub use error::*;
+pub use lockout::*;
 pub use login_attempt::*;
+pub use passkey::*;
 pub use password::*;
+pub use password_policy::*;
+pub use rate_limit::*;
+pub use recovery_code::*;
+pub use totp::*;
 pub use two_fa_code::*;
 pub use user::*;
+pub use user_id::*;
@@ -0,0 +1,27 @@
+use crate::domain::UserId;
+
+// A WebAuthn credential registered by a user. The id is base64url encoded as browsers send it,
+// the public key is a SEC1 encoded P-256 point.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PasskeyCredential {
+    pub credential_id: String,
+    pub user_id: UserId,
+    pub user_handle: String,
+    pub public_key: Vec<u8>,
+    pub sign_count: u32,
+    pub name: String,
+}
+
+// What a challenge was issued for. A passkey login starts without knowing the user,
+// a 2FA one only accepts the credentials of the user who passed the password step.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PasskeyCeremony {
+    Registration { user_id: UserId, user_handle: String },
+    Authentication { user_id: Option<UserId> },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PasskeyChallenge {
+    pub challenge: String,
+    pub ceremony: PasskeyCeremony,
+}
@@ -1,13 +1,22 @@
+use crate::domain::PasswordViolation;
 use secrecy::{ExposeSecret, SecretBox};
 use std::hash::{Hash, Hasher};
+use unicode_normalization::UnicodeNormalization;
+
+// Hard bounds of any password, the configurable policy only applies to new ones
+const MIN_LENGTH: usize = 8;
+const MAX_LENGTH: usize = 128;
 
 #[derive(Debug)]
 pub struct Password(SecretBox<String>);
 
 #[derive(Debug, thiserror::Error)]
 pub enum PasswordError {
-    #[error("Invalid email format")]
+    #[error("Invalid password format")]
     InvalidFormat,
+
+    #[error("Password does not meet the policy")]
+    PolicyViolation(Vec<PasswordViolation>),
 }
 
 impl PartialEq for Password {
@@ -45,9 +54,16 @@ impl Password {
         Ok(password_object)
     }
 
+    // New passwords are stored NFKC normalized, so the same text typed on another keyboard or layout hashes the same.
+    // Hashes made before that were of the password as typed, logins try both.
+    pub fn normalized(&self) -> Self {
+        let normalized: String = self.0.expose_secret().nfkc().collect();
+        Password(SecretBox::new(Box::new(normalized)))
+    }
+
     pub fn is_correct_password(&self) -> bool {
-        let pass_size = self.0.expose_secret().len();
-        pass_size >= 8 && pass_size <= 128
+        let pass_size = self.0.expose_secret().chars().count();
+        (MIN_LENGTH..=MAX_LENGTH).contains(&pass_size)
     }
 }
 
@@ -167,6 +183,27 @@ mod tests {
         assert!(password.is_ok());
     }
 
+    #[test]
+    fn test_length_counts_characters() {
+        // 8 characters, 16 bytes
+        let secret = SecretBox::new(Box::new("ñ".repeat(8)));
+        assert!(Password::new(secret).is_ok());
+
+        let secret = SecretBox::new(Box::new("é".repeat(128)));
+        assert!(Password::new(secret).is_ok());
+    }
+
+    #[test]
+    fn test_password_is_nfkc_normalized() {
+        // Fullwidth letters and a decomposed accent
+        let password = Password::new(SecretBox::new(Box::new("ｐａｓｓｗｏｒｄcafe\u{301}".to_string()))).unwrap();
+        let expected = Password::new(SecretBox::new(Box::new("passwordcafé".to_string()))).unwrap();
+
+        assert_ne!(password, expected);
+        assert_eq!(password.normalized(), expected);
+        assert_eq!(password.normalized().as_ref().expose_secret(), "passwordcafé");
+    }
+
     #[test]
     fn test_boundary_conditions() {
         // Test exact boundary conditions
@@ -0,0 +1,268 @@
+use crate::domain::PasswordError;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use unicode_normalization::UnicodeNormalization;
+
+// Most used passwords first, one per line, lowercase
+const COMMON_PASSWORDS: &str = include_str!("common_passwords.txt");
+
+// A rule a new password breaks, all of them are reported at once
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "rule", rename_all = "snake_case")]
+pub enum PasswordViolation {
+    TooShort {
+        #[serde(rename = "minLength")]
+        min_length: usize,
+    },
+    TooLong {
+        #[serde(rename = "maxLength")]
+        max_length: usize,
+    },
+    ContainsEmail,
+    CommonPassword,
+    TooWeak {
+        #[serde(rename = "entropyBits")]
+        entropy_bits: u32,
+        #[serde(rename = "minEntropyBits")]
+        min_entropy_bits: u32,
+    },
+}
+
+impl PasswordViolation {
+    pub fn rule(&self) -> &'static str {
+        match self {
+            Self::TooShort { .. } => "too_short",
+            Self::TooLong { .. } => "too_long",
+            Self::ContainsEmail => "contains_email",
+            Self::CommonPassword => "common_password",
+            Self::TooWeak { .. } => "too_weak",
+        }
+    }
+}
+
+// Rules a password must follow when it is chosen (signup, change, reset), logins only check the hard bounds of
+// `Password` so tightening the policy doesn't lock anyone out
+#[derive(Debug, Clone)]
+pub struct PasswordPolicy {
+    min_length: usize,
+    max_length: usize,
+    reject_email: bool,
+    blocklist: HashSet<&'static str>,
+    min_entropy_bits: u32,
+}
+
+impl PasswordPolicy {
+    // Number of passwords in the bundled list, the largest `blocklist_size` that means anything
+    pub fn bundled_blocklist_size() -> usize {
+        COMMON_PASSWORDS.lines().count()
+    }
+
+    // `blocklist_size` takes the N most used passwords of the bundled list, 0 disables the blocklist
+    pub fn new(
+        min_length: usize,
+        max_length: usize,
+        reject_email: bool,
+        blocklist_size: usize,
+        min_entropy_bits: u32,
+    ) -> Self {
+        Self {
+            min_length,
+            max_length,
+            reject_email,
+            blocklist: COMMON_PASSWORDS.lines().take(blocklist_size).collect(),
+            min_entropy_bits,
+        }
+    }
+
+    pub fn check(
+        &self,
+        password: &str,
+        email: &str,
+    ) -> Result<(), PasswordError> {
+        let password: String = password.nfkc().collect();
+        let lowercase = password.to_lowercase();
+        let length = password.chars().count();
+        let mut violations = Vec::new();
+
+        if length < self.min_length {
+            violations.push(PasswordViolation::TooShort {
+                min_length: self.min_length,
+            });
+        }
+
+        if length > self.max_length {
+            violations.push(PasswordViolation::TooLong {
+                max_length: self.max_length,
+            });
+        }
+
+        if self.reject_email && contains_local_part(&lowercase, email) {
+            violations.push(PasswordViolation::ContainsEmail);
+        }
+
+        if self.blocklist.contains(lowercase.as_str()) {
+            violations.push(PasswordViolation::CommonPassword);
+        }
+
+        let entropy_bits = estimate_entropy_bits(&password) as u32;
+        if entropy_bits < self.min_entropy_bits {
+            violations.push(PasswordViolation::TooWeak {
+                entropy_bits,
+                min_entropy_bits: self.min_entropy_bits,
+            });
+        }
+
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(PasswordError::PolicyViolation(violations))
+        }
+    }
+}
+
+// Local parts shorter than 3 characters would match too many passwords by chance
+fn contains_local_part(
+    lowercase_password: &str,
+    email: &str,
+) -> bool {
+    let local_part: String = email
+        .rsplit_once('@')
+        .map_or(email, |(local, _)| local)
+        .nfkc()
+        .collect();
+    let local_part = local_part.to_lowercase();
+    local_part.chars().count() >= 3 && lowercase_password.contains(&local_part)
+}
+
+// Brute force estimate: the size of the character classes used, to the power of the length. A character repeating
+// the previous one or following it in sequence (aaaa, 1234, abcd) barely adds to the guessing work and counts less.
+pub fn estimate_entropy_bits(password: &str) -> f64 {
+    let mut pool = 0;
+    if password.chars().any(|c| c.is_ascii_lowercase()) {
+        pool += 26;
+    }
+    if password.chars().any(|c| c.is_ascii_uppercase()) {
+        pool += 26;
+    }
+    if password.chars().any(|c| c.is_ascii_digit()) {
+        pool += 10;
+    }
+    if password.chars().any(|c| c.is_ascii_punctuation() || c == ' ') {
+        pool += 33;
+    }
+    if !password.is_ascii() {
+        pool += 100;
+    }
+    if pool == 0 {
+        return 0.0;
+    }
+
+    let mut effective_length = 0.0;
+    let mut previous: Option<char> = None;
+    for c in password.chars() {
+        effective_length += match previous {
+            Some(p) if p == c => 0.25,
+            Some(p) if (c as i64 - p as i64).abs() == 1 => 0.5,
+            _ => 1.0,
+        };
+        previous = Some(c);
+    }
+
+    effective_length * f64::from(pool).log2()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> PasswordPolicy {
+        PasswordPolicy::new(8, 128, true, usize::MAX, 30)
+    }
+
+    fn violations(
+        password: &str,
+        email: &str,
+    ) -> Vec<PasswordViolation> {
+        match policy().check(password, email) {
+            Ok(()) => Vec::new(),
+            Err(PasswordError::PolicyViolation(violations)) => violations,
+            Err(e) => panic!("unexpected error {:?}", e),
+        }
+    }
+
+    #[test]
+    fn test_accepts_a_strong_password() {
+        assert!(policy().check("Tr0ub4dor&3-horse", "jane.doe@example.com").is_ok());
+    }
+
+    #[test]
+    fn test_min_length_counts_characters() {
+        // 7 characters, 14 bytes
+        assert_eq!(
+            violations("ñÿçøæßð", "jane@example.com"),
+            vec![PasswordViolation::TooShort { min_length: 8 }]
+        );
+        assert!(violations("ñÿçøæßðé", "jane@example.com").is_empty());
+    }
+
+    #[test]
+    fn test_max_length() {
+        let policy = PasswordPolicy::new(8, 16, true, 0, 0);
+        assert!(matches!(
+            policy.check(&"x9!Q".repeat(5), "jane@example.com"),
+            Err(PasswordError::PolicyViolation(v)) if v == vec![PasswordViolation::TooLong { max_length: 16 }]
+        ));
+    }
+
+    #[test]
+    fn test_rejects_the_email_local_part() {
+        assert_eq!(
+            violations("xX-JaneDoe-91!", "janedoe@example.com"),
+            vec![PasswordViolation::ContainsEmail]
+        );
+        // Too short to be a meaningful match
+        assert!(violations("xX-Jo-91!qwz", "jo@example.com").is_empty());
+    }
+
+    #[test]
+    fn test_rejects_common_passwords_after_normalization() {
+        let violations = violations("ＰＡＳＳＷＯＲＤ123", "jane@example.com");
+        assert!(violations.contains(&PasswordViolation::CommonPassword));
+    }
+
+    #[test]
+    fn test_blocklist_size_takes_the_most_used() {
+        // "password1" is further down the bundled list
+        let policy = PasswordPolicy::new(8, 128, true, 10, 0);
+        assert!(policy.check("password1", "jane@example.com").is_ok());
+        assert!(policy.check("12345678", "jane@example.com").is_err());
+    }
+
+    #[test]
+    fn test_bundled_blocklist_size() {
+        assert_eq!(PasswordPolicy::bundled_blocklist_size(), 447);
+    }
+
+    #[test]
+    fn test_reports_every_broken_rule() {
+        let rules: Vec<_> = violations("aaaa", "jane@example.com")
+            .iter()
+            .map(|v| v.rule())
+            .collect();
+        assert_eq!(rules, vec!["too_short", "too_weak"]);
+    }
+
+    #[test]
+    fn test_entropy_estimate() {
+        assert_eq!(estimate_entropy_bits(""), 0.0);
+        assert!(estimate_entropy_bits("aaaaaaaa") < estimate_entropy_bits("qmzrvxkt"));
+        assert!(estimate_entropy_bits("abcdefgh") < estimate_entropy_bits("qmzrvxkt"));
+        assert!(estimate_entropy_bits("qmzrvxkt") < estimate_entropy_bits("qM7#rVx!"));
+    }
+
+    #[test]
+    fn test_violation_json() {
+        let json = serde_json::to_value(PasswordViolation::TooShort { min_length: 12 }).unwrap();
+        assert_eq!(json, serde_json::json!({ "rule": "too_short", "minLength": 12 }));
+    }
+}
@@ -0,0 +1,72 @@
+// Outcome of taking a token from a bucket, what the `RateLimit-*` headers are made of
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimitDecision {
+    pub allowed: bool,
+    pub limit: u32,
+    pub remaining: u32,
+    // Seconds until the bucket is full again
+    pub reset_seconds: u64,
+    // Seconds until the next token, 0 when the request was allowed
+    pub retry_after_seconds: u64,
+}
+
+impl RateLimitDecision {
+    // `tokens` is what is left in the bucket after the request
+    pub fn new(
+        allowed: bool,
+        tokens: f64,
+        capacity: u32,
+        refill_per_minute: u32,
+    ) -> Self {
+        let refill_per_second = f64::from(refill_per_minute) / 60.0;
+        let retry_after_seconds = if allowed {
+            0
+        } else {
+            ((1.0 - tokens) / refill_per_second).ceil().max(1.0) as u64
+        };
+
+        Self {
+            allowed,
+            limit: capacity,
+            remaining: tokens.floor().max(0.0) as u32,
+            reset_seconds: ((f64::from(capacity) - tokens) / refill_per_second).ceil().max(0.0) as u64,
+            retry_after_seconds,
+        }
+    }
+}
+
+// Tokens of a bucket `elapsed_seconds` after it held `tokens`
+pub fn refill_tokens(
+    tokens: f64,
+    elapsed_seconds: f64,
+    capacity: u32,
+    refill_per_minute: u32,
+) -> f64 {
+    (tokens + elapsed_seconds.max(0.0) * f64::from(refill_per_minute) / 60.0).min(f64::from(capacity))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_refill_is_capped_at_the_capacity() {
+        assert_eq!(refill_tokens(0.0, 30.0, 10, 10), 5.0);
+        assert_eq!(refill_tokens(4.0, 600.0, 10, 10), 10.0);
+        assert_eq!(refill_tokens(4.0, -1.0, 10, 10), 4.0);
+    }
+
+    #[test]
+    fn test_decision_of_an_empty_bucket() {
+        let decision = RateLimitDecision::new(false, 0.5, 10, 60);
+
+        assert_eq!(decision.remaining, 0);
+        assert_eq!(decision.retry_after_seconds, 1);
+        assert_eq!(decision.reset_seconds, 10);
+
+        let decision = RateLimitDecision::new(true, 9.0, 10, 6);
+        assert_eq!(decision.remaining, 9);
+        assert_eq!(decision.retry_after_seconds, 0);
+        assert_eq!(decision.reset_seconds, 10);
+    }
+}
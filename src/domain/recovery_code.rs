@@ -0,0 +1,111 @@
+use color_eyre::eyre::{Result, eyre};
+use rand::Rng;
+use secrecy::{ExposeSecret, SecretBox};
+use std::fmt;
+
+pub const RECOVERY_CODE_COUNT: usize = 10;
+
+const CODE_LENGTH: usize = 10;
+// No 0/o, 1/l/i: the codes are often written down and typed back by hand
+const ALPHABET: &[u8] = b"abcdefghjkmnpqrstuvwxyz23456789";
+
+// Single-use fallback factor for 2FA accounts, shown once as `xxxxx-xxxxx` and only stored hashed.
+// Kept normalized: lowercase, without the dash.
+#[derive(Debug)]
+pub struct RecoveryCode(SecretBox<String>);
+
+impl RecoveryCode {
+    // Accepts what users type back: any case, with or without the dash and spaces
+    pub fn parse(code: &str) -> Result<Self> {
+        let normalized = code
+            .chars()
+            .filter(|c| *c != '-' && !c.is_whitespace())
+            .collect::<String>()
+            .to_lowercase();
+
+        if normalized.len() != CODE_LENGTH || !normalized.bytes().all(|c| ALPHABET.contains(&c)) {
+            return Err(eyre!("Malformed recovery code"));
+        }
+
+        Ok(Self(SecretBox::new(Box::from(normalized))))
+    }
+
+    pub fn generate_set() -> Vec<Self> {
+        (0..RECOVERY_CODE_COUNT).map(|_| Self::default()).collect()
+    }
+}
+
+impl Default for RecoveryCode {
+    fn default() -> Self {
+        let mut rng = rand::rng();
+        let code = (0..CODE_LENGTH)
+            .map(|_| ALPHABET[rng.random_range(0..ALPHABET.len())] as char)
+            .collect::<String>();
+
+        RecoveryCode(SecretBox::new(Box::from(code)))
+    }
+}
+
+impl PartialEq for RecoveryCode {
+    fn eq(
+        &self,
+        other: &Self,
+    ) -> bool {
+        self.0.expose_secret() == other.0.expose_secret()
+    }
+}
+
+impl AsRef<SecretBox<String>> for RecoveryCode {
+    fn as_ref(&self) -> &SecretBox<String> {
+        &self.0
+    }
+}
+
+impl fmt::Display for RecoveryCode {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        let (first, second) = self.0.expose_secret().split_at(CODE_LENGTH / 2);
+        write!(f, "{}-{}", first, second)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display_and_parse_round_trip() {
+        let code = RecoveryCode::default();
+        let displayed = code.to_string();
+
+        assert_eq!(displayed.len(), CODE_LENGTH + 1);
+        assert_eq!(&displayed[5..6], "-");
+        assert_eq!(RecoveryCode::parse(&displayed).unwrap(), code);
+    }
+
+    #[test]
+    fn test_parse_normalizes_user_input() {
+        let code = RecoveryCode::parse("abcde-fghjk").unwrap();
+
+        assert_eq!(RecoveryCode::parse(" ABCDE FGHJK ").unwrap(), code);
+        assert_eq!(RecoveryCode::parse("abcdefghjk").unwrap(), code);
+    }
+
+    #[test]
+    fn test_reject_malformed_codes() {
+        assert!(RecoveryCode::parse("abcde-fgh").is_err());
+        assert!(RecoveryCode::parse("abcde-fghjkm").is_err());
+        assert!(RecoveryCode::parse("abcde-fgh1k").is_err());
+        assert!(RecoveryCode::parse("123456").is_err());
+    }
+
+    #[test]
+    fn test_generate_set() {
+        let codes = RecoveryCode::generate_set();
+
+        assert_eq!(codes.len(), RECOVERY_CODE_COUNT);
+        assert!(codes.iter().all(|code| RecoveryCode::parse(&code.to_string()).is_ok()));
+    }
+}
@@ -0,0 +1,173 @@
+use color_eyre::eyre::{Result, eyre};
+use rand::RngCore;
+use secrecy::{ExposeSecret, SecretBox};
+use totp_rs::{Algorithm, Secret, TOTP};
+
+const SECRET_LENGTH: usize = 20;
+const DIGITS: usize = 6;
+const STEP_SECONDS: u64 = 30;
+
+// Shared secret of an authenticator app (RFC 6238, SHA1, 6 digits, 30 seconds steps).
+// Only ever stored encrypted, see `TotpCipher`.
+pub struct TotpSecret(SecretBox<Vec<u8>>);
+
+impl TotpSecret {
+    pub fn new(secret: Vec<u8>) -> Result<Self> {
+        if secret.len() < 16 {
+            return Err(eyre!("TOTP secret must be at least 128 bits"));
+        }
+        Ok(Self(SecretBox::new(Box::from(secret))))
+    }
+
+    pub fn from_base32(secret: &str) -> Result<Self> {
+        let secret = Secret::Encoded(secret.to_string())
+            .to_bytes()
+            .map_err(|e| eyre!("Invalid base32 TOTP secret: {:?}", e))?;
+        Self::new(secret)
+    }
+
+    pub fn expose_bytes(&self) -> &[u8] {
+        self.0.expose_secret()
+    }
+
+    pub fn to_base32(&self) -> String {
+        self.totp("", "").get_secret_base32()
+    }
+
+    // The URI authenticator apps import, usually shown as a QR code
+    pub fn otpauth_uri(
+        &self,
+        issuer: &str,
+        account_name: &str,
+    ) -> Result<String> {
+        // The label separates issuer and account with a colon, neither may contain one
+        if issuer.contains(':') || account_name.contains(':') {
+            return Err(eyre!("TOTP issuer and account name can't contain ':'"));
+        }
+        Ok(self.totp(issuer, account_name).get_url())
+    }
+
+    pub fn code_at(
+        &self,
+        unix_time: u64,
+    ) -> String {
+        self.totp("", "").generate(unix_time)
+    }
+
+    // The time step the code belongs to, looking `drift_steps` steps before and after the current
+    // one to tolerate clock skew. Callers record the step so the same code can't be replayed.
+    pub fn matching_step(
+        &self,
+        code: &str,
+        unix_time: u64,
+        drift_steps: u64,
+    ) -> Option<u64> {
+        let totp = self.totp("", "");
+        let current_step = unix_time / STEP_SECONDS;
+
+        (current_step.saturating_sub(drift_steps)..=current_step + drift_steps)
+            .find(|step| totp.check(code, step * STEP_SECONDS))
+    }
+
+    fn totp(
+        &self,
+        issuer: &str,
+        account_name: &str,
+    ) -> TOTP {
+        let issuer = Some(issuer.to_string()).filter(|issuer| !issuer.is_empty());
+        TOTP::new_unchecked(
+            Algorithm::SHA1,
+            DIGITS,
+            0,
+            STEP_SECONDS,
+            self.0.expose_secret().clone(),
+            issuer,
+            account_name.to_string(),
+        )
+    }
+}
+
+impl Default for TotpSecret {
+    fn default() -> Self {
+        let mut secret = vec![0u8; SECRET_LENGTH];
+        rand::rng().fill_bytes(&mut secret);
+
+        TotpSecret(SecretBox::new(Box::from(secret)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // RFC 6238 appendix B, SHA1 secret, truncated to 6 digits
+    fn rfc_secret() -> TotpSecret {
+        TotpSecret::new(b"12345678901234567890".to_vec()).unwrap()
+    }
+
+    #[test]
+    fn test_rfc6238_vectors() {
+        let secret = rfc_secret();
+
+        assert_eq!(secret.code_at(59), "287082");
+        assert_eq!(secret.code_at(1111111109), "081804");
+        assert_eq!(secret.code_at(1234567890), "005924");
+        assert_eq!(secret.code_at(2000000000), "279037");
+    }
+
+    #[test]
+    fn test_matching_step_within_drift() {
+        let secret = rfc_secret();
+        let now = 1234567890;
+        let previous_code = secret.code_at(now - STEP_SECONDS);
+
+        assert_eq!(
+            secret.matching_step(&previous_code, now, 1),
+            Some(now / STEP_SECONDS - 1)
+        );
+        assert_eq!(secret.matching_step(&previous_code, now, 0), None);
+        assert_eq!(
+            secret.matching_step(&secret.code_at(now), now, 0),
+            Some(now / STEP_SECONDS)
+        );
+    }
+
+    #[test]
+    fn test_reject_code_outside_drift() {
+        let secret = rfc_secret();
+        let now = 1234567890;
+
+        assert_eq!(
+            secret.matching_step(&secret.code_at(now + 3 * STEP_SECONDS), now, 1),
+            None
+        );
+    }
+
+    #[test]
+    fn test_otpauth_uri() {
+        let secret = rfc_secret();
+        let uri = secret.otpauth_uri("LGRB", "user@example.com").unwrap();
+
+        assert_eq!(
+            uri,
+            format!(
+                "otpauth://totp/LGRB:user%40example.com?secret={}&issuer=LGRB",
+                secret.to_base32()
+            )
+        );
+        assert!(secret.otpauth_uri("LG:RB", "user@example.com").is_err());
+    }
+
+    #[test]
+    fn test_base32_round_trip() {
+        let secret = TotpSecret::default();
+        let decoded = TotpSecret::from_base32(&secret.to_base32()).unwrap();
+
+        assert_eq!(decoded.expose_bytes(), secret.expose_bytes());
+    }
+
+    #[test]
+    fn test_reject_short_secret() {
+        assert!(TotpSecret::new(vec![0u8; 10]).is_err());
+    }
+}
@@ -1,11 +1,21 @@
 use color_eyre::eyre::{Report, Result};
 use rand::Rng;
 use secrecy::{ExposeSecret, SecretBox};
+use serde::{Deserialize, Serialize};
 use std::fmt;
 
 #[derive(Debug)]
 pub struct TwoFACode(SecretBox<String>);
 
+// How the second factor is proven: a code emailed for the attempt or the current code of an
+// authenticator app (TOTP)
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TwoFAMethod {
+    Email,
+    Totp,
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum TwoFACodeError {
     #[error("2FA code must be exactly 6 characters long")]
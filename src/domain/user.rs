@@ -1,11 +1,13 @@
-use crate::domain::{Email, EmailError, Password, PasswordError};
+use crate::domain::{Email, EmailError, Password, PasswordError, UserId};
 use secrecy::SecretBox;
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct User {
+    id: UserId,
     email: Email,
     password: Password,
     requires_2fa: bool,
+    email_verified: bool,
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -29,12 +31,36 @@ impl User {
         let email = Email::new(SecretBox::new(Box::from(email)))?;
         let password = Password::new(SecretBox::new(Box::from(password)))?;
         Ok(Self {
+            id: UserId::default(),
             email,
             password,
             requires_2fa,
+            email_verified: false,
         })
     }
 
+    // New users get a fresh id, the stores set the id of the existing ones
+    pub fn with_id(
+        mut self,
+        id: UserId,
+    ) -> Self {
+        self.id = id;
+        self
+    }
+
+    // New users start unverified, the stores set the flag of the existing ones
+    pub fn with_email_verified(
+        mut self,
+        email_verified: bool,
+    ) -> Self {
+        self.email_verified = email_verified;
+        self
+    }
+
+    pub fn id(&self) -> &UserId {
+        &self.id
+    }
+
     pub fn email(&self) -> &Email {
         &self.email
     }
@@ -46,4 +72,22 @@ impl User {
     pub fn requires_2fa(&self) -> bool {
         self.requires_2fa
     }
+
+    pub fn email_verified(&self) -> bool {
+        self.email_verified
+    }
+
+    pub fn set_email(
+        &mut self,
+        email: Email,
+    ) {
+        self.email = email;
+    }
+
+    pub fn set_password(
+        &mut self,
+        password: Password,
+    ) {
+        self.password = password;
+    }
 }
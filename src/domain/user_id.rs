@@ -0,0 +1,62 @@
+use std::fmt;
+use uuid::Uuid;
+
+// Stable identifier of an account, unlike the email it never changes. It is the `sub` of the tokens.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct UserId(Uuid);
+
+#[derive(Debug, thiserror::Error)]
+pub enum UserIdError {
+    #[error("Invalid UUID format")]
+    InvalidFormat,
+}
+
+impl UserId {
+    pub fn parse(id: &str) -> Result<Self, UserIdError> {
+        Uuid::parse_str(id).map(Self).map_err(|_| UserIdError::InvalidFormat)
+    }
+}
+
+impl Default for UserId {
+    fn default() -> Self {
+        Self(Uuid::new_v4())
+    }
+}
+
+impl From<Uuid> for UserId {
+    fn from(id: Uuid) -> Self {
+        Self(id)
+    }
+}
+
+impl AsRef<Uuid> for UserId {
+    fn as_ref(&self) -> &Uuid {
+        &self.0
+    }
+}
+
+impl fmt::Display for UserId {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_round_trip() {
+        let id = UserId::default();
+        assert_eq!(UserId::parse(&id.to_string()).unwrap(), id);
+    }
+
+    #[test]
+    fn test_parse_rejects_other_values() {
+        assert!(UserId::parse("user@example.com").is_err());
+        assert!(UserId::parse("").is_err());
+    }
+}
@@ -0,0 +1,125 @@
+use crate::app_state::AppState;
+use crate::domain::AuthAPIError;
+use crate::domain::data_stores::{Revocation, SessionEpochStoreError, UserStoreError};
+use crate::grpc::auth_service::auth_service::{
+    GetUserRequest, GetUserResponse, RevocationEvent, RevokeUserSessionsRequest, RevokeUserSessionsResponse,
+    TokenRevoked, UserSessionsRevoked, WatchRevocationsRequest,
+    auth_admin_service_server::{AuthAdminService, AuthAdminServiceServer},
+    revocation_event,
+};
+use crate::grpc::auth_service::parse_email;
+use crate::grpc::service_auth::{ServiceIdentity, service_auth_interceptor};
+use crate::utils::revoke_user_sessions;
+use color_eyre::eyre::eyre;
+use secrecy::ExposeSecret;
+use std::pin::Pin;
+use tokio_stream::{Stream, StreamExt};
+use tonic::service::interceptor::InterceptedService;
+use tonic::{Request, Response, Status};
+
+pub struct AuthAdminServiceImpl {
+    state: AppState,
+}
+
+#[tonic::async_trait]
+impl AuthAdminService for AuthAdminServiceImpl {
+    type WatchRevocationsStream = Pin<Box<dyn Stream<Item = Result<RevocationEvent, Status>> + Send>>;
+
+    #[tracing::instrument(name = "gRPC admin GetUser", skip_all)]
+    async fn get_user(
+        &self,
+        request: Request<GetUserRequest>,
+    ) -> Result<Response<GetUserResponse>, Status> {
+        let service_id = caller_service_id(&request)?;
+        let email = parse_email(request.into_inner().email)?;
+
+        let user = match self.state.user_store.read().await.get_user(&email).await {
+            Ok(user) => user,
+            Err(UserStoreError::UserNotFound) => return Err(Status::not_found("User not found")),
+            Err(e) => return Err(AuthAPIError::UnexpectedError(e.into()).into()),
+        };
+        tracing::info!(service_id, "User looked up by an internal service");
+
+        Ok(Response::new(GetUserResponse {
+            email: user.email().as_ref().expose_secret().clone(),
+            requires_2fa: user.requires_2fa(),
+            user_id: user.id().to_string(),
+        }))
+    }
+
+    #[tracing::instrument(name = "gRPC admin RevokeUserSessions", skip_all)]
+    async fn revoke_user_sessions(
+        &self,
+        request: Request<RevokeUserSessionsRequest>,
+    ) -> Result<Response<RevokeUserSessionsResponse>, Status> {
+        let service_id = caller_service_id(&request)?;
+        let email = parse_email(request.into_inner().email)?;
+
+        let user_id = match self.state.user_store.read().await.get_user(&email).await {
+            Ok(user) => *user.id(),
+            Err(UserStoreError::UserNotFound) => return Err(Status::not_found("User not found")),
+            Err(e) => return Err(AuthAPIError::UnexpectedError(e.into()).into()),
+        };
+
+        let session_epoch = match revoke_user_sessions(&self.state, &user_id).await {
+            Ok(epoch) => epoch,
+            Err(SessionEpochStoreError::UserNotFound) => return Err(Status::not_found("User not found")),
+            Err(SessionEpochStoreError::UnexpectedError(e)) => return Err(AuthAPIError::UnexpectedError(e).into()),
+        };
+        tracing::info!(service_id, "User sessions revoked by an internal service");
+
+        Ok(Response::new(RevokeUserSessionsResponse { session_epoch }))
+    }
+
+    #[tracing::instrument(name = "gRPC admin WatchRevocations", skip_all)]
+    async fn watch_revocations(
+        &self,
+        request: Request<WatchRevocationsRequest>,
+    ) -> Result<Response<Self::WatchRevocationsStream>, Status> {
+        let service_id = caller_service_id(&request)?;
+
+        let revocations = self
+            .state
+            .banned_token_store
+            .read()
+            .await
+            .watch_revocations()
+            .await
+            .map_err(|e| AuthAPIError::UnexpectedError(eyre!("Failed to watch the revocations: {:?}", e)))?;
+        tracing::info!(service_id, "Internal service watching the revocations");
+
+        let events = revocations.map(|revocation| Ok(RevocationEvent::from(revocation)));
+        Ok(Response::new(Box::pin(events)))
+    }
+}
+
+impl From<Revocation> for RevocationEvent {
+    fn from(revocation: Revocation) -> Self {
+        let revocation = match revocation {
+            Revocation::Token { token } => revocation_event::Revocation::Token(TokenRevoked { token }),
+            Revocation::UserSessions { subject, session_epoch } => {
+                revocation_event::Revocation::UserSessions(UserSessionsRevoked { subject, session_epoch })
+            }
+        };
+        RevocationEvent {
+            revocation: Some(revocation),
+        }
+    }
+}
+
+// The interceptor always sets the identity, a missing one means the service was mounted without it
+fn caller_service_id<T>(request: &Request<T>) -> Result<String, Status> {
+    request
+        .extensions()
+        .get::<ServiceIdentity>()
+        .map(|identity| identity.service_id.clone())
+        .ok_or_else(|| Status::unauthenticated("Missing service identity"))
+}
+
+pub type ServiceAuthInterceptor = fn(Request<()>) -> Result<Request<()>, Status>;
+
+pub fn create_admin_grpc_service(
+    state: AppState
+) -> InterceptedService<AuthAdminServiceServer<AuthAdminServiceImpl>, ServiceAuthInterceptor> {
+    AuthAdminServiceServer::with_interceptor(AuthAdminServiceImpl { state }, service_auth_interceptor)
+}
@@ -5,39 +5,389 @@ pub mod auth_service {
 }
 
 use auth_service::{
-    VerifyTokenRequest, VerifyTokenResponse,
+    DeleteAccountRequest, DeleteAccountResponse, IssueServiceTokenRequest, IssueServiceTokenResponse, LoginRequest,
+    LoginResponse, LogoutRequest, LogoutResponse, RefreshTokenRequest, RefreshTokenResponse, RejectionReason,
+    SignupRequest, SignupResponse, TokenPair as GrpcTokenPair, TokenType as GrpcTokenType, TwoFactorChallenge,
+    TwoFactorMethod as GrpcTwoFactorMethod, Verify2FaRequest, Verify2FaResponse, VerifyTokenRequest,
+    VerifyTokenResponse,
     auth_service_server::{AuthService, AuthServiceServer},
+    delete_account_response, login_response,
 };
 
-use crate::utils::validate_token;
+use crate::app_state::AppState;
+use crate::domain::data_stores::ServiceCredentialStoreError;
+use crate::domain::{
+    AuthAPIError, Email, LoginAttemptId, Password, PasswordError, PasswordViolation, TwoFAMethod, User,
+};
+use crate::routes::{
+    DeleteAccountOutcome, LoginOutcome, SecondFactor, authenticate_user, complete_two_fa_login, create_user,
+    delete_confirmed_account, end_session, refresh_session,
+};
+use crate::utils::{
+    AuthenticatedUser, PASSWORD_POLICY, SERVICE_TOKEN_TTL_SECONDS, TokenPair, TokenRejection, TokenType,
+    ValidateTokenError, authorize_token, check_rate_limit, generate_service_token,
+};
+use jsonwebtoken::errors::ErrorKind;
+use secrecy::{ExposeSecret, SecretBox};
 
-pub struct AuthServiceImpl;
+pub struct AuthServiceImpl {
+    state: AppState,
+}
 
 #[tonic::async_trait]
 impl AuthService for AuthServiceImpl {
+    #[tracing::instrument(name = "gRPC VerifyToken", skip_all)]
     async fn verify_token(
         &self,
         request: Request<VerifyTokenRequest>,
     ) -> Result<Response<VerifyTokenResponse>, Status> {
         let req = request.into_inner();
         let token = req.token;
-        // TODO: Adding validation token that reads from a Database, not using a local storage.
-        match validate_token(&token).await {
-            Ok(_) => Ok(Response::new(VerifyTokenResponse {
+
+        match authorize_token(&self.state, &token, TokenType::Access).await {
+            Ok(claims) => Ok(Response::new(VerifyTokenResponse {
                 valid: true,
                 message: "Token is valid".to_string(),
+                subject: claims.sub,
+                token_type: GrpcTokenType::from(claims.token_type).into(),
+                expires_at: claims.exp as i64,
+                reason: RejectionReason::Unspecified.into(),
             })),
-            Err(e) => {
-                println!("error validating the token {:?}", e);
+            Err(TokenRejection::UnexpectedError(e)) => {
+                tracing::error!("Failed to verify a token: {:?}", e);
+                Err(Status::internal("Unexpected error"))
+            }
+            Err(rejection) => {
+                tracing::debug!("Token rejected: {}", rejection);
                 Ok(Response::new(VerifyTokenResponse {
                     valid: false,
                     message: "Token is not valid".to_string(),
+                    reason: RejectionReason::from(&rejection).into(),
+                    ..Default::default()
                 }))
             }
         }
     }
+
+    #[tracing::instrument(name = "gRPC Signup", skip_all)]
+    async fn signup(
+        &self,
+        request: Request<SignupRequest>,
+    ) -> Result<Response<SignupResponse>, Status> {
+        let client_ip = request.remote_addr().map(|address| address.ip());
+        let req = request.into_inner();
+        check_rate_limit(&self.state, "/signup", client_ip, Some(&req.email)).await?;
+
+        PASSWORD_POLICY
+            .check(&req.password, &req.email)
+            .map_err(AuthAPIError::from)?;
+        let user = User::new(req.email, req.password, req.requires_2fa).map_err(AuthAPIError::from)?;
+        let recovery_codes = create_user(&self.state, user).await?;
+
+        Ok(Response::new(SignupResponse {
+            message: "User created successfully!".to_string(),
+            recovery_codes,
+        }))
+    }
+
+    #[tracing::instrument(name = "gRPC Login", skip_all)]
+    async fn login(
+        &self,
+        request: Request<LoginRequest>,
+    ) -> Result<Response<LoginResponse>, Status> {
+        let client_ip = request.remote_addr().map(|address| address.ip());
+        let req = request.into_inner();
+        check_rate_limit(&self.state, "/login", client_ip, Some(&req.email)).await?;
+        let email = parse_email(req.email)?;
+        let password = Password::new(SecretBox::new(Box::from(req.password))).map_err(AuthAPIError::from)?;
+
+        let totp_code = Some(req.totp_code.as_str()).filter(|code| !code.is_empty());
+
+        let result = match authenticate_user(&self.state, &email, &password, totp_code, client_ip).await? {
+            LoginOutcome::Authenticated(token_pair) => login_response::Result::Tokens(token_pair.into()),
+            LoginOutcome::TwoFactorRequired(login_attempt_id, method) => {
+                login_response::Result::TwoFactorChallenge((login_attempt_id, method).into())
+            }
+        };
+
+        Ok(Response::new(LoginResponse { result: Some(result) }))
+    }
+
+    #[tracing::instrument(name = "gRPC Verify2FA", skip_all)]
+    async fn verify2_fa(
+        &self,
+        request: Request<Verify2FaRequest>,
+    ) -> Result<Response<Verify2FaResponse>, Status> {
+        let client_ip = request.remote_addr().map(|address| address.ip());
+        let req = request.into_inner();
+        check_rate_limit(&self.state, "/verify-2fa", client_ip, Some(&req.email)).await?;
+        let email = parse_email(req.email)?;
+
+        let two_fa_code = Some(req.two_fa_code.as_str()).filter(|code| !code.is_empty());
+        let recovery_code = Some(req.recovery_code.as_str()).filter(|code| !code.is_empty());
+        let second_factor = SecondFactor::from_request(two_fa_code, recovery_code, None)?;
+
+        let token_pair =
+            complete_two_fa_login(&self.state, &email, &req.login_attempt_id, second_factor, client_ip).await?;
+
+        Ok(Response::new(Verify2FaResponse {
+            tokens: Some(token_pair.into()),
+        }))
+    }
+
+    #[tracing::instrument(name = "gRPC RefreshToken", skip_all)]
+    async fn refresh_token(
+        &self,
+        request: Request<RefreshTokenRequest>,
+    ) -> Result<Response<RefreshTokenResponse>, Status> {
+        let client_ip = request.remote_addr().map(|address| address.ip());
+        let req = request.into_inner();
+        check_rate_limit(&self.state, "/refresh-token", client_ip, None).await?;
+        if req.refresh_token.is_empty() {
+            return Err(AuthAPIError::MissingToken.into());
+        }
+
+        let token_pair = refresh_session(&self.state, &req.refresh_token).await?;
+
+        Ok(Response::new(RefreshTokenResponse {
+            tokens: Some(token_pair.into()),
+        }))
+    }
+
+    #[tracing::instrument(name = "gRPC Logout", skip_all)]
+    async fn logout(
+        &self,
+        request: Request<LogoutRequest>,
+    ) -> Result<Response<LogoutResponse>, Status> {
+        let req = request.into_inner();
+        let user = authenticate(&self.state, req.access_token).await?;
+
+        let refresh_token = Some(req.refresh_token.as_str()).filter(|token| !token.is_empty());
+        end_session(&self.state, Some(&user), refresh_token).await?;
+
+        Ok(Response::new(LogoutResponse {}))
+    }
+
+    #[tracing::instrument(name = "gRPC DeleteAccount", skip_all)]
+    async fn delete_account(
+        &self,
+        request: Request<DeleteAccountRequest>,
+    ) -> Result<Response<DeleteAccountResponse>, Status> {
+        let client_ip = request.remote_addr().map(|address| address.ip());
+        let req = request.into_inner();
+        let user = authenticate(&self.state, req.access_token).await?;
+        let password =
+            Password::new(SecretBox::new(Box::from(req.password))).map_err(|_| AuthAPIError::IncorrectCredentials)?;
+
+        let login_attempt_id = Some(req.login_attempt_id.as_str()).filter(|id| !id.is_empty());
+        let two_fa_code = Some(req.two_fa_code.as_str()).filter(|code| !code.is_empty());
+
+        let result =
+            match delete_confirmed_account(&self.state, &user, &password, client_ip, login_attempt_id, two_fa_code)
+                .await?
+            {
+                DeleteAccountOutcome::Deleted => delete_account_response::Result::Deleted(true),
+                DeleteAccountOutcome::TwoFactorRequired(login_attempt_id, method) => {
+                    delete_account_response::Result::TwoFactorChallenge((login_attempt_id, method).into())
+                }
+            };
+
+        Ok(Response::new(DeleteAccountResponse { result: Some(result) }))
+    }
+
+    // Client-credentials exchange for internal services, the token authorizes calls to AuthAdminService
+    #[tracing::instrument(name = "gRPC IssueServiceToken", skip_all)]
+    async fn issue_service_token(
+        &self,
+        request: Request<IssueServiceTokenRequest>,
+    ) -> Result<Response<IssueServiceTokenResponse>, Status> {
+        let client_ip = request.remote_addr().map(|address| address.ip());
+        let req = request.into_inner();
+        // No HTTP route does this, the `*` buckets of the address apply
+        check_rate_limit(&self.state, "/service-token", client_ip, None).await?;
+        let secret = SecretBox::new(Box::from(req.service_secret));
+
+        match self
+            .state
+            .service_credential_store
+            .read()
+            .await
+            .validate_credentials(&req.service_id, &secret)
+            .await
+        {
+            Ok(()) => (),
+            Err(ServiceCredentialStoreError::UnexpectedError(e)) => {
+                return Err(AuthAPIError::UnexpectedError(e).into());
+            }
+            Err(_) => return Err(Status::unauthenticated("Invalid service credentials")),
+        }
+
+        let access_token = generate_service_token(&req.service_id).map_err(AuthAPIError::from)?;
+
+        Ok(Response::new(IssueServiceTokenResponse {
+            access_token,
+            expires_in: *SERVICE_TOKEN_TTL_SECONDS,
+        }))
+    }
+}
+
+pub(crate) fn parse_email(email: String) -> Result<Email, Status> {
+    Ok(Email::new(SecretBox::new(Box::from(email))).map_err(AuthAPIError::from)?)
+}
+
+async fn authenticate(
+    state: &AppState,
+    access_token: String,
+) -> Result<AuthenticatedUser, AuthAPIError> {
+    if access_token.is_empty() {
+        return Err(AuthAPIError::MissingToken);
+    }
+    AuthenticatedUser::from_token(state, access_token).await
+}
+
+impl From<TokenPair> for GrpcTokenPair {
+    fn from(token_pair: TokenPair) -> Self {
+        GrpcTokenPair {
+            access_token: token_pair.access_token,
+            refresh_token: token_pair.refresh_token,
+        }
+    }
 }
 
-pub fn create_grpc_service() -> AuthServiceServer<AuthServiceImpl> {
-    AuthServiceServer::new(AuthServiceImpl)
+impl From<(LoginAttemptId, TwoFAMethod)> for TwoFactorChallenge {
+    fn from((login_attempt_id, method): (LoginAttemptId, TwoFAMethod)) -> Self {
+        let method = match method {
+            TwoFAMethod::Email => GrpcTwoFactorMethod::Email,
+            TwoFAMethod::Totp => GrpcTwoFactorMethod::Totp,
+        };
+
+        TwoFactorChallenge {
+            message: "2FA required".to_string(),
+            login_attempt_id: login_attempt_id.id().expose_secret().clone(),
+            method: method.into(),
+        }
+    }
+}
+
+// Same error semantics as the HTTP API, mapped on gRPC status codes
+impl From<AuthAPIError> for Status {
+    fn from(error: AuthAPIError) -> Self {
+        match error {
+            AuthAPIError::UserAlreadyExists => Status::already_exists("User already exists"),
+            AuthAPIError::IncorrectCredentials => Status::unauthenticated("Incorrect credentials"),
+            AuthAPIError::MissingToken => Status::unauthenticated("Missing JWT token"),
+            AuthAPIError::TokenNotValid => Status::unauthenticated("JWT token not valid"),
+            AuthAPIError::Forbidden => Status::permission_denied("Not allowed to act on this account"),
+            AuthAPIError::EmailNotVerified => Status::failed_precondition("Email not verified"),
+            AuthAPIError::PasswordReused => Status::invalid_argument("New password must differ from the current one"),
+            AuthAPIError::PasswordError(PasswordError::PolicyViolation(violations)) => {
+                let rules: Vec<_> = violations.iter().map(PasswordViolation::rule).collect();
+                Status::invalid_argument(format!("Password does not meet the policy: {}", rules.join(", ")))
+            }
+            AuthAPIError::EmailOrPasswordIncorrect
+            | AuthAPIError::PasswordError(_)
+            | AuthAPIError::EmailError(_)
+            | AuthAPIError::UserError(_) => Status::invalid_argument("Email or password incorrect"),
+            AuthAPIError::TwoFAMalformedError => Status::invalid_argument("Error two-factor authentication malformed"),
+            AuthAPIError::TooManyTwoFAAttempts => Status::resource_exhausted("Too many 2FA attempts, log in again"),
+            AuthAPIError::AccountLocked(_) => Status::resource_exhausted("Account temporarily locked"),
+            AuthAPIError::TooManyLoginAttempts(_) => {
+                Status::resource_exhausted("Too many failed logins, try again later")
+            }
+            AuthAPIError::RateLimited(_) => Status::resource_exhausted("Too many requests"),
+            AuthAPIError::LoginAttemptIdMalformedError => Status::invalid_argument("Error login attempt id malformed"),
+            AuthAPIError::TotpAlreadyEnabled => Status::already_exists("TOTP already enabled"),
+            AuthAPIError::TotpNotEnrolled => Status::failed_precondition("TOTP enrollment not started"),
+            AuthAPIError::TwoFANotEnabled => Status::failed_precondition("2FA is not enabled"),
+            AuthAPIError::InvalidPasskeyResponse => Status::invalid_argument("Invalid passkey response"),
+            AuthAPIError::PasskeyAlreadyRegistered => Status::already_exists("Passkey already registered"),
+            AuthAPIError::PasskeyNotRegistered => Status::failed_precondition("No passkey registered"),
+            error => {
+                tracing::error!("gRPC request failed: {:?}", error);
+                Status::internal("Unexpected error")
+            }
+        }
+    }
+}
+
+impl From<TokenType> for GrpcTokenType {
+    fn from(token_type: TokenType) -> Self {
+        match token_type {
+            TokenType::Access => GrpcTokenType::Access,
+            TokenType::Refresh => GrpcTokenType::Refresh,
+            TokenType::Service => GrpcTokenType::Service,
+            TokenType::PasswordReset | TokenType::EmailVerification | TokenType::EmailChange => {
+                GrpcTokenType::Unspecified
+            }
+        }
+    }
+}
+
+impl From<&TokenRejection> for RejectionReason {
+    fn from(rejection: &TokenRejection) -> Self {
+        match rejection {
+            TokenRejection::InvalidToken(ValidateTokenError::InvalidToken(e)) => match e.kind() {
+                ErrorKind::InvalidSignature => RejectionReason::InvalidSignature,
+                ErrorKind::ExpiredSignature => RejectionReason::Expired,
+                ErrorKind::ImmatureSignature => RejectionReason::NotYetValid,
+                ErrorKind::InvalidIssuer
+                | ErrorKind::InvalidAudience
+                | ErrorKind::InvalidSubject
+                | ErrorKind::MissingRequiredClaim(_) => RejectionReason::InvalidClaims,
+                _ => RejectionReason::Malformed,
+            },
+            TokenRejection::InvalidToken(ValidateTokenError::UnexpectedTokenType) => RejectionReason::WrongTokenType,
+            TokenRejection::InvalidToken(ValidateTokenError::IssuedInTheFuture) => RejectionReason::NotYetValid,
+            TokenRejection::Banned => RejectionReason::Banned,
+            TokenRejection::SessionRevoked => RejectionReason::SessionRevoked,
+            TokenRejection::UnexpectedError(_) => RejectionReason::Unspecified,
+        }
+    }
+}
+
+pub fn create_grpc_service(state: AppState) -> AuthServiceServer<AuthServiceImpl> {
+    AuthServiceServer::new(AuthServiceImpl { state })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn invalid_token(kind: ErrorKind) -> TokenRejection {
+        TokenRejection::InvalidToken(ValidateTokenError::InvalidToken(kind.into()))
+    }
+
+    #[test]
+    fn test_rejection_reasons() {
+        let cases = [
+            (
+                invalid_token(ErrorKind::InvalidSignature),
+                RejectionReason::InvalidSignature,
+            ),
+            (invalid_token(ErrorKind::ExpiredSignature), RejectionReason::Expired),
+            (
+                invalid_token(ErrorKind::ImmatureSignature),
+                RejectionReason::NotYetValid,
+            ),
+            (
+                invalid_token(ErrorKind::InvalidAudience),
+                RejectionReason::InvalidClaims,
+            ),
+            (invalid_token(ErrorKind::InvalidToken), RejectionReason::Malformed),
+            (
+                TokenRejection::InvalidToken(ValidateTokenError::UnexpectedTokenType),
+                RejectionReason::WrongTokenType,
+            ),
+            (
+                TokenRejection::InvalidToken(ValidateTokenError::IssuedInTheFuture),
+                RejectionReason::NotYetValid,
+            ),
+            (TokenRejection::Banned, RejectionReason::Banned),
+            (TokenRejection::SessionRevoked, RejectionReason::SessionRevoked),
+        ];
+
+        for (rejection, reason) in cases {
+            assert_eq!(RejectionReason::from(&rejection), reason, "{}", rejection);
+        }
+    }
 }
@@ -0,0 +1,119 @@
+use crate::app_state::EmailClientType;
+use crate::grpc::admin_service::AuthAdminServiceImpl;
+use crate::grpc::auth_service::AuthServiceImpl;
+use crate::grpc::auth_service::auth_service::{
+    auth_admin_service_server::AuthAdminServiceServer, auth_service_server::AuthServiceServer,
+};
+use redis::aio::MultiplexedConnection;
+use sqlx::PgPool;
+use std::time::Duration;
+use tonic::server::NamedService;
+use tonic_health::ServingStatus;
+use tonic_health::server::HealthReporter;
+
+pub const POSTGRES_HEALTH_SERVICE: &str = "postgres";
+pub const REDIS_HEALTH_SERVICE: &str = "redis";
+pub const EMAIL_HEALTH_SERVICE: &str = "email";
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(10);
+const CHECK_TIMEOUT: Duration = Duration::from_secs(3);
+
+// The dependencies reported by the `grpc.health.v1` service, each one under its own service name.
+// The server (empty name) and the auth services are SERVING only while all the dependencies are.
+pub struct DependencyHealth {
+    pg_pool: PgPool,
+    redis_conn: MultiplexedConnection,
+    email_client: EmailClientType,
+}
+
+impl DependencyHealth {
+    pub fn new(
+        pg_pool: PgPool,
+        redis_conn: MultiplexedConnection,
+        email_client: EmailClientType,
+    ) -> Self {
+        Self {
+            pg_pool,
+            redis_conn,
+            email_client,
+        }
+    }
+
+    // Runs every check once and publishes the statuses
+    pub async fn report(
+        &self,
+        reporter: &HealthReporter,
+    ) {
+        let dependencies = [
+            (POSTGRES_HEALTH_SERVICE, self.check_postgres().await),
+            (REDIS_HEALTH_SERVICE, self.check_redis().await),
+            (EMAIL_HEALTH_SERVICE, self.check_email().await),
+        ];
+        let all_serving = dependencies.iter().all(|(_, serving)| *serving);
+
+        for (name, serving) in dependencies {
+            reporter.set_service_status(name, serving_status(serving)).await;
+        }
+        for name in [
+            "",
+            AuthServiceServer::<AuthServiceImpl>::NAME,
+            AuthAdminServiceServer::<AuthAdminServiceImpl>::NAME,
+        ] {
+            reporter.set_service_status(name, serving_status(all_serving)).await;
+        }
+    }
+
+    async fn check_postgres(&self) -> bool {
+        let check = sqlx::query("SELECT 1").execute(&self.pg_pool);
+        healthy(POSTGRES_HEALTH_SERVICE, check).await
+    }
+
+    async fn check_redis(&self) -> bool {
+        let mut conn = self.redis_conn.clone();
+        let ping = redis::cmd("PING");
+        healthy(REDIS_HEALTH_SERVICE, ping.query_async::<_, String>(&mut conn)).await
+    }
+
+    async fn check_email(&self) -> bool {
+        let email_client = self.email_client.read().await;
+        healthy(EMAIL_HEALTH_SERVICE, email_client.check_health()).await
+    }
+}
+
+// Re-checks the dependencies forever, meant to be spawned next to the gRPC server
+pub async fn report_dependency_health(
+    reporter: HealthReporter,
+    dependencies: DependencyHealth,
+) {
+    let mut interval = tokio::time::interval(CHECK_INTERVAL);
+    loop {
+        interval.tick().await;
+        dependencies.report(&reporter).await;
+    }
+}
+
+// A check that hangs counts as a failure
+async fn healthy<T, E: std::fmt::Debug>(
+    name: &str,
+    check: impl Future<Output = Result<T, E>>,
+) -> bool {
+    match tokio::time::timeout(CHECK_TIMEOUT, check).await {
+        Ok(Ok(_)) => true,
+        Ok(Err(e)) => {
+            tracing::warn!("Health check of {} failed: {:?}", name, e);
+            false
+        }
+        Err(_) => {
+            tracing::warn!("Health check of {} timed out", name);
+            false
+        }
+    }
+}
+
+fn serving_status(serving: bool) -> ServingStatus {
+    if serving {
+        ServingStatus::Serving
+    } else {
+        ServingStatus::NotServing
+    }
+}
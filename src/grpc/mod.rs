@@ -1 +1,4 @@
+pub mod admin_service;
 pub mod auth_service;
+pub mod health;
+pub mod service_auth;
@@ -0,0 +1,74 @@
+use crate::utils::{TokenType, validate_token};
+use tonic::{Request, Status};
+
+// The registered internal service behind a call, put in the request extensions by `service_auth_interceptor`
+#[derive(Debug, Clone)]
+pub struct ServiceIdentity {
+    pub service_id: String,
+}
+
+// Guards the admin service: callers must send a service token obtained with IssueServiceToken as
+// `authorization: Bearer <token>`. Service tokens are short-lived and only checked for signature,
+// claims and type, no store is hit on the way in.
+pub fn service_auth_interceptor(mut request: Request<()>) -> Result<Request<()>, Status> {
+    let token = extract_bearer_token(&request).ok_or_else(|| Status::unauthenticated("Missing service token"))?;
+
+    let claims = validate_token(token, TokenType::Service).map_err(|e| {
+        tracing::debug!("Service token rejected: {}", e);
+        Status::unauthenticated("Service token not valid")
+    })?;
+
+    request
+        .extensions_mut()
+        .insert(ServiceIdentity { service_id: claims.sub });
+    Ok(request)
+}
+
+fn extract_bearer_token(request: &Request<()>) -> Option<&str> {
+    let header = request.metadata().get("authorization")?.to_str().ok()?;
+    let (scheme, token) = header.split_once(' ')?;
+    if !scheme.eq_ignore_ascii_case("bearer") || token.trim().is_empty() {
+        return None;
+    }
+    Some(token.trim())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::UserId;
+    use crate::utils::{generate_service_token, generate_token_pair};
+
+    fn request_with_authorization(value: &str) -> Request<()> {
+        let mut request = Request::new(());
+        request.metadata_mut().insert("authorization", value.parse().unwrap());
+        request
+    }
+
+    #[test]
+    fn test_service_token_is_accepted() {
+        let token = generate_service_token("billing").unwrap();
+        let request = service_auth_interceptor(request_with_authorization(&format!("Bearer {}", token))).unwrap();
+
+        let identity = request.extensions().get::<ServiceIdentity>().unwrap();
+        assert_eq!(identity.service_id, "billing");
+    }
+
+    #[test]
+    fn test_missing_token_is_rejected() {
+        let status = service_auth_interceptor(Request::new(())).unwrap_err();
+        assert_eq!(status.code(), tonic::Code::Unauthenticated);
+
+        let status = service_auth_interceptor(request_with_authorization("Basic abc")).unwrap_err();
+        assert_eq!(status.code(), tonic::Code::Unauthenticated);
+    }
+
+    #[test]
+    fn test_user_access_token_is_rejected() {
+        let token_pair = generate_token_pair(&UserId::default(), "family", 0).unwrap();
+
+        let request = request_with_authorization(&format!("Bearer {}", token_pair.access_token));
+        let status = service_auth_interceptor(request).unwrap_err();
+        assert_eq!(status.code(), tonic::Code::Unauthenticated);
+    }
+}
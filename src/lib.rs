@@ -5,37 +5,72 @@ pub mod routes;
 pub mod services;
 pub mod utils;
 
-use crate::domain::AuthAPIError;
-use crate::routes::{delete_account, health_check, login, logout, refresh_token, signup, verify_2fa};
-use crate::utils::{CORS_ALLOWED_ORIGINS, PGSQL_MAX_CONNECTIONS, make_span_with_request_id, on_request, on_response};
+use crate::domain::{AuthAPIError, PasswordError, PasswordViolation};
+use crate::routes::{
+    change_email, change_password, confirm_email_change, confirm_password_reset, confirm_totp, delete_account,
+    enroll_totp, finish_passkey_login, finish_passkey_registration, get_recovery_codes, health_check, jwks, login,
+    logout, logout_all, refresh_token, regenerate_recovery_codes, request_password_reset, resend_verification_email,
+    signup, start_passkey_login, start_passkey_registration, start_passkey_two_fa, verify_2fa, verify_email,
+    verify_token,
+};
+use crate::utils::{
+    CORS_ALLOWED_ORIGINS, PGSQL_MAX_CONNECTIONS, TlsConfig, make_span_with_request_id, on_request, on_response,
+    rate_limit,
+};
 use app_state::AppState;
+use axum::extract::ConnectInfo;
+use axum::extract::connect_info::IntoMakeServiceWithConnectInfo;
+use axum::http::header::RETRY_AFTER;
 use axum::http::{Method, StatusCode};
+use axum::middleware;
+use axum::middleware::AddExtension;
 use axum::response::{IntoResponse, Response};
 use axum::routing::{delete, get, post};
 use axum::serve::Serve;
 use axum::{Json, Router};
+use axum_server::tls_rustls::RustlsAcceptor;
 use secrecy::{ExposeSecret, SecretBox};
 use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
 use sqlx::postgres::PgPoolOptions;
 use std::error::Error;
+use std::net::SocketAddr;
 use tower_http::cors::CorsLayer;
 use tower_http::services::ServeDir;
 use tower_http::trace::TraceLayer;
 
 pub struct Application {
-    server: Serve<Router, Router>,
+    server: HttpServer,
     pub address: String,
 }
 
+enum HttpServer {
+    Plain(Serve<IntoMakeServiceWithConnectInfo<Router, SocketAddr>, AddExtension<Router, ConnectInfo<SocketAddr>>>),
+    Tls(Box<axum_server::Server<RustlsAcceptor>>, Router),
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct ErrorResponse {
     pub error_message: String,
+
+    // Every rule a refused new password breaks
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub violations: Vec<PasswordViolation>,
 }
 
 impl IntoResponse for AuthAPIError {
     fn into_response(self) -> Response {
         log_error_chain(&self);
+        let retry_after = match self {
+            AuthAPIError::AccountLocked(seconds)
+            | AuthAPIError::TooManyLoginAttempts(seconds)
+            | AuthAPIError::RateLimited(seconds) => Some(seconds),
+            _ => None,
+        };
+        let violations = match &self {
+            AuthAPIError::PasswordError(PasswordError::PolicyViolation(violations)) => violations.clone(),
+            _ => Vec::new(),
+        };
         let (status, error_message) = match self {
             AuthAPIError::IncorrectCredentials => (StatusCode::UNAUTHORIZED, "Incorrect credentials"),
             AuthAPIError::UnexpectedError(_) => (StatusCode::INTERNAL_SERVER_ERROR, "Unexpected error"),
@@ -43,19 +78,44 @@ impl IntoResponse for AuthAPIError {
             AuthAPIError::EmailOrPasswordIncorrect => (StatusCode::BAD_REQUEST, "Email or password incorrect"),
             AuthAPIError::MissingToken => (StatusCode::BAD_REQUEST, "Missing JWT token"),
             AuthAPIError::TokenNotValid => (StatusCode::UNAUTHORIZED, "JWT token not valid"),
+            AuthAPIError::Forbidden => (StatusCode::FORBIDDEN, "Not allowed to act on this account"),
+            AuthAPIError::EmailNotVerified => (StatusCode::FORBIDDEN, "Email not verified"),
+            AuthAPIError::PasswordError(PasswordError::PolicyViolation(_)) => {
+                (StatusCode::BAD_REQUEST, "Password does not meet the policy")
+            }
+            AuthAPIError::PasswordError(_) => (StatusCode::BAD_REQUEST, "Invalid password"),
+            AuthAPIError::PasswordReused => (StatusCode::BAD_REQUEST, "New password must differ from the current one"),
             AuthAPIError::ErrorAddingToBannedTokens => {
                 (StatusCode::INTERNAL_SERVER_ERROR, "Error adding to banned tokens")
             }
             AuthAPIError::TwoFAMalformedError => (StatusCode::BAD_REQUEST, "Error two-factor authentication malformed"),
+            AuthAPIError::TooManyTwoFAAttempts => {
+                (StatusCode::TOO_MANY_REQUESTS, "Too many 2FA attempts, log in again")
+            }
+            AuthAPIError::AccountLocked(_) => (StatusCode::LOCKED, "Account temporarily locked"),
+            AuthAPIError::TooManyLoginAttempts(_) => {
+                (StatusCode::TOO_MANY_REQUESTS, "Too many failed logins, try again later")
+            }
+            AuthAPIError::RateLimited(_) => (StatusCode::TOO_MANY_REQUESTS, "Too many requests"),
             AuthAPIError::LoginAttemptIdMalformedError => (StatusCode::BAD_REQUEST, "Error login attempt id malformed"),
+            AuthAPIError::TotpAlreadyEnabled => (StatusCode::CONFLICT, "TOTP already enabled"),
+            AuthAPIError::TotpNotEnrolled => (StatusCode::BAD_REQUEST, "TOTP enrollment not started"),
+            AuthAPIError::TwoFANotEnabled => (StatusCode::BAD_REQUEST, "2FA is not enabled"),
+            AuthAPIError::InvalidPasskeyResponse => (StatusCode::BAD_REQUEST, "Invalid passkey response"),
+            AuthAPIError::PasskeyAlreadyRegistered => (StatusCode::CONFLICT, "Passkey already registered"),
+            AuthAPIError::PasskeyNotRegistered => (StatusCode::BAD_REQUEST, "No passkey registered"),
             _ => (StatusCode::INTERNAL_SERVER_ERROR, "Unexpected error"),
         };
 
         let body = Json(ErrorResponse {
             error_message: error_message.to_string(),
+            violations,
         });
 
-        (status, body).into_response()
+        match retry_after {
+            Some(seconds) => (status, [(RETRY_AFTER, seconds.to_string())], body).into_response(),
+            None => (status, body).into_response(),
+        }
     }
 }
 
@@ -64,37 +124,88 @@ impl Application {
         app_state: AppState,
         address: &str,
     ) -> Result<Self, Box<dyn Error>> {
-        let router = Router::new()
-            .nest_service("/", ServeDir::new("assets"))
-            .route("/health-check", get(health_check))
-            .route("/signup", post(signup))
-            .route("/delete-account", delete(delete_account))
-            .route("/login", post(login))
-            .route("/logout", post(logout))
-            .route("/verify-2fa", post(verify_2fa))
-            .route("/refresh-token", post(refresh_token))
-            .with_state(app_state)
-            .layer(cors()?)
-            .layer(
-                TraceLayer::new_for_http()
-                    .make_span_with(make_span_with_request_id)
-                    .on_request(on_request)
-                    .on_response(on_response),
-            );
-
         let listener = tokio::net::TcpListener::bind(address).await?;
         let address = listener.local_addr()?.to_string();
-        let server = axum::serve(listener, router);
+        let server = HttpServer::Plain(axum::serve(
+            listener,
+            router(app_state)?.into_make_service_with_connect_info::<SocketAddr>(),
+        ));
 
         Ok(Application { server, address })
     }
 
+    // Same routes served over HTTPS with the configured certificate
+    pub async fn build_with_tls(
+        app_state: AppState,
+        address: &str,
+        tls: &TlsConfig,
+    ) -> Result<Self, Box<dyn Error>> {
+        let listener = std::net::TcpListener::bind(address)?;
+        listener.set_nonblocking(true)?;
+        let address = listener.local_addr()?.to_string();
+        let server = axum_server::from_tcp_rustls(listener, tls.http_config().await?);
+
+        Ok(Application {
+            server: HttpServer::Tls(Box::new(server), router(app_state)?),
+            address,
+        })
+    }
+
     pub async fn run(self) -> Result<(), std::io::Error> {
         tracing::info!("listening on {}", &self.address);
-        self.server.await
+        match self.server {
+            HttpServer::Plain(server) => server.await,
+            HttpServer::Tls(server, router) => {
+                server
+                    .serve(router.into_make_service_with_connect_info::<SocketAddr>())
+                    .await
+            }
+        }
     }
 }
 
+fn router(app_state: AppState) -> Result<Router, Box<dyn Error>> {
+    Ok(Router::new()
+        .nest_service("/", ServeDir::new("assets"))
+        .route("/health-check", get(health_check))
+        .route("/.well-known/jwks.json", get(jwks))
+        .route("/signup", post(signup))
+        .route("/delete-account", delete(delete_account))
+        .route("/login", post(login))
+        .route("/logout", post(logout))
+        .route("/logout-all", post(logout_all))
+        .route("/verify-2fa", post(verify_2fa))
+        .route("/enroll-totp", post(enroll_totp))
+        .route("/confirm-totp", post(confirm_totp))
+        .route(
+            "/recovery-codes",
+            get(get_recovery_codes).post(regenerate_recovery_codes),
+        )
+        .route("/passkeys/register/start", post(start_passkey_registration))
+        .route("/passkeys/register/finish", post(finish_passkey_registration))
+        .route("/passkeys/login/start", post(start_passkey_login))
+        .route("/passkeys/login/finish", post(finish_passkey_login))
+        .route("/passkeys/2fa/start", post(start_passkey_two_fa))
+        .route("/verify-email", get(verify_email))
+        .route("/verify-email/resend", post(resend_verification_email))
+        .route("/change-password", post(change_password))
+        .route("/change-email", post(change_email))
+        .route("/change-email/confirm", get(confirm_email_change))
+        .route("/password-reset/request", post(request_password_reset))
+        .route("/password-reset/confirm", post(confirm_password_reset))
+        .route("/refresh-token", post(refresh_token))
+        .route("/verify-token", post(verify_token))
+        .layer(middleware::from_fn_with_state(app_state.clone(), rate_limit))
+        .with_state(app_state)
+        .layer(cors()?)
+        .layer(
+            TraceLayer::new_for_http()
+                .make_span_with(make_span_with_request_id)
+                .on_request(on_request)
+                .on_response(on_response),
+        ))
+}
+
 fn cors() -> Result<CorsLayer, Box<dyn Error>> {
     let allowed_origins = &CORS_ALLOWED_ORIGINS;
     let origins: Result<Vec<_>, _> = allowed_origins.split(',').map(|origin| origin.trim().parse()).collect();
@@ -1,50 +1,112 @@
 use auth_service::app_state::AppState;
+use auth_service::domain::data_stores::ServiceCredentialStore;
+use auth_service::grpc::admin_service::create_admin_grpc_service;
 use auth_service::grpc::auth_service::create_grpc_service;
-use auth_service::services::data_stores::{PostgresUserStore, RedisBannedTokenStore, RedisTwoFACodeStore};
+use auth_service::grpc::health::{DependencyHealth, report_dependency_health};
+use auth_service::services::data_stores::{
+    PostgresPasskeyStore, PostgresRecoveryCodeStore, PostgresServiceCredentialStore, PostgresSessionEpochStore,
+    PostgresTotpStore, PostgresUserStore, RedisBannedTokenStore, RedisLoginLockoutStore, RedisPasskeyChallengeStore,
+    RedisRateLimitStore, RedisRefreshTokenStore, RedisSessionEpochCache, RedisTwoFACodeStore,
+};
 use auth_service::services::email::SesEmailClient;
-use auth_service::utils::{DATABASE_URL, REDIS_HOST_NAME, init_tracing, prod};
+use auth_service::utils::{
+    DATABASE_URL, GRPC_ADDRESS, HTTP_ADDRESS, REDIS_HOST_NAME, TLS_CONFIG, init_tracing, reload_jwt_key_ring,
+};
 use auth_service::{Application, get_postgres_pool, get_redis_client};
+use rand::Rng;
+use rand::distr::Alphanumeric;
+use secrecy::SecretBox;
 use sqlx::PgPool;
+use std::net::SocketAddr;
 use std::sync::Arc;
+use tokio::signal::unix::{SignalKind, signal};
 use tokio::sync::RwLock;
 use tonic::transport::Server;
 use tonic_reflection::server::Builder as ReflectionBuilder;
 
+const SERVICE_SECRET_LENGTH: usize = 48;
+
 #[tokio::main]
 async fn main() {
     color_eyre::install().expect("Failed to install color_eyre");
     init_tracing().expect("Failed to initialize tracing");
-    let redis_client = configure_redis().await;
+
+    let args: Vec<String> = std::env::args().collect();
+    if let [_, command, service_id] = args.as_slice()
+        && command == "register-service"
+    {
+        register_service(service_id).await;
+        return;
+    }
+
+    let (redis_client, redis_conn) = configure_redis().await;
     let ses_client = SesEmailClient::new("us-east-1", "auth@rustybootcamp.xyz".to_owned())
         .await
         .expect("SesEmailClient creation failed");
 
+    let pg_pool = configure_postgresql().await;
+
     let app_state = AppState::new(
-        Arc::new(RwLock::new(PostgresUserStore::new(configure_postgresql().await))),
-        Arc::new(RwLock::new(RedisBannedTokenStore::new(redis_client.clone()))),
-        Arc::new(RwLock::new(RedisTwoFACodeStore::new(redis_client))),
+        Arc::new(RwLock::new(PostgresUserStore::new(pg_pool.clone()))),
+        Arc::new(RwLock::new(RedisBannedTokenStore::new(
+            redis_conn.clone(),
+            redis_client,
+        ))),
+        Arc::new(RwLock::new(RedisRefreshTokenStore::new(redis_conn.clone()))),
+        Arc::new(RwLock::new(RedisSessionEpochCache::new(
+            redis_conn.clone(),
+            PostgresSessionEpochStore::new(pg_pool.clone()),
+        ))),
+        Arc::new(RwLock::new(RedisTwoFACodeStore::new(redis_conn.clone()))),
         Arc::new(RwLock::new(ses_client)),
+        Arc::new(RwLock::new(PostgresServiceCredentialStore::new(pg_pool.clone()))),
+        Arc::new(RwLock::new(PostgresTotpStore::new(pg_pool.clone()))),
+        Arc::new(RwLock::new(PostgresRecoveryCodeStore::new(pg_pool.clone()))),
+        Arc::new(RwLock::new(PostgresPasskeyStore::new(pg_pool.clone()))),
+        Arc::new(RwLock::new(RedisPasskeyChallengeStore::new(redis_conn.clone()))),
+        Arc::new(RwLock::new(RedisLoginLockoutStore::new(redis_conn.clone()))),
+        Arc::new(RwLock::new(RedisRateLimitStore::new(redis_conn.clone()))),
     );
 
-    let http_app = Application::build(app_state, prod::APP_ADDRESS)
-        .await
-        .expect("Failed to build app");
+    let grpc_service = create_grpc_service(app_state.clone());
+    let admin_grpc_service = create_admin_grpc_service(app_state.clone());
 
-    let grpc_service = create_grpc_service();
-    let grpc_addr = "0.0.0.0:50051".parse().unwrap(); // TODO: add error handling
+    let (health_reporter, health_service) = tonic_health::server::health_reporter();
+    let dependencies = DependencyHealth::new(pg_pool, redis_conn, app_state.email_client.clone());
+
+    let http_app = match TLS_CONFIG.as_ref() {
+        Some(tls) => Application::build_with_tls(app_state, &HTTP_ADDRESS, tls).await,
+        None => Application::build(app_state, &HTTP_ADDRESS).await,
+    }
+    .expect("Failed to build app");
+
+    let grpc_addr: SocketAddr = GRPC_ADDRESS.parse().expect("Invalid gRPC listen address");
+    let mut grpc_builder = Server::builder();
+    if let Some(tls) = TLS_CONFIG.as_ref() {
+        let tls_config = tls.grpc_config().await.expect("Failed to load the TLS certificate");
+        grpc_builder = grpc_builder
+            .tls_config(tls_config)
+            .expect("Failed to configure gRPC TLS");
+    }
 
     let reflection = ReflectionBuilder::configure()
         .register_encoded_file_descriptor_set(include_bytes!("../proto/proto_descriptor.bin"))
+        .register_encoded_file_descriptor_set(tonic_health::pb::FILE_DESCRIPTOR_SET)
         .build_v1()
         .expect("Failed to build a reflection service");
 
+    tokio::spawn(reload_keys_on_sighup());
+    tokio::spawn(report_dependency_health(health_reporter, dependencies));
+
     let http_server = tokio::spawn(async move {
         http_app.run().await.expect("Failed to run HTTP app");
     });
 
     let grpc_server = tokio::spawn(async move {
-        Server::builder()
+        grpc_builder
+            .add_service(health_service)
             .add_service(grpc_service)
+            .add_service(admin_grpc_service)
             .add_service(reflection)
             .serve(grpc_addr)
             .await
@@ -57,6 +119,36 @@ async fn main() {
     }
 }
 
+// Rotating keys: update the config and send SIGHUP, tokens signed by retired keys stay valid
+async fn reload_keys_on_sighup() {
+    let mut hangup = signal(SignalKind::hangup()).expect("Failed to install the SIGHUP handler");
+
+    while hangup.recv().await.is_some() {
+        match reload_jwt_key_ring() {
+            Ok(()) => tracing::info!("JWT key ring reloaded"),
+            Err(e) => tracing::error!("Failed to reload the JWT key ring, keeping the current one: {:?}", e),
+        }
+    }
+}
+
+// `auth-service register-service <service-id>` registers an internal service allowed to call the
+// admin gRPC service and prints its secret once, only the hash is stored
+async fn register_service(service_id: &str) {
+    let pg_pool = configure_postgresql().await;
+    let secret: String = rand::rng()
+        .sample_iter(&Alphanumeric)
+        .take(SERVICE_SECRET_LENGTH)
+        .map(char::from)
+        .collect();
+
+    PostgresServiceCredentialStore::new(pg_pool)
+        .add_service(service_id, SecretBox::new(Box::from(secret.clone())))
+        .await
+        .expect("Failed to register the service");
+
+    println!("Service {} registered, its secret is: {}", service_id, secret);
+}
+
 async fn configure_postgresql() -> PgPool {
     let pg_pool = get_postgres_pool(&DATABASE_URL)
         .await
@@ -67,11 +159,13 @@ async fn configure_postgresql() -> PgPool {
     pg_pool
 }
 
-async fn configure_redis() -> redis::aio::MultiplexedConnection {
+async fn configure_redis() -> (redis::Client, redis::aio::MultiplexedConnection) {
     let client = get_redis_client(REDIS_HOST_NAME.to_owned()).expect("Failed to get a Redis client");
 
-    client
+    let conn = client
         .get_multiplexed_async_connection()
         .await
-        .expect("Failed to create Redis connection manager")
+        .expect("Failed to create Redis connection manager");
+
+    (client, conn)
 }
@@ -0,0 +1,168 @@
+use crate::app_state::AppState;
+use crate::domain::data_stores::{TotpStoreError, UserStoreError};
+use crate::domain::{AuthAPIError, Email, Password, UserId};
+use crate::routes::{totp_cipher, validate_email, verify_current_password};
+use crate::utils::{
+    AuthenticatedUser, ClientIp, EMAIL_CHANGE_TOKEN_TTL_SECONDS, EMAIL_CHANGE_URL, TokenType, authorize_token, email,
+    generate_email_change_token,
+};
+use axum::Json;
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use secrecy::{ExposeSecret, SecretBox};
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize)]
+pub struct ChangeEmailRequest {
+    #[serde(rename = "newEmail")]
+    pub new_email: String,
+    pub password: SecretBox<String>,
+}
+
+#[derive(Deserialize)]
+pub struct ConfirmEmailChangeQuery {
+    pub token: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub struct ChangeEmailResponse {
+    pub message: String,
+}
+
+// The password is asked again and nothing changes yet: the new address gets a confirmation link,
+// the current one a notice in case someone else holds a session.
+#[tracing::instrument(name = "ChangeEmail", skip_all)]
+pub async fn change_email(
+    State(state): State<AppState>,
+    ClientIp(client_ip): ClientIp,
+    caller: AuthenticatedUser,
+    Json(request): Json<ChangeEmailRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let password = Password::new(request.password).map_err(|_| AuthAPIError::IncorrectCredentials)?;
+    verify_current_password(&state, &caller.email, &password, Some(client_ip)).await?;
+
+    let new_email = validate_email(&request.new_email)?;
+    let new_email = Email::new(SecretBox::new(Box::from(new_email.to_owned())))?;
+    match state.user_store.read().await.get_user(&new_email).await {
+        Ok(_) => return Err(AuthAPIError::UserAlreadyExists),
+        Err(UserStoreError::UserNotFound) => (),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+
+    let token = generate_email_change_token(&caller.user_id, &new_email, caller.claims.epoch)?;
+    let separator = if EMAIL_CHANGE_URL.contains('?') { '&' } else { '?' };
+    let content = format!(
+        "Open {}{}token={} within {} minutes to use this address for your account.",
+        *EMAIL_CHANGE_URL,
+        separator,
+        token,
+        *EMAIL_CHANGE_TOKEN_TTL_SECONDS / 60
+    );
+    state
+        .email_client
+        .read()
+        .await
+        .send_email(&new_email, email::EMAIL_CHANGE_SUBJECT, &content)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    notify_email_change(&state, &caller.email, &new_email).await;
+
+    let response = Json(ChangeEmailResponse {
+        message: "A confirmation link has been sent to the new address".to_string(),
+    });
+    Ok((StatusCode::ACCEPTED, response))
+}
+
+// The link sent to the new address. The account keeps its id, so the sessions and the tokens
+// already handed to other services stay valid.
+#[tracing::instrument(name = "ConfirmEmailChange", skip_all)]
+pub async fn confirm_email_change(
+    State(state): State<AppState>,
+    Query(query): Query<ConfirmEmailChangeQuery>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let claims = authorize_token(&state, &query.token, TokenType::EmailChange).await?;
+    let user_id = claims.user_id().map_err(|_| AuthAPIError::TokenNotValid)?;
+    let new_email = claims.email().map_err(|_| AuthAPIError::TokenNotValid)?;
+    let email = match state.user_store.read().await.get_user_by_id(&user_id).await {
+        Ok(user) => user.email().clone(),
+        Err(UserStoreError::UserNotFound) => return Err(AuthAPIError::TokenNotValid),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    };
+    let totp_secret = reencrypt_totp_secret(&state, &user_id, &email, &new_email).await?;
+
+    match state
+        .user_store
+        .write()
+        .await
+        .update_email(&user_id, new_email, totp_secret)
+        .await
+    {
+        Ok(()) => (),
+        Err(UserStoreError::UserAlreadyExists) => return Err(AuthAPIError::UserAlreadyExists),
+        Err(UserStoreError::UserNotFound) => return Err(AuthAPIError::TokenNotValid),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+
+    // The link works once, replayed after a later change it would move the account back
+    state
+        .banned_token_store
+        .write()
+        .await
+        .store_token(&query.token)
+        .await
+        .map_err(|_| AuthAPIError::ErrorAddingToBannedTokens)?;
+
+    let response = Json(ChangeEmailResponse {
+        message: "Email changed".to_string(),
+    });
+    Ok((StatusCode::OK, response))
+}
+
+// The authenticator secret is bound to the address it is encrypted with, it has to be sealed again
+// for the new one or the next TOTP check fails to decrypt it
+async fn reencrypt_totp_secret(
+    state: &AppState,
+    user_id: &UserId,
+    email: &Email,
+    new_email: &Email,
+) -> Result<Option<String>, AuthAPIError> {
+    let enrollment = match state.totp_store.read().await.get_enrollment(user_id).await {
+        Ok(enrollment) => enrollment,
+        Err(TotpStoreError::NotEnrolled) => return Ok(None),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    };
+
+    let cipher = totp_cipher()?;
+    let secret = cipher
+        .decrypt(&enrollment.encrypted_secret, email.as_ref().expose_secret())
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    let encrypted_secret = cipher
+        .encrypt(&secret, new_email.as_ref().expose_secret())
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    Ok(Some(encrypted_secret))
+}
+
+// The confirmation link is already out, a failed notice is only logged
+async fn notify_email_change(
+    state: &AppState,
+    email: &Email,
+    new_email: &Email,
+) {
+    let content = format!(
+        "Moving the account {} to {} has been requested. If it wasn't you, reset your password and log out everywhere.",
+        email.as_ref().expose_secret(),
+        new_email.as_ref().expose_secret()
+    );
+
+    if let Err(e) = state
+        .email_client
+        .read()
+        .await
+        .send_email(email, email::EMAIL_CHANGE_NOTICE_SUBJECT, &content)
+        .await
+    {
+        tracing::warn!("Failed to send the email change notice: {:?}", e);
+    }
+}
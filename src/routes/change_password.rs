@@ -0,0 +1,86 @@
+use crate::app_state::AppState;
+use crate::domain::data_stores::UserStoreError;
+use crate::domain::{AuthAPIError, Email, Password};
+use crate::routes::verify_current_password;
+use crate::utils::{AuthenticatedUser, ClientIp, PASSWORD_POLICY, email};
+use axum::Json;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use secrecy::{ExposeSecret, SecretBox};
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize)]
+pub struct ChangePasswordRequest {
+    #[serde(rename = "currentPassword")]
+    pub current_password: SecretBox<String>,
+    #[serde(rename = "newPassword")]
+    pub new_password: SecretBox<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub struct ChangePasswordResponse {
+    pub message: String,
+}
+
+// The access token alone isn't enough, the current password is asked again. The sessions are
+// kept, the owner is told by email in case someone else holds one of them.
+#[tracing::instrument(name = "ChangePassword", skip_all)]
+pub async fn change_password(
+    State(state): State<AppState>,
+    ClientIp(client_ip): ClientIp,
+    caller: AuthenticatedUser,
+    Json(request): Json<ChangePasswordRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let current_password = Password::new(request.current_password).map_err(|_| AuthAPIError::IncorrectCredentials)?;
+    verify_current_password(&state, &caller.email, &current_password, Some(client_ip)).await?;
+
+    PASSWORD_POLICY.check(
+        request.new_password.expose_secret(),
+        caller.email.as_ref().expose_secret(),
+    )?;
+    let new_password = Password::new(request.new_password)?;
+    if new_password.normalized() == current_password.normalized() {
+        return Err(AuthAPIError::PasswordReused);
+    }
+
+    match state
+        .user_store
+        .write()
+        .await
+        .update_password(&caller.email, new_password)
+        .await
+    {
+        Ok(()) => (),
+        Err(UserStoreError::UserNotFound) => return Err(AuthAPIError::TokenNotValid),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+
+    notify_password_changed(&state, &caller.email).await;
+
+    let response = Json(ChangePasswordResponse {
+        message: "Password updated".to_string(),
+    });
+    Ok((StatusCode::OK, response))
+}
+
+// The password is already changed, a failed notice is only logged
+async fn notify_password_changed(
+    state: &AppState,
+    email: &Email,
+) {
+    let content = format!(
+        "The password of {} has just been changed. If it wasn't you, reset it and log out everywhere.",
+        email.as_ref().expose_secret()
+    );
+
+    if let Err(e) = state
+        .email_client
+        .read()
+        .await
+        .send_email(email, email::PASSWORD_CHANGED_SUBJECT, &content)
+        .await
+    {
+        tracing::warn!("Failed to send the password change notification: {:?}", e);
+    }
+}
@@ -0,0 +1,108 @@
+use crate::app_state::AppState;
+use crate::domain::data_stores::TotpStoreError;
+use crate::domain::{AuthAPIError, Email, UserId};
+use crate::routes::{RecoveryCodesResponse, issue_recovery_codes, validate_two_fa_code};
+use crate::utils::{AuthenticatedUser, TOTP_CIPHER, TOTP_DRIFT_STEPS, TotpCipher};
+use axum::Json;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use color_eyre::eyre::eyre;
+use secrecy::ExposeSecret;
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+pub struct ConfirmTotpRequest {
+    pub code: String,
+}
+
+// A first valid code proves the authenticator app holds the secret, from then on the logins
+// of the user ask for a TOTP code instead of emailing one. A new set of recovery codes comes with it.
+#[tracing::instrument(name = "ConfirmTotp", skip_all)]
+pub async fn confirm_totp(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Json(request): Json<ConfirmTotpRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let code = validate_two_fa_code(&request.code)?;
+    confirm_totp_enrollment(&state, &user.user_id, &user.email, code).await?;
+
+    let recovery_codes = issue_recovery_codes(&state, &user.user_id).await?;
+    Ok((StatusCode::OK, Json(RecoveryCodesResponse { recovery_codes })))
+}
+
+pub(crate) async fn confirm_totp_enrollment(
+    state: &AppState,
+    user_id: &UserId,
+    email: &Email,
+    code: &str,
+) -> Result<(), AuthAPIError> {
+    if totp_enabled(state, user_id).await? {
+        return Err(AuthAPIError::TotpAlreadyEnabled);
+    }
+
+    verify_totp_code(state, user_id, email, code).await?;
+
+    state
+        .totp_store
+        .write()
+        .await
+        .confirm(user_id)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))
+}
+
+// Check the code against the authenticator secret of the user and burn its time step,
+// a code is accepted once even though it stays valid for the whole drift window. The secret is
+// stored for the user id but sealed with the current email.
+pub(crate) async fn verify_totp_code(
+    state: &AppState,
+    user_id: &UserId,
+    email: &Email,
+    code: &str,
+) -> Result<(), AuthAPIError> {
+    let enrollment = match state.totp_store.read().await.get_enrollment(user_id).await {
+        Ok(enrollment) => enrollment,
+        Err(TotpStoreError::NotEnrolled) => return Err(AuthAPIError::TotpNotEnrolled),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    };
+
+    let secret = totp_cipher()?
+        .decrypt(&enrollment.encrypted_secret, email.as_ref().expose_secret())
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    let now = chrono::Utc::now().timestamp() as u64;
+    let time_step = secret
+        .matching_step(code, now, *TOTP_DRIFT_STEPS)
+        .ok_or(AuthAPIError::IncorrectCredentials)?;
+
+    match state
+        .totp_store
+        .write()
+        .await
+        .use_time_step(user_id, time_step as i64)
+        .await
+    {
+        Ok(_) => Ok(()),
+        Err(TotpStoreError::TimeStepAlreadyUsed) => Err(AuthAPIError::IncorrectCredentials),
+        Err(TotpStoreError::NotEnrolled) => Err(AuthAPIError::TotpNotEnrolled),
+        Err(e) => Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+}
+
+// Only a confirmed enrollment counts, a pending one doesn't change how the user logs in
+pub(crate) async fn totp_enabled(
+    state: &AppState,
+    user_id: &UserId,
+) -> Result<bool, AuthAPIError> {
+    match state.totp_store.read().await.get_enrollment(user_id).await {
+        Ok(enrollment) => Ok(enrollment.confirmed),
+        Err(TotpStoreError::NotEnrolled) => Ok(false),
+        Err(e) => Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+}
+
+pub(crate) fn totp_cipher() -> Result<&'static TotpCipher, AuthAPIError> {
+    TOTP_CIPHER
+        .as_ref()
+        .map_err(|e| AuthAPIError::UnexpectedError(eyre!("TOTP is unavailable: {}", e)))
+}
@@ -1,43 +1,167 @@
 use crate::app_state::AppState;
-use crate::domain::{AuthAPIError, Email};
+use crate::domain::data_stores::{Revocation, SessionEpochStoreError};
+use crate::domain::{AuthAPIError, Email, LoginAttemptId, Password, TwoFAMethod};
+use crate::routes::{Reauthentication, SecondFactor, TwoFactorAuthResponse, reauthenticate};
+use crate::utils::{AuthenticatedUser, ClientIp, remove_token_cookies, revoke_user_sessions};
 use axum::Json;
 use axum::extract::State;
 use axum::http::StatusCode;
-use axum::response::IntoResponse;
-use color_eyre::eyre::eyre;
-use secrecy::SecretBox;
-use serde::{Deserialize, Serialize};
+use axum::response::{IntoResponse, Response};
+use axum_extra::extract::CookieJar;
+use secrecy::{ExposeSecret, SecretBox};
+use serde::Deserialize;
+use std::net::IpAddr;
 
 #[derive(Deserialize)]
 pub struct DeleteRequest {
     pub email: String,
-}
+    pub password: SecretBox<String>,
+
+    #[serde(rename = "loginAttemptId")]
+    pub login_attempt_id: Option<String>,
 
-#[derive(Debug, Serialize, Deserialize, Eq, PartialEq)]
-pub struct DeleteResponse {
-    pub message: String,
+    #[serde(rename = "2FACode")]
+    pub two_fa_code: Option<String>,
 }
 
+// Deleting an account needs the access token of that account and the current password.
+// Accounts with 2FA get a challenge on the first call (206), a code is emailed unless they use an
+// authenticator app, and confirm with the code on the second one.
 #[tracing::instrument(name = "DeleteAccount", skip_all)]
 pub async fn delete_account(
     State(state): State<AppState>,
+    ClientIp(client_ip): ClientIp,
+    caller: AuthenticatedUser,
+    jar: CookieJar,
     Json(request): Json<DeleteRequest>,
-) -> Result<impl IntoResponse, AuthAPIError> {
-    let mut user_store = state.user_store.write().await;
-
+) -> Result<(CookieJar, Response), AuthAPIError> {
     let email = Email::new(SecretBox::new(Box::from(request.email)))?;
-    match user_store.delete_account(&email).await {
-        Ok(_) => {}
-        Err(_) => {
-            return Err(AuthAPIError::UnexpectedError(eyre!(
-                "Unexpected error deleting an account"
-            )));
+    if email != caller.email {
+        return Err(AuthAPIError::Forbidden);
+    }
+
+    let password = Password::new(request.password).map_err(|_| AuthAPIError::IncorrectCredentials)?;
+
+    let outcome = delete_confirmed_account(
+        &state,
+        &caller,
+        &password,
+        Some(client_ip),
+        request.login_attempt_id.as_deref(),
+        request.two_fa_code.as_deref(),
+    )
+    .await?;
+
+    match outcome {
+        DeleteAccountOutcome::Deleted => Ok((remove_token_cookies(jar), StatusCode::NO_CONTENT.into_response())),
+        DeleteAccountOutcome::TwoFactorRequired(login_attempt_id, method) => {
+            let response = Json(TwoFactorAuthResponse {
+                message: "2FA required".to_string(),
+                login_attempt_id: login_attempt_id.id().expose_secret().clone(),
+                method,
+            });
+            Ok((jar, (StatusCode::PARTIAL_CONTENT, response).into_response()))
+        }
+    }
+}
+
+pub(crate) enum DeleteAccountOutcome {
+    Deleted,
+    TwoFactorRequired(LoginAttemptId, TwoFAMethod),
+}
+
+// Delete the caller's account once the password (and the 2FA code when enabled) is confirmed.
+// Without a code a 2FA account gets challenged and nothing is deleted yet.
+pub(crate) async fn delete_confirmed_account(
+    state: &AppState,
+    caller: &AuthenticatedUser,
+    password: &Password,
+    client_ip: Option<IpAddr>,
+    login_attempt_id: Option<&str>,
+    two_fa_code: Option<&str>,
+) -> Result<DeleteAccountOutcome, AuthAPIError> {
+    let email = &caller.email;
+
+    let second_factor = two_fa_code.map(SecondFactor::Code);
+    if let Reauthentication::TwoFactorRequired(login_attempt_id, method) =
+        reauthenticate(state, email, password, client_ip, login_attempt_id, second_factor).await?
+    {
+        return Ok(DeleteAccountOutcome::TwoFactorRequired(login_attempt_id, method));
+    }
+
+    // Read before the row goes, the watchers are told about the epoch after it
+    let session_epoch = state
+        .session_epoch_store
+        .read()
+        .await
+        .get_epoch(&caller.user_id)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    // Nothing is cleaned up before the account is gone, a failed deletion leaves it as it was
+    state
+        .user_store
+        .write()
+        .await
+        .delete_account(email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    revoke_sessions(state, caller, session_epoch).await?;
+
+    Ok(DeleteAccountOutcome::Deleted)
+}
+
+// Bumping the session epoch kills every access and refresh token of the user, when the epoch
+// lived in the deleted row its tokens already fail the check and only the watchers are told.
+// The pending 2FA code and the authenticator secret go away with the account.
+async fn revoke_sessions(
+    state: &AppState,
+    caller: &AuthenticatedUser,
+    session_epoch: i64,
+) -> Result<(), AuthAPIError> {
+    let email = &caller.email;
+    match revoke_user_sessions(state, &caller.user_id).await {
+        Ok(_) => (),
+        Err(SessionEpochStoreError::UserNotFound) => {
+            let revocation = Revocation::UserSessions {
+                subject: caller.user_id.to_string(),
+                session_epoch: session_epoch + 1,
+            };
+            if let Err(e) = state
+                .banned_token_store
+                .read()
+                .await
+                .publish_revocation(revocation)
+                .await
+            {
+                tracing::warn!("Failed to publish a user sessions revocation: {:?}", e);
+            }
         }
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
     }
 
-    let response = Json(DeleteResponse {
-        message: "Account deleted".to_string(),
-    });
+    state
+        .banned_token_store
+        .write()
+        .await
+        .store_token(&caller.token)
+        .await
+        .map_err(|_| AuthAPIError::ErrorAddingToBannedTokens)?;
+
+    state
+        .two_fa_code_store
+        .write()
+        .await
+        .remove_code(email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
 
-    Ok((StatusCode::NO_CONTENT, response))
+    state
+        .totp_store
+        .write()
+        .await
+        .remove(&caller.user_id)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))
 }
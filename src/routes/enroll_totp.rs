@@ -0,0 +1,59 @@
+use crate::app_state::AppState;
+use crate::domain::data_stores::TotpStoreError;
+use crate::domain::{AuthAPIError, Email, TotpSecret, UserId};
+use crate::routes::totp_cipher;
+use crate::utils::{AuthenticatedUser, TOTP_ISSUER};
+use axum::Json;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use secrecy::ExposeSecret;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EnrollTotpResponse {
+    pub secret: String,
+    #[serde(rename = "otpauthUri")]
+    pub otpauth_uri: String,
+}
+
+// Hands out a new authenticator secret, it only protects the logins once `/confirm-totp`
+// receives a first valid code. Enrolling again before that replaces the pending secret.
+#[tracing::instrument(name = "EnrollTotp", skip_all)]
+pub async fn enroll_totp(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let response = start_totp_enrollment(&state, &user.user_id, &user.email).await?;
+    Ok((StatusCode::OK, Json(response)))
+}
+
+pub(crate) async fn start_totp_enrollment(
+    state: &AppState,
+    user_id: &UserId,
+    email: &Email,
+) -> Result<EnrollTotpResponse, AuthAPIError> {
+    let account_name = email.as_ref().expose_secret();
+    let secret = TotpSecret::default();
+    let otpauth_uri = secret
+        .otpauth_uri(&TOTP_ISSUER, account_name)
+        .map_err(AuthAPIError::UnexpectedError)?;
+    let encrypted_secret = totp_cipher()?
+        .encrypt(&secret, account_name)
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    match state
+        .totp_store
+        .write()
+        .await
+        .set_pending_secret(user_id, encrypted_secret)
+        .await
+    {
+        Ok(_) => Ok(EnrollTotpResponse {
+            secret: secret.to_base32(),
+            otpauth_uri,
+        }),
+        Err(TotpStoreError::AlreadyEnabled) => Err(AuthAPIError::TotpAlreadyEnabled),
+        Err(e) => Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+}
@@ -0,0 +1,13 @@
+use crate::utils::jwt_key_ring;
+use axum::Json;
+use axum::http::header;
+use axum::response::IntoResponse;
+
+// Publishes the public keys of the key ring, current and retired, so other services can verify our tokens offline
+#[tracing::instrument(name = "Jwks", skip_all)]
+pub async fn jwks() -> impl IntoResponse {
+    (
+        [(header::CACHE_CONTROL, "public, max-age=300")],
+        Json(jwt_key_ring().jwks()),
+    )
+}
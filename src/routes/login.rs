@@ -1,7 +1,11 @@
 use crate::app_state::AppState;
 use crate::domain::data_stores::UserStoreError;
-use crate::domain::{AuthAPIError, Email, LoginAttemptId, Password, TwoFACode};
-use crate::utils::{email, generate_auth_cookie, generate_refresh_cookie};
+use crate::domain::{AuthAPIError, Email, LoginAttemptId, Password, TwoFACode, TwoFAMethod, User};
+use crate::routes::{ensure_email_verified, totp_enabled, validate_two_fa_code, verify_totp_code};
+use crate::utils::{
+    ALLOW_UNVERIFIED_LOGIN, ClientIp, TokenPair, add_token_cookies, clear_failed_logins, email, ensure_login_allowed,
+    issue_token_pair, record_failed_login,
+};
 use axum::Json;
 use axum::extract::State;
 use axum::http::StatusCode;
@@ -10,11 +14,16 @@ use axum_extra::extract::CookieJar;
 use color_eyre::eyre::eyre;
 use secrecy::{ExposeSecret, SecretBox};
 use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
 
 #[derive(Deserialize)]
 pub struct LoginRequest {
     pub email: String,
     pub password: SecretBox<String>,
+
+    // Authenticator users can log in with a single call by sending their current code
+    #[serde(rename = "totpCode")]
+    pub totp_code: Option<String>,
 }
 
 // The login route can return 2 possible success responses.
@@ -32,52 +41,144 @@ pub struct TwoFactorAuthResponse {
     pub message: String,
     #[serde(rename = "loginAttemptId")]
     pub login_attempt_id: String,
+    pub method: TwoFAMethod,
 }
 
 #[tracing::instrument(name = "Login", skip_all)]
 pub async fn login(
     State(state): State<AppState>,
+    ClientIp(client_ip): ClientIp,
     jar: CookieJar,
     Json(request): Json<LoginRequest>,
 ) -> Result<(StatusCode, CookieJar, impl IntoResponse), AuthAPIError> {
     // TODO: move this validation other part of the code
-    if request.email.is_empty() || request.password.expose_secret().len() < 8 || !request.email.contains('@') {
+    if request.email.is_empty() || !request.email.contains('@') {
         return Err(AuthAPIError::EmailOrPasswordIncorrect);
     }
 
-    let store = &state.user_store.read().await;
+    // Only the hard bounds, a password chosen under an older policy still logs in
     let email = &Email::new(SecretBox::new(Box::from(request.email)))?;
-    let password = &Password::new(request.password)?;
-    match store.validate_user(email, password).await {
-        Ok(_) => (),
-        Err(_) => return Err(AuthAPIError::IncorrectCredentials),
+    let password = &Password::new(request.password).map_err(|_| AuthAPIError::EmailOrPasswordIncorrect)?;
+
+    match authenticate_user(&state, email, password, request.totp_code.as_deref(), Some(client_ip)).await? {
+        LoginOutcome::Authenticated(token_pair) => Ok((
+            StatusCode::OK,
+            add_token_cookies(jar, &token_pair),
+            Json(LoginResponse::RegularAuth),
+        )),
+        LoginOutcome::TwoFactorRequired(login_attempt_id, method) => Ok((
+            StatusCode::PARTIAL_CONTENT,
+            jar,
+            Json(LoginResponse::TwoFactorAuth(TwoFactorAuthResponse {
+                message: "2FA required".to_string(),
+                login_attempt_id: login_attempt_id.id().expose_secret().clone(),
+                method,
+            })),
+        )),
     }
+}
 
-    let user = match store.get_user(email).await {
-        Ok(user) => user,
-        Err(e) => {
-            return match e {
-                UserStoreError::UserAlreadyExists => Err(AuthAPIError::UserAlreadyExists),
-                UserStoreError::UserNotFound => Err(AuthAPIError::UnexpectedError(eyre!("User didn't find in store"))),
-                UserStoreError::IncorrectCredentials => Err(AuthAPIError::IncorrectCredentials),
-                UserStoreError::UnexpectedError(e) => Err(AuthAPIError::UnexpectedError(e.into())),
-            };
+pub(crate) enum LoginOutcome {
+    Authenticated(TokenPair),
+    TwoFactorRequired(LoginAttemptId, TwoFAMethod),
+}
+
+// Check the credentials, then either issue tokens or start the 2FA challenge.
+// Shared by the HTTP and gRPC logins, the caller decides how to hand out the tokens.
+// Wrong passwords, and wrong single-call TOTP codes, are counted per account and per `client_ip`,
+// see `record_failed_login`.
+pub(crate) async fn authenticate_user(
+    state: &AppState,
+    email: &Email,
+    password: &Password,
+    totp_code: Option<&str>,
+    client_ip: Option<IpAddr>,
+) -> Result<LoginOutcome, AuthAPIError> {
+    ensure_login_allowed(state, email, client_ip).await?;
+
+    let user = {
+        let store = state.user_store.read().await;
+        match store.validate_user(email, password).await {
+            Ok(_) => (),
+            Err(UserStoreError::UnexpectedError(e)) => return Err(AuthAPIError::UnexpectedError(e)),
+            Err(e) => {
+                let account_exists = e == UserStoreError::IncorrectCredentials;
+                return Err(record_failed_login(state, email, client_ip, account_exists).await);
+            }
+        }
+
+        match store.get_user(email).await {
+            Ok(user) => user,
+            Err(e) => {
+                return match e {
+                    UserStoreError::UserAlreadyExists => Err(AuthAPIError::UserAlreadyExists),
+                    UserStoreError::UserNotFound => {
+                        Err(AuthAPIError::UnexpectedError(eyre!("User didn't find in store")))
+                    }
+                    UserStoreError::IncorrectCredentials => Err(AuthAPIError::IncorrectCredentials),
+                    UserStoreError::UnexpectedError(e) => Err(AuthAPIError::UnexpectedError(e)),
+                };
+            }
         }
     };
+    ensure_email_verified(&user, *ALLOW_UNVERIFIED_LOGIN)?;
 
-    if user.requires_2fa() {
-        return handle_2fa(&user.email(), &state, jar).await;
+    // The failed logins are only cleared once the second factor is through as well, a right
+    // password alone would otherwise reset the counter between guesses of the code
+    let Some(method) = two_fa_method(state, &user).await? else {
+        clear_failed_logins(state, email).await?;
+        return Ok(LoginOutcome::Authenticated(issue_token_pair(state, email).await?));
+    };
+
+    if method == TwoFAMethod::Totp
+        && let Some(totp_code) = totp_code
+    {
+        // No pending attempt to count against here, a wrong code counts like a wrong password
+        match verify_totp_code(state, user.id(), email, validate_two_fa_code(totp_code)?).await {
+            Ok(()) => (),
+            Err(AuthAPIError::IncorrectCredentials) => {
+                return Err(record_failed_login(state, email, client_ip, true).await);
+            }
+            Err(e) => return Err(e),
+        }
+        clear_failed_logins(state, email).await?;
+        return Ok(LoginOutcome::Authenticated(issue_token_pair(state, email).await?));
     }
 
-    handle_no_2fa(&user.email(), jar).await
+    let login_attempt_id = start_two_fa_challenge(email, state, method).await?;
+    Ok(LoginOutcome::TwoFactorRequired(login_attempt_id, method))
 }
 
-async fn handle_2fa(
+// An enabled authenticator app takes over from the emailed codes
+pub(crate) async fn two_fa_method(
+    state: &AppState,
+    user: &User,
+) -> Result<Option<TwoFAMethod>, AuthAPIError> {
+    if totp_enabled(state, user.id()).await? {
+        return Ok(Some(TwoFAMethod::Totp));
+    }
+
+    Ok(user.requires_2fa().then_some(TwoFAMethod::Email))
+}
+
+// Open a 2FA attempt. Authenticator users get nothing sent, the code they will type is checked
+// against their TOTP secret and the stored one is never used.
+pub(crate) async fn start_two_fa_challenge(
     email: &Email,
     state: &AppState,
-    jar: CookieJar,
-) -> Result<(StatusCode, CookieJar, Json<LoginResponse>), AuthAPIError> {
-    let login_attempt_id = LoginAttemptId::default();
+    method: TwoFAMethod,
+) -> Result<LoginAttemptId, AuthAPIError> {
+    match method {
+        TwoFAMethod::Email => send_two_fa_code(email, state).await,
+        TwoFAMethod::Totp => store_login_attempt(email, state, TwoFACode::default()).await,
+    }
+}
+
+// Email a new 2FA code to the user and keep it with the attempt it belongs to
+pub(crate) async fn send_two_fa_code(
+    email: &Email,
+    state: &AppState,
+) -> Result<LoginAttemptId, AuthAPIError> {
     let two_fa_code = TwoFACode::default();
 
     match state
@@ -97,6 +198,16 @@ async fn handle_2fa(
         }
     }
 
+    store_login_attempt(email, state, two_fa_code).await
+}
+
+async fn store_login_attempt(
+    email: &Email,
+    state: &AppState,
+    two_fa_code: TwoFACode,
+) -> Result<LoginAttemptId, AuthAPIError> {
+    let login_attempt_id = LoginAttemptId::default();
+
     match state
         .two_fa_code_store
         .write()
@@ -104,26 +215,7 @@ async fn handle_2fa(
         .add_code(email, login_attempt_id.clone(), two_fa_code)
         .await
     {
-        Ok(_) => Ok((
-            StatusCode::PARTIAL_CONTENT,
-            jar,
-            Json(LoginResponse::TwoFactorAuth(TwoFactorAuthResponse {
-                message: "2FA required".to_string(),
-                login_attempt_id: login_attempt_id.id().expose_secret().clone(),
-            })),
-        )),
+        Ok(_) => Ok(login_attempt_id),
         Err(e) => Err(AuthAPIError::UnexpectedError(e.into())),
     }
 }
-
-async fn handle_no_2fa(
-    email: &Email,
-    jar: CookieJar,
-) -> Result<(StatusCode, CookieJar, Json<LoginResponse>), AuthAPIError> {
-    Ok((
-        StatusCode::OK,
-        jar.add(generate_auth_cookie(&email)?)
-            .add(generate_refresh_cookie(&email)?),
-        Json(LoginResponse::RegularAuth),
-    ))
-}
@@ -1,36 +1,68 @@
 use crate::app_state::AppState;
 use crate::domain::AuthAPIError;
-use crate::utils::{JWT_COOKIE_NAME, validate_token};
+use crate::utils::{AuthenticatedUser, JWT_REFRESH_COOKIE_NAME, TokenType, remove_token_cookies, validate_token};
 use axum::extract::State;
 use axum::http::StatusCode;
-use axum::response::IntoResponse;
 use axum_extra::extract::CookieJar;
-use axum_extra::extract::cookie::Cookie;
 
+// The access token is optional, once it expired the `jwt-refresh` cookie alone still ends the
+// session. Whatever the outcome the cookies are cleared, the client is logged out on its side.
 #[tracing::instrument(name = "Logout", skip_all)]
 pub async fn logout(
     State(state): State<AppState>,
+    user: Result<AuthenticatedUser, AuthAPIError>,
     jar: CookieJar,
-) -> Result<(CookieJar, impl IntoResponse), AuthAPIError> {
-    match jar.get(JWT_COOKIE_NAME) {
-        Some(cookie) => {
-            validate_token(&cookie.value().to_string())
-                .await
-                .map_err(|_| AuthAPIError::TokenNotValid)?;
-            match state
-                .banned_token_store
-                .write()
-                .await
-                .store_token(&cookie.value().to_string())
-                .await
-            {
-                Ok(_) => {
-                    let jar = jar.remove(Cookie::build(JWT_COOKIE_NAME));
-                    Ok((jar, StatusCode::OK.into_response()))
-                }
-                Err(_) => Err(AuthAPIError::ErrorAddingToBannedTokens),
-            }
-        }
-        None => Err(AuthAPIError::MissingToken),
+) -> (CookieJar, Result<StatusCode, AuthAPIError>) {
+    let refresh_token = jar.get(JWT_REFRESH_COOKIE_NAME).map(|cookie| cookie.value().to_owned());
+    let result = match (user, refresh_token) {
+        (Ok(user), refresh_token) => end_session(&state, Some(&user), refresh_token.as_deref()).await,
+        (Err(_), Some(refresh_token)) => end_session(&state, None, Some(&refresh_token)).await,
+        (Err(e), None) => Err(e),
+    };
+
+    (remove_token_cookies(jar), result.map(|_| StatusCode::OK))
+}
+
+// Ban the access token and revoke the refresh token family of the session, it would otherwise
+// keep minting access tokens. The refresh token only matters when it comes from another family
+// than the access token, or when there is no valid access token left. It has to belong to the
+// same user, one user can't log another one out with a leaked refresh token.
+pub(crate) async fn end_session(
+    state: &AppState,
+    user: Option<&AuthenticatedUser>,
+    refresh_token: Option<&str>,
+) -> Result<(), AuthAPIError> {
+    let mut family_ids = Vec::new();
+    if let Some(user) = user {
+        state
+            .banned_token_store
+            .write()
+            .await
+            .store_token(&user.token)
+            .await
+            .map_err(|_| AuthAPIError::ErrorAddingToBannedTokens)?;
+        family_ids.push(user.claims.fid.clone());
+    }
+
+    if let Some(refresh_token) = refresh_token
+        && let Ok(refresh_claims) = validate_token(refresh_token, TokenType::Refresh)
+        && user.is_none_or(|user| refresh_claims.user_id().is_ok_and(|user_id| user_id == user.user_id))
+        && !family_ids.contains(&refresh_claims.fid)
+    {
+        family_ids.push(refresh_claims.fid);
     }
+
+    if family_ids.is_empty() {
+        return Err(AuthAPIError::TokenNotValid);
+    }
+
+    let mut refresh_token_store = state.refresh_token_store.write().await;
+    for family_id in &family_ids {
+        refresh_token_store
+            .revoke_family(family_id)
+            .await
+            .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    }
+
+    Ok(())
 }
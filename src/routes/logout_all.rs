@@ -0,0 +1,22 @@
+use crate::app_state::AppState;
+use crate::domain::AuthAPIError;
+use crate::domain::data_stores::SessionEpochStoreError;
+use crate::utils::{AuthenticatedUser, remove_token_cookies, revoke_user_sessions};
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum_extra::extract::CookieJar;
+
+// Bumping the session epoch invalidates every access and refresh token issued to the user so far
+#[tracing::instrument(name = "LogoutAll", skip_all)]
+pub async fn logout_all(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    jar: CookieJar,
+) -> Result<(CookieJar, impl IntoResponse), AuthAPIError> {
+    match revoke_user_sessions(&state, &user.user_id).await {
+        Ok(_) => Ok((remove_token_cookies(jar), StatusCode::OK.into_response())),
+        Err(SessionEpochStoreError::UserNotFound) => Err(AuthAPIError::TokenNotValid),
+        Err(SessionEpochStoreError::UnexpectedError(e)) => Err(AuthAPIError::UnexpectedError(e)),
+    }
+}
@@ -1,17 +1,43 @@
+mod change_email;
+mod change_password;
+mod confirm_totp;
 mod delete_account;
+mod enroll_totp;
 mod health_check;
+mod jwks;
 mod login;
 mod logout;
+mod logout_all;
+mod passkey_login;
+mod passkey_registration;
+mod password_reset;
+mod reauthenticate;
+mod recovery_codes;
 mod refresh_token;
 mod signup;
 mod verify_2fa;
 mod verify_captcha;
+mod verify_email;
+mod verify_token;
 
+pub use change_email::*;
+pub use change_password::*;
+pub use confirm_totp::*;
 pub use delete_account::*;
+pub use enroll_totp::*;
 pub use health_check::*;
+pub use jwks::*;
 pub use login::*;
 pub use logout::*;
+pub use logout_all::*;
+pub use passkey_login::*;
+pub use passkey_registration::*;
+pub use password_reset::*;
+pub(crate) use reauthenticate::*;
+pub use recovery_codes::*;
 pub use refresh_token::*;
 pub use signup::*;
 pub use verify_2fa::*;
 pub use verify_captcha::*;
+pub use verify_email::*;
+pub use verify_token::*;
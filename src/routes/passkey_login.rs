@@ -0,0 +1,275 @@
+use crate::app_state::AppState;
+use crate::domain::data_stores::{PasskeyChallengeStoreError, PasskeyStoreError, TwoFACodeStoreError, UserStoreError};
+use crate::domain::{AuthAPIError, Email, PasskeyCeremony, PasskeyChallenge, UserId};
+use crate::routes::{LoginResponse, ensure_email_verified, validate_email, validate_login_attempt_id};
+use crate::utils::{
+    ALLOW_UNVERIFIED_LOGIN, PASSKEY_CHALLENGE_TTL_SECONDS, RELYING_PARTY, WebauthnError, add_token_cookies,
+    issue_token_pair, new_challenge,
+};
+use axum::Json;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum_extra::extract::CookieJar;
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use secrecy::{ExposeSecret, SecretBox};
+use serde::{Deserialize, Serialize};
+
+// Options for `navigator.credentials.create()` or `.get()`, `challengeId` comes back with the result
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PasskeyOptionsResponse {
+    #[serde(rename = "challengeId")]
+    pub challenge_id: String,
+    #[serde(rename = "publicKey")]
+    pub public_key: serde_json::Value,
+}
+
+// The `PublicKeyCredential.toJSON()` of a `navigator.credentials.get()` result
+#[derive(Deserialize)]
+pub struct PasskeyAssertion {
+    pub id: String,
+    pub response: AssertionResponse,
+}
+
+#[derive(Deserialize)]
+pub struct AssertionResponse {
+    #[serde(rename = "clientDataJSON")]
+    pub client_data_json: String,
+    #[serde(rename = "authenticatorData")]
+    pub authenticator_data: String,
+    pub signature: String,
+}
+
+#[derive(Deserialize)]
+pub struct PasskeyAssertionRequest {
+    #[serde(rename = "challengeId")]
+    pub challenge_id: String,
+    pub credential: PasskeyAssertion,
+}
+
+#[derive(Deserialize)]
+pub struct StartPasskeyTwoFARequest {
+    pub email: String,
+    #[serde(rename = "loginAttemptId")]
+    pub login_attempt_id: String,
+}
+
+// First factor login: no email is asked, the authenticator offers the passkeys it holds for this site
+#[tracing::instrument(name = "StartPasskeyLogin", skip_all)]
+pub async fn start_passkey_login(State(state): State<AppState>) -> Result<impl IntoResponse, AuthAPIError> {
+    let response = start_passkey_assertion(&state, None, Vec::new()).await?;
+    Ok((StatusCode::OK, Json(response)))
+}
+
+// A passkey checks both possession and the user (PIN or biometrics), so it logs in on its own
+// without the 2FA step of a password login. An unverified email is refused like on the password login.
+#[tracing::instrument(name = "FinishPasskeyLogin", skip_all)]
+pub async fn finish_passkey_login(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Json(request): Json<PasskeyAssertionRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let user_id = verify_passkey_assertion(&state, &request, None).await?;
+    let user = state
+        .user_store
+        .read()
+        .await
+        .get_user_by_id(&user_id)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    ensure_email_verified(&user, *ALLOW_UNVERIFIED_LOGIN)?;
+    let token_pair = issue_token_pair(&state, user.email()).await?;
+
+    Ok((
+        StatusCode::OK,
+        add_token_cookies(jar, &token_pair),
+        Json(LoginResponse::RegularAuth),
+    ))
+}
+
+// Second factor: after the password step, the assertion is sent to `/verify-2fa` as `passkey`
+#[tracing::instrument(name = "StartPasskeyTwoFA", skip_all)]
+pub async fn start_passkey_two_fa(
+    State(state): State<AppState>,
+    Json(request): Json<StartPasskeyTwoFARequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = validate_email(&request.email)?;
+    let email = Email::new(SecretBox::new(Box::from(email.to_owned())))?;
+    let login_attempt_id = validate_login_attempt_id(&request.login_attempt_id)?;
+
+    let stored_login_attempt = match state.two_fa_code_store.read().await.get_code(&email).await {
+        Ok((stored_login_attempt, _)) => stored_login_attempt,
+        Err(TwoFACodeStoreError::LoginAttemptIdNotFound) => return Err(AuthAPIError::IncorrectCredentials),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    };
+    if stored_login_attempt.id().expose_secret() != login_attempt_id {
+        return Err(AuthAPIError::IncorrectCredentials);
+    }
+
+    let user_id = match state.user_store.read().await.get_user(&email).await {
+        Ok(user) => *user.id(),
+        Err(UserStoreError::UserNotFound) => return Err(AuthAPIError::IncorrectCredentials),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    };
+    let credentials = state
+        .passkey_store
+        .read()
+        .await
+        .get_credentials(&user_id)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    if credentials.is_empty() {
+        return Err(AuthAPIError::PasskeyNotRegistered);
+    }
+
+    let allowed = credentials
+        .into_iter()
+        .map(|credential| credential.credential_id)
+        .collect();
+    let response = start_passkey_assertion(&state, Some(user_id), allowed).await?;
+    Ok((StatusCode::OK, Json(response)))
+}
+
+async fn start_passkey_assertion(
+    state: &AppState,
+    user_id: Option<UserId>,
+    allowed_credentials: Vec<String>,
+) -> Result<PasskeyOptionsResponse, AuthAPIError> {
+    let challenge = new_challenge();
+    let user_verification = if user_id.is_none() { "required" } else { "preferred" };
+    let public_key = serde_json::json!({
+        "challenge": challenge,
+        "rpId": RELYING_PARTY.id,
+        "timeout": PASSKEY_CHALLENGE_TTL_SECONDS * 1000,
+        "allowCredentials": allowed_credentials
+            .iter()
+            .map(|id| serde_json::json!({ "type": "public-key", "id": id }))
+            .collect::<Vec<_>>(),
+        "userVerification": user_verification,
+    });
+
+    let challenge_id = store_passkey_challenge(
+        state,
+        PasskeyChallenge {
+            challenge,
+            ceremony: PasskeyCeremony::Authentication { user_id },
+        },
+    )
+    .await?;
+
+    Ok(PasskeyOptionsResponse {
+        challenge_id,
+        public_key,
+    })
+}
+
+// Check an assertion against the challenge it answers and return the owner of the credential.
+// `expected_user` is the user of a 2FA login, without it the assertion is a first factor and
+// must come with user verification. A signature counter that doesn't grow points to a cloned
+// authenticator, the assertion is refused.
+pub(crate) async fn verify_passkey_assertion(
+    state: &AppState,
+    request: &PasskeyAssertionRequest,
+    expected_user: Option<&UserId>,
+) -> Result<UserId, AuthAPIError> {
+    let challenge = take_passkey_challenge(state, &request.challenge_id).await?;
+    let PasskeyCeremony::Authentication { user_id } = challenge.ceremony else {
+        return Err(AuthAPIError::IncorrectCredentials);
+    };
+    if user_id.as_ref() != expected_user {
+        return Err(AuthAPIError::IncorrectCredentials);
+    }
+
+    let credential = match state
+        .passkey_store
+        .read()
+        .await
+        .get_credential(&request.credential.id)
+        .await
+    {
+        Ok(credential) => credential,
+        Err(PasskeyStoreError::CredentialNotFound) => return Err(AuthAPIError::IncorrectCredentials),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    };
+    if expected_user.is_some_and(|user_id| *user_id != credential.user_id) {
+        return Err(AuthAPIError::IncorrectCredentials);
+    }
+
+    let response = &request.credential.response;
+    let sign_count = RELYING_PARTY
+        .verify_assertion(
+            &challenge.challenge,
+            &decode_base64url(&response.client_data_json)?,
+            &decode_base64url(&response.authenticator_data)?,
+            &decode_base64url(&response.signature)?,
+            &credential.public_key,
+            expected_user.is_none(),
+        )
+        .map_err(passkey_error)?;
+
+    if (sign_count != 0 || credential.sign_count != 0) && sign_count <= credential.sign_count {
+        tracing::warn!("Passkey signature counter went backwards, the authenticator may be cloned");
+        return Err(AuthAPIError::IncorrectCredentials);
+    }
+
+    state
+        .passkey_store
+        .write()
+        .await
+        .update_sign_count(&credential.credential_id, sign_count)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    Ok(credential.user_id)
+}
+
+pub(crate) async fn store_passkey_challenge(
+    state: &AppState,
+    challenge: PasskeyChallenge,
+) -> Result<String, AuthAPIError> {
+    let challenge_id = uuid::Uuid::new_v4().to_string();
+
+    state
+        .passkey_challenge_store
+        .write()
+        .await
+        .add_challenge(&challenge_id, challenge)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    Ok(challenge_id)
+}
+
+// Challenges are single use, an expired or already answered one fails like a wrong credential
+pub(crate) async fn take_passkey_challenge(
+    state: &AppState,
+    challenge_id: &str,
+) -> Result<PasskeyChallenge, AuthAPIError> {
+    match state
+        .passkey_challenge_store
+        .write()
+        .await
+        .take_challenge(challenge_id)
+        .await
+    {
+        Ok(challenge) => Ok(challenge),
+        Err(PasskeyChallengeStoreError::ChallengeNotFound) => Err(AuthAPIError::IncorrectCredentials),
+        Err(e) => Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+}
+
+pub(crate) fn decode_base64url(value: &str) -> Result<Vec<u8>, AuthAPIError> {
+    URL_SAFE_NO_PAD
+        .decode(value.trim_end_matches('='))
+        .map_err(|_| AuthAPIError::InvalidPasskeyResponse)
+}
+
+pub(crate) fn passkey_error(error: WebauthnError) -> AuthAPIError {
+    if error.is_malformed() {
+        return AuthAPIError::InvalidPasskeyResponse;
+    }
+
+    tracing::debug!("Passkey response rejected: {}", error);
+    AuthAPIError::IncorrectCredentials
+}
@@ -0,0 +1,208 @@
+use crate::app_state::AppState;
+use crate::domain::data_stores::PasskeyStoreError;
+use crate::domain::{AuthAPIError, Email, PasskeyCeremony, PasskeyChallenge, PasskeyCredential, Password};
+use crate::routes::{
+    PasskeyOptionsResponse, Reauthentication, SecondFactor, TwoFactorAuthResponse, decode_base64url, passkey_error,
+    reauthenticate, store_passkey_challenge, take_passkey_challenge,
+};
+use crate::utils::{
+    AuthenticatedUser, COSE_ALG_ES256, ClientIp, PASSKEY_CHALLENGE_TTL_SECONDS, RELYING_PARTY, WEBAUTHN_RP_NAME, email,
+    new_challenge, new_user_handle,
+};
+use axum::Json;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use secrecy::{ExposeSecret, SecretBox};
+use serde::{Deserialize, Serialize};
+
+const DEFAULT_PASSKEY_NAME: &str = "Passkey";
+
+#[derive(Deserialize)]
+pub struct StartPasskeyRegistrationRequest {
+    pub password: SecretBox<String>,
+
+    #[serde(rename = "loginAttemptId")]
+    pub login_attempt_id: Option<String>,
+
+    #[serde(rename = "2FACode")]
+    pub two_fa_code: Option<String>,
+}
+
+// The `PublicKeyCredential.toJSON()` of a `navigator.credentials.create()` result
+#[derive(Deserialize)]
+pub struct RegistrationCredential {
+    pub id: String,
+    pub response: AttestationResponse,
+}
+
+#[derive(Deserialize)]
+pub struct AttestationResponse {
+    #[serde(rename = "clientDataJSON")]
+    pub client_data_json: String,
+    #[serde(rename = "attestationObject")]
+    pub attestation_object: String,
+}
+
+#[derive(Deserialize)]
+pub struct FinishPasskeyRegistrationRequest {
+    #[serde(rename = "challengeId")]
+    pub challenge_id: String,
+    pub name: Option<String>,
+    pub credential: RegistrationCredential,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PasskeyRegistrationResponse {
+    #[serde(rename = "credentialId")]
+    pub credential_id: String,
+}
+
+// Options for `navigator.credentials.create()`. Passkeys are discoverable credentials so that
+// they can log in without typing the email, the ones the user already has are excluded.
+// A passkey is a first factor of its own: the password and the 2FA code (when enabled) are asked
+// again, and only this call hands out the challenge the finish step needs.
+#[tracing::instrument(name = "StartPasskeyRegistration", skip_all)]
+pub async fn start_passkey_registration(
+    State(state): State<AppState>,
+    ClientIp(client_ip): ClientIp,
+    user: AuthenticatedUser,
+    Json(request): Json<StartPasskeyRegistrationRequest>,
+) -> Result<Response, AuthAPIError> {
+    let password = Password::new(request.password).map_err(|_| AuthAPIError::IncorrectCredentials)?;
+    let second_factor = request.two_fa_code.as_deref().map(SecondFactor::Code);
+    if let Reauthentication::TwoFactorRequired(login_attempt_id, method) = reauthenticate(
+        &state,
+        &user.email,
+        &password,
+        Some(client_ip),
+        request.login_attempt_id.as_deref(),
+        second_factor,
+    )
+    .await?
+    {
+        let response = Json(TwoFactorAuthResponse {
+            message: "2FA required".to_string(),
+            login_attempt_id: login_attempt_id.id().expose_secret().clone(),
+            method,
+        });
+        return Ok((StatusCode::PARTIAL_CONTENT, response).into_response());
+    }
+
+    let credentials = state
+        .passkey_store
+        .read()
+        .await
+        .get_credentials(&user.user_id)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    let user_handle = credentials
+        .first()
+        .map(|credential| credential.user_handle.clone())
+        .unwrap_or_else(new_user_handle);
+
+    let challenge = new_challenge();
+    let account_name = user.email.as_ref().expose_secret();
+    let public_key = serde_json::json!({
+        "challenge": challenge,
+        "rp": { "id": RELYING_PARTY.id, "name": *WEBAUTHN_RP_NAME },
+        "user": { "id": user_handle, "name": account_name, "displayName": account_name },
+        "pubKeyCredParams": [{ "type": "public-key", "alg": COSE_ALG_ES256 }],
+        "timeout": PASSKEY_CHALLENGE_TTL_SECONDS * 1000,
+        "excludeCredentials": credentials
+            .iter()
+            .map(|credential| serde_json::json!({ "type": "public-key", "id": credential.credential_id }))
+            .collect::<Vec<_>>(),
+        "authenticatorSelection": { "residentKey": "required", "userVerification": "preferred" },
+        "attestation": "none",
+    });
+
+    let challenge_id = store_passkey_challenge(
+        &state,
+        PasskeyChallenge {
+            challenge,
+            ceremony: PasskeyCeremony::Registration {
+                user_id: user.user_id,
+                user_handle,
+            },
+        },
+    )
+    .await?;
+
+    let response = Json(PasskeyOptionsResponse {
+        challenge_id,
+        public_key,
+    });
+    Ok((StatusCode::OK, response).into_response())
+}
+
+#[tracing::instrument(name = "FinishPasskeyRegistration", skip_all)]
+pub async fn finish_passkey_registration(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Json(request): Json<FinishPasskeyRegistrationRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let challenge = take_passkey_challenge(&state, &request.challenge_id).await?;
+    let PasskeyCeremony::Registration { user_id, user_handle } = challenge.ceremony else {
+        return Err(AuthAPIError::IncorrectCredentials);
+    };
+    if user_id != user.user_id {
+        return Err(AuthAPIError::Forbidden);
+    }
+
+    let client_data_json = decode_base64url(&request.credential.response.client_data_json)?;
+    let attestation_object = decode_base64url(&request.credential.response.attestation_object)?;
+    let registered = RELYING_PARTY
+        .verify_registration(&challenge.challenge, &client_data_json, &attestation_object)
+        .map_err(passkey_error)?;
+
+    let credential_id = URL_SAFE_NO_PAD.encode(&registered.credential_id);
+    if credential_id != request.credential.id {
+        return Err(AuthAPIError::InvalidPasskeyResponse);
+    }
+
+    let credential = PasskeyCredential {
+        credential_id: credential_id.clone(),
+        user_id,
+        user_handle,
+        public_key: registered.public_key,
+        sign_count: registered.sign_count,
+        name: request
+            .name
+            .filter(|name| !name.trim().is_empty())
+            .unwrap_or_else(|| DEFAULT_PASSKEY_NAME.to_string()),
+    };
+
+    match state.passkey_store.write().await.add_credential(credential).await {
+        Ok(_) => (),
+        Err(PasskeyStoreError::CredentialAlreadyExists) => return Err(AuthAPIError::PasskeyAlreadyRegistered),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+
+    notify_passkey_added(&state, &user.email).await;
+
+    Ok((StatusCode::CREATED, Json(PasskeyRegistrationResponse { credential_id })))
+}
+
+// The passkey is stored already, a failed notice is only logged
+async fn notify_passkey_added(
+    state: &AppState,
+    email: &Email,
+) {
+    let content = format!(
+        "A passkey has just been added to {}. If it wasn't you, reset your password and log out everywhere.",
+        email.as_ref().expose_secret()
+    );
+
+    if let Err(e) = state
+        .email_client
+        .read()
+        .await
+        .send_email(email, email::PASSKEY_ADDED_SUBJECT, &content)
+        .await
+    {
+        tracing::warn!("Failed to send the passkey notification: {:?}", e);
+    }
+}
@@ -0,0 +1,132 @@
+use crate::app_state::AppState;
+use crate::domain::data_stores::UserStoreError;
+use crate::domain::{AuthAPIError, Email, Password};
+use crate::routes::validate_email;
+use crate::utils::{
+    PASSWORD_POLICY, PASSWORD_RESET_TOKEN_TTL_SECONDS, PASSWORD_RESET_URL, TokenType, authorize_token,
+    clear_failed_logins, email, generate_password_reset_token, remove_token_cookies, revoke_user_sessions,
+};
+use axum::Json;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum_extra::extract::CookieJar;
+use color_eyre::Report;
+use secrecy::{ExposeSecret, SecretBox};
+use serde::{Deserialize, Serialize};
+use tracing::Instrument;
+
+#[derive(Deserialize)]
+pub struct PasswordResetRequest {
+    pub email: String,
+}
+
+#[derive(Deserialize)]
+pub struct ConfirmPasswordResetRequest {
+    pub token: String,
+    #[serde(rename = "newPassword")]
+    pub new_password: SecretBox<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub struct PasswordResetResponse {
+    pub message: String,
+}
+
+// Known and unknown emails get the same answer. The account lookup and the email are done once
+// the response is sent, so that the response time doesn't tell them apart either.
+#[tracing::instrument(name = "RequestPasswordReset", skip_all)]
+pub async fn request_password_reset(
+    State(state): State<AppState>,
+    Json(request): Json<PasswordResetRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = validate_email(&request.email)?;
+    let email = Email::new(SecretBox::new(Box::from(email.to_owned())))?;
+
+    tokio::spawn(
+        async move {
+            if let Err(e) = send_password_reset_link(&state, &email).await {
+                tracing::error!("Failed to send the password reset link: {:?}", e);
+            }
+        }
+        .in_current_span(),
+    );
+
+    let response = Json(PasswordResetResponse {
+        message: "If the account exists, a password reset link has been sent".to_string(),
+    });
+    Ok((StatusCode::ACCEPTED, response))
+}
+
+async fn send_password_reset_link(
+    state: &AppState,
+    email: &Email,
+) -> Result<(), Report> {
+    let user_id = match state.user_store.read().await.get_user(email).await {
+        Ok(user) => *user.id(),
+        Err(UserStoreError::UserNotFound) => return Ok(()),
+        Err(e) => return Err(e.into()),
+    };
+
+    let epoch = state.session_epoch_store.read().await.get_epoch(&user_id).await?;
+    let token = generate_password_reset_token(&user_id, epoch)?;
+    let separator = if PASSWORD_RESET_URL.contains('?') { '&' } else { '?' };
+    let content = format!(
+        "Open {}{}token={} within {} minutes to choose a new password. If you didn't ask for it, ignore this email.",
+        *PASSWORD_RESET_URL,
+        separator,
+        token,
+        *PASSWORD_RESET_TOKEN_TTL_SECONDS / 60
+    );
+
+    state
+        .email_client
+        .read()
+        .await
+        .send_email(email, email::PASSWORD_RESET_SUBJECT, &content)
+        .await?;
+    Ok(())
+}
+
+// Setting the new password logs the user out everywhere, which also spends the reset token.
+// The owner of the mailbox is proven, so the failed logins of the account are cleared too.
+#[tracing::instrument(name = "ConfirmPasswordReset", skip_all)]
+pub async fn confirm_password_reset(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Json(request): Json<ConfirmPasswordResetRequest>,
+) -> Result<(CookieJar, impl IntoResponse), AuthAPIError> {
+    let claims = authorize_token(&state, &request.token, TokenType::PasswordReset).await?;
+    let user_id = claims.user_id().map_err(|_| AuthAPIError::TokenNotValid)?;
+    let email = match state.user_store.read().await.get_user_by_id(&user_id).await {
+        Ok(user) => user.email().clone(),
+        Err(UserStoreError::UserNotFound) => return Err(AuthAPIError::TokenNotValid),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    };
+    PASSWORD_POLICY.check(request.new_password.expose_secret(), email.as_ref().expose_secret())?;
+    let password = Password::new(request.new_password)?;
+
+    match state.user_store.write().await.update_password(&email, password).await {
+        Ok(()) => (),
+        Err(UserStoreError::UserNotFound) => return Err(AuthAPIError::TokenNotValid),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+
+    // The link came through the mailbox, which verifies it as well
+    state
+        .user_store
+        .write()
+        .await
+        .verify_email(&email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    revoke_user_sessions(&state, &user_id)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    clear_failed_logins(&state, &email).await?;
+
+    let response = Json(PasswordResetResponse {
+        message: "Password updated, log in again".to_string(),
+    });
+    Ok((remove_token_cookies(jar), (StatusCode::OK, response)))
+}
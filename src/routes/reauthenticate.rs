@@ -0,0 +1,72 @@
+use crate::app_state::AppState;
+use crate::domain::data_stores::UserStoreError;
+use crate::domain::{AuthAPIError, Email, LoginAttemptId, Password, TwoFAMethod};
+use crate::routes::{SecondFactor, start_two_fa_challenge, two_fa_method, verify_two_fa_code};
+use crate::utils::{ensure_login_allowed, record_failed_login};
+use std::net::IpAddr;
+
+pub(crate) enum Reauthentication {
+    Confirmed,
+    TwoFactorRequired(LoginAttemptId, TwoFAMethod),
+}
+
+// Changes that would outlive a stolen access token ask for the password again, and for the second
+// factor when the account has one. Without an answer a 2FA account gets challenged (a code is
+// emailed unless it uses an authenticator app) and the caller repeats the request with it.
+pub(crate) async fn reauthenticate(
+    state: &AppState,
+    email: &Email,
+    password: &Password,
+    client_ip: Option<IpAddr>,
+    login_attempt_id: Option<&str>,
+    second_factor: Option<SecondFactor<'_>>,
+) -> Result<Reauthentication, AuthAPIError> {
+    verify_current_password(state, email, password, client_ip).await?;
+
+    let user = state
+        .user_store
+        .read()
+        .await
+        .get_user(email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    let Some(method) = two_fa_method(state, &user).await? else {
+        return Ok(Reauthentication::Confirmed);
+    };
+
+    match (login_attempt_id, second_factor) {
+        (Some(login_attempt_id), Some(second_factor)) => {
+            verify_two_fa_code(state, email, login_attempt_id, second_factor, client_ip).await?;
+            state
+                .two_fa_code_store
+                .write()
+                .await
+                .remove_code(email)
+                .await
+                .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+            Ok(Reauthentication::Confirmed)
+        }
+        _ => {
+            let login_attempt_id = start_two_fa_challenge(email, state, method).await?;
+            Ok(Reauthentication::TwoFactorRequired(login_attempt_id, method))
+        }
+    }
+}
+
+// A wrong password counts towards the lockouts like on the login, an access token doesn't make
+// the password of its account any cheaper to guess
+pub(crate) async fn verify_current_password(
+    state: &AppState,
+    email: &Email,
+    password: &Password,
+    client_ip: Option<IpAddr>,
+) -> Result<(), AuthAPIError> {
+    ensure_login_allowed(state, email, client_ip).await?;
+
+    let result = state.user_store.read().await.validate_user(email, password).await;
+    match result {
+        Ok(()) => Ok(()),
+        Err(UserStoreError::UnexpectedError(e)) => Err(AuthAPIError::UnexpectedError(e)),
+        Err(_) => Err(record_failed_login(state, email, client_ip, true).await),
+    }
+}
@@ -0,0 +1,143 @@
+use crate::app_state::AppState;
+use crate::domain::{AuthAPIError, Email, Password, RecoveryCode, UserId};
+use crate::routes::{Reauthentication, SecondFactor, TwoFactorAuthResponse, reauthenticate, two_fa_method};
+use crate::utils::{AuthenticatedUser, ClientIp, email};
+use axum::Json;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use secrecy::{ExposeSecret, SecretBox};
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize)]
+pub struct RegenerateRecoveryCodesRequest {
+    pub password: SecretBox<String>,
+
+    #[serde(rename = "loginAttemptId")]
+    pub login_attempt_id: Option<String>,
+
+    #[serde(rename = "2FACode")]
+    pub two_fa_code: Option<String>,
+
+    #[serde(rename = "recoveryCode")]
+    pub recovery_code: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RecoveryCodesResponse {
+    #[serde(rename = "recoveryCodes")]
+    pub recovery_codes: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct RemainingRecoveryCodesResponse {
+    pub remaining: usize,
+}
+
+#[tracing::instrument(name = "GetRecoveryCodes", skip_all)]
+pub async fn get_recovery_codes(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let remaining = state
+        .recovery_code_store
+        .read()
+        .await
+        .remaining_codes(&user.user_id)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    Ok((StatusCode::OK, Json(RemainingRecoveryCodesResponse { remaining })))
+}
+
+// A new set replaces the previous one, for users who used up or lost their codes. The codes get
+// around 2FA, so the password and a second factor (the 2FA code or one of the current recovery
+// codes) are asked again: without one the first call gets a 2FA challenge (206).
+#[tracing::instrument(name = "RegenerateRecoveryCodes", skip_all)]
+pub async fn regenerate_recovery_codes(
+    State(state): State<AppState>,
+    ClientIp(client_ip): ClientIp,
+    user: AuthenticatedUser,
+    Json(request): Json<RegenerateRecoveryCodesRequest>,
+) -> Result<Response, AuthAPIError> {
+    let account = state
+        .user_store
+        .read()
+        .await
+        .get_user(&user.email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    if two_fa_method(&state, &account).await?.is_none() {
+        return Err(AuthAPIError::TwoFANotEnabled);
+    }
+
+    let password = Password::new(request.password).map_err(|_| AuthAPIError::IncorrectCredentials)?;
+    let second_factor = match (request.two_fa_code.as_deref(), request.recovery_code.as_deref()) {
+        (None, None) => None,
+        (two_fa_code, recovery_code) => Some(SecondFactor::from_request(two_fa_code, recovery_code, None)?),
+    };
+    if let Reauthentication::TwoFactorRequired(login_attempt_id, method) = reauthenticate(
+        &state,
+        &user.email,
+        &password,
+        Some(client_ip),
+        request.login_attempt_id.as_deref(),
+        second_factor,
+    )
+    .await?
+    {
+        let response = Json(TwoFactorAuthResponse {
+            message: "2FA required".to_string(),
+            login_attempt_id: login_attempt_id.id().expose_secret().clone(),
+            method,
+        });
+        return Ok((StatusCode::PARTIAL_CONTENT, response).into_response());
+    }
+
+    let recovery_codes = issue_recovery_codes(&state, &user.user_id).await?;
+    notify_recovery_codes_regenerated(&state, &user.email).await;
+
+    Ok((StatusCode::OK, Json(RecoveryCodesResponse { recovery_codes })).into_response())
+}
+
+// The new codes are stored already, a failed notice is only logged
+async fn notify_recovery_codes_regenerated(
+    state: &AppState,
+    email: &Email,
+) {
+    let content = format!(
+        "New recovery codes have just been generated for {}, the previous ones no longer work. If it wasn't you, \
+         reset your password and log out everywhere.",
+        email.as_ref().expose_secret()
+    );
+
+    if let Err(e) = state
+        .email_client
+        .read()
+        .await
+        .send_email(email, email::RECOVERY_CODES_SUBJECT, &content)
+        .await
+    {
+        tracing::warn!("Failed to send the recovery codes notification: {:?}", e);
+    }
+}
+
+// Generate and store a new set, the plain codes are returned to be shown to the user once
+pub(crate) async fn issue_recovery_codes(
+    state: &AppState,
+    user_id: &UserId,
+) -> Result<Vec<String>, AuthAPIError> {
+    let codes = RecoveryCode::generate_set();
+    let displayed = codes.iter().map(ToString::to_string).collect();
+
+    state
+        .recovery_code_store
+        .write()
+        .await
+        .replace_codes(user_id, codes)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    Ok(displayed)
+}
@@ -1,11 +1,12 @@
-use crate::domain::{AuthAPIError, Email};
+use crate::app_state::AppState;
+use crate::domain::AuthAPIError;
 use crate::utils::{
-    JWT_REFRESH_COOKIE_NAME, generate_auth_cookie, generate_refresh_cookie, generate_token_pair, validate_token,
+    JWT_REFRESH_COOKIE_NAME, TokenPair, TokenType, add_token_cookies, authorize_token, rotate_token_pair,
 };
+use axum::extract::State;
 use axum::http::StatusCode;
 use axum::response::IntoResponse;
 use axum_extra::extract::CookieJar;
-use secrecy::SecretBox;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -16,21 +17,16 @@ pub struct RefreshTokenResponse {
 }
 
 #[tracing::instrument(name = "RefreshToken", skip_all)]
-pub async fn refresh_token(jar: CookieJar) -> Result<(CookieJar, impl IntoResponse), AuthAPIError> {
+pub async fn refresh_token(
+    State(state): State<AppState>,
+    jar: CookieJar,
+) -> Result<(CookieJar, impl IntoResponse), AuthAPIError> {
     let refresh_cookie = jar.get(JWT_REFRESH_COOKIE_NAME).ok_or(AuthAPIError::MissingToken)?;
 
     let refresh_token = refresh_cookie.value();
 
-    let claims = validate_token(refresh_token)
-        .await
-        .map_err(|_| AuthAPIError::TokenNotValid)?;
-
-    if claims.token_type != "refresh" {
-        return Err(AuthAPIError::TokenNotValid);
-    }
-
-    let email = &Email::new(SecretBox::new(Box::from(claims.sub)))?;
-    let new_token_pair = generate_token_pair(&email)?;
+    let new_token_pair = refresh_session(&state, refresh_token).await?;
+    let jar = add_token_cookies(jar, &new_token_pair);
 
     let response = RefreshTokenResponse {
         message: "Tokens refreshed successfully".to_string(),
@@ -38,9 +34,26 @@ pub async fn refresh_token(jar: CookieJar) -> Result<(CookieJar, impl IntoRespon
         refresh_token: new_token_pair.refresh_token,
     };
 
-    Ok((
-        jar.add(generate_auth_cookie(&email)?)
-            .add(generate_refresh_cookie(&email)?),
-        (StatusCode::OK, axum::Json(response)).into_response(),
-    ))
+    Ok((jar, (StatusCode::OK, axum::Json(response)).into_response()))
+}
+
+// Trade a refresh token for the next token pair of its family
+pub(crate) async fn refresh_session(
+    state: &AppState,
+    refresh_token: &str,
+) -> Result<TokenPair, AuthAPIError> {
+    let claims = authorize_token(state, refresh_token, TokenType::Refresh).await?;
+
+    let family_revoked = state
+        .refresh_token_store
+        .read()
+        .await
+        .is_family_revoked(&claims.fid)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    if family_revoked {
+        return Err(AuthAPIError::TokenNotValid);
+    }
+
+    rotate_token_pair(state, &claims).await
 }
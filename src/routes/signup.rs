@@ -1,6 +1,8 @@
 use crate::app_state::AppState;
 use crate::domain::data_stores::UserStoreError;
 use crate::domain::{AuthAPIError, User};
+use crate::routes::{issue_recovery_codes, send_verification_email};
+use crate::utils::PASSWORD_POLICY;
 use axum::Json;
 use axum::extract::State;
 use axum::http::StatusCode;
@@ -20,6 +22,10 @@ pub struct SignupRequest {
 #[derive(Debug, Serialize, Deserialize, Eq, PartialEq)]
 pub struct SignupResponse {
     pub message: String,
+
+    // Only for 2FA accounts, the codes are never shown again
+    #[serde(rename = "recoveryCodes", default, skip_serializing_if = "Vec::is_empty")]
+    pub recovery_codes: Vec<String>,
 }
 
 #[tracing::instrument(name = "Signup", skip_all)]
@@ -28,27 +34,52 @@ pub async fn signup(
     Json(request): Json<SignupRequest>,
 ) -> Result<impl IntoResponse, AuthAPIError> {
     // todo: validate email format inside the domain
-    if request.email.is_empty() || request.password.len() < 8 || !request.email.contains('@') {
+    if request.email.is_empty() || request.password.is_empty() || !request.email.contains('@') {
         return Err(AuthAPIError::EmailOrPasswordIncorrect);
     }
+    PASSWORD_POLICY.check(&request.password, &request.email)?;
 
     let user = User::new(request.email, request.password, request.requires_2fa)?;
+    let recovery_codes = create_user(&state, user).await?;
+
+    let response = Json(SignupResponse {
+        message: "User created successfully!".to_string(),
+        recovery_codes,
+    });
+    Ok((StatusCode::CREATED, response))
+}
+
+// Store the user unverified and send the verification email, a 2FA account gets its recovery
+// codes right away
+pub(crate) async fn create_user(
+    state: &AppState,
+    user: User,
+) -> Result<Vec<String>, AuthAPIError> {
+    let user_id = *user.id();
+    let email = user.email().clone();
+    let requires_2fa = user.requires_2fa();
+
     let result = state.user_store.write().await.add_user(user).await;
-    match result {
-        Ok(()) => {
-            let response = Json(SignupResponse {
-                message: "User created successfully!".to_string(),
-            });
-            Ok((StatusCode::CREATED, response))
-        }
-        Err(e) => match e {
+    if let Err(e) = result {
+        return match e {
             UserStoreError::UserAlreadyExists => Err(AuthAPIError::UserAlreadyExists),
             UserStoreError::UserNotFound => Err(AuthAPIError::UnexpectedError(eyre!(
                 "Unexpected user didn't find, error during signup"
             ))),
             UserStoreError::IncorrectCredentials => Err(AuthAPIError::IncorrectCredentials),
-            UserStoreError::UnexpectedError(e) => Err(AuthAPIError::UnexpectedError(e.into())),
-        },
+            UserStoreError::UnexpectedError(e) => Err(AuthAPIError::UnexpectedError(e)),
+        };
+    }
+
+    // The account exists either way, a lost email is sent again with /verify-email/resend
+    if let Err(e) = send_verification_email(state, &user_id, &email).await {
+        tracing::warn!("Failed to send the verification email: {:?}", e);
+    }
+
+    if requires_2fa {
+        issue_recovery_codes(state, &user_id).await
+    } else {
+        Ok(Vec::new())
     }
 }
 
@@ -56,8 +87,12 @@ pub async fn signup(
 mod tests {
     use super::*;
     use crate::app_state::AppState;
-    use crate::domain::AuthAPIError;
-    use crate::domain::data_stores::{MockBannedTokenStore, MockTwoFACodeStore, MockUserStore, UserStoreError};
+    use crate::domain::data_stores::{
+        MockBannedTokenStore, MockLoginLockoutStore, MockPasskeyChallengeStore, MockPasskeyStore, MockRateLimitStore,
+        MockRecoveryCodeStore, MockRefreshTokenStore, MockServiceCredentialStore, MockSessionEpochStore, MockTotpStore,
+        MockTwoFACodeStore, MockUserStore, UserStoreError,
+    };
+    use crate::domain::{AuthAPIError, PasswordError, PasswordViolation};
     use crate::services::email::MockEmailClient;
     use axum::Json;
     use axum::extract::State;
@@ -75,8 +110,17 @@ mod tests {
         AppState {
             user_store: Arc::new(RwLock::new(mock_store)),
             banned_token_store: Arc::new(RwLock::new(mock_banned_token_store)),
+            refresh_token_store: Arc::new(RwLock::new(MockRefreshTokenStore::new())),
+            session_epoch_store: Arc::new(RwLock::new(MockSessionEpochStore::new())),
             two_fa_code_store: Arc::new(RwLock::new(mock_two_fa_code_store)),
             email_client: Arc::new(RwLock::new(email_client)),
+            service_credential_store: Arc::new(RwLock::new(MockServiceCredentialStore::new())),
+            totp_store: Arc::new(RwLock::new(MockTotpStore::new())),
+            recovery_code_store: Arc::new(RwLock::new(MockRecoveryCodeStore::new())),
+            passkey_store: Arc::new(RwLock::new(MockPasskeyStore::new())),
+            passkey_challenge_store: Arc::new(RwLock::new(MockPasskeyChallengeStore::new())),
+            login_lockout_store: Arc::new(RwLock::new(MockLoginLockoutStore::new())),
+            rate_limit_store: Arc::new(RwLock::new(MockRateLimitStore::new())),
         }
     }
 
@@ -131,12 +175,16 @@ mod tests {
         );
         let request = SignupRequest {
             email: SafeEmail().fake(),
-            password: FakePassword(0..7).fake(),
+            password: FakePassword(1..7).fake(),
             requires_2fa: false,
         };
 
         let result = signup(State(state), Json(request)).await;
-        assert!(matches!(result, Err(AuthAPIError::EmailOrPasswordIncorrect)));
+        assert!(matches!(
+            result,
+            Err(AuthAPIError::PasswordError(PasswordError::PolicyViolation(violations)))
+                if violations.contains(&PasswordViolation::TooShort { min_length: 8 })
+        ));
     }
 
     #[tokio::test]
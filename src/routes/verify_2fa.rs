@@ -1,6 +1,11 @@
 use crate::app_state::AppState;
-use crate::domain::{AuthAPIError, Email};
-use crate::utils::{generate_auth_cookie, generate_refresh_cookie};
+use crate::domain::data_stores::{RecoveryCodeStoreError, TwoFACodeStoreError, UserStoreError};
+use crate::domain::{AuthAPIError, Email, RecoveryCode, TwoFACode, UserId};
+use crate::routes::{PasskeyAssertionRequest, totp_enabled, verify_passkey_assertion, verify_totp_code};
+use crate::utils::{
+    ClientIp, TWO_FA_MAX_ATTEMPTS, TokenPair, add_token_cookies, clear_failed_logins, ensure_login_allowed,
+    issue_token_pair, record_failed_login,
+};
 use axum::Json;
 use axum::extract::State;
 use axum::http::StatusCode;
@@ -8,6 +13,7 @@ use axum::response::IntoResponse;
 use axum_extra::extract::CookieJar;
 use secrecy::{ExposeSecret, SecretBox};
 use serde::Deserialize;
+use std::net::IpAddr;
 
 #[derive(Deserialize)]
 pub struct Verify2FARequest {
@@ -17,53 +23,207 @@ pub struct Verify2FARequest {
     pub login_attempt_id: String,
 
     #[serde(rename = "2FACode")]
-    pub two_fa_code: String,
+    pub two_fa_code: Option<String>,
+
+    #[serde(rename = "recoveryCode")]
+    pub recovery_code: Option<String>,
+
+    // The assertion of a challenge started with `/passkeys/2fa/start`
+    pub passkey: Option<PasskeyAssertionRequest>,
+}
+
+// What a 2FA challenge is answered with: the emailed or authenticator code, a registered
+// passkey, or one of the recovery codes when that factor is lost
+pub(crate) enum SecondFactor<'a> {
+    Code(&'a str),
+    RecoveryCode(&'a str),
+    Passkey(&'a PasskeyAssertionRequest),
+}
+
+impl<'a> SecondFactor<'a> {
+    pub(crate) fn from_request(
+        two_fa_code: Option<&'a str>,
+        recovery_code: Option<&'a str>,
+        passkey: Option<&'a PasskeyAssertionRequest>,
+    ) -> Result<Self, AuthAPIError> {
+        match (two_fa_code, recovery_code, passkey) {
+            (Some(two_fa_code), None, None) => Ok(Self::Code(two_fa_code)),
+            (None, Some(recovery_code), None) => Ok(Self::RecoveryCode(recovery_code)),
+            (None, None, Some(passkey)) => Ok(Self::Passkey(passkey)),
+            _ => Err(AuthAPIError::TwoFAMalformedError),
+        }
+    }
 }
 
 #[tracing::instrument(name = "Signup", skip_all)]
 pub async fn verify_2fa(
     State(state): State<AppState>,
+    ClientIp(client_ip): ClientIp,
     jar: CookieJar,
     Json(request): Json<Verify2FARequest>,
 ) -> Result<(CookieJar, impl IntoResponse), AuthAPIError> {
     let email = validate_email(&request.email)?;
-    let login_attempt_id = validate_login_attempt_id(&request.login_attempt_id)?;
-    let two_fa_code = validate_two_fa_code(&request.two_fa_code)?;
     let email = &Email::new(SecretBox::new(Box::from(email.to_owned())))?;
 
-    match state.two_fa_code_store.read().await.get_code(&email).await {
-        Ok(stored_data) => {
-            let (stored_login_attempt, stored_two_fa_code) = stored_data;
+    let second_factor = SecondFactor::from_request(
+        request.two_fa_code.as_deref(),
+        request.recovery_code.as_deref(),
+        request.passkey.as_ref(),
+    )?;
 
-            if stored_login_attempt.clone().id().expose_secret() != login_attempt_id {
-                return Err(AuthAPIError::IncorrectCredentials);
-            }
+    let token_pair =
+        complete_two_fa_login(&state, email, &request.login_attempt_id, second_factor, Some(client_ip)).await?;
+    Ok((add_token_cookies(jar, &token_pair), StatusCode::OK.into_response()))
+}
 
-            if stored_two_fa_code.clone().code().expose_secret() != two_fa_code {
-                return Err(AuthAPIError::IncorrectCredentials);
-            }
-        }
+// Check the answer against the pending 2FA attempt of the user, authenticator users answer
+// with their current TOTP code instead of the emailed one. Every wrong answer is counted and
+// the pending attempt is dropped after `TWO_FA_MAX_ATTEMPTS` of them, the user has to log in again.
+// Wrong answers also count towards the login lockout, or new login rounds would reset the guessing.
+pub(crate) async fn verify_two_fa_code(
+    state: &AppState,
+    email: &Email,
+    login_attempt_id: &str,
+    second_factor: SecondFactor<'_>,
+    client_ip: Option<IpAddr>,
+) -> Result<(), AuthAPIError> {
+    ensure_login_allowed(state, email, client_ip).await?;
+
+    let (stored_login_attempt, stored_two_fa_code) = match state.two_fa_code_store.read().await.get_code(email).await {
+        Ok(stored_data) => stored_data,
         Err(_) => return Err(AuthAPIError::IncorrectCredentials),
+    };
+
+    if stored_login_attempt.id().expose_secret() != login_attempt_id {
+        return Err(AuthAPIError::IncorrectCredentials);
+    }
+
+    // The second factors are kept for the user id
+    let user_id = match state.user_store.read().await.get_user(email).await {
+        Ok(user) => *user.id(),
+        Err(UserStoreError::UserNotFound) => return Err(AuthAPIError::IncorrectCredentials),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    };
+
+    let result = match second_factor {
+        SecondFactor::Code(two_fa_code) => verify_code(state, &user_id, email, &stored_two_fa_code, two_fa_code).await,
+        SecondFactor::RecoveryCode(recovery_code) => use_recovery_code(state, &user_id, recovery_code).await,
+        SecondFactor::Passkey(passkey) => verify_passkey_assertion(state, passkey, Some(&user_id))
+            .await
+            .map(|_| ()),
+    };
+
+    match result {
+        Err(AuthAPIError::IncorrectCredentials) => Err(record_failed_attempt(state, email, client_ip).await),
+        result => result,
+    }
+}
+
+async fn verify_code(
+    state: &AppState,
+    user_id: &UserId,
+    email: &Email,
+    stored_two_fa_code: &TwoFACode,
+    two_fa_code: &str,
+) -> Result<(), AuthAPIError> {
+    if totp_enabled(state, user_id).await? {
+        return verify_totp_code(state, user_id, email, two_fa_code).await;
     }
 
-    match state.two_fa_code_store.write().await.remove_code(&email).await {
-        Ok(()) => Ok((
-            jar.add(generate_auth_cookie(&email)?)
-                .add(generate_refresh_cookie(&email)?),
-            StatusCode::OK.into_response(),
-        )),
-        Err(_) => Err(AuthAPIError::IncorrectCredentials),
+    if stored_two_fa_code.as_ref().expose_secret() != two_fa_code {
+        return Err(AuthAPIError::IncorrectCredentials);
     }
+
+    Ok(())
+}
+
+// Returns the error the wrong answer ends with. A lockout also drops the pending attempt, the
+// per-attempt limit is reported first when both are reached by the same answer.
+async fn record_failed_attempt(
+    state: &AppState,
+    email: &Email,
+    client_ip: Option<IpAddr>,
+) -> AuthAPIError {
+    let lockout_error = record_failed_login(state, email, client_ip, true).await;
+    let locked = match lockout_error {
+        AuthAPIError::IncorrectCredentials => false,
+        AuthAPIError::UnexpectedError(_) => return lockout_error,
+        _ => true,
+    };
+
+    let mut two_fa_code_store = state.two_fa_code_store.write().await;
+
+    let failed_attempts = match two_fa_code_store.record_failed_attempt(email).await {
+        Ok(failed_attempts) => failed_attempts,
+        Err(TwoFACodeStoreError::LoginAttemptIdNotFound) => return AuthAPIError::IncorrectCredentials,
+        Err(e) => return AuthAPIError::UnexpectedError(e.into()),
+    };
+    if failed_attempts < *TWO_FA_MAX_ATTEMPTS && !locked {
+        return AuthAPIError::IncorrectCredentials;
+    }
+
+    tracing::warn!("Too many failed 2FA attempts, the pending login attempt is dropped");
+    if let Err(e) = two_fa_code_store.remove_code(email).await {
+        return AuthAPIError::UnexpectedError(e.into());
+    }
+    if failed_attempts < *TWO_FA_MAX_ATTEMPTS {
+        return lockout_error;
+    }
+    AuthAPIError::TooManyTwoFAAttempts
+}
+
+// A recovery code works once, whatever the usual second factor of the account is
+async fn use_recovery_code(
+    state: &AppState,
+    user_id: &UserId,
+    recovery_code: &str,
+) -> Result<(), AuthAPIError> {
+    let recovery_code = RecoveryCode::parse(recovery_code).map_err(|_| AuthAPIError::TwoFAMalformedError)?;
+
+    match state
+        .recovery_code_store
+        .write()
+        .await
+        .use_code(user_id, &recovery_code)
+        .await
+    {
+        Ok(_) => Ok(()),
+        Err(RecoveryCodeStoreError::InvalidCode) => Err(AuthAPIError::IncorrectCredentials),
+        Err(e) => Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+}
+
+// Second step of a 2FA login, the code is single use
+pub(crate) async fn complete_two_fa_login(
+    state: &AppState,
+    email: &Email,
+    login_attempt_id: &str,
+    second_factor: SecondFactor<'_>,
+    client_ip: Option<IpAddr>,
+) -> Result<TokenPair, AuthAPIError> {
+    let login_attempt_id = validate_login_attempt_id(login_attempt_id)?;
+    let second_factor = match second_factor {
+        SecondFactor::Code(two_fa_code) => SecondFactor::Code(validate_two_fa_code(two_fa_code)?),
+        recovery_code => recovery_code,
+    };
+    verify_two_fa_code(state, email, login_attempt_id, second_factor, client_ip).await?;
+
+    if state.two_fa_code_store.write().await.remove_code(email).await.is_err() {
+        return Err(AuthAPIError::IncorrectCredentials);
+    }
+    clear_failed_logins(state, email).await?;
+
+    issue_token_pair(state, email).await
 }
 
-fn validate_email(email: &str) -> Result<&str, AuthAPIError> {
+pub(crate) fn validate_email(email: &str) -> Result<&str, AuthAPIError> {
     if email.is_empty() || !email.contains('@') {
         return Err(AuthAPIError::EmailOrPasswordIncorrect);
     }
     Ok(email)
 }
 
-fn validate_login_attempt_id(login_attempt_id: &str) -> Result<&str, AuthAPIError> {
+pub(crate) fn validate_login_attempt_id(login_attempt_id: &str) -> Result<&str, AuthAPIError> {
     if login_attempt_id.is_empty() {
         return Err(AuthAPIError::LoginAttemptIdMalformedError);
     }
@@ -74,7 +234,7 @@ fn validate_login_attempt_id(login_attempt_id: &str) -> Result<&str, AuthAPIErro
     }
 }
 
-fn validate_two_fa_code(two_fa_code: &str) -> Result<&str, AuthAPIError> {
+pub(crate) fn validate_two_fa_code(two_fa_code: &str) -> Result<&str, AuthAPIError> {
     if two_fa_code.len() != 6 {
         return Err(AuthAPIError::TwoFAMalformedError);
     }
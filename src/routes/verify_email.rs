@@ -0,0 +1,153 @@
+use crate::app_state::AppState;
+use crate::domain::data_stores::UserStoreError;
+use crate::domain::{AuthAPIError, Email, User, UserId};
+use crate::routes::validate_email;
+use crate::utils::{
+    EMAIL_VERIFICATION_TOKEN_TTL_SECONDS, EMAIL_VERIFICATION_URL, TokenType, email, generate_email_verification_token,
+    validate_token,
+};
+use axum::Json;
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use color_eyre::Report;
+use secrecy::SecretBox;
+use serde::{Deserialize, Serialize};
+use tracing::Instrument;
+
+#[derive(Deserialize)]
+pub struct VerifyEmailQuery {
+    pub token: String,
+}
+
+#[derive(Deserialize)]
+pub struct ResendVerificationRequest {
+    pub email: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub struct VerifyEmailResponse {
+    pub message: String,
+}
+
+// The link of the verification email, a link opened twice verifies twice. A link sent to an
+// address the account no longer uses verifies nothing.
+#[tracing::instrument(name = "VerifyEmail", skip_all)]
+pub async fn verify_email(
+    State(state): State<AppState>,
+    Query(query): Query<VerifyEmailQuery>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let claims = validate_token(&query.token, TokenType::EmailVerification).map_err(|_| AuthAPIError::TokenNotValid)?;
+    let user_id = claims.user_id().map_err(|_| AuthAPIError::TokenNotValid)?;
+    let email = claims.email().map_err(|_| AuthAPIError::TokenNotValid)?;
+
+    match state.user_store.read().await.get_user_by_id(&user_id).await {
+        Ok(user) if *user.email() == email => (),
+        Ok(_) | Err(UserStoreError::UserNotFound) => return Err(AuthAPIError::TokenNotValid),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+
+    match state.user_store.write().await.verify_email(&email).await {
+        Ok(()) => (),
+        Err(UserStoreError::UserNotFound) => return Err(AuthAPIError::TokenNotValid),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+
+    let response = Json(VerifyEmailResponse {
+        message: "Email verified".to_string(),
+    });
+    Ok((StatusCode::OK, response))
+}
+
+// Same answer whether the account exists, is already verified or not, like the password reset
+// requests. The email goes out after the response, the rate limits throttle the resends.
+#[tracing::instrument(name = "ResendVerificationEmail", skip_all)]
+pub async fn resend_verification_email(
+    State(state): State<AppState>,
+    Json(request): Json<ResendVerificationRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = validate_email(&request.email)?;
+    let email = Email::new(SecretBox::new(Box::from(email.to_owned())))?;
+
+    tokio::spawn(
+        async move {
+            let user = match state.user_store.read().await.get_user(&email).await {
+                Ok(user) => user,
+                Err(UserStoreError::UserNotFound) => return,
+                Err(e) => {
+                    tracing::error!("Failed to look up the user to verify: {:?}", e);
+                    return;
+                }
+            };
+
+            if !user.email_verified()
+                && let Err(e) = send_verification_email(&state, user.id(), &email).await
+            {
+                tracing::error!("Failed to send the verification email: {:?}", e);
+            }
+        }
+        .in_current_span(),
+    );
+
+    let response = Json(VerifyEmailResponse {
+        message: "If the account exists and isn't verified yet, a verification email has been sent".to_string(),
+    });
+    Ok((StatusCode::ACCEPTED, response))
+}
+
+pub(crate) async fn send_verification_email(
+    state: &AppState,
+    user_id: &UserId,
+    email: &Email,
+) -> Result<(), Report> {
+    let token = generate_email_verification_token(user_id, email)?;
+    let separator = if EMAIL_VERIFICATION_URL.contains('?') { '&' } else { '?' };
+    let content = format!(
+        "Open {}{}token={} within {} minutes to confirm your email address.",
+        *EMAIL_VERIFICATION_URL,
+        separator,
+        token,
+        *EMAIL_VERIFICATION_TOKEN_TTL_SECONDS / 60
+    );
+
+    state
+        .email_client
+        .read()
+        .await
+        .send_email(email, email::VERIFICATION_SUBJECT, &content)
+        .await?;
+    Ok(())
+}
+
+// Whether an account may log in before its email is verified is a deployment choice
+pub(crate) fn ensure_email_verified(
+    user: &User,
+    allow_unverified_login: bool,
+) -> Result<(), AuthAPIError> {
+    if user.email_verified() || allow_unverified_login {
+        return Ok(());
+    }
+
+    Err(AuthAPIError::EmailNotVerified)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fake::Fake;
+    use fake::faker::internet::en::{Password as FakePassword, SafeEmail};
+
+    #[test]
+    fn test_unverified_users_log_in_only_when_allowed() {
+        let user = User::new(SafeEmail().fake(), FakePassword(8..20).fake(), false).unwrap();
+
+        assert!(ensure_email_verified(&user, true).is_ok());
+        assert!(matches!(
+            ensure_email_verified(&user, false),
+            Err(AuthAPIError::EmailNotVerified)
+        ));
+
+        let user = user.with_email_verified(true);
+        assert!(ensure_email_verified(&user, false).is_ok());
+    }
+}
@@ -0,0 +1,41 @@
+use crate::app_state::AppState;
+use crate::domain::AuthAPIError;
+use crate::utils::{TokenType, authorize_token};
+use axum::Json;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize)]
+pub struct VerifyTokenRequest {
+    pub token: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VerifyTokenResponse {
+    pub subject: String,
+    pub token_type: TokenType,
+    pub expires_at: usize,
+    pub expires_in: u64,
+}
+
+// Same checks as the gRPC VerifyToken: signature, expiry, ban list and session epoch
+#[tracing::instrument(name = "VerifyToken", skip_all)]
+pub async fn verify_token(
+    State(state): State<AppState>,
+    Json(request): Json<VerifyTokenRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let claims = authorize_token(&state, &request.token, TokenType::Access).await?;
+    let now = Utc::now().timestamp().max(0) as u64;
+
+    let response = VerifyTokenResponse {
+        subject: claims.sub,
+        token_type: claims.token_type,
+        expires_at: claims.exp,
+        expires_in: (claims.exp as u64).saturating_sub(now),
+    };
+
+    Ok((StatusCode::OK, Json(response)))
+}
@@ -1,10 +1,25 @@
-use crate::domain::data_stores::{BannedTokenStore, BannedTokenStoreError};
+use crate::domain::data_stores::{BannedTokenStore, BannedTokenStoreError, Revocation, RevocationStream};
 use async_trait::async_trait;
 use std::collections::HashSet;
+use tokio::sync::broadcast;
+use tokio_stream::StreamExt;
+use tokio_stream::wrappers::BroadcastStream;
+
+const REVOCATION_CHANNEL_CAPACITY: usize = 1024;
 
-#[derive(Default)]
 pub struct HashsetBannedTokenStore {
     tokens: HashSet<String>,
+    revocations: broadcast::Sender<Revocation>,
+}
+
+impl Default for HashsetBannedTokenStore {
+    fn default() -> Self {
+        let (revocations, _) = broadcast::channel(REVOCATION_CHANNEL_CAPACITY);
+        Self {
+            tokens: HashSet::new(),
+            revocations,
+        }
+    }
 }
 
 #[async_trait]
@@ -16,7 +31,10 @@ impl BannedTokenStore for HashsetBannedTokenStore {
         if !self.tokens.insert(token.to_string()) {
             return Err(BannedTokenStoreError::TokenAlreadyBanned);
         }
-        Ok(())
+        self.publish_revocation(Revocation::Token {
+            token: token.to_string(),
+        })
+        .await
     }
 
     async fn is_banned(
@@ -25,6 +43,20 @@ impl BannedTokenStore for HashsetBannedTokenStore {
     ) -> Result<bool, BannedTokenStoreError> {
         Ok(self.tokens.contains(token))
     }
+
+    async fn publish_revocation(
+        &self,
+        revocation: Revocation,
+    ) -> Result<(), BannedTokenStoreError> {
+        // Sending only fails when nobody is subscribed
+        let _ = self.revocations.send(revocation);
+        Ok(())
+    }
+
+    async fn watch_revocations(&self) -> Result<RevocationStream, BannedTokenStoreError> {
+        let stream = BroadcastStream::new(self.revocations.subscribe()).map_while(Result::ok);
+        Ok(Box::pin(stream))
+    }
 }
 
 #[cfg(test)]
@@ -121,4 +153,29 @@ mod tests {
         let duplicate_result = store.store_token(empty_token).await;
         assert_eq!(duplicate_result, Err(BannedTokenStoreError::TokenAlreadyBanned));
     }
+
+    #[tokio::test]
+    async fn test_watch_revocations_receives_banned_tokens() {
+        let mut store = HashsetBannedTokenStore::default();
+        let mut revocations = store.watch_revocations().await.unwrap();
+
+        let fake_token: String = Sentence(3..5).fake();
+        store.store_token(&fake_token).await.unwrap();
+        store
+            .publish_revocation(Revocation::UserSessions {
+                subject: "user@example.com".to_string(),
+                session_epoch: 2,
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(revocations.next().await, Some(Revocation::Token { token: fake_token }));
+        assert_eq!(
+            revocations.next().await,
+            Some(Revocation::UserSessions {
+                subject: "user@example.com".to_string(),
+                session_epoch: 2,
+            })
+        );
+    }
 }
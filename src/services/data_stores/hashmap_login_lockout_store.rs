@@ -0,0 +1,129 @@
+use crate::domain::LockoutSubject;
+use crate::domain::data_stores::{LoginLockoutStore, LoginLockoutStoreError};
+use crate::utils::{LOCKOUT_FAILURE_WINDOW_SECONDS, LOCKOUT_RESET_SECONDS};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+// Counters are kept with the instant they expire at, like the keys of the Redis store
+#[derive(Default)]
+pub struct HashmapLoginLockoutStore {
+    failures: HashMap<LockoutSubject, (u32, Instant)>,
+    locks: HashMap<LockoutSubject, Instant>,
+    streaks: HashMap<LockoutSubject, (u32, Instant)>,
+}
+
+fn live_count(
+    counter: Option<&(u32, Instant)>,
+    now: Instant,
+) -> u32 {
+    counter
+        .filter(|(_, expires_at)| *expires_at > now)
+        .map_or(0, |(count, _)| *count)
+}
+
+#[async_trait::async_trait]
+impl LoginLockoutStore for HashmapLoginLockoutStore {
+    async fn locked_for(
+        &self,
+        subject: &LockoutSubject,
+    ) -> Result<Option<u64>, LoginLockoutStoreError> {
+        Ok(self
+            .locks
+            .get(subject)
+            .and_then(|locked_until| locked_until.checked_duration_since(Instant::now()))
+            .map(|remaining| remaining.as_secs().max(1)))
+    }
+
+    async fn record_failure(
+        &mut self,
+        subject: &LockoutSubject,
+    ) -> Result<u32, LoginLockoutStoreError> {
+        let now = Instant::now();
+        let failures = live_count(self.failures.get(subject), now) + 1;
+        let expires_at = match self.failures.get(subject) {
+            Some((_, expires_at)) if *expires_at > now => *expires_at,
+            _ => now + Duration::from_secs(*LOCKOUT_FAILURE_WINDOW_SECONDS),
+        };
+
+        self.failures.insert(subject.clone(), (failures, expires_at));
+        Ok(failures)
+    }
+
+    async fn lockout_streak(
+        &self,
+        subject: &LockoutSubject,
+    ) -> Result<u32, LoginLockoutStoreError> {
+        Ok(live_count(self.streaks.get(subject), Instant::now()))
+    }
+
+    async fn lock(
+        &mut self,
+        subject: &LockoutSubject,
+        seconds: u64,
+    ) -> Result<(), LoginLockoutStoreError> {
+        let now = Instant::now();
+        let streak = live_count(self.streaks.get(subject), now) + 1;
+
+        self.locks.insert(subject.clone(), now + Duration::from_secs(seconds));
+        self.failures.remove(subject);
+        self.streaks.insert(
+            subject.clone(),
+            (streak, now + Duration::from_secs(*LOCKOUT_RESET_SECONDS)),
+        );
+        Ok(())
+    }
+
+    async fn reset(
+        &mut self,
+        subject: &LockoutSubject,
+    ) -> Result<(), LoginLockoutStoreError> {
+        self.failures.remove(subject);
+        self.streaks.remove(subject);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::{IpAddr, Ipv4Addr};
+
+    #[tokio::test]
+    async fn test_lock_clears_the_failures_and_extends_the_streak() {
+        let mut store = HashmapLoginLockoutStore::default();
+        let subject = LockoutSubject::Ip(IpAddr::V4(Ipv4Addr::LOCALHOST));
+
+        assert_eq!(store.record_failure(&subject).await, Ok(1));
+        assert_eq!(store.record_failure(&subject).await, Ok(2));
+        assert_eq!(store.locked_for(&subject).await, Ok(None));
+
+        store.lock(&subject, 60).await.unwrap();
+        assert!(
+            store
+                .locked_for(&subject)
+                .await
+                .unwrap()
+                .is_some_and(|seconds| seconds <= 60)
+        );
+        assert_eq!(store.lockout_streak(&subject).await, Ok(1));
+        assert_eq!(store.record_failure(&subject).await, Ok(1));
+
+        store.lock(&subject, 0).await.unwrap();
+        assert_eq!(store.locked_for(&subject).await, Ok(None));
+        assert_eq!(store.lockout_streak(&subject).await, Ok(2));
+    }
+
+    #[tokio::test]
+    async fn test_reset_keeps_a_running_lock() {
+        let mut store = HashmapLoginLockoutStore::default();
+        let subject = LockoutSubject::Ip(IpAddr::V4(Ipv4Addr::LOCALHOST));
+
+        store.lock(&subject, 60).await.unwrap();
+        store.record_failure(&subject).await.unwrap();
+        store.reset(&subject).await.unwrap();
+
+        assert_eq!(store.lockout_streak(&subject).await, Ok(0));
+        assert_eq!(store.record_failure(&subject).await, Ok(1));
+        assert!(store.locked_for(&subject).await.unwrap().is_some());
+    }
+}
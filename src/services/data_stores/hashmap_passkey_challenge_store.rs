@@ -0,0 +1,51 @@
+use crate::domain::PasskeyChallenge;
+use crate::domain::data_stores::{PasskeyChallengeStore, PasskeyChallengeStoreError};
+use std::collections::HashMap;
+
+#[derive(Default)]
+pub struct HashmapPasskeyChallengeStore {
+    challenges: HashMap<String, PasskeyChallenge>,
+}
+
+#[async_trait::async_trait]
+impl PasskeyChallengeStore for HashmapPasskeyChallengeStore {
+    async fn add_challenge(
+        &mut self,
+        challenge_id: &str,
+        challenge: PasskeyChallenge,
+    ) -> Result<(), PasskeyChallengeStoreError> {
+        self.challenges.insert(challenge_id.to_string(), challenge);
+        Ok(())
+    }
+
+    async fn take_challenge(
+        &mut self,
+        challenge_id: &str,
+    ) -> Result<PasskeyChallenge, PasskeyChallengeStoreError> {
+        self.challenges
+            .remove(challenge_id)
+            .ok_or(PasskeyChallengeStoreError::ChallengeNotFound)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::PasskeyCeremony;
+
+    #[tokio::test]
+    async fn test_challenges_are_single_use() {
+        let mut store = HashmapPasskeyChallengeStore::default();
+        let challenge = PasskeyChallenge {
+            challenge: "challenge".to_string(),
+            ceremony: PasskeyCeremony::Authentication { user_id: None },
+        };
+        store.add_challenge("id", challenge.clone()).await.unwrap();
+
+        assert_eq!(store.take_challenge("id").await, Ok(challenge));
+        assert_eq!(
+            store.take_challenge("id").await,
+            Err(PasskeyChallengeStoreError::ChallengeNotFound)
+        );
+    }
+}
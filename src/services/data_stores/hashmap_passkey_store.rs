@@ -0,0 +1,126 @@
+use crate::domain::data_stores::{PasskeyStore, PasskeyStoreError};
+use crate::domain::{PasskeyCredential, UserId};
+use std::collections::HashMap;
+
+#[derive(Default)]
+pub struct HashmapPasskeyStore {
+    credentials: HashMap<String, PasskeyCredential>,
+}
+
+#[async_trait::async_trait]
+impl PasskeyStore for HashmapPasskeyStore {
+    async fn add_credential(
+        &mut self,
+        credential: PasskeyCredential,
+    ) -> Result<(), PasskeyStoreError> {
+        if self.credentials.contains_key(&credential.credential_id) {
+            return Err(PasskeyStoreError::CredentialAlreadyExists);
+        }
+
+        self.credentials.insert(credential.credential_id.clone(), credential);
+        Ok(())
+    }
+
+    async fn get_credential(
+        &self,
+        credential_id: &str,
+    ) -> Result<PasskeyCredential, PasskeyStoreError> {
+        self.credentials
+            .get(credential_id)
+            .cloned()
+            .ok_or(PasskeyStoreError::CredentialNotFound)
+    }
+
+    async fn get_credentials(
+        &self,
+        user_id: &UserId,
+    ) -> Result<Vec<PasskeyCredential>, PasskeyStoreError> {
+        Ok(self
+            .credentials
+            .values()
+            .filter(|credential| &credential.user_id == user_id)
+            .cloned()
+            .collect())
+    }
+
+    async fn update_sign_count(
+        &mut self,
+        credential_id: &str,
+        sign_count: u32,
+    ) -> Result<(), PasskeyStoreError> {
+        let credential = self
+            .credentials
+            .get_mut(credential_id)
+            .ok_or(PasskeyStoreError::CredentialNotFound)?;
+        credential.sign_count = sign_count;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn credential(
+        credential_id: &str,
+        user_id: UserId,
+    ) -> PasskeyCredential {
+        PasskeyCredential {
+            credential_id: credential_id.to_string(),
+            user_id,
+            user_handle: "handle".to_string(),
+            public_key: vec![4, 1, 2],
+            sign_count: 0,
+            name: "Passkey".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_add_and_get_credentials() {
+        let mut store = HashmapPasskeyStore::default();
+        let first = credential("first", UserId::default());
+        store.add_credential(first.clone()).await.unwrap();
+        store
+            .add_credential(credential("other", UserId::default()))
+            .await
+            .unwrap();
+
+        assert_eq!(store.get_credential("first").await, Ok(first.clone()));
+        assert_eq!(store.get_credentials(&first.user_id).await, Ok(vec![first]));
+        assert_eq!(
+            store.get_credential("missing").await,
+            Err(PasskeyStoreError::CredentialNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_reject_duplicated_credential() {
+        let mut store = HashmapPasskeyStore::default();
+        store
+            .add_credential(credential("first", UserId::default()))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            store.add_credential(credential("first", UserId::default())).await,
+            Err(PasskeyStoreError::CredentialAlreadyExists)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_update_sign_count() {
+        let mut store = HashmapPasskeyStore::default();
+        store
+            .add_credential(credential("first", UserId::default()))
+            .await
+            .unwrap();
+
+        store.update_sign_count("first", 5).await.unwrap();
+
+        assert_eq!(store.get_credential("first").await.unwrap().sign_count, 5);
+        assert_eq!(
+            store.update_sign_count("missing", 1).await,
+            Err(PasskeyStoreError::CredentialNotFound)
+        );
+    }
+}
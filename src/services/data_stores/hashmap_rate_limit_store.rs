@@ -0,0 +1,61 @@
+use crate::domain::data_stores::{RateLimitStore, RateLimitStoreError};
+use crate::domain::{RateLimitDecision, refill_tokens};
+use std::collections::HashMap;
+use std::sync::{Mutex, PoisonError};
+use std::time::Instant;
+
+// Buckets of a single instance, for tests and local runs
+#[derive(Default)]
+pub struct HashmapRateLimitStore {
+    buckets: Mutex<HashMap<String, (f64, Instant)>>,
+}
+
+#[async_trait::async_trait]
+impl RateLimitStore for HashmapRateLimitStore {
+    async fn take_token(
+        &self,
+        key: &str,
+        capacity: u32,
+        refill_per_minute: u32,
+    ) -> Result<RateLimitDecision, RateLimitStoreError> {
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap_or_else(PoisonError::into_inner);
+
+        let tokens = match buckets.get(key) {
+            Some((tokens, updated_at)) => refill_tokens(
+                *tokens,
+                now.duration_since(*updated_at).as_secs_f64(),
+                capacity,
+                refill_per_minute,
+            ),
+            None => f64::from(capacity),
+        };
+        let allowed = tokens >= 1.0;
+        let tokens = if allowed { tokens - 1.0 } else { tokens };
+
+        buckets.insert(key.to_string(), (tokens, now));
+        Ok(RateLimitDecision::new(allowed, tokens, capacity, refill_per_minute))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_bucket_empties_after_its_capacity() {
+        let store = HashmapRateLimitStore::default();
+
+        for remaining in (0..3).rev() {
+            let decision = store.take_token("ip:127.0.0.1", 3, 1).await.unwrap();
+            assert!(decision.allowed);
+            assert_eq!(decision.remaining, remaining);
+        }
+
+        let decision = store.take_token("ip:127.0.0.1", 3, 1).await.unwrap();
+        assert!(!decision.allowed);
+        assert!(decision.retry_after_seconds > 0 && decision.retry_after_seconds <= 60);
+
+        assert!(store.take_token("ip:127.0.0.2", 3, 1).await.unwrap().allowed);
+    }
+}
@@ -0,0 +1,97 @@
+use crate::domain::data_stores::{RecoveryCodeStore, RecoveryCodeStoreError};
+use crate::domain::{RecoveryCode, UserId};
+use std::collections::HashMap;
+
+#[derive(Default)]
+pub struct HashmapRecoveryCodeStore {
+    codes: HashMap<UserId, Vec<RecoveryCode>>,
+}
+
+#[async_trait::async_trait]
+impl RecoveryCodeStore for HashmapRecoveryCodeStore {
+    async fn replace_codes(
+        &mut self,
+        user_id: &UserId,
+        codes: Vec<RecoveryCode>,
+    ) -> Result<(), RecoveryCodeStoreError> {
+        self.codes.insert(*user_id, codes);
+        Ok(())
+    }
+
+    async fn use_code(
+        &mut self,
+        user_id: &UserId,
+        code: &RecoveryCode,
+    ) -> Result<(), RecoveryCodeStoreError> {
+        let codes = self.codes.get_mut(user_id).ok_or(RecoveryCodeStoreError::InvalidCode)?;
+        let position = codes
+            .iter()
+            .position(|stored| stored == code)
+            .ok_or(RecoveryCodeStoreError::InvalidCode)?;
+
+        codes.swap_remove(position);
+        Ok(())
+    }
+
+    async fn remaining_codes(
+        &self,
+        user_id: &UserId,
+    ) -> Result<usize, RecoveryCodeStoreError> {
+        Ok(self.codes.get(user_id).map_or(0, Vec::len))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    fn code(value: &str) -> RecoveryCode {
+        RecoveryCode::parse(value).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_codes_are_single_use() {
+        let mut store = HashmapRecoveryCodeStore::default();
+        let user_id = UserId::default();
+        store
+            .replace_codes(&user_id, vec![code("aaaaa-bbbbb"), code("ccccc-ddddd")])
+            .await
+            .unwrap();
+
+        assert_eq!(store.use_code(&user_id, &code("aaaaa-bbbbb")).await, Ok(()));
+        assert_eq!(
+            store.use_code(&user_id, &code("aaaaa-bbbbb")).await,
+            Err(RecoveryCodeStoreError::InvalidCode)
+        );
+        assert_eq!(store.remaining_codes(&user_id).await, Ok(1));
+    }
+
+    #[tokio::test]
+    async fn test_replace_codes_invalidates_the_old_set() {
+        let mut store = HashmapRecoveryCodeStore::default();
+        let user_id = UserId::default();
+        store.replace_codes(&user_id, vec![code("aaaaa-bbbbb")]).await.unwrap();
+        store.replace_codes(&user_id, vec![code("ccccc-ddddd")]).await.unwrap();
+
+        assert_eq!(
+            store.use_code(&user_id, &code("aaaaa-bbbbb")).await,
+            Err(RecoveryCodeStoreError::InvalidCode)
+        );
+        assert_eq!(store.use_code(&user_id, &code("ccccc-ddddd")).await, Ok(()));
+    }
+
+    #[tokio::test]
+    async fn test_codes_belong_to_their_user() {
+        let mut store = HashmapRecoveryCodeStore::default();
+        let other = UserId::default();
+        store
+            .replace_codes(&UserId::default(), vec![code("aaaaa-bbbbb")])
+            .await
+            .unwrap();
+
+        assert_eq!(
+            store.use_code(&other, &code("aaaaa-bbbbb")).await,
+            Err(RecoveryCodeStoreError::InvalidCode)
+        );
+        assert_eq!(store.remaining_codes(&other).await, Ok(0));
+    }
+}
@@ -0,0 +1,178 @@
+use crate::domain::data_stores::{RefreshTokenStore, RefreshTokenStoreError};
+use async_trait::async_trait;
+use std::collections::{HashMap, HashSet};
+
+#[derive(Default)]
+struct RefreshTokenFamily {
+    active: HashSet<String>,
+    consumed: HashSet<String>,
+    revoked: bool,
+}
+
+#[derive(Default)]
+pub struct HashmapRefreshTokenStore {
+    families: HashMap<String, RefreshTokenFamily>,
+}
+
+#[async_trait]
+impl RefreshTokenStore for HashmapRefreshTokenStore {
+    async fn add_token(
+        &mut self,
+        family_id: &str,
+        token_id: &str,
+    ) -> Result<(), RefreshTokenStoreError> {
+        let family = self.families.entry(family_id.to_string()).or_default();
+        if family.revoked {
+            return Err(RefreshTokenStoreError::FamilyRevoked);
+        }
+
+        family.active.insert(token_id.to_string());
+        Ok(())
+    }
+
+    async fn consume_token(
+        &mut self,
+        family_id: &str,
+        token_id: &str,
+    ) -> Result<(), RefreshTokenStoreError> {
+        let family = self
+            .families
+            .get_mut(family_id)
+            .ok_or(RefreshTokenStoreError::TokenNotFound)?;
+
+        if family.revoked {
+            return Err(RefreshTokenStoreError::FamilyRevoked);
+        }
+
+        if family.active.remove(token_id) {
+            family.consumed.insert(token_id.to_string());
+            return Ok(());
+        }
+
+        if family.consumed.contains(token_id) {
+            family.revoked = true;
+            family.active.clear();
+            return Err(RefreshTokenStoreError::TokenReused);
+        }
+
+        Err(RefreshTokenStoreError::TokenNotFound)
+    }
+
+    async fn revoke_family(
+        &mut self,
+        family_id: &str,
+    ) -> Result<(), RefreshTokenStoreError> {
+        let family = self.families.entry(family_id.to_string()).or_default();
+        family.revoked = true;
+        family.active.clear();
+        Ok(())
+    }
+
+    async fn is_family_revoked(
+        &self,
+        family_id: &str,
+    ) -> Result<bool, RefreshTokenStoreError> {
+        Ok(self.families.get(family_id).is_some_and(|family| family.revoked))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    fn new_id() -> String {
+        Uuid::new_v4().to_string()
+    }
+
+    #[tokio::test]
+    async fn test_consume_active_token() {
+        let mut store = HashmapRefreshTokenStore::default();
+        let (family_id, token_id) = (new_id(), new_id());
+
+        store.add_token(&family_id, &token_id).await.unwrap();
+
+        assert_eq!(store.consume_token(&family_id, &token_id).await, Ok(()));
+        assert_eq!(store.is_family_revoked(&family_id).await, Ok(false));
+    }
+
+    #[tokio::test]
+    async fn test_consume_unknown_token() {
+        let mut store = HashmapRefreshTokenStore::default();
+        let family_id = new_id();
+
+        let result = store.consume_token(&family_id, &new_id()).await;
+        assert_eq!(result, Err(RefreshTokenStoreError::TokenNotFound));
+
+        store.add_token(&family_id, &new_id()).await.unwrap();
+        let result = store.consume_token(&family_id, &new_id()).await;
+        assert_eq!(result, Err(RefreshTokenStoreError::TokenNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_rotation_within_family() {
+        let mut store = HashmapRefreshTokenStore::default();
+        let family_id = new_id();
+        let first = new_id();
+        let second = new_id();
+
+        store.add_token(&family_id, &first).await.unwrap();
+        store.consume_token(&family_id, &first).await.unwrap();
+        store.add_token(&family_id, &second).await.unwrap();
+
+        assert_eq!(store.consume_token(&family_id, &second).await, Ok(()));
+    }
+
+    #[tokio::test]
+    async fn test_reused_token_revokes_family() {
+        let mut store = HashmapRefreshTokenStore::default();
+        let family_id = new_id();
+        let first = new_id();
+        let second = new_id();
+
+        store.add_token(&family_id, &first).await.unwrap();
+        store.consume_token(&family_id, &first).await.unwrap();
+        store.add_token(&family_id, &second).await.unwrap();
+
+        let result = store.consume_token(&family_id, &first).await;
+        assert_eq!(result, Err(RefreshTokenStoreError::TokenReused));
+        assert_eq!(store.is_family_revoked(&family_id).await, Ok(true));
+
+        let result = store.consume_token(&family_id, &second).await;
+        assert_eq!(result, Err(RefreshTokenStoreError::FamilyRevoked));
+    }
+
+    #[tokio::test]
+    async fn test_revoke_family() {
+        let mut store = HashmapRefreshTokenStore::default();
+        let (family_id, token_id) = (new_id(), new_id());
+
+        store.add_token(&family_id, &token_id).await.unwrap();
+        store.revoke_family(&family_id).await.unwrap();
+
+        assert_eq!(store.is_family_revoked(&family_id).await, Ok(true));
+        let result = store.consume_token(&family_id, &token_id).await;
+        assert_eq!(result, Err(RefreshTokenStoreError::FamilyRevoked));
+
+        let result = store.add_token(&family_id, &new_id()).await;
+        assert_eq!(result, Err(RefreshTokenStoreError::FamilyRevoked));
+    }
+
+    #[tokio::test]
+    async fn test_families_are_independent() {
+        let mut store = HashmapRefreshTokenStore::default();
+        let (family_1, token_1) = (new_id(), new_id());
+        let (family_2, token_2) = (new_id(), new_id());
+
+        store.add_token(&family_1, &token_1).await.unwrap();
+        store.add_token(&family_2, &token_2).await.unwrap();
+        store.revoke_family(&family_1).await.unwrap();
+
+        assert_eq!(store.is_family_revoked(&family_2).await, Ok(false));
+        assert_eq!(store.consume_token(&family_2, &token_2).await, Ok(()));
+        assert_eq!(
+            store.consume_token(&family_2, &token_1).await,
+            Err(RefreshTokenStoreError::TokenNotFound)
+        );
+    }
+}
@@ -0,0 +1,83 @@
+use crate::domain::data_stores::{ServiceCredentialStore, ServiceCredentialStoreError};
+use secrecy::{ExposeSecret, SecretBox};
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+
+#[derive(Default)]
+pub struct HashmapServiceCredentialStore {
+    secrets: HashMap<String, SecretBox<String>>,
+}
+
+#[async_trait::async_trait]
+impl ServiceCredentialStore for HashmapServiceCredentialStore {
+    async fn add_service(
+        &mut self,
+        service_id: &str,
+        secret: SecretBox<String>,
+    ) -> Result<(), ServiceCredentialStoreError> {
+        match self.secrets.entry(service_id.to_string()) {
+            Entry::Occupied(_) => Err(ServiceCredentialStoreError::ServiceAlreadyExists),
+            Entry::Vacant(entry) => {
+                entry.insert(secret);
+                Ok(())
+            }
+        }
+    }
+
+    async fn validate_credentials(
+        &self,
+        service_id: &str,
+        secret: &SecretBox<String>,
+    ) -> Result<(), ServiceCredentialStoreError> {
+        match self.secrets.get(service_id) {
+            Some(stored) if stored.expose_secret() == secret.expose_secret() => Ok(()),
+            _ => Err(ServiceCredentialStoreError::InvalidCredentials),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secret(value: &str) -> SecretBox<String> {
+        SecretBox::new(Box::from(value.to_string()))
+    }
+
+    #[tokio::test]
+    async fn test_validate_registered_service() {
+        let mut store = HashmapServiceCredentialStore::default();
+        store.add_service("billing", secret("billing-secret")).await.unwrap();
+
+        assert_eq!(
+            store.validate_credentials("billing", &secret("billing-secret")).await,
+            Ok(())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_reject_wrong_secret_and_unknown_service() {
+        let mut store = HashmapServiceCredentialStore::default();
+        store.add_service("billing", secret("billing-secret")).await.unwrap();
+
+        assert_eq!(
+            store.validate_credentials("billing", &secret("wrong-secret")).await,
+            Err(ServiceCredentialStoreError::InvalidCredentials)
+        );
+        assert_eq!(
+            store.validate_credentials("unknown", &secret("billing-secret")).await,
+            Err(ServiceCredentialStoreError::InvalidCredentials)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_register_service_twice() {
+        let mut store = HashmapServiceCredentialStore::default();
+        store.add_service("billing", secret("billing-secret")).await.unwrap();
+
+        assert_eq!(
+            store.add_service("billing", secret("other-secret")).await,
+            Err(ServiceCredentialStoreError::ServiceAlreadyExists)
+        );
+    }
+}
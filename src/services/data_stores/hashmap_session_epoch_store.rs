@@ -0,0 +1,58 @@
+use crate::domain::UserId;
+use crate::domain::data_stores::{SessionEpochStore, SessionEpochStoreError};
+use std::collections::HashMap;
+
+#[derive(Default)]
+pub struct HashmapSessionEpochStore {
+    epochs: HashMap<UserId, i64>,
+}
+
+#[async_trait::async_trait]
+impl SessionEpochStore for HashmapSessionEpochStore {
+    async fn get_epoch(
+        &self,
+        user_id: &UserId,
+    ) -> Result<i64, SessionEpochStoreError> {
+        Ok(self.epochs.get(user_id).copied().unwrap_or_default())
+    }
+
+    async fn increment_epoch(
+        &mut self,
+        user_id: &UserId,
+    ) -> Result<i64, SessionEpochStoreError> {
+        let epoch = self.epochs.entry(*user_id).or_default();
+        *epoch += 1;
+        Ok(*epoch)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_unknown_user_starts_at_zero() {
+        let store = HashmapSessionEpochStore::default();
+        assert_eq!(store.get_epoch(&UserId::default()).await, Ok(0));
+    }
+
+    #[tokio::test]
+    async fn test_increment_epoch() {
+        let mut store = HashmapSessionEpochStore::default();
+        let user_id = UserId::default();
+
+        assert_eq!(store.increment_epoch(&user_id).await, Ok(1));
+        assert_eq!(store.increment_epoch(&user_id).await, Ok(2));
+        assert_eq!(store.get_epoch(&user_id).await, Ok(2));
+    }
+
+    #[tokio::test]
+    async fn test_epochs_are_per_user() {
+        let mut store = HashmapSessionEpochStore::default();
+        let user_id = UserId::default();
+        let other_user_id = UserId::default();
+
+        store.increment_epoch(&user_id).await.unwrap();
+        assert_eq!(store.get_epoch(&other_user_id).await, Ok(0));
+    }
+}
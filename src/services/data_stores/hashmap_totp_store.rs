@@ -0,0 +1,139 @@
+use crate::domain::UserId;
+use crate::domain::data_stores::{TotpEnrollment, TotpStore, TotpStoreError};
+use std::collections::HashMap;
+
+struct StoredTotp {
+    enrollment: TotpEnrollment,
+    last_time_step: Option<i64>,
+}
+
+#[derive(Default)]
+pub struct HashmapTotpStore {
+    enrollments: HashMap<UserId, StoredTotp>,
+}
+
+#[async_trait::async_trait]
+impl TotpStore for HashmapTotpStore {
+    async fn set_pending_secret(
+        &mut self,
+        user_id: &UserId,
+        encrypted_secret: String,
+    ) -> Result<(), TotpStoreError> {
+        if let Some(stored) = self.enrollments.get(user_id)
+            && stored.enrollment.confirmed
+        {
+            return Err(TotpStoreError::AlreadyEnabled);
+        }
+
+        self.enrollments.insert(
+            *user_id,
+            StoredTotp {
+                enrollment: TotpEnrollment {
+                    encrypted_secret,
+                    confirmed: false,
+                },
+                last_time_step: None,
+            },
+        );
+        Ok(())
+    }
+
+    async fn get_enrollment(
+        &self,
+        user_id: &UserId,
+    ) -> Result<TotpEnrollment, TotpStoreError> {
+        self.enrollments
+            .get(user_id)
+            .map(|stored| stored.enrollment.clone())
+            .ok_or(TotpStoreError::NotEnrolled)
+    }
+
+    async fn confirm(
+        &mut self,
+        user_id: &UserId,
+    ) -> Result<(), TotpStoreError> {
+        let stored = self.enrollments.get_mut(user_id).ok_or(TotpStoreError::NotEnrolled)?;
+        stored.enrollment.confirmed = true;
+        Ok(())
+    }
+
+    async fn use_time_step(
+        &mut self,
+        user_id: &UserId,
+        time_step: i64,
+    ) -> Result<(), TotpStoreError> {
+        let stored = self.enrollments.get_mut(user_id).ok_or(TotpStoreError::NotEnrolled)?;
+        if stored.last_time_step.is_some_and(|last| last >= time_step) {
+            return Err(TotpStoreError::TimeStepAlreadyUsed);
+        }
+
+        stored.last_time_step = Some(time_step);
+        Ok(())
+    }
+
+    async fn remove(
+        &mut self,
+        user_id: &UserId,
+    ) -> Result<(), TotpStoreError> {
+        self.enrollments.remove(user_id);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_enroll_and_confirm() {
+        let mut store = HashmapTotpStore::default();
+        let user_id = UserId::default();
+
+        assert_eq!(store.get_enrollment(&user_id).await, Err(TotpStoreError::NotEnrolled));
+
+        store.set_pending_secret(&user_id, "first".to_string()).await.unwrap();
+        store.set_pending_secret(&user_id, "second".to_string()).await.unwrap();
+        store.confirm(&user_id).await.unwrap();
+
+        assert_eq!(
+            store.get_enrollment(&user_id).await,
+            Ok(TotpEnrollment {
+                encrypted_secret: "second".to_string(),
+                confirmed: true,
+            })
+        );
+        assert_eq!(
+            store.set_pending_secret(&user_id, "third".to_string()).await,
+            Err(TotpStoreError::AlreadyEnabled)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_time_steps_are_single_use() {
+        let mut store = HashmapTotpStore::default();
+        let user_id = UserId::default();
+        store.set_pending_secret(&user_id, "secret".to_string()).await.unwrap();
+
+        assert_eq!(store.use_time_step(&user_id, 100).await, Ok(()));
+        assert_eq!(
+            store.use_time_step(&user_id, 100).await,
+            Err(TotpStoreError::TimeStepAlreadyUsed)
+        );
+        assert_eq!(
+            store.use_time_step(&user_id, 99).await,
+            Err(TotpStoreError::TimeStepAlreadyUsed)
+        );
+        assert_eq!(store.use_time_step(&user_id, 101).await, Ok(()));
+    }
+
+    #[tokio::test]
+    async fn test_remove_enrollment() {
+        let mut store = HashmapTotpStore::default();
+        let user_id = UserId::default();
+        store.set_pending_secret(&user_id, "secret".to_string()).await.unwrap();
+
+        store.remove(&user_id).await.unwrap();
+
+        assert_eq!(store.get_enrollment(&user_id).await, Err(TotpStoreError::NotEnrolled));
+    }
+}
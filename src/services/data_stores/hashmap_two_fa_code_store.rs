@@ -6,7 +6,7 @@ use std::collections::hash_map::Entry;
 
 #[derive(Default)]
 pub struct HashmapTwoFACodeStore {
-    codes: HashMap<Email, (LoginAttemptId, TwoFACode)>,
+    codes: HashMap<Email, (LoginAttemptId, TwoFACode, u32)>,
 }
 
 #[async_trait]
@@ -19,10 +19,10 @@ impl TwoFACodeStore for HashmapTwoFACodeStore {
     ) -> Result<(), TwoFACodeStoreError> {
         match self.codes.entry(email.to_owned()) {
             Entry::Occupied(mut entry) => {
-                entry.insert((login_attempt_id, code));
+                entry.insert((login_attempt_id, code, 0));
             }
             Entry::Vacant(entry) => {
-                entry.insert((login_attempt_id, code));
+                entry.insert((login_attempt_id, code, 0));
             }
         }
         Ok(())
@@ -40,7 +40,22 @@ impl TwoFACodeStore for HashmapTwoFACodeStore {
         &self,
         email: &Email,
     ) -> Result<(LoginAttemptId, TwoFACode), TwoFACodeStoreError> {
-        self.codes.get(email).cloned().ok_or(TwoFACodeStoreError::UserNotFound)
+        self.codes
+            .get(email)
+            .map(|(login_attempt_id, code, _)| (login_attempt_id.clone(), code.clone()))
+            .ok_or(TwoFACodeStoreError::UserNotFound)
+    }
+
+    async fn record_failed_attempt(
+        &mut self,
+        email: &Email,
+    ) -> Result<u32, TwoFACodeStoreError> {
+        let (_, _, failed_attempts) = self
+            .codes
+            .get_mut(email)
+            .ok_or(TwoFACodeStoreError::LoginAttemptIdNotFound)?;
+        *failed_attempts += 1;
+        Ok(*failed_attempts)
     }
 }
 
@@ -239,6 +254,29 @@ mod tests {
         assert_eq!(stored_data.1, code);
     }
 
+    #[tokio::test]
+    async fn test_record_failed_attempt_counts_until_a_new_code() {
+        let mut store = HashmapTwoFACodeStore::default();
+        let email = &Email::new(SecretBox::new(SafeEmail().fake())).unwrap();
+        assert_eq!(
+            store.record_failed_attempt(email).await.unwrap_err(),
+            TwoFACodeStoreError::LoginAttemptIdNotFound
+        );
+
+        store
+            .add_code(email, LoginAttemptId::default(), TwoFACode::default())
+            .await
+            .unwrap();
+        assert_eq!(store.record_failed_attempt(email).await.unwrap(), 1);
+        assert_eq!(store.record_failed_attempt(email).await.unwrap(), 2);
+
+        store
+            .add_code(email, LoginAttemptId::default(), TwoFACode::default())
+            .await
+            .unwrap();
+        assert_eq!(store.record_failed_attempt(email).await.unwrap(), 1);
+    }
+
     #[tokio::test]
     async fn test_default_store_is_empty() {
         let store = HashmapTwoFACodeStore::default();
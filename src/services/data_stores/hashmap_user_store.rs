@@ -1,5 +1,5 @@
 use crate::domain::data_stores::{UserStore, UserStoreError};
-use crate::domain::{Email, Password, User};
+use crate::domain::{Email, Password, User, UserId};
 use std::collections::HashMap;
 use std::collections::hash_map::Entry;
 
@@ -12,11 +12,12 @@ pub struct HashmapUserStore {
 impl UserStore for HashmapUserStore {
     async fn add_user(
         &mut self,
-        user: User,
+        mut user: User,
     ) -> Result<(), UserStoreError> {
         match self.users.entry(user.email().to_owned()) {
             Entry::Occupied(_) => Err(UserStoreError::UserAlreadyExists),
             Entry::Vacant(entry) => {
+                user.set_password(user.password().normalized());
                 entry.insert(user);
                 Ok(())
             }
@@ -30,19 +31,67 @@ impl UserStore for HashmapUserStore {
         self.users.get(email).ok_or(UserStoreError::UserNotFound).cloned()
     }
 
+    async fn get_user_by_id(
+        &self,
+        id: &UserId,
+    ) -> Result<User, UserStoreError> {
+        self.users
+            .values()
+            .find(|user| user.id() == id)
+            .ok_or(UserStoreError::UserNotFound)
+            .cloned()
+    }
+
     async fn validate_user(
         &self,
         email: &Email,
         password: &Password,
     ) -> Result<(), UserStoreError> {
         let user = self.get_user(email).await?;
-        if user.password() != password {
+        if user.password() != password && *user.password() != password.normalized() {
             return Err(UserStoreError::IncorrectCredentials);
         }
 
         Ok(())
     }
 
+    async fn verify_email(
+        &mut self,
+        email: &Email,
+    ) -> Result<(), UserStoreError> {
+        let user = self.users.get_mut(email).ok_or(UserStoreError::UserNotFound)?;
+        *user = user.clone().with_email_verified(true);
+        Ok(())
+    }
+
+    async fn update_email(
+        &mut self,
+        id: &UserId,
+        email: Email,
+        // The TOTP enrollments live in their own store here
+        _totp_secret: Option<String>,
+    ) -> Result<(), UserStoreError> {
+        let mut user = self.get_user_by_id(id).await?;
+        if self.users.get(&email).is_some_and(|other| other.id() != id) {
+            return Err(UserStoreError::UserAlreadyExists);
+        }
+
+        self.users.remove(user.email());
+        user.set_email(email.clone());
+        self.users.insert(email, user.with_email_verified(true));
+        Ok(())
+    }
+
+    async fn update_password(
+        &mut self,
+        email: &Email,
+        password: Password,
+    ) -> Result<(), UserStoreError> {
+        let user = self.users.get_mut(email).ok_or(UserStoreError::UserNotFound)?;
+        user.set_password(password.normalized());
+        Ok(())
+    }
+
     async fn delete_account(
         &mut self,
         email: &Email,
@@ -57,7 +106,7 @@ mod tests {
     use super::*;
     use fake::Fake;
     use fake::faker::internet::en::{Password as FakePassword, SafeEmail};
-    use secrecy::SecretBox;
+    use secrecy::{ExposeSecret, SecretBox};
 
     #[tokio::test]
     async fn test_user_add() {
@@ -151,6 +200,108 @@ mod tests {
         assert!(validation_ok.is_ok());
     }
 
+    #[tokio::test]
+    async fn test_verify_email() {
+        let mut hash_map_user = HashmapUserStore::default();
+        let user_email: String = SafeEmail().fake();
+        let email = Email::new(SecretBox::new(Box::from(user_email.clone()))).unwrap();
+
+        let user_01 = User::new(user_email, FakePassword(8..20).fake(), false).unwrap();
+        assert!(hash_map_user.add_user(user_01).await.is_ok());
+        assert!(!hash_map_user.get_user(&email).await.unwrap().email_verified());
+
+        assert!(hash_map_user.verify_email(&email).await.is_ok());
+        assert!(hash_map_user.get_user(&email).await.unwrap().email_verified());
+
+        let not_found = hash_map_user
+            .verify_email(&Email::new(SecretBox::new(SafeEmail().fake())).unwrap())
+            .await;
+        assert_eq!(not_found, Err(UserStoreError::UserNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_update_email() {
+        let mut hash_map_user = HashmapUserStore::default();
+        let old_email = Email::new(SecretBox::new(SafeEmail().fake())).unwrap();
+        let new_email = Email::new(SecretBox::new(SafeEmail().fake())).unwrap();
+        let taken_email: String = SafeEmail().fake();
+
+        let user_01 = User::new(
+            old_email.as_ref().expose_secret().to_owned(),
+            FakePassword(8..20).fake(),
+            false,
+        )
+        .unwrap();
+        let user_id = *user_01.id();
+        assert!(hash_map_user.add_user(user_01).await.is_ok());
+        let user_02 = User::new(taken_email.clone(), FakePassword(8..20).fake(), false).unwrap();
+        assert!(hash_map_user.add_user(user_02).await.is_ok());
+
+        let taken = hash_map_user
+            .update_email(
+                &user_id,
+                Email::new(SecretBox::new(Box::from(taken_email))).unwrap(),
+                None,
+            )
+            .await;
+        assert_eq!(taken, Err(UserStoreError::UserAlreadyExists));
+
+        assert!(
+            hash_map_user
+                .update_email(&user_id, new_email.clone(), None)
+                .await
+                .is_ok()
+        );
+        let user = hash_map_user.get_user_by_id(&user_id).await.unwrap();
+        assert_eq!(user.email(), &new_email);
+        assert!(user.email_verified());
+        assert_eq!(
+            hash_map_user.get_user(&old_email).await,
+            Err(UserStoreError::UserNotFound)
+        );
+
+        let not_found = hash_map_user.update_email(&UserId::default(), new_email, None).await;
+        assert_eq!(not_found, Err(UserStoreError::UserNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_update_password() {
+        let mut hash_map_user = HashmapUserStore::default();
+        let user_email: String = SafeEmail().fake();
+        let old_password: String = FakePassword(8..20).fake();
+        let new_password: String = FakePassword(8..20).fake();
+        let email = Email::new(SecretBox::new(Box::from(user_email.clone()))).unwrap();
+
+        let user_01 = User::new(user_email, old_password.clone(), false).unwrap();
+        assert!(hash_map_user.add_user(user_01).await.is_ok());
+
+        let result = hash_map_user
+            .update_password(
+                &email,
+                Password::new(SecretBox::new(Box::from(new_password.clone()))).unwrap(),
+            )
+            .await;
+        assert!(result.is_ok());
+
+        let validation_failed = hash_map_user
+            .validate_user(&email, &Password::new(SecretBox::new(Box::from(old_password))).unwrap())
+            .await;
+        assert_eq!(validation_failed, Err(UserStoreError::IncorrectCredentials));
+
+        let validation_ok = hash_map_user
+            .validate_user(&email, &Password::new(SecretBox::new(Box::from(new_password))).unwrap())
+            .await;
+        assert!(validation_ok.is_ok());
+
+        let not_found = hash_map_user
+            .update_password(
+                &Email::new(SecretBox::new(SafeEmail().fake())).unwrap(),
+                Password::new(SecretBox::new(FakePassword(8..20).fake())).unwrap(),
+            )
+            .await;
+        assert_eq!(not_found, Err(UserStoreError::UserNotFound));
+    }
+
     #[tokio::test]
     async fn test_delete_account() {
         let mut hash_map_user = HashmapUserStore::default();
@@ -1,13 +1,53 @@
 mod hashmap_banned_token_store;
+mod hashmap_login_lockout_store;
+mod hashmap_passkey_challenge_store;
+mod hashmap_passkey_store;
+mod hashmap_rate_limit_store;
+mod hashmap_recovery_code_store;
+mod hashmap_refresh_token_store;
+mod hashmap_service_credential_store;
+mod hashmap_session_epoch_store;
+mod hashmap_totp_store;
 mod hashmap_two_fa_code_store;
 mod hashmap_user_store;
+mod postgres_passkey_store;
+mod postgres_recovery_code_store;
+mod postgres_refresh_token_store;
+mod postgres_service_credential_store;
+mod postgres_session_epoch_store;
+mod postgres_totp_store;
 mod postgres_user_store;
 mod redis_banned_token_store;
+mod redis_login_lockout_store;
+mod redis_passkey_challenge_store;
+mod redis_rate_limit_store;
+mod redis_refresh_token_store;
+mod redis_session_epoch_cache;
 mod redis_two_fa_code_store;
 
 pub use hashmap_banned_token_store::*;
+pub use hashmap_login_lockout_store::*;
+pub use hashmap_passkey_challenge_store::*;
+pub use hashmap_passkey_store::*;
+pub use hashmap_rate_limit_store::*;
+pub use hashmap_recovery_code_store::*;
+pub use hashmap_refresh_token_store::*;
+pub use hashmap_service_credential_store::*;
+pub use hashmap_session_epoch_store::*;
+pub use hashmap_totp_store::*;
 pub use hashmap_two_fa_code_store::*;
 pub use hashmap_user_store::*;
+pub use postgres_passkey_store::*;
+pub use postgres_recovery_code_store::*;
+pub use postgres_refresh_token_store::*;
+pub use postgres_service_credential_store::*;
+pub use postgres_session_epoch_store::*;
+pub use postgres_totp_store::*;
 pub use postgres_user_store::*;
 pub use redis_banned_token_store::*;
+pub use redis_login_lockout_store::*;
+pub use redis_passkey_challenge_store::*;
+pub use redis_rate_limit_store::*;
+pub use redis_refresh_token_store::*;
+pub use redis_session_epoch_cache::*;
 pub use redis_two_fa_code_store::*;
@@ -0,0 +1,137 @@
+use crate::domain::data_stores::{PasskeyStore, PasskeyStoreError};
+use crate::domain::{PasskeyCredential, UserId};
+use sqlx::PgPool;
+
+pub struct PostgresPasskeyStore {
+    pool: PgPool,
+}
+
+impl PostgresPasskeyStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+struct PasskeyRow {
+    credential_id: String,
+    user_id: uuid::Uuid,
+    user_handle: String,
+    public_key: Vec<u8>,
+    sign_count: i64,
+    name: String,
+}
+
+impl TryFrom<PasskeyRow> for PasskeyCredential {
+    type Error = PasskeyStoreError;
+
+    fn try_from(row: PasskeyRow) -> Result<Self, Self::Error> {
+        Ok(PasskeyCredential {
+            credential_id: row.credential_id,
+            user_id: row.user_id.into(),
+            user_handle: row.user_handle,
+            public_key: row.public_key,
+            sign_count: u32::try_from(row.sign_count).map_err(|e| PasskeyStoreError::UnexpectedError(e.into()))?,
+            name: row.name,
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl PasskeyStore for PostgresPasskeyStore {
+    #[tracing::instrument(name = "Adding passkey credential to PostgreSQL", skip_all)]
+    async fn add_credential(
+        &mut self,
+        credential: PasskeyCredential,
+    ) -> Result<(), PasskeyStoreError> {
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO passkey_credentials (credential_id, user_id, user_handle, public_key, sign_count, name)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (credential_id) DO NOTHING
+            "#,
+            credential.credential_id,
+            credential.user_id.as_ref(),
+            credential.user_handle,
+            credential.public_key,
+            i64::from(credential.sign_count),
+            credential.name
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| PasskeyStoreError::UnexpectedError(e.into()))?;
+
+        match result.rows_affected() {
+            0 => Err(PasskeyStoreError::CredentialAlreadyExists),
+            _ => Ok(()),
+        }
+    }
+
+    #[tracing::instrument(name = "Retrieving passkey credential from PostgreSQL", skip_all)]
+    async fn get_credential(
+        &self,
+        credential_id: &str,
+    ) -> Result<PasskeyCredential, PasskeyStoreError> {
+        sqlx::query_as!(
+            PasskeyRow,
+            r#"
+            SELECT credential_id, user_id, user_handle, public_key, sign_count, name
+            FROM passkey_credentials
+            WHERE credential_id = $1
+            "#,
+            credential_id
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| PasskeyStoreError::UnexpectedError(e.into()))?
+        .ok_or(PasskeyStoreError::CredentialNotFound)?
+        .try_into()
+    }
+
+    #[tracing::instrument(name = "Retrieving passkey credentials of a user from PostgreSQL", skip_all)]
+    async fn get_credentials(
+        &self,
+        user_id: &UserId,
+    ) -> Result<Vec<PasskeyCredential>, PasskeyStoreError> {
+        sqlx::query_as!(
+            PasskeyRow,
+            r#"
+            SELECT credential_id, user_id, user_handle, public_key, sign_count, name
+            FROM passkey_credentials
+            WHERE user_id = $1
+            ORDER BY created_at
+            "#,
+            user_id.as_ref()
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| PasskeyStoreError::UnexpectedError(e.into()))?
+        .into_iter()
+        .map(PasskeyCredential::try_from)
+        .collect()
+    }
+
+    #[tracing::instrument(name = "Updating passkey sign count in PostgreSQL", skip_all)]
+    async fn update_sign_count(
+        &mut self,
+        credential_id: &str,
+        sign_count: u32,
+    ) -> Result<(), PasskeyStoreError> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE passkey_credentials
+            SET sign_count = $2, last_used_at = NOW()
+            WHERE credential_id = $1
+            "#,
+            credential_id,
+            i64::from(sign_count)
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| PasskeyStoreError::UnexpectedError(e.into()))?;
+
+        match result.rows_affected() {
+            0 => Err(PasskeyStoreError::CredentialNotFound),
+            _ => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,112 @@
+use super::postgres_user_store::{compute_password_hash, verify_password_hash};
+use crate::domain::data_stores::{RecoveryCodeStore, RecoveryCodeStoreError};
+use crate::domain::{RecoveryCode, UserId};
+use secrecy::ExposeSecret;
+use sqlx::PgPool;
+
+pub struct PostgresRecoveryCodeStore {
+    pool: PgPool,
+}
+
+impl PostgresRecoveryCodeStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl RecoveryCodeStore for PostgresRecoveryCodeStore {
+    #[tracing::instrument(name = "Replacing recovery codes in PostgreSQL", skip_all)]
+    async fn replace_codes(
+        &mut self,
+        user_id: &UserId,
+        codes: Vec<RecoveryCode>,
+    ) -> Result<(), RecoveryCodeStoreError> {
+        // Hash before opening the transaction, Argon2 is slow on purpose
+        let mut code_hashes = Vec::with_capacity(codes.len());
+        for code in codes {
+            let code_hash = compute_password_hash(code.as_ref().expose_secret().to_string())
+                .await
+                .map_err(RecoveryCodeStoreError::UnexpectedError)?;
+            code_hashes.push(code_hash.expose_secret().to_string());
+        }
+
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| RecoveryCodeStoreError::UnexpectedError(e.into()))?;
+
+        sqlx::query!(r#"DELETE FROM recovery_codes WHERE user_id = $1"#, user_id.as_ref())
+            .execute(&mut *transaction)
+            .await
+            .map_err(|e| RecoveryCodeStoreError::UnexpectedError(e.into()))?;
+
+        sqlx::query!(
+            r#"INSERT INTO recovery_codes (user_id, code_hash) SELECT $1, UNNEST($2::TEXT[])"#,
+            user_id.as_ref(),
+            &code_hashes
+        )
+        .execute(&mut *transaction)
+        .await
+        .map_err(|e| RecoveryCodeStoreError::UnexpectedError(e.into()))?;
+
+        transaction
+            .commit()
+            .await
+            .map_err(|e| RecoveryCodeStoreError::UnexpectedError(e.into()))
+    }
+
+    #[tracing::instrument(name = "Using recovery code in PostgreSQL", skip_all)]
+    async fn use_code(
+        &mut self,
+        user_id: &UserId,
+        code: &RecoveryCode,
+    ) -> Result<(), RecoveryCodeStoreError> {
+        let stored_codes = sqlx::query!(
+            r#"SELECT id, code_hash FROM recovery_codes WHERE user_id = $1"#,
+            user_id.as_ref()
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| RecoveryCodeStoreError::UnexpectedError(e.into()))?;
+
+        for stored in stored_codes {
+            if verify_password_hash(stored.code_hash, code.as_ref().expose_secret().to_string())
+                .await
+                .is_err()
+            {
+                continue;
+            }
+
+            // Deleting the row consumes the code, a concurrent request using it finds nothing to delete
+            let result = sqlx::query!(r#"DELETE FROM recovery_codes WHERE id = $1"#, stored.id)
+                .execute(&self.pool)
+                .await
+                .map_err(|e| RecoveryCodeStoreError::UnexpectedError(e.into()))?;
+
+            return match result.rows_affected() {
+                0 => Err(RecoveryCodeStoreError::InvalidCode),
+                _ => Ok(()),
+            };
+        }
+
+        Err(RecoveryCodeStoreError::InvalidCode)
+    }
+
+    #[tracing::instrument(name = "Counting recovery codes in PostgreSQL", skip_all)]
+    async fn remaining_codes(
+        &self,
+        user_id: &UserId,
+    ) -> Result<usize, RecoveryCodeStoreError> {
+        let remaining = sqlx::query_scalar!(
+            r#"SELECT COUNT(*) AS "count!" FROM recovery_codes WHERE user_id = $1"#,
+            user_id.as_ref()
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| RecoveryCodeStoreError::UnexpectedError(e.into()))?;
+
+        Ok(remaining as usize)
+    }
+}
@@ -0,0 +1,137 @@
+use crate::domain::data_stores::{RefreshTokenStore, RefreshTokenStoreError};
+use crate::utils::REFRESH_TOKEN_TTL_SECONDS;
+use sqlx::PgPool;
+
+pub struct PostgresRefreshTokenStore {
+    pool: PgPool,
+}
+
+impl PostgresRefreshTokenStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl RefreshTokenStore for PostgresRefreshTokenStore {
+    #[tracing::instrument(name = "Adding refresh token to PostgreSQL", skip_all)]
+    async fn add_token(
+        &mut self,
+        family_id: &str,
+        token_id: &str,
+    ) -> Result<(), RefreshTokenStoreError> {
+        // A family is only useful while its newest token is, expired families are dropped with their tokens
+        sqlx::query!(r#"DELETE FROM refresh_token_families WHERE expires_at < NOW()"#)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| RefreshTokenStoreError::UnexpectedError(e.into()))?;
+
+        let family = sqlx::query!(
+            r#"
+            INSERT INTO refresh_token_families (family_id, expires_at)
+            VALUES ($1, NOW() + make_interval(secs => $2))
+            ON CONFLICT (family_id) DO UPDATE SET expires_at = EXCLUDED.expires_at
+            RETURNING revoked
+            "#,
+            family_id,
+            *REFRESH_TOKEN_TTL_SECONDS as f64
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| RefreshTokenStoreError::UnexpectedError(e.into()))?;
+
+        if family.revoked {
+            return Err(RefreshTokenStoreError::FamilyRevoked);
+        }
+
+        sqlx::query!(
+            r#"INSERT INTO refresh_tokens (token_id, family_id) VALUES ($1, $2)"#,
+            token_id,
+            family_id
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| RefreshTokenStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Consuming refresh token in PostgreSQL", skip_all)]
+    async fn consume_token(
+        &mut self,
+        family_id: &str,
+        token_id: &str,
+    ) -> Result<(), RefreshTokenStoreError> {
+        if self.is_family_revoked(family_id).await? {
+            return Err(RefreshTokenStoreError::FamilyRevoked);
+        }
+
+        // The conditional update makes consumption atomic, only one request can flip the flag
+        let result = sqlx::query!(
+            r#"UPDATE refresh_tokens SET consumed = TRUE WHERE token_id = $1 AND family_id = $2 AND consumed = FALSE"#,
+            token_id,
+            family_id
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| RefreshTokenStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 1 {
+            return Ok(());
+        }
+
+        let existing = sqlx::query!(
+            r#"SELECT consumed FROM refresh_tokens WHERE token_id = $1 AND family_id = $2"#,
+            token_id,
+            family_id
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| RefreshTokenStoreError::UnexpectedError(e.into()))?;
+
+        match existing {
+            Some(record) if record.consumed => {
+                self.revoke_family(family_id).await?;
+                Err(RefreshTokenStoreError::TokenReused)
+            }
+            _ => Err(RefreshTokenStoreError::TokenNotFound),
+        }
+    }
+
+    #[tracing::instrument(name = "Revoking refresh token family in PostgreSQL", skip_all)]
+    async fn revoke_family(
+        &mut self,
+        family_id: &str,
+    ) -> Result<(), RefreshTokenStoreError> {
+        sqlx::query!(
+            r#"
+            INSERT INTO refresh_token_families (family_id, revoked, expires_at)
+            VALUES ($1, TRUE, NOW() + make_interval(secs => $2))
+            ON CONFLICT (family_id) DO UPDATE SET revoked = TRUE
+            "#,
+            family_id,
+            *REFRESH_TOKEN_TTL_SECONDS as f64
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| RefreshTokenStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Checking refresh token family in PostgreSQL", skip_all)]
+    async fn is_family_revoked(
+        &self,
+        family_id: &str,
+    ) -> Result<bool, RefreshTokenStoreError> {
+        let result = sqlx::query!(
+            r#"SELECT revoked FROM refresh_token_families WHERE family_id = $1"#,
+            family_id
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| RefreshTokenStoreError::UnexpectedError(e.into()))?;
+
+        Ok(result.is_some_and(|record| record.revoked))
+    }
+}
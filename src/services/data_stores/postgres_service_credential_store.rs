@@ -0,0 +1,64 @@
+use super::postgres_user_store::{compute_password_hash, verify_password_hash};
+use crate::domain::data_stores::{ServiceCredentialStore, ServiceCredentialStoreError};
+use secrecy::{ExposeSecret, SecretBox};
+use sqlx::PgPool;
+
+pub struct PostgresServiceCredentialStore {
+    pool: PgPool,
+}
+
+impl PostgresServiceCredentialStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl ServiceCredentialStore for PostgresServiceCredentialStore {
+    #[tracing::instrument(name = "Registering service in PostgreSQL", skip_all)]
+    async fn add_service(
+        &mut self,
+        service_id: &str,
+        secret: SecretBox<String>,
+    ) -> Result<(), ServiceCredentialStoreError> {
+        let secret_hash = compute_password_hash(secret.expose_secret().to_string())
+            .await
+            .map_err(ServiceCredentialStoreError::UnexpectedError)?;
+
+        let result = sqlx::query!(
+            r#"INSERT INTO service_credentials (service_id, secret_hash) VALUES ($1, $2)"#,
+            service_id,
+            secret_hash.expose_secret()
+        )
+        .execute(&self.pool)
+        .await;
+
+        match result {
+            Ok(_) => Ok(()),
+            Err(sqlx::Error::Database(db_err)) if db_err.is_unique_violation() => {
+                Err(ServiceCredentialStoreError::ServiceAlreadyExists)
+            }
+            Err(e) => Err(ServiceCredentialStoreError::UnexpectedError(e.into())),
+        }
+    }
+
+    #[tracing::instrument(name = "Validating service credentials in PostgreSQL", skip_all)]
+    async fn validate_credentials(
+        &self,
+        service_id: &str,
+        secret: &SecretBox<String>,
+    ) -> Result<(), ServiceCredentialStoreError> {
+        let secret_hash = sqlx::query_scalar!(
+            r#"SELECT secret_hash FROM service_credentials WHERE service_id = $1"#,
+            service_id
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| ServiceCredentialStoreError::UnexpectedError(e.into()))?
+        .ok_or(ServiceCredentialStoreError::InvalidCredentials)?;
+
+        verify_password_hash(secret_hash, secret.expose_secret().to_string())
+            .await
+            .map_err(|_| ServiceCredentialStoreError::InvalidCredentials)
+    }
+}
@@ -0,0 +1,43 @@
+use crate::domain::UserId;
+use crate::domain::data_stores::{SessionEpochStore, SessionEpochStoreError};
+use sqlx::PgPool;
+
+pub struct PostgresSessionEpochStore {
+    pool: PgPool,
+}
+
+impl PostgresSessionEpochStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl SessionEpochStore for PostgresSessionEpochStore {
+    #[tracing::instrument(name = "Retrieving session epoch from PostgreSQL", skip_all)]
+    async fn get_epoch(
+        &self,
+        user_id: &UserId,
+    ) -> Result<i64, SessionEpochStoreError> {
+        sqlx::query_scalar!(r#"SELECT session_epoch FROM users WHERE id = $1"#, user_id.as_ref())
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| SessionEpochStoreError::UnexpectedError(e.into()))?
+            .ok_or(SessionEpochStoreError::UserNotFound)
+    }
+
+    #[tracing::instrument(name = "Incrementing session epoch in PostgreSQL", skip_all)]
+    async fn increment_epoch(
+        &mut self,
+        user_id: &UserId,
+    ) -> Result<i64, SessionEpochStoreError> {
+        sqlx::query_scalar!(
+            r#"UPDATE users SET session_epoch = session_epoch + 1 WHERE id = $1 RETURNING session_epoch"#,
+            user_id.as_ref()
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| SessionEpochStoreError::UnexpectedError(e.into()))?
+        .ok_or(SessionEpochStoreError::UserNotFound)
+    }
+}
@@ -0,0 +1,119 @@
+use crate::domain::UserId;
+use crate::domain::data_stores::{TotpEnrollment, TotpStore, TotpStoreError};
+use sqlx::PgPool;
+
+pub struct PostgresTotpStore {
+    pool: PgPool,
+}
+
+impl PostgresTotpStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl TotpStore for PostgresTotpStore {
+    #[tracing::instrument(name = "Storing pending TOTP secret in PostgreSQL", skip_all)]
+    async fn set_pending_secret(
+        &mut self,
+        user_id: &UserId,
+        encrypted_secret: String,
+    ) -> Result<(), TotpStoreError> {
+        // The conflict update is skipped for an enabled secret, no row comes back then
+        let stored = sqlx::query_scalar!(
+            r#"
+            INSERT INTO totp_secrets (user_id, encrypted_secret)
+            VALUES ($1, $2)
+            ON CONFLICT (user_id) DO UPDATE
+                SET encrypted_secret = EXCLUDED.encrypted_secret, last_time_step = NULL
+                WHERE totp_secrets.confirmed = FALSE
+            RETURNING user_id
+            "#,
+            user_id.as_ref(),
+            encrypted_secret
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| TotpStoreError::UnexpectedError(e.into()))?;
+
+        stored.map(|_| ()).ok_or(TotpStoreError::AlreadyEnabled)
+    }
+
+    #[tracing::instrument(name = "Retrieving TOTP enrollment from PostgreSQL", skip_all)]
+    async fn get_enrollment(
+        &self,
+        user_id: &UserId,
+    ) -> Result<TotpEnrollment, TotpStoreError> {
+        sqlx::query_as!(
+            TotpEnrollment,
+            r#"SELECT encrypted_secret, confirmed FROM totp_secrets WHERE user_id = $1"#,
+            user_id.as_ref()
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| TotpStoreError::UnexpectedError(e.into()))?
+        .ok_or(TotpStoreError::NotEnrolled)
+    }
+
+    #[tracing::instrument(name = "Confirming TOTP enrollment in PostgreSQL", skip_all)]
+    async fn confirm(
+        &mut self,
+        user_id: &UserId,
+    ) -> Result<(), TotpStoreError> {
+        let result = sqlx::query!(
+            r#"UPDATE totp_secrets SET confirmed = TRUE WHERE user_id = $1"#,
+            user_id.as_ref()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| TotpStoreError::UnexpectedError(e.into()))?;
+
+        match result.rows_affected() {
+            0 => Err(TotpStoreError::NotEnrolled),
+            _ => Ok(()),
+        }
+    }
+
+    #[tracing::instrument(name = "Recording TOTP time step in PostgreSQL", skip_all)]
+    async fn use_time_step(
+        &mut self,
+        user_id: &UserId,
+        time_step: i64,
+    ) -> Result<(), TotpStoreError> {
+        // A single conditional update, two concurrent requests can't both use the same step
+        let result = sqlx::query!(
+            r#"
+            UPDATE totp_secrets SET last_time_step = $2
+            WHERE user_id = $1 AND (last_time_step IS NULL OR last_time_step < $2)
+            "#,
+            user_id.as_ref(),
+            time_step
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| TotpStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() > 0 {
+            return Ok(());
+        }
+
+        match self.get_enrollment(user_id).await {
+            Ok(_) => Err(TotpStoreError::TimeStepAlreadyUsed),
+            Err(e) => Err(e),
+        }
+    }
+
+    #[tracing::instrument(name = "Removing TOTP enrollment from PostgreSQL", skip_all)]
+    async fn remove(
+        &mut self,
+        user_id: &UserId,
+    ) -> Result<(), TotpStoreError> {
+        sqlx::query!(r#"DELETE FROM totp_secrets WHERE user_id = $1"#, user_id.as_ref())
+            .execute(&self.pool)
+            .await
+            .map_err(|e| TotpStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+}
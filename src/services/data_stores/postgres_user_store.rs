@@ -1,5 +1,5 @@
 use crate::domain::{
-    Email, Password, User,
+    Email, Password, User, UserId,
     data_stores::{UserStore, UserStoreError},
 };
 use argon2::{
@@ -27,15 +27,17 @@ impl UserStore for PostgresUserStore {
         &mut self,
         user: User,
     ) -> Result<(), UserStoreError> {
-        let password_hash = compute_password_hash(user.password().as_ref().expose_secret().to_string())
+        let password_hash = compute_password_hash(user.password().normalized().as_ref().expose_secret().to_string())
             .await
             .map_err(|e| UserStoreError::UnexpectedError(e))?;
 
         let result = sqlx::query!(
-            r#"INSERT INTO users (email, password_hash, requires_2fa) VALUES ($1, $2, $3)"#,
+            r#"INSERT INTO users (id, email, password_hash, requires_2fa, email_verified) VALUES ($1, $2, $3, $4, $5)"#,
+            user.id().as_ref(),
             user.email().as_ref().expose_secret(),
             &password_hash.expose_secret(),
-            user.requires_2fa()
+            user.requires_2fa(),
+            user.email_verified()
         )
         .execute(&self.pool)
         .await;
@@ -65,7 +67,7 @@ impl UserStore for PostgresUserStore {
         email: &Email,
     ) -> Result<User, UserStoreError> {
         let result = sqlx::query!(
-            r#"SELECT email, password_hash, requires_2fa FROM users WHERE email = $1"#,
+            r#"SELECT id, email, password_hash, requires_2fa, email_verified FROM users WHERE email = $1"#,
             email.as_ref().expose_secret()
         )
         .fetch_optional(&self.pool)
@@ -75,7 +77,34 @@ impl UserStore for PostgresUserStore {
         match result {
             Some(record) => {
                 let user = User::new(record.email, record.password_hash, record.requires_2fa)
-                    .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+                    .map_err(|e| UserStoreError::UnexpectedError(e.into()))?
+                    .with_id(record.id.into())
+                    .with_email_verified(record.email_verified);
+                Ok(user)
+            }
+            None => Err(UserStoreError::UserNotFound),
+        }
+    }
+
+    #[tracing::instrument(name = "Retrieving user by id from PostgreSQL", skip_all)]
+    async fn get_user_by_id(
+        &self,
+        id: &UserId,
+    ) -> Result<User, UserStoreError> {
+        let result = sqlx::query!(
+            r#"SELECT id, email, password_hash, requires_2fa, email_verified FROM users WHERE id = $1"#,
+            id.as_ref()
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        match result {
+            Some(record) => {
+                let user = User::new(record.email, record.password_hash, record.requires_2fa)
+                    .map_err(|e| UserStoreError::UnexpectedError(e.into()))?
+                    .with_id(record.id.into())
+                    .with_email_verified(record.email_verified);
                 Ok(user)
             }
             None => Err(UserStoreError::UserNotFound),
@@ -98,15 +127,123 @@ impl UserStore for PostgresUserStore {
 
         match result {
             Some(record) => {
-                verify_password_hash(record.password_hash, password.as_ref().expose_secret().to_string())
+                // Hashes made before passwords were normalized are of the password as typed
+                if verify_password_hash(
+                    record.password_hash.clone(),
+                    password.as_ref().expose_secret().to_string(),
+                )
+                .await
+                .is_ok()
+                {
+                    return Ok(());
+                }
+
+                let normalized = password.normalized();
+                if normalized == *password {
+                    return Err(UserStoreError::IncorrectCredentials);
+                }
+                verify_password_hash(record.password_hash, normalized.as_ref().expose_secret().to_string())
                     .await
-                    .map_err(|_| UserStoreError::IncorrectCredentials)?;
-                Ok(())
+                    .map_err(|_| UserStoreError::IncorrectCredentials)
             }
             None => Err(UserStoreError::UserNotFound),
         }
     }
 
+    #[tracing::instrument(name = "Verifying user email in PostgreSQL", skip_all)]
+    async fn verify_email(
+        &mut self,
+        email: &Email,
+    ) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            r#"UPDATE users SET email_verified = TRUE WHERE email = $1"#,
+            email.as_ref().expose_secret()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            Err(UserStoreError::UserNotFound)
+        } else {
+            Ok(())
+        }
+    }
+
+    #[tracing::instrument(name = "Updating user email in PostgreSQL", skip_all)]
+    async fn update_email(
+        &mut self,
+        id: &UserId,
+        email: Email,
+        totp_secret: Option<String>,
+    ) -> Result<(), UserStoreError> {
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        // The TOTP secret row follows the address through ON UPDATE CASCADE
+        let result = sqlx::query!(
+            r#"UPDATE users SET email = $1, email_verified = TRUE WHERE id = $2"#,
+            email.as_ref().expose_secret(),
+            id.as_ref()
+        )
+        .execute(&mut *transaction)
+        .await;
+
+        match result {
+            Ok(result) if result.rows_affected() == 0 => return Err(UserStoreError::UserNotFound),
+            Ok(_) => (),
+            Err(sqlx::Error::Database(db_err)) if db_err.is_unique_violation() => {
+                return Err(UserStoreError::UserAlreadyExists);
+            }
+            Err(e) => return Err(UserStoreError::UnexpectedError(e.into())),
+        }
+
+        if let Some(totp_secret) = totp_secret {
+            sqlx::query!(
+                r#"UPDATE totp_secrets SET encrypted_secret = $1 WHERE user_id = $2"#,
+                totp_secret,
+                id.as_ref()
+            )
+            .execute(&mut *transaction)
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+        }
+
+        transaction
+            .commit()
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))
+    }
+
+    #[tracing::instrument(name = "Updating user password in PostgreSQL", skip_all)]
+    async fn update_password(
+        &mut self,
+        email: &Email,
+        password: Password,
+    ) -> Result<(), UserStoreError> {
+        let password_hash = compute_password_hash(password.normalized().as_ref().expose_secret().to_string())
+            .await
+            .map_err(UserStoreError::UnexpectedError)?;
+
+        let result = sqlx::query!(
+            r#"UPDATE users SET password_hash = $1 WHERE email = $2"#,
+            password_hash.expose_secret(),
+            email.as_ref().expose_secret()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            Err(UserStoreError::UserNotFound)
+        } else {
+            Ok(())
+        }
+    }
+
     #[tracing::instrument(name = "Deleting user in PostgreSQL", skip_all)]
     async fn delete_account(
         &mut self,
@@ -126,7 +263,7 @@ impl UserStore for PostgresUserStore {
 }
 
 #[tracing::instrument(name = "Verify the password hash", skip_all)]
-async fn verify_password_hash(
+pub(super) async fn verify_password_hash(
     expected_password_hash: String,
     password_candidate: String,
 ) -> Result<()> {
@@ -146,7 +283,7 @@ async fn verify_password_hash(
 }
 
 #[tracing::instrument(name = "Computing password hash", skip_all)]
-async fn compute_password_hash(password: String) -> Result<SecretBox<String>> {
+pub(super) async fn compute_password_hash(password: String) -> Result<SecretBox<String>> {
     let current_span: tracing::Span = tracing::Span::current();
 
     let result = tokio::task::spawn_blocking(move || {
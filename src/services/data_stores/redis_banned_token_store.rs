@@ -1,15 +1,21 @@
-use crate::domain::data_stores::{BannedTokenStore, BannedTokenStoreError};
+use crate::domain::data_stores::{BannedTokenStore, BannedTokenStoreError, Revocation, RevocationStream};
 use crate::utils::TOKEN_TTL_SECONDS;
-use crate::utils::redis_env::BANNED_TOKEN_KEY_PREFIX;
+use crate::utils::redis_env::{BANNED_TOKEN_KEY_PREFIX, REVOCATIONS_CHANNEL};
 use redis::aio::MultiplexedConnection;
+use tokio_stream::StreamExt;
 
 pub struct RedisBannedTokenStore {
     conn: MultiplexedConnection,
+    // Subscribing needs a dedicated connection, every watcher opens its own from the client
+    client: redis::Client,
 }
 
 impl RedisBannedTokenStore {
-    pub fn new(conn: MultiplexedConnection) -> Self {
-        Self { conn }
+    pub fn new(
+        conn: MultiplexedConnection,
+        client: redis::Client,
+    ) -> Self {
+        Self { conn, client }
     }
 }
 
@@ -19,13 +25,25 @@ impl BannedTokenStore for RedisBannedTokenStore {
         &mut self,
         token: &str,
     ) -> Result<(), BannedTokenStoreError> {
-        Ok(redis::cmd("SETEX")
+        redis::cmd("SETEX")
             .arg(&get_key(token))
             .arg(*TOKEN_TTL_SECONDS)
             .arg(true)
             .query_async::<_, ()>(&mut self.conn.clone())
             .await
-            .map_err(|_| BannedTokenStoreError::UnexpectedError)?)
+            .map_err(|_| BannedTokenStoreError::UnexpectedError)?;
+
+        // The token is banned at this point, watchers missing the event still find it with `is_banned`
+        if let Err(e) = self
+            .publish_revocation(Revocation::Token {
+                token: token.to_string(),
+            })
+            .await
+        {
+            tracing::warn!("Failed to publish the revocation of a banned token: {:?}", e);
+        }
+
+        Ok(())
     }
 
     async fn is_banned(
@@ -38,6 +56,45 @@ impl BannedTokenStore for RedisBannedTokenStore {
             .await
             .map_err(|_| BannedTokenStoreError::UnexpectedError)
     }
+
+    async fn publish_revocation(
+        &self,
+        revocation: Revocation,
+    ) -> Result<(), BannedTokenStoreError> {
+        let payload = serde_json::to_string(&revocation).map_err(|_| BannedTokenStoreError::UnexpectedError)?;
+
+        redis::cmd("PUBLISH")
+            .arg(REVOCATIONS_CHANNEL)
+            .arg(payload)
+            .query_async::<_, ()>(&mut self.conn.clone())
+            .await
+            .map_err(|_| BannedTokenStoreError::UnexpectedError)
+    }
+
+    async fn watch_revocations(&self) -> Result<RevocationStream, BannedTokenStoreError> {
+        let mut pubsub = self
+            .client
+            .get_async_pubsub()
+            .await
+            .map_err(|_| BannedTokenStoreError::UnexpectedError)?;
+        pubsub
+            .subscribe(REVOCATIONS_CHANNEL)
+            .await
+            .map_err(|_| BannedTokenStoreError::UnexpectedError)?;
+
+        let stream = pubsub.into_on_message().filter_map(|message| {
+            let revocation = message
+                .get_payload::<String>()
+                .ok()
+                .and_then(|payload| serde_json::from_str::<Revocation>(&payload).ok());
+            if revocation.is_none() {
+                tracing::warn!("Ignoring a malformed message on the revocations channel");
+            }
+            revocation
+        });
+
+        Ok(Box::pin(stream))
+    }
 }
 
 fn get_key(token: &str) -> String {
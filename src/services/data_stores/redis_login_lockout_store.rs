@@ -0,0 +1,118 @@
+use crate::domain::LockoutSubject;
+use crate::domain::data_stores::{LoginLockoutStore, LoginLockoutStoreError};
+use crate::utils::redis_env::{LOGIN_FAILURES_KEY_PREFIX, LOGIN_LOCK_KEY_PREFIX, LOGIN_LOCKOUT_STREAK_KEY_PREFIX};
+use crate::utils::{LOCKOUT_FAILURE_WINDOW_SECONDS, LOCKOUT_RESET_SECONDS};
+use redis::aio::MultiplexedConnection;
+
+// Shared by every instance, so that spreading the guesses over the replicas doesn't help
+pub struct RedisLoginLockoutStore {
+    conn: MultiplexedConnection,
+}
+
+impl RedisLoginLockoutStore {
+    pub fn new(conn: MultiplexedConnection) -> Self {
+        Self { conn }
+    }
+}
+
+#[async_trait::async_trait]
+impl LoginLockoutStore for RedisLoginLockoutStore {
+    async fn locked_for(
+        &self,
+        subject: &LockoutSubject,
+    ) -> Result<Option<u64>, LoginLockoutStoreError> {
+        let ttl = redis::cmd("TTL")
+            .arg(get_key(LOGIN_LOCK_KEY_PREFIX, subject))
+            .query_async::<_, i64>(&mut self.conn.clone())
+            .await
+            .map_err(|e| LoginLockoutStoreError::UnexpectedError(e.into()))?;
+
+        Ok((ttl > 0).then_some(ttl as u64))
+    }
+
+    async fn record_failure(
+        &mut self,
+        subject: &LockoutSubject,
+    ) -> Result<u32, LoginLockoutStoreError> {
+        let key = get_key(LOGIN_FAILURES_KEY_PREFIX, subject);
+
+        // The window starts with its first failure, NX keeps the expiry the later ones find. Both run in the same
+        // transaction so a counter can't be left without an expiry.
+        let (failures,) = redis::pipe()
+            .atomic()
+            .cmd("INCR")
+            .arg(&key)
+            .cmd("EXPIRE")
+            .arg(&key)
+            .arg(*LOCKOUT_FAILURE_WINDOW_SECONDS)
+            .arg("NX")
+            .ignore()
+            .query_async::<_, (u32,)>(&mut self.conn.clone())
+            .await
+            .map_err(|e| LoginLockoutStoreError::UnexpectedError(e.into()))?;
+
+        Ok(failures)
+    }
+
+    async fn lockout_streak(
+        &self,
+        subject: &LockoutSubject,
+    ) -> Result<u32, LoginLockoutStoreError> {
+        let streak = redis::cmd("GET")
+            .arg(get_key(LOGIN_LOCKOUT_STREAK_KEY_PREFIX, subject))
+            .query_async::<_, Option<u32>>(&mut self.conn.clone())
+            .await
+            .map_err(|e| LoginLockoutStoreError::UnexpectedError(e.into()))?;
+
+        Ok(streak.unwrap_or(0))
+    }
+
+    async fn lock(
+        &mut self,
+        subject: &LockoutSubject,
+        seconds: u64,
+    ) -> Result<(), LoginLockoutStoreError> {
+        let streak_key = get_key(LOGIN_LOCKOUT_STREAK_KEY_PREFIX, subject);
+
+        redis::pipe()
+            .atomic()
+            .cmd("SET")
+            .arg(get_key(LOGIN_LOCK_KEY_PREFIX, subject))
+            .arg(1)
+            .arg("EX")
+            .arg(seconds.max(1))
+            .ignore()
+            .cmd("DEL")
+            .arg(get_key(LOGIN_FAILURES_KEY_PREFIX, subject))
+            .ignore()
+            .cmd("INCR")
+            .arg(&streak_key)
+            .ignore()
+            .cmd("EXPIRE")
+            .arg(&streak_key)
+            .arg(*LOCKOUT_RESET_SECONDS)
+            .ignore()
+            .query_async::<_, ()>(&mut self.conn.clone())
+            .await
+            .map_err(|e| LoginLockoutStoreError::UnexpectedError(e.into()))
+    }
+
+    async fn reset(
+        &mut self,
+        subject: &LockoutSubject,
+    ) -> Result<(), LoginLockoutStoreError> {
+        redis::cmd("DEL")
+            .arg(get_key(LOGIN_FAILURES_KEY_PREFIX, subject))
+            .arg(get_key(LOGIN_LOCKOUT_STREAK_KEY_PREFIX, subject))
+            .query_async::<_, ()>(&mut self.conn.clone())
+            .await
+            .map_err(|e| LoginLockoutStoreError::UnexpectedError(e.into()))
+    }
+}
+
+fn get_key(
+    prefix: &str,
+    subject: &LockoutSubject,
+) -> String {
+    format!("{}{}", prefix, subject.key())
+}
@@ -0,0 +1,97 @@
+use crate::domain::data_stores::{PasskeyChallengeStore, PasskeyChallengeStoreError};
+use crate::domain::{PasskeyCeremony, PasskeyChallenge, UserId};
+use crate::utils::PASSKEY_CHALLENGE_TTL_SECONDS;
+use crate::utils::redis_env::PASSKEY_CHALLENGE_KEY_PREFIX;
+use redis::aio::MultiplexedConnection;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize)]
+struct StoredChallenge {
+    challenge: String,
+    registration: bool,
+    user_id: Option<String>,
+    user_handle: Option<String>,
+}
+
+pub struct RedisPasskeyChallengeStore {
+    conn: MultiplexedConnection,
+}
+
+impl RedisPasskeyChallengeStore {
+    pub fn new(conn: MultiplexedConnection) -> Self {
+        Self { conn }
+    }
+}
+
+#[async_trait::async_trait]
+impl PasskeyChallengeStore for RedisPasskeyChallengeStore {
+    async fn add_challenge(
+        &mut self,
+        challenge_id: &str,
+        challenge: PasskeyChallenge,
+    ) -> Result<(), PasskeyChallengeStoreError> {
+        let stored = match challenge.ceremony {
+            PasskeyCeremony::Registration { user_id, user_handle } => StoredChallenge {
+                challenge: challenge.challenge,
+                registration: true,
+                user_id: Some(user_id.to_string()),
+                user_handle: Some(user_handle),
+            },
+            PasskeyCeremony::Authentication { user_id } => StoredChallenge {
+                challenge: challenge.challenge,
+                registration: false,
+                user_id: user_id.map(|user_id| user_id.to_string()),
+                user_handle: None,
+            },
+        };
+        let value =
+            serde_json::to_string(&stored).map_err(|e| PasskeyChallengeStoreError::UnexpectedError(e.into()))?;
+
+        redis::cmd("SETEX")
+            .arg(get_key(challenge_id))
+            .arg(PASSKEY_CHALLENGE_TTL_SECONDS)
+            .arg(value)
+            .query_async::<_, ()>(&mut self.conn.clone())
+            .await
+            .map_err(|e| PasskeyChallengeStoreError::UnexpectedError(e.into()))
+    }
+
+    async fn take_challenge(
+        &mut self,
+        challenge_id: &str,
+    ) -> Result<PasskeyChallenge, PasskeyChallengeStoreError> {
+        let value = redis::cmd("GETDEL")
+            .arg(get_key(challenge_id))
+            .query_async::<_, Option<String>>(&mut self.conn.clone())
+            .await
+            .map_err(|e| PasskeyChallengeStoreError::UnexpectedError(e.into()))?
+            .ok_or(PasskeyChallengeStoreError::ChallengeNotFound)?;
+
+        let stored: StoredChallenge =
+            serde_json::from_str(&value).map_err(|e| PasskeyChallengeStoreError::UnexpectedError(e.into()))?;
+        let user_id = stored
+            .user_id
+            .map(|user_id| UserId::parse(&user_id))
+            .transpose()
+            .map_err(|e| PasskeyChallengeStoreError::UnexpectedError(e.into()))?;
+
+        let ceremony = match (stored.registration, user_id, stored.user_handle) {
+            (true, Some(user_id), Some(user_handle)) => PasskeyCeremony::Registration { user_id, user_handle },
+            (false, user_id, None) => PasskeyCeremony::Authentication { user_id },
+            _ => {
+                return Err(PasskeyChallengeStoreError::UnexpectedError(color_eyre::eyre::eyre!(
+                    "Inconsistent passkey challenge"
+                )));
+            }
+        };
+
+        Ok(PasskeyChallenge {
+            challenge: stored.challenge,
+            ceremony,
+        })
+    }
+}
+
+fn get_key(challenge_id: &str) -> String {
+    format!("{}{}", PASSKEY_CHALLENGE_KEY_PREFIX, challenge_id)
+}
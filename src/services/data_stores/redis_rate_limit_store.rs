@@ -0,0 +1,67 @@
+use crate::domain::RateLimitDecision;
+use crate::domain::data_stores::{RateLimitStore, RateLimitStoreError};
+use crate::utils::redis_env::RATE_LIMIT_KEY_PREFIX;
+use redis::Script;
+use redis::aio::MultiplexedConnection;
+
+// Refill and take in one step so that the instances sharing a bucket can't race. The clock is
+// the one of Redis, the instances may not agree on the time.
+const TAKE_TOKEN_SCRIPT: &str = r#"
+local capacity = tonumber(ARGV[1])
+local refill_per_ms = tonumber(ARGV[2]) / 60000
+local time = redis.call('TIME')
+local now = tonumber(time[1]) * 1000 + math.floor(tonumber(time[2]) / 1000)
+
+local bucket = redis.call('HMGET', KEYS[1], 'tokens', 'updated_at')
+local tokens = tonumber(bucket[1]) or capacity
+local updated_at = tonumber(bucket[2]) or now
+tokens = math.min(capacity, tokens + math.max(0, now - updated_at) * refill_per_ms)
+
+local allowed = 0
+if tokens >= 1 then
+    tokens = tokens - 1
+    allowed = 1
+end
+
+redis.call('HSET', KEYS[1], 'tokens', tostring(tokens), 'updated_at', now)
+redis.call('PEXPIRE', KEYS[1], math.max(1, math.ceil((capacity - tokens) / refill_per_ms)))
+return { allowed, tostring(tokens) }
+"#;
+
+pub struct RedisRateLimitStore {
+    conn: MultiplexedConnection,
+    script: Script,
+}
+
+impl RedisRateLimitStore {
+    pub fn new(conn: MultiplexedConnection) -> Self {
+        Self {
+            conn,
+            script: Script::new(TAKE_TOKEN_SCRIPT),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl RateLimitStore for RedisRateLimitStore {
+    async fn take_token(
+        &self,
+        key: &str,
+        capacity: u32,
+        refill_per_minute: u32,
+    ) -> Result<RateLimitDecision, RateLimitStoreError> {
+        let (allowed, tokens) = self
+            .script
+            .key(format!("{}{}", RATE_LIMIT_KEY_PREFIX, key))
+            .arg(capacity)
+            .arg(refill_per_minute)
+            .invoke_async::<_, (bool, String)>(&mut self.conn.clone())
+            .await
+            .map_err(|e| RateLimitStoreError::UnexpectedError(e.into()))?;
+
+        let tokens = tokens
+            .parse::<f64>()
+            .map_err(|e| RateLimitStoreError::UnexpectedError(e.into()))?;
+        Ok(RateLimitDecision::new(allowed, tokens, capacity, refill_per_minute))
+    }
+}
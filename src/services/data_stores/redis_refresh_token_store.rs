@@ -0,0 +1,104 @@
+use crate::domain::data_stores::{RefreshTokenStore, RefreshTokenStoreError};
+use crate::utils::REFRESH_TOKEN_TTL_SECONDS;
+use crate::utils::redis_env::{REFRESH_TOKEN_KEY_PREFIX, REVOKED_REFRESH_FAMILY_KEY_PREFIX};
+use redis::aio::MultiplexedConnection;
+
+const ACTIVE: &str = "active";
+const CONSUMED: &str = "consumed";
+
+pub struct RedisRefreshTokenStore {
+    conn: MultiplexedConnection,
+}
+
+impl RedisRefreshTokenStore {
+    pub fn new(conn: MultiplexedConnection) -> Self {
+        Self { conn }
+    }
+}
+
+#[async_trait::async_trait]
+impl RefreshTokenStore for RedisRefreshTokenStore {
+    async fn add_token(
+        &mut self,
+        family_id: &str,
+        token_id: &str,
+    ) -> Result<(), RefreshTokenStoreError> {
+        if self.is_family_revoked(family_id).await? {
+            return Err(RefreshTokenStoreError::FamilyRevoked);
+        }
+
+        Ok(redis::cmd("SETEX")
+            .arg(get_token_key(family_id, token_id))
+            .arg(*REFRESH_TOKEN_TTL_SECONDS)
+            .arg(ACTIVE)
+            .query_async::<_, ()>(&mut self.conn.clone())
+            .await
+            .map_err(|e| RefreshTokenStoreError::UnexpectedError(e.into()))?)
+    }
+
+    async fn consume_token(
+        &mut self,
+        family_id: &str,
+        token_id: &str,
+    ) -> Result<(), RefreshTokenStoreError> {
+        if self.is_family_revoked(family_id).await? {
+            return Err(RefreshTokenStoreError::FamilyRevoked);
+        }
+
+        // SET ... XX GET flips the token to consumed and returns its previous state atomically,
+        // so two concurrent refreshes with the same token can't both succeed
+        let previous = redis::cmd("SET")
+            .arg(get_token_key(family_id, token_id))
+            .arg(CONSUMED)
+            .arg("XX")
+            .arg("KEEPTTL")
+            .arg("GET")
+            .query_async::<_, Option<String>>(&mut self.conn.clone())
+            .await
+            .map_err(|e| RefreshTokenStoreError::UnexpectedError(e.into()))?;
+
+        match previous.as_deref() {
+            Some(ACTIVE) => Ok(()),
+            Some(_) => {
+                self.revoke_family(family_id).await?;
+                Err(RefreshTokenStoreError::TokenReused)
+            }
+            None => Err(RefreshTokenStoreError::TokenNotFound),
+        }
+    }
+
+    async fn revoke_family(
+        &mut self,
+        family_id: &str,
+    ) -> Result<(), RefreshTokenStoreError> {
+        Ok(redis::cmd("SETEX")
+            .arg(get_revoked_family_key(family_id))
+            .arg(*REFRESH_TOKEN_TTL_SECONDS)
+            .arg(true)
+            .query_async::<_, ()>(&mut self.conn.clone())
+            .await
+            .map_err(|e| RefreshTokenStoreError::UnexpectedError(e.into()))?)
+    }
+
+    async fn is_family_revoked(
+        &self,
+        family_id: &str,
+    ) -> Result<bool, RefreshTokenStoreError> {
+        redis::cmd("EXISTS")
+            .arg(get_revoked_family_key(family_id))
+            .query_async(&mut self.conn.clone())
+            .await
+            .map_err(|e| RefreshTokenStoreError::UnexpectedError(e.into()))
+    }
+}
+
+fn get_token_key(
+    family_id: &str,
+    token_id: &str,
+) -> String {
+    format!("{}{}:{}", REFRESH_TOKEN_KEY_PREFIX, family_id, token_id)
+}
+
+fn get_revoked_family_key(family_id: &str) -> String {
+    format!("{}{}", REVOKED_REFRESH_FAMILY_KEY_PREFIX, family_id)
+}
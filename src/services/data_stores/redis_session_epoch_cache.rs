@@ -0,0 +1,92 @@
+use crate::domain::UserId;
+use crate::domain::data_stores::{SessionEpochStore, SessionEpochStoreError};
+use crate::utils::REFRESH_TOKEN_TTL_SECONDS;
+use crate::utils::redis_env::SESSION_EPOCH_KEY_PREFIX;
+use redis::aio::MultiplexedConnection;
+
+// Read-through Redis cache in front of the store that owns the epochs, every authenticated
+// request reads the epoch so it shouldn't hit the database each time
+pub struct RedisSessionEpochCache {
+    conn: MultiplexedConnection,
+    store: Box<dyn SessionEpochStore>,
+}
+
+impl RedisSessionEpochCache {
+    pub fn new(
+        conn: MultiplexedConnection,
+        store: impl SessionEpochStore + 'static,
+    ) -> Self {
+        Self {
+            conn,
+            store: Box::new(store),
+        }
+    }
+
+    async fn cache_epoch(
+        &self,
+        user_id: &UserId,
+        epoch: i64,
+    ) -> Result<(), SessionEpochStoreError> {
+        redis::cmd("SETEX")
+            .arg(get_key(user_id))
+            .arg(*REFRESH_TOKEN_TTL_SECONDS)
+            .arg(epoch)
+            .query_async::<_, ()>(&mut self.conn.clone())
+            .await
+            .map_err(|e| SessionEpochStoreError::UnexpectedError(e.into()))
+    }
+
+    async fn evict_epoch(
+        &self,
+        user_id: &UserId,
+    ) -> Result<(), SessionEpochStoreError> {
+        redis::cmd("DEL")
+            .arg(get_key(user_id))
+            .query_async::<_, ()>(&mut self.conn.clone())
+            .await
+            .map_err(|e| SessionEpochStoreError::UnexpectedError(e.into()))
+    }
+}
+
+#[async_trait::async_trait]
+impl SessionEpochStore for RedisSessionEpochCache {
+    async fn get_epoch(
+        &self,
+        user_id: &UserId,
+    ) -> Result<i64, SessionEpochStoreError> {
+        let cached = redis::cmd("GET")
+            .arg(get_key(user_id))
+            .query_async::<_, Option<i64>>(&mut self.conn.clone())
+            .await
+            .map_err(|e| SessionEpochStoreError::UnexpectedError(e.into()))?;
+
+        if let Some(epoch) = cached {
+            return Ok(epoch);
+        }
+
+        let epoch = self.store.get_epoch(user_id).await?;
+        self.cache_epoch(user_id, epoch).await?;
+        Ok(epoch)
+    }
+
+    async fn increment_epoch(
+        &mut self,
+        user_id: &UserId,
+    ) -> Result<i64, SessionEpochStoreError> {
+        let epoch = match self.store.increment_epoch(user_id).await {
+            Ok(epoch) => epoch,
+            // The user is gone, a cached epoch would keep their tokens valid until it expires
+            Err(SessionEpochStoreError::UserNotFound) => {
+                self.evict_epoch(user_id).await?;
+                return Err(SessionEpochStoreError::UserNotFound);
+            }
+            Err(e) => return Err(e),
+        };
+        self.cache_epoch(user_id, epoch).await?;
+        Ok(epoch)
+    }
+}
+
+fn get_key(user_id: &UserId) -> String {
+    format!("{}{}", SESSION_EPOCH_KEY_PREFIX, user_id)
+}
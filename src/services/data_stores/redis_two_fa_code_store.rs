@@ -2,15 +2,16 @@ use crate::domain::{
     Email, LoginAttemptId, TwoFACode,
     data_stores::{TwoFACodeStore, TwoFACodeStoreError},
 };
-use crate::utils::TOKEN_TTL_SECONDS;
+use crate::utils::TWO_FA_CODE_TTL_SECONDS;
 use crate::utils::redis_env::TWO_FA_CODE_PREFIX;
 use redis::aio::MultiplexedConnection;
 use secrecy::{ExposeSecret, SecretBox};
-use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize)]
-struct TwoFATuple(pub String, pub String);
+const LOGIN_ATTEMPT_ID_FIELD: &str = "login_attempt_id";
+const CODE_FIELD: &str = "code";
+const FAILED_ATTEMPTS_FIELD: &str = "failed_attempts";
 
+// A pending code is a hash, the failure counter sits next to the code and expires with it
 pub struct RedisTwoFACodeStore {
     conn: MultiplexedConnection,
 }
@@ -29,16 +30,26 @@ impl TwoFACodeStore for RedisTwoFACodeStore {
         login_attempt_id: LoginAttemptId,
         code: TwoFACode,
     ) -> Result<(), TwoFACodeStoreError> {
-        let two_fa = serde_json::to_string(&TwoFATuple(
-            login_attempt_id.as_ref().expose_secret().to_string(),
-            code.as_ref().expose_secret().to_string(),
-        ))
-        .map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))?;
+        let key = get_key(email);
 
-        Ok(redis::cmd("SETEX")
-            .arg(&get_key(email))
-            .arg(*TOKEN_TTL_SECONDS)
-            .arg(&two_fa)
+        Ok(redis::pipe()
+            .atomic()
+            .cmd("DEL")
+            .arg(&key)
+            .ignore()
+            .cmd("HSET")
+            .arg(&key)
+            .arg(LOGIN_ATTEMPT_ID_FIELD)
+            .arg(login_attempt_id.as_ref().expose_secret())
+            .arg(CODE_FIELD)
+            .arg(code.as_ref().expose_secret())
+            .arg(FAILED_ATTEMPTS_FIELD)
+            .arg(0)
+            .ignore()
+            .cmd("EXPIRE")
+            .arg(&key)
+            .arg(*TWO_FA_CODE_TTL_SECONDS)
+            .ignore()
             .query_async::<_, ()>(&mut self.conn.clone())
             .await
             .map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))?)
@@ -59,29 +70,53 @@ impl TwoFACodeStore for RedisTwoFACodeStore {
         &self,
         email: &Email,
     ) -> Result<(LoginAttemptId, TwoFACode), TwoFACodeStoreError> {
-        let key = get_key(email);
-
-        let value = redis::cmd("GET")
-            .arg(&key)
-            .query_async::<_, Option<String>>(&mut self.conn.clone())
+        let (login_attempt_id, code) = redis::cmd("HMGET")
+            .arg(get_key(email))
+            .arg(LOGIN_ATTEMPT_ID_FIELD)
+            .arg(CODE_FIELD)
+            .query_async::<_, (Option<String>, Option<String>)>(&mut self.conn.clone())
             .await
             .map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))?;
 
-        match value {
-            Some(json_string) => {
-                let two_fa_tuple: TwoFATuple =
-                    serde_json::from_str(&json_string).map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))?;
-
-                let login_attempt_id = LoginAttemptId::new(SecretBox::new(Box::from(two_fa_tuple.0)))
+        match (login_attempt_id, code) {
+            (Some(login_attempt_id), Some(code)) => {
+                let login_attempt_id = LoginAttemptId::new(SecretBox::new(Box::from(login_attempt_id)))
                     .map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))?;
 
-                let two_fa_code = TwoFACode::new(SecretBox::new(Box::from(two_fa_tuple.1)))
+                let two_fa_code = TwoFACode::new(SecretBox::new(Box::from(code)))
                     .map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))?;
 
                 Ok((login_attempt_id, two_fa_code))
             }
-            None => Err(TwoFACodeStoreError::LoginAttemptIdNotFound),
+            _ => Err(TwoFACodeStoreError::LoginAttemptIdNotFound),
+        }
+    }
+
+    async fn record_failed_attempt(
+        &mut self,
+        email: &Email,
+    ) -> Result<u32, TwoFACodeStoreError> {
+        let key = get_key(email);
+
+        let (failed_attempts, ttl) = redis::pipe()
+            .atomic()
+            .cmd("HINCRBY")
+            .arg(&key)
+            .arg(FAILED_ATTEMPTS_FIELD)
+            .arg(1)
+            .cmd("TTL")
+            .arg(&key)
+            .query_async::<_, (u32, i64)>(&mut self.conn.clone())
+            .await
+            .map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))?;
+
+        // No expiry means the code was gone and HINCRBY just created a bare counter
+        if ttl < 0 {
+            self.remove_code(email).await?;
+            return Err(TwoFACodeStoreError::LoginAttemptIdNotFound);
         }
+
+        Ok(failed_attempts)
     }
 }
 
@@ -135,6 +135,16 @@ impl EmailClient for SesEmailClient {
             .await
             .map_err(|e| EmailClientError::UnexpectedError(e.into()))
     }
+
+    async fn check_health(&self) -> Result<(), EmailClientError> {
+        // Same call as the start-up validation, without its logging since this runs periodically
+        self.client
+            .get_account()
+            .send()
+            .await
+            .map(|_| ())
+            .map_err(|e| EmailClientError::UnexpectedError(SesEmailError::SesService(e.into()).into()))
+    }
 }
 
 #[cfg(test)]
@@ -1,12 +1,28 @@
 use crate::domain::Email;
 use crate::domain::client::{EmailClient, EmailClientError};
 use secrecy::ExposeSecret;
+use std::sync::{Mutex, PoisonError};
 
-pub struct MockEmailClient;
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SentEmail {
+    pub recipient: String,
+    pub subject: String,
+    pub content: String,
+}
+
+// Prints the emails and keeps them, tests read the codes and links they carry
+#[derive(Default)]
+pub struct MockEmailClient {
+    sent_emails: Mutex<Vec<SentEmail>>,
+}
 
 impl MockEmailClient {
     pub fn new() -> Self {
-        MockEmailClient
+        Self::default()
+    }
+
+    pub fn sent_emails(&self) -> Vec<SentEmail> {
+        self.sent_emails.lock().unwrap_or_else(PoisonError::into_inner).clone()
     }
 }
 
@@ -25,6 +41,18 @@ impl EmailClient for MockEmailClient {
             content
         );
 
+        self.sent_emails
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(SentEmail {
+                recipient: recipient.as_ref().expose_secret().to_string(),
+                subject: subject.to_string(),
+                content: content.to_string(),
+            });
+        Ok(())
+    }
+
+    async fn check_health(&self) -> Result<(), EmailClientError> {
         Ok(())
     }
 }
@@ -1,34 +1,56 @@
 use super::constants::JWT_COOKIE_NAME;
-use crate::domain::Email;
-use crate::utils::{COOKIE_DOMAIN, JWT_REFRESH_COOKIE_NAME, JWT_SECRET, REFRESH_TOKEN_TTL_SECONDS, TOKEN_TTL_SECONDS};
+use crate::app_state::AppState;
+use crate::domain::data_stores::{RefreshTokenStoreError, Revocation, SessionEpochStoreError};
+use crate::domain::{AuthAPIError, Email, UserId};
+use crate::utils::{
+    COOKIE_DOMAIN, EMAIL_CHANGE_TOKEN_TTL_SECONDS, EMAIL_VERIFICATION_TOKEN_TTL_SECONDS, JWT_AUDIENCE, JWT_ISSUER,
+    JWT_REFRESH_COOKIE_NAME, PASSWORD_RESET_TOKEN_TTL_SECONDS, REFRESH_TOKEN_TTL_SECONDS, SERVICE_TOKEN_TTL_SECONDS,
+    TOKEN_TTL_SECONDS, jwt_key_ring,
+};
+use axum_extra::extract::CookieJar;
 use axum_extra::extract::cookie::{Cookie, SameSite};
 use chrono::Utc;
-use jsonwebtoken::{DecodingKey, EncodingKey, Validation, decode, encode};
-use secrecy::ExposeSecret;
+use color_eyre::Report;
+use color_eyre::eyre::eyre;
+use jsonwebtoken::errors::ErrorKind;
+use jsonwebtoken::{decode, encode};
+use secrecy::{ExposeSecret, SecretBox};
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
 
-// Create a cookie with a new JWT auth token
-pub fn generate_auth_cookie(email: &Email) -> Result<Cookie<'static>, GenerateTokenError> {
-    let token = generate_token_pair(email)?;
-    let cookie = Cookie::build((JWT_COOKIE_NAME, token.access_token))
+// Create the cookie carrying a JWT access token
+pub fn create_auth_cookie(access_token: String) -> Cookie<'static> {
+    Cookie::build((JWT_COOKIE_NAME, access_token))
         .domain(COOKIE_DOMAIN.as_str())
         .path("/")
         .http_only(true)
         .same_site(SameSite::Lax)
-        .build();
-    Ok(cookie)
+        .build()
 }
 
-pub fn generate_refresh_cookie(email: &Email) -> Result<Cookie<'static>, GenerateTokenError> {
-    let token = generate_token_pair(email)?;
-    let cookie = Cookie::build((JWT_REFRESH_COOKIE_NAME, token.refresh_token))
+// Create the cookie carrying a JWT refresh token
+pub fn create_refresh_cookie(refresh_token: String) -> Cookie<'static> {
+    Cookie::build((JWT_REFRESH_COOKIE_NAME, refresh_token))
         .domain(COOKIE_DOMAIN.as_str())
         .path("/")
         .http_only(true)
         .same_site(SameSite::Lax)
-        .build();
+        .build()
+}
+
+// Removal cookies must carry the same domain and path the cookies were set with, otherwise the browser keeps them
+pub fn remove_token_cookies(jar: CookieJar) -> CookieJar {
+    jar.remove(create_auth_cookie(String::new()))
+        .remove(create_refresh_cookie(String::new()))
+}
 
-    Ok(cookie)
+// Set both cookies of a token pair, they always come from the same pair
+pub fn add_token_cookies(
+    jar: CookieJar,
+    token_pair: &TokenPair,
+) -> CookieJar {
+    jar.add(create_auth_cookie(token_pair.access_token.clone()))
+        .add(create_refresh_cookie(token_pair.refresh_token.clone()))
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -40,83 +62,432 @@ pub enum GenerateTokenError {
     UnexpectedError,
 }
 
-// Check if the JWT auth token is valid by decoding it using the JWT secret
-pub async fn validate_token(token: &str) -> Result<Claims, jsonwebtoken::errors::Error> {
-    decode::<Claims>(
-        token,
-        &DecodingKey::from_secret(JWT_SECRET.expose_secret().as_bytes()),
-        &Validation::default(),
-    )
-    .map(|data| data.claims)
+#[derive(Debug, thiserror::Error)]
+pub enum ValidateTokenError {
+    #[error("Invalid JWT token")]
+    InvalidToken(#[from] jsonwebtoken::errors::Error),
+
+    #[error("Unexpected JWT token type")]
+    UnexpectedTokenType,
+
+    #[error("JWT token issued in the future")]
+    IssuedInTheFuture,
+}
+
+// Check if the JWT auth token is valid by decoding it with the key named in its header,
+// retired keys from the key ring keep verifying tokens issued before a rotation.
+// Issuer, audience, expiry and not-before are enforced, and the token must be of the expected type.
+pub fn validate_token(
+    token: &str,
+    expected_type: TokenType,
+) -> Result<Claims, ValidateTokenError> {
+    let key_ring = jwt_key_ring();
+    let key = key_ring.verification_key(token)?;
+
+    let mut validation = key.validation();
+    validation.set_issuer(&[JWT_ISSUER.as_str()]);
+    validation.set_audience(&[JWT_AUDIENCE.as_str()]);
+    validation.set_required_spec_claims(&["exp", "nbf", "iss", "aud", "sub"]);
+    validation.validate_nbf = true;
+
+    let claims = decode::<Claims>(token, key.decoding_key(), &validation)?.claims;
+
+    if claims.token_type != expected_type {
+        return Err(ValidateTokenError::UnexpectedTokenType);
+    }
+
+    let now = Utc::now().timestamp() as u64;
+    if claims.iat as u64 > now + validation.leeway {
+        return Err(ValidateTokenError::IssuedInTheFuture);
+    }
+
+    Ok(claims)
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum TokenRejection {
+    #[error("Invalid token")]
+    InvalidToken(#[from] ValidateTokenError),
+
+    #[error("Token has been banned")]
+    Banned,
+
+    #[error("Token belongs to a session that has been logged out everywhere")]
+    SessionRevoked,
+
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+// Validate a token and check it against the server side state: banned tokens (logged out) are
+// rejected, and so is a token issued before the last "log out everywhere" of its user since it
+// carries an outdated session epoch. Every HTTP route and gRPC method accepting a token goes through here.
+pub async fn authorize_token(
+    state: &AppState,
+    token: &str,
+    expected_type: TokenType,
+) -> Result<Claims, TokenRejection> {
+    let claims = validate_token(token, expected_type)?;
+
+    let banned = state
+        .banned_token_store
+        .read()
+        .await
+        .is_banned(token)
+        .await
+        .map_err(|e| TokenRejection::UnexpectedError(eyre!("Failed to check the banned tokens: {:?}", e)))?;
+    if banned {
+        return Err(TokenRejection::Banned);
+    }
+    let user_id = claims.user_id()?;
+
+    // Tokens of deleted accounts are rejected along with the logged out sessions
+    let epoch = match state.session_epoch_store.read().await.get_epoch(&user_id).await {
+        Ok(epoch) => epoch,
+        Err(SessionEpochStoreError::UserNotFound) => return Err(TokenRejection::SessionRevoked),
+        Err(e) => return Err(TokenRejection::UnexpectedError(e.into())),
+    };
+    if claims.epoch != epoch {
+        return Err(TokenRejection::SessionRevoked);
+    }
+
+    Ok(claims)
 }
 
-// Create a JWT auth token by encoding claims using the JWT secret
+// Create a JWT auth token by signing claims with the current key of the key ring
 fn create_token(claims: &Claims) -> Result<String, jsonwebtoken::errors::Error> {
-    encode(
-        &jsonwebtoken::Header::default(),
-        &claims,
-        &EncodingKey::from_secret(JWT_SECRET.expose_secret().as_bytes()),
-    )
+    let key_ring = jwt_key_ring();
+    let key = key_ring.current();
+    let encoding_key = key.encoding_key().ok_or(ErrorKind::InvalidKeyFormat)?;
+
+    encode(&key.header(), &claims, encoding_key)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TokenType {
+    Access,
+    Refresh,
+    Service,
+    #[serde(rename = "password_reset")]
+    PasswordReset,
+    #[serde(rename = "email_verification")]
+    EmailVerification,
+    #[serde(rename = "email_change")]
+    EmailChange,
 }
 
+// `sub` is the stable user id (the service id for service tokens), the email of an account can change.
+// The tokens proving a mailbox also carry the address they were mailed to in `email`.
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Claims {
     pub sub: String,
+    pub iss: String,
+    pub aud: String,
     pub exp: usize,
-    pub token_type: String,
+    pub iat: usize,
+    pub nbf: usize,
+    pub jti: String,
+    pub fid: String,
+    pub epoch: i64,
+    pub token_type: TokenType,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+}
+
+impl Claims {
+    fn new(
+        subject: String,
+        family_id: &str,
+        epoch: i64,
+        token_type: TokenType,
+        ttl_seconds: i64,
+    ) -> Result<Self, GenerateTokenError> {
+        let now = Utc::now();
+        let delta = chrono::Duration::try_seconds(ttl_seconds).ok_or(GenerateTokenError::UnexpectedError)?;
+        let exp = now
+            .checked_add_signed(delta)
+            .ok_or(GenerateTokenError::UnexpectedError)?
+            .timestamp();
+        let issued_at: usize = now
+            .timestamp()
+            .try_into()
+            .map_err(|_| GenerateTokenError::UnexpectedError)?;
+
+        Ok(Self {
+            sub: subject,
+            iss: JWT_ISSUER.to_string(),
+            aud: JWT_AUDIENCE.to_string(),
+            exp: exp.try_into().map_err(|_| GenerateTokenError::UnexpectedError)?,
+            iat: issued_at,
+            nbf: issued_at,
+            jti: Uuid::new_v4().to_string(),
+            fid: family_id.to_string(),
+            epoch,
+            token_type,
+            email: None,
+        })
+    }
+
+    pub fn user_id(&self) -> Result<UserId, ValidateTokenError> {
+        UserId::parse(&self.sub).map_err(|_| ValidateTokenError::InvalidToken(ErrorKind::InvalidSubject.into()))
+    }
+
+    // The address a mailbox proving token was sent to
+    pub fn email(&self) -> Result<Email, ValidateTokenError> {
+        let email = self
+            .email
+            .clone()
+            .ok_or_else(|| ValidateTokenError::InvalidToken(ErrorKind::MissingRequiredClaim("email".into()).into()))?;
+        Email::new(SecretBox::new(Box::from(email)))
+            .map_err(|_| ValidateTokenError::InvalidToken(ErrorKind::InvalidToken.into()))
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct TokenPair {
     pub access_token: String,
     pub refresh_token: String,
+    pub family_id: String,
+    pub refresh_token_id: String,
 }
 
-pub fn generate_token_pair(email: &Email) -> Result<TokenPair, GenerateTokenError> {
-    let delta = chrono::Duration::try_seconds(*TOKEN_TTL_SECONDS).ok_or(GenerateTokenError::UnexpectedError)?;
-    let access_exp = Utc::now()
-        .checked_add_signed(delta)
-        .ok_or(GenerateTokenError::UnexpectedError)?
-        .timestamp();
-
-    let delta = chrono::Duration::try_seconds(*REFRESH_TOKEN_TTL_SECONDS).ok_or(GenerateTokenError::UnexpectedError)?;
-    let refresh_exp = Utc::now()
-        .checked_add_signed(delta)
-        .ok_or(GenerateTokenError::UnexpectedError)?
-        .timestamp();
-
-    let access_claims = Claims {
-        sub: email.as_ref().expose_secret().to_string(),
-        exp: access_exp.try_into().map_err(|_| GenerateTokenError::UnexpectedError)?,
-        token_type: "access".to_string(),
-    };
-
-    let refresh_claims = Claims {
-        sub: email.as_ref().expose_secret().to_string(),
-        exp: refresh_exp
-            .try_into()
-            .map_err(|_| GenerateTokenError::UnexpectedError)?,
-        token_type: "refresh".to_string(),
-    };
+// Both tokens of a pair carry the refresh token family (`fid`) they belong to
+// and the session epoch of the user at issue time
+pub fn generate_token_pair(
+    user_id: &UserId,
+    family_id: &str,
+    epoch: i64,
+) -> Result<TokenPair, GenerateTokenError> {
+    let subject = user_id.to_string();
+    let access_claims = Claims::new(subject.clone(), family_id, epoch, TokenType::Access, *TOKEN_TTL_SECONDS)?;
+    let refresh_claims = Claims::new(
+        subject,
+        family_id,
+        epoch,
+        TokenType::Refresh,
+        *REFRESH_TOKEN_TTL_SECONDS,
+    )?;
 
     Ok(TokenPair {
         access_token: create_token(&access_claims).map_err(GenerateTokenError::TokenError)?,
         refresh_token: create_token(&refresh_claims).map_err(GenerateTokenError::TokenError)?,
+        family_id: family_id.to_string(),
+        refresh_token_id: refresh_claims.jti,
     })
 }
 
+// Service tokens identify a registered internal service (`sub` is its service id), they belong
+// to no user session so they carry no refresh token family and a zero epoch
+pub fn generate_service_token(service_id: &str) -> Result<String, GenerateTokenError> {
+    let claims = Claims::new(
+        service_id.to_string(),
+        "",
+        0,
+        TokenType::Service,
+        *SERVICE_TOKEN_TTL_SECONDS,
+    )?;
+
+    create_token(&claims).map_err(GenerateTokenError::TokenError)
+}
+
+// Password reset tokens are mailed in the reset link. They carry the session epoch of the user
+// like the session tokens: the reset bumps it, so a token is used once and the links sent before
+// a reset, or a "log out everywhere", stop working.
+pub fn generate_password_reset_token(
+    user_id: &UserId,
+    epoch: i64,
+) -> Result<String, GenerateTokenError> {
+    let claims = Claims::new(
+        user_id.to_string(),
+        "",
+        epoch,
+        TokenType::PasswordReset,
+        *PASSWORD_RESET_TOKEN_TTL_SECONDS,
+    )?;
+
+    create_token(&claims).map_err(GenerateTokenError::TokenError)
+}
+
+// Email verification tokens are mailed at signup. They only prove the mailbox and verifying twice
+// changes nothing, so they are checked with `validate_token` and carry no session epoch.
+pub fn generate_email_verification_token(
+    user_id: &UserId,
+    email: &Email,
+) -> Result<String, GenerateTokenError> {
+    let mut claims = Claims::new(
+        user_id.to_string(),
+        "",
+        0,
+        TokenType::EmailVerification,
+        *EMAIL_VERIFICATION_TOKEN_TTL_SECONDS,
+    )?;
+    claims.email = Some(email.as_ref().expose_secret().to_string());
+
+    create_token(&claims).map_err(GenerateTokenError::TokenError)
+}
+
+// Email change tokens are mailed to the new address and carry it. Like the reset tokens they carry
+// the session epoch, a "log out everywhere" or a password reset cancels the pending changes.
+pub fn generate_email_change_token(
+    user_id: &UserId,
+    new_email: &Email,
+    epoch: i64,
+) -> Result<String, GenerateTokenError> {
+    let mut claims = Claims::new(
+        user_id.to_string(),
+        "",
+        epoch,
+        TokenType::EmailChange,
+        *EMAIL_CHANGE_TOKEN_TTL_SECONDS,
+    )?;
+    claims.email = Some(new_email.as_ref().expose_secret().to_string());
+
+    create_token(&claims).map_err(GenerateTokenError::TokenError)
+}
+
+// Issue the first token pair of a new refresh token family, used when a user logs in
+pub async fn issue_token_pair(
+    state: &AppState,
+    email: &Email,
+) -> Result<TokenPair, AuthAPIError> {
+    let user_id = *state
+        .user_store
+        .read()
+        .await
+        .get_user(email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?
+        .id();
+    let epoch = state
+        .session_epoch_store
+        .read()
+        .await
+        .get_epoch(&user_id)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    let family_id = Uuid::new_v4().to_string();
+    let token_pair = generate_token_pair(&user_id, &family_id, epoch)?;
+
+    state
+        .refresh_token_store
+        .write()
+        .await
+        .add_token(&family_id, &token_pair.refresh_token_id)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    Ok(token_pair)
+}
+
+// Consume the presented refresh token and issue the next pair of the same family.
+// Presenting a refresh token twice revokes its whole family. The claims must come from
+// `authorize_token`, the new pair keeps their session epoch.
+pub async fn rotate_token_pair(
+    state: &AppState,
+    refresh_claims: &Claims,
+) -> Result<TokenPair, AuthAPIError> {
+    let user_id = refresh_claims.user_id().map_err(|_| AuthAPIError::TokenNotValid)?;
+    let mut refresh_token_store = state.refresh_token_store.write().await;
+
+    match refresh_token_store
+        .consume_token(&refresh_claims.fid, &refresh_claims.jti)
+        .await
+    {
+        Ok(()) => (),
+        Err(RefreshTokenStoreError::TokenReused) => {
+            tracing::warn!("Refresh token reuse detected, the token family has been revoked");
+            return Err(AuthAPIError::TokenNotValid);
+        }
+        Err(RefreshTokenStoreError::UnexpectedError(e)) => return Err(AuthAPIError::UnexpectedError(e)),
+        Err(_) => return Err(AuthAPIError::TokenNotValid),
+    }
+
+    let token_pair = generate_token_pair(&user_id, &refresh_claims.fid, refresh_claims.epoch)?;
+    refresh_token_store
+        .add_token(&refresh_claims.fid, &token_pair.refresh_token_id)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    Ok(token_pair)
+}
+
+// Log a user out everywhere by bumping the session epoch, then tell the revocation subscribers.
+// The epoch is what `authorize_token` checks, so a failed publication is only logged.
+pub async fn revoke_user_sessions(
+    state: &AppState,
+    user_id: &UserId,
+) -> Result<i64, SessionEpochStoreError> {
+    let session_epoch = state.session_epoch_store.write().await.increment_epoch(user_id).await?;
+
+    let revocation = Revocation::UserSessions {
+        subject: user_id.to_string(),
+        session_epoch,
+    };
+    if let Err(e) = state
+        .banned_token_store
+        .read()
+        .await
+        .publish_revocation(revocation)
+        .await
+    {
+        tracing::warn!("Failed to publish a user sessions revocation: {:?}", e);
+    }
+
+    Ok(session_epoch)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::domain::User;
+    use crate::services::data_stores::{
+        HashmapLoginLockoutStore, HashmapPasskeyChallengeStore, HashmapPasskeyStore, HashmapRateLimitStore,
+        HashmapRecoveryCodeStore, HashmapRefreshTokenStore, HashmapServiceCredentialStore, HashmapSessionEpochStore,
+        HashmapTotpStore, HashmapTwoFACodeStore, HashmapUserStore, HashsetBannedTokenStore,
+    };
+    use crate::services::email::MockEmailClient;
+    use axum::http::HeaderMap;
+    use axum::http::header::{COOKIE, SET_COOKIE};
+    use axum::response::IntoResponse;
     use fake::Fake;
-    use fake::faker::internet::en::SafeEmail;
-    use secrecy::SecretBox;
+    use fake::faker::internet::en::{Password as FakePassword, SafeEmail};
+    use std::sync::Arc;
+    use tokio::sync::RwLock;
+
+    fn new_family_id() -> String {
+        Uuid::new_v4().to_string()
+    }
+
+    fn create_app_state() -> AppState {
+        AppState::new(
+            Arc::new(RwLock::new(HashmapUserStore::default())),
+            Arc::new(RwLock::new(HashsetBannedTokenStore::default())),
+            Arc::new(RwLock::new(HashmapRefreshTokenStore::default())),
+            Arc::new(RwLock::new(HashmapSessionEpochStore::default())),
+            Arc::new(RwLock::new(HashmapTwoFACodeStore::default())),
+            Arc::new(RwLock::new(MockEmailClient::new())),
+            Arc::new(RwLock::new(HashmapServiceCredentialStore::default())),
+            Arc::new(RwLock::new(HashmapTotpStore::default())),
+            Arc::new(RwLock::new(HashmapRecoveryCodeStore::default())),
+            Arc::new(RwLock::new(HashmapPasskeyStore::default())),
+            Arc::new(RwLock::new(HashmapPasskeyChallengeStore::default())),
+            Arc::new(RwLock::new(HashmapLoginLockoutStore::default())),
+            Arc::new(RwLock::new(HashmapRateLimitStore::default())),
+        )
+    }
+
+    async fn add_user(state: &AppState) -> User {
+        let user = User::new(SafeEmail().fake(), FakePassword(8..20).fake(), false).unwrap();
+        state.user_store.write().await.add_user(user.clone()).await.unwrap();
+        user
+    }
 
     #[tokio::test]
-    async fn test_generate_auth_cookie() {
-        let fake_email: String = SafeEmail().fake();
-        let email = Email::new(SecretBox::new(Box::from(fake_email.to_owned()))).unwrap();
-        let cookie = generate_auth_cookie(&email).unwrap();
+    async fn test_create_auth_cookie() {
+        let user_id = UserId::default();
+        let token_pair = generate_token_pair(&user_id, &new_family_id(), 0).unwrap();
+        let cookie = create_auth_cookie(token_pair.access_token);
         assert_eq!(cookie.name(), JWT_COOKIE_NAME);
         assert_eq!(cookie.value().split('.').count(), 3);
         assert_eq!(cookie.path(), Some("/"));
@@ -126,16 +497,15 @@ mod tests {
     #[tokio::test]
     async fn test_validate_token_with_invalid_token() {
         let token = "invalid_token".to_owned();
-        let result = validate_token(&token).await;
+        let result = validate_token(&token, TokenType::Access);
         assert!(result.is_err());
     }
 
     #[tokio::test]
     async fn test_generate_token_pair_success() {
-        let fake_email: String = SafeEmail().fake();
-        let email = Email::new(SecretBox::new(Box::from(fake_email.clone()))).unwrap();
+        let user_id = UserId::default();
 
-        let result = generate_token_pair(&email);
+        let result = generate_token_pair(&user_id, &new_family_id(), 0);
 
         assert!(result.is_ok());
         let token_pair = result.unwrap();
@@ -149,31 +519,29 @@ mod tests {
 
     #[tokio::test]
     async fn test_generate_token_pair_tokens_are_valid() {
-        let fake_email: String = SafeEmail().fake();
-        let email = Email::new(SecretBox::new(Box::from(fake_email.clone()))).unwrap();
+        let user_id = UserId::default();
 
-        let token_pair = generate_token_pair(&email).unwrap();
+        let token_pair = generate_token_pair(&user_id, &new_family_id(), 0).unwrap();
 
-        let access_claims = validate_token(&token_pair.access_token).await.unwrap();
-        assert_eq!(access_claims.sub, fake_email);
-        assert_eq!(access_claims.token_type, "access");
+        let access_claims = validate_token(&token_pair.access_token, TokenType::Access).unwrap();
+        assert_eq!(access_claims.sub, user_id.to_string());
+        assert_eq!(access_claims.token_type, TokenType::Access);
 
-        let refresh_claims = validate_token(&token_pair.refresh_token).await.unwrap();
-        assert_eq!(refresh_claims.sub, fake_email);
-        assert_eq!(refresh_claims.token_type, "refresh");
+        let refresh_claims = validate_token(&token_pair.refresh_token, TokenType::Refresh).unwrap();
+        assert_eq!(refresh_claims.sub, user_id.to_string());
+        assert_eq!(refresh_claims.token_type, TokenType::Refresh);
     }
 
     #[tokio::test]
     async fn test_generate_token_pair_expiration_times() {
-        let fake_email: String = SafeEmail().fake();
-        let email = Email::new(SecretBox::new(Box::from(fake_email))).unwrap();
+        let user_id = UserId::default();
 
         let before_generation = Utc::now();
-        let token_pair = generate_token_pair(&email).unwrap();
+        let token_pair = generate_token_pair(&user_id, &new_family_id(), 0).unwrap();
         let after_generation = Utc::now();
 
-        let access_claims = validate_token(&token_pair.access_token).await.unwrap();
-        let refresh_claims = validate_token(&token_pair.refresh_token).await.unwrap();
+        let access_claims = validate_token(&token_pair.access_token, TokenType::Access).unwrap();
+        let refresh_claims = validate_token(&token_pair.refresh_token, TokenType::Refresh).unwrap();
 
         let delta = chrono::Duration::try_seconds(*TOKEN_TTL_SECONDS).unwrap();
         let expected_access_min = before_generation + delta - chrono::Duration::try_seconds(1).unwrap();
@@ -197,74 +565,282 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_generate_token_pair_different_emails_produce_different_tokens() {
-        let email1 = Email::new(SecretBox::new(Box::from("user1@example.com".to_string()))).unwrap();
-        let email2 = Email::new(SecretBox::new(Box::from("user2@example.com".to_string()))).unwrap();
+    async fn test_generate_token_pair_different_users_produce_different_tokens() {
+        let user_id1 = UserId::default();
+        let user_id2 = UserId::default();
 
-        let token_pair1 = generate_token_pair(&email1).unwrap();
-        let token_pair2 = generate_token_pair(&email2).unwrap();
+        let token_pair1 = generate_token_pair(&user_id1, &new_family_id(), 0).unwrap();
+        let token_pair2 = generate_token_pair(&user_id2, &new_family_id(), 0).unwrap();
 
         assert_ne!(token_pair1.access_token, token_pair2.access_token);
         assert_ne!(token_pair1.refresh_token, token_pair2.refresh_token);
 
-        let claims1 = validate_token(&token_pair1.access_token).await.unwrap();
-        let claims2 = validate_token(&token_pair2.access_token).await.unwrap();
+        let claims1 = validate_token(&token_pair1.access_token, TokenType::Access).unwrap();
+        let claims2 = validate_token(&token_pair2.access_token, TokenType::Access).unwrap();
 
-        assert_eq!(claims1.sub, "user1@example.com");
-        assert_eq!(claims2.sub, "user2@example.com");
+        assert_eq!(claims1.sub, user_id1.to_string());
+        assert_eq!(claims2.sub, user_id2.to_string());
     }
 
     #[tokio::test]
     async fn test_generate_token_pair_multiple_calls_produce_different_tokens() {
-        let fake_email: String = SafeEmail().fake();
-        let email = Email::new(SecretBox::new(Box::from(fake_email))).unwrap();
+        let user_id = UserId::default();
 
-        let token_pair1 = generate_token_pair(&email).unwrap();
+        let token_pair1 = generate_token_pair(&user_id, &new_family_id(), 0).unwrap();
         tokio::time::sleep(tokio::time::Duration::from_millis(1500)).await;
-        let token_pair2 = generate_token_pair(&email).unwrap();
+        let token_pair2 = generate_token_pair(&user_id, &new_family_id(), 0).unwrap();
 
         assert_ne!(token_pair1.access_token, token_pair2.access_token);
         assert_ne!(token_pair1.refresh_token, token_pair2.refresh_token);
     }
 
     #[tokio::test]
-    async fn test_generate_token_pair_with_various_email_formats() {
-        let fake_email1: String = SafeEmail().fake();
-        let fake_email2: String = SafeEmail().fake();
-        let fake_email3: String = SafeEmail().fake();
-        let fake_email4: String = SafeEmail().fake();
-        let fake_email5: String = SafeEmail().fake();
-        let test_emails = vec![fake_email1, fake_email2, fake_email3, fake_email4, fake_email5];
-
-        for email_str in test_emails {
-            let email = Email::new(SecretBox::new(Box::from(email_str.to_string()))).unwrap();
-            let result = generate_token_pair(&email);
+    async fn test_generate_token_pair_with_various_users() {
+        for _ in 0..5 {
+            let user_id = UserId::default();
+            let result = generate_token_pair(&user_id, &new_family_id(), 0);
 
-            assert!(result.is_ok(), "Failed to generate token pair for email: {}", email_str);
+            assert!(result.is_ok(), "Failed to generate token pair for user: {}", user_id);
 
             let token_pair = result.unwrap();
-            let claims = validate_token(&token_pair.access_token).await.unwrap();
-            assert_eq!(claims.sub, email_str);
+            let claims = validate_token(&token_pair.access_token, TokenType::Access).unwrap();
+            assert_eq!(claims.user_id().unwrap(), user_id);
         }
     }
 
     #[tokio::test]
     async fn test_token_pair_structure() {
-        let fake_email: String = SafeEmail().fake();
-        let email = Email::new(SecretBox::new(Box::from(fake_email.clone()))).unwrap();
+        let user_id = UserId::default();
 
-        let token_pair = generate_token_pair(&email).unwrap();
+        let token_pair = generate_token_pair(&user_id, &new_family_id(), 0).unwrap();
 
-        let access_claims = validate_token(&token_pair.access_token).await.unwrap();
-        assert_eq!(access_claims.sub, fake_email);
-        assert_eq!(access_claims.token_type, "access");
+        let access_claims = validate_token(&token_pair.access_token, TokenType::Access).unwrap();
+        assert_eq!(access_claims.sub, user_id.to_string());
+        assert_eq!(access_claims.token_type, TokenType::Access);
         assert!(access_claims.exp > 0);
 
-        let refresh_claims = validate_token(&token_pair.refresh_token).await.unwrap();
-        assert_eq!(refresh_claims.sub, fake_email);
-        assert_eq!(refresh_claims.token_type, "refresh");
+        let refresh_claims = validate_token(&token_pair.refresh_token, TokenType::Refresh).unwrap();
+        assert_eq!(refresh_claims.sub, user_id.to_string());
+        assert_eq!(refresh_claims.token_type, TokenType::Refresh);
         assert!(refresh_claims.exp > 0);
 
         assert!(refresh_claims.exp > access_claims.exp);
     }
+
+    #[tokio::test]
+    async fn test_token_type_is_enforced() {
+        let user_id = UserId::default();
+        let token_pair = generate_token_pair(&user_id, &new_family_id(), 0).unwrap();
+
+        let result = validate_token(&token_pair.access_token, TokenType::Refresh);
+        assert!(matches!(result, Err(ValidateTokenError::UnexpectedTokenType)));
+
+        let result = validate_token(&token_pair.refresh_token, TokenType::Access);
+        assert!(matches!(result, Err(ValidateTokenError::UnexpectedTokenType)));
+    }
+
+    #[tokio::test]
+    async fn test_registered_claims_are_set() {
+        let user_id = UserId::default();
+
+        let before_generation = Utc::now().timestamp() as usize;
+        let token_pair = generate_token_pair(&user_id, &new_family_id(), 0).unwrap();
+
+        let access_claims = validate_token(&token_pair.access_token, TokenType::Access).unwrap();
+        let refresh_claims = validate_token(&token_pair.refresh_token, TokenType::Refresh).unwrap();
+
+        assert_eq!(access_claims.iss, *JWT_ISSUER);
+        assert_eq!(access_claims.aud, *JWT_AUDIENCE);
+        assert!(access_claims.iat >= before_generation);
+        assert_eq!(access_claims.nbf, access_claims.iat);
+        assert!(Uuid::parse_str(&access_claims.jti).is_ok());
+        assert_ne!(access_claims.jti, refresh_claims.jti);
+    }
+
+    #[tokio::test]
+    async fn test_wrong_issuer_or_audience_is_rejected() {
+        let fake_email: String = SafeEmail().fake();
+
+        let mut claims = Claims::new(fake_email.clone(), &new_family_id(), 0, TokenType::Access, 60).unwrap();
+        claims.iss = "someone-else".to_string();
+        let token = create_token(&claims).unwrap();
+        assert!(matches!(
+            validate_token(&token, TokenType::Access),
+            Err(ValidateTokenError::InvalidToken(_))
+        ));
+
+        let mut claims = Claims::new(fake_email.clone(), &new_family_id(), 0, TokenType::Access, 60).unwrap();
+        claims.aud = "another-service".to_string();
+        let token = create_token(&claims).unwrap();
+        assert!(matches!(
+            validate_token(&token, TokenType::Access),
+            Err(ValidateTokenError::InvalidToken(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_token_not_yet_valid_is_rejected() {
+        let fake_email: String = SafeEmail().fake();
+
+        let mut claims = Claims::new(fake_email.clone(), &new_family_id(), 0, TokenType::Access, 3600).unwrap();
+        claims.nbf += 600;
+        let token = create_token(&claims).unwrap();
+        assert!(validate_token(&token, TokenType::Access).is_err());
+
+        let mut claims = Claims::new(fake_email.clone(), &new_family_id(), 0, TokenType::Access, 3600).unwrap();
+        claims.iat += 600;
+        let token = create_token(&claims).unwrap();
+        assert!(matches!(
+            validate_token(&token, TokenType::Access),
+            Err(ValidateTokenError::IssuedInTheFuture)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_token_pair_shares_the_family() {
+        let user_id = UserId::default();
+        let family_id = new_family_id();
+
+        let token_pair = generate_token_pair(&user_id, &family_id, 0).unwrap();
+        assert_eq!(token_pair.family_id, family_id);
+
+        let access_claims = validate_token(&token_pair.access_token, TokenType::Access).unwrap();
+        let refresh_claims = validate_token(&token_pair.refresh_token, TokenType::Refresh).unwrap();
+        assert_eq!(access_claims.fid, family_id);
+        assert_eq!(refresh_claims.fid, family_id);
+        assert_eq!(refresh_claims.jti, token_pair.refresh_token_id);
+    }
+
+    #[tokio::test]
+    async fn test_remove_token_cookies_matches_domain_and_path() {
+        let user_id = UserId::default();
+        let token_pair = generate_token_pair(&user_id, &new_family_id(), 0).unwrap();
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            COOKIE,
+            format!(
+                "{}={}; {}={}",
+                JWT_COOKIE_NAME, token_pair.access_token, JWT_REFRESH_COOKIE_NAME, token_pair.refresh_token
+            )
+            .parse()
+            .unwrap(),
+        );
+        let jar = CookieJar::from_headers(&headers);
+
+        let jar = remove_token_cookies(jar);
+        assert!(jar.get(JWT_COOKIE_NAME).is_none());
+        assert!(jar.get(JWT_REFRESH_COOKIE_NAME).is_none());
+
+        let response = jar.into_response();
+        let removals: Vec<_> = response
+            .headers()
+            .get_all(SET_COOKIE)
+            .iter()
+            .map(|header| Cookie::parse(header.to_str().unwrap().to_owned()).unwrap())
+            .collect();
+        assert_eq!(removals.len(), 2);
+        for cookie in removals {
+            assert_eq!(cookie.domain(), Some(COOKIE_DOMAIN.as_str()));
+            assert_eq!(cookie.path(), Some("/"));
+            assert_eq!(cookie.value(), "");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_add_token_cookies_uses_the_same_pair() {
+        let user_id = UserId::default();
+        let token_pair = generate_token_pair(&user_id, &new_family_id(), 0).unwrap();
+
+        let jar = add_token_cookies(CookieJar::new(), &token_pair);
+        assert_eq!(jar.get(JWT_COOKIE_NAME).unwrap().value(), token_pair.access_token);
+        assert_eq!(
+            jar.get(JWT_REFRESH_COOKIE_NAME).unwrap().value(),
+            token_pair.refresh_token
+        );
+    }
+
+    #[tokio::test]
+    async fn test_authorize_token_rejects_outdated_session_epoch() {
+        let state = create_app_state();
+        let user = add_user(&state).await;
+
+        let token_pair = issue_token_pair(&state, user.email()).await.unwrap();
+        let claims = authorize_token(&state, &token_pair.access_token, TokenType::Access)
+            .await
+            .unwrap();
+        assert_eq!(claims.epoch, 0);
+
+        state
+            .session_epoch_store
+            .write()
+            .await
+            .increment_epoch(user.id())
+            .await
+            .unwrap();
+
+        for (token, token_type) in [
+            (&token_pair.access_token, TokenType::Access),
+            (&token_pair.refresh_token, TokenType::Refresh),
+        ] {
+            let result = authorize_token(&state, token, token_type).await;
+            assert!(matches!(result, Err(TokenRejection::SessionRevoked)));
+        }
+
+        let token_pair = issue_token_pair(&state, user.email()).await.unwrap();
+        let claims = authorize_token(&state, &token_pair.access_token, TokenType::Access)
+            .await
+            .unwrap();
+        assert_eq!(claims.epoch, 1);
+    }
+
+    #[tokio::test]
+    async fn test_rotate_token_pair_keeps_the_family_and_epoch() {
+        let state = create_app_state();
+        let user = add_user(&state).await;
+
+        let token_pair = issue_token_pair(&state, user.email()).await.unwrap();
+        let refresh_claims = authorize_token(&state, &token_pair.refresh_token, TokenType::Refresh)
+            .await
+            .unwrap();
+
+        let rotated = rotate_token_pair(&state, &refresh_claims).await.unwrap();
+        let rotated_claims = authorize_token(&state, &rotated.refresh_token, TokenType::Refresh)
+            .await
+            .unwrap();
+        assert_eq!(rotated_claims.fid, refresh_claims.fid);
+        assert_eq!(rotated_claims.epoch, refresh_claims.epoch);
+
+        let result = rotate_token_pair(&state, &refresh_claims).await;
+        assert!(matches!(result, Err(AuthAPIError::TokenNotValid)));
+    }
+
+    #[tokio::test]
+    async fn test_email_subject_is_rejected() {
+        let state = create_app_state();
+        let fake_email: String = SafeEmail().fake();
+
+        // Tokens issued before the user ids carried the email as subject
+        let claims = Claims::new(fake_email, &new_family_id(), 0, TokenType::Access, 60).unwrap();
+        let token = create_token(&claims).unwrap();
+        let result = authorize_token(&state, &token, TokenType::Access).await;
+        assert!(matches!(result, Err(TokenRejection::InvalidToken(_))));
+    }
+
+    #[tokio::test]
+    async fn test_email_change_token_carries_the_new_address() {
+        let user_id = UserId::default();
+        let fake_email: String = SafeEmail().fake();
+        let email = Email::new(SecretBox::new(Box::from(fake_email))).unwrap();
+
+        let token = generate_email_change_token(&user_id, &email, 3).unwrap();
+        let claims = validate_token(&token, TokenType::EmailChange).unwrap();
+        assert_eq!(claims.user_id().unwrap(), user_id);
+        assert_eq!(claims.email().unwrap(), email);
+        assert_eq!(claims.epoch, 3);
+
+        let token_pair = generate_token_pair(&user_id, &new_family_id(), 0).unwrap();
+        let claims = validate_token(&token_pair.access_token, TokenType::Access).unwrap();
+        assert!(claims.email.is_none());
+        assert!(claims.email().is_err());
+    }
 }
@@ -0,0 +1,115 @@
+use crate::app_state::AppState;
+use crate::domain::data_stores::UserStoreError;
+use crate::domain::{AuthAPIError, Email, UserId};
+use crate::utils::{Claims, JWT_COOKIE_NAME, TokenType, authorize_token};
+use axum::async_trait;
+use axum::extract::FromRequestParts;
+use axum::http::header::AUTHORIZATION;
+use axum::http::request::Parts;
+use axum_extra::extract::CookieJar;
+
+// The caller identified by a valid access token. Taking it as a handler argument makes the route
+// protected: the token is read from the `jwt` cookie or an `Authorization: Bearer` header and goes
+// through `authorize_token`, so signature, token type, ban list and session epoch are all checked.
+// The token only names the user id, the current email is looked up.
+pub struct AuthenticatedUser {
+    pub user_id: UserId,
+    pub email: Email,
+    pub claims: Claims,
+    pub token: String,
+}
+
+#[async_trait]
+impl FromRequestParts<AppState> for AuthenticatedUser {
+    type Rejection = AuthAPIError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let token = extract_access_token(parts).ok_or(AuthAPIError::MissingToken)?;
+        Self::from_token(state, token).await
+    }
+}
+
+impl AuthenticatedUser {
+    // For callers that don't go through axum, the gRPC service passes tokens in the messages
+    pub async fn from_token(
+        state: &AppState,
+        token: String,
+    ) -> Result<Self, AuthAPIError> {
+        let claims = authorize_token(state, &token, TokenType::Access).await?;
+        let user_id = claims.user_id().map_err(|_| AuthAPIError::TokenNotValid)?;
+        let email = match state.user_store.read().await.get_user_by_id(&user_id).await {
+            Ok(user) => user.email().clone(),
+            Err(UserStoreError::UserNotFound) => return Err(AuthAPIError::TokenNotValid),
+            Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+        };
+
+        Ok(Self {
+            user_id,
+            email,
+            claims,
+            token,
+        })
+    }
+}
+
+// The cookie wins when both are present, browsers always send it
+fn extract_access_token(parts: &Parts) -> Option<String> {
+    if let Some(cookie) = CookieJar::from_headers(&parts.headers).get(JWT_COOKIE_NAME) {
+        return Some(cookie.value().to_owned());
+    }
+
+    let header = parts.headers.get(AUTHORIZATION)?.to_str().ok()?;
+    let (scheme, token) = header.split_once(' ')?;
+    if !scheme.eq_ignore_ascii_case("bearer") || token.trim().is_empty() {
+        return None;
+    }
+    Some(token.trim().to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::Request;
+    use axum::http::header::COOKIE;
+
+    fn parts(
+        name: axum::http::HeaderName,
+        value: &str,
+    ) -> Parts {
+        let (parts, _) = Request::builder().header(name, value).body(()).unwrap().into_parts();
+        parts
+    }
+
+    #[test]
+    fn test_extract_token_from_cookie() {
+        let parts = parts(COOKIE, &format!("{}=cookie-token", JWT_COOKIE_NAME));
+        assert_eq!(extract_access_token(&parts), Some("cookie-token".to_owned()));
+    }
+
+    #[test]
+    fn test_extract_token_from_bearer_header() {
+        let parts = parts(AUTHORIZATION, "Bearer header-token");
+        assert_eq!(extract_access_token(&parts), Some("header-token".to_owned()));
+
+        let parts = self::parts(AUTHORIZATION, "bearer header-token");
+        assert_eq!(extract_access_token(&parts), Some("header-token".to_owned()));
+    }
+
+    #[test]
+    fn test_reject_other_authorization_schemes() {
+        let parts = parts(AUTHORIZATION, "Basic dXNlcjpwYXNz");
+        assert_eq!(extract_access_token(&parts), None);
+
+        let parts = self::parts(AUTHORIZATION, "Bearer ");
+        assert_eq!(extract_access_token(&parts), None);
+    }
+
+    #[test]
+    fn test_missing_token() {
+        let (parts, _) = Request::builder().body(()).unwrap().into_parts();
+        assert_eq!(extract_access_token(&parts), None);
+    }
+}
@@ -0,0 +1,148 @@
+use crate::domain::AuthAPIError;
+use crate::utils::TRUSTED_PROXIES;
+use axum::async_trait;
+use axum::extract::{ConnectInfo, FromRequestParts};
+use axum::http::HeaderMap;
+use axum::http::request::Parts;
+use color_eyre::eyre::eyre;
+use ipnet::IpNet;
+use std::net::{IpAddr, SocketAddr};
+use std::str::FromStr;
+
+const X_FORWARDED_FOR: &str = "x-forwarded-for";
+
+// The address the request comes from. Behind a trusted proxy it is read from `X-Forwarded-For`,
+// right to left, skipping the proxies of the chain: the entries before the first untrusted hop are
+// written by the client and can't be believed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClientIp(pub IpAddr);
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for ClientIp {
+    type Rejection = AuthAPIError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        _state: &S,
+    ) -> Result<Self, Self::Rejection> {
+        let ConnectInfo(peer) = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .ok_or_else(|| AuthAPIError::UnexpectedError(eyre!("The server doesn't provide the peer address")))?;
+
+        Ok(Self(TRUSTED_PROXIES.client_ip(peer.ip(), &parts.headers)))
+    }
+}
+
+// Addresses or CIDR ranges of the proxies in front of the service, from `trusted_proxies`
+#[derive(Debug, Clone, Default)]
+pub struct TrustedProxies(Vec<IpNet>);
+
+impl TrustedProxies {
+    pub fn parse(proxies: &str) -> Result<Self, ipnet::AddrParseError> {
+        proxies
+            .split(',')
+            .map(str::trim)
+            .filter(|proxy| !proxy.is_empty())
+            .map(|proxy| match IpAddr::from_str(proxy) {
+                Ok(ip) => Ok(IpNet::from(ip)),
+                Err(_) => IpNet::from_str(proxy),
+            })
+            .collect::<Result<_, _>>()
+            .map(Self)
+    }
+
+    fn contains(
+        &self,
+        ip: &IpAddr,
+    ) -> bool {
+        self.0.iter().any(|network| network.contains(ip))
+    }
+
+    pub fn client_ip(
+        &self,
+        peer: IpAddr,
+        headers: &HeaderMap,
+    ) -> IpAddr {
+        let mut client_ip = peer;
+        if !self.contains(&client_ip) {
+            return client_ip;
+        }
+
+        let hops = headers
+            .get_all(X_FORWARDED_FOR)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .collect::<Vec<_>>();
+
+        for hop in hops.into_iter().rev() {
+            // A garbled entry ends the chain, the last proxy is the best guess
+            let Ok(ip) = IpAddr::from_str(hop.trim()) else {
+                break;
+            };
+            client_ip = ip;
+            if !self.contains(&client_ip) {
+                break;
+            }
+        }
+
+        client_ip
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn forwarded_for(chain: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(X_FORWARDED_FOR, HeaderValue::from_str(chain).unwrap());
+        headers
+    }
+
+    fn ip(address: &str) -> IpAddr {
+        IpAddr::from_str(address).unwrap()
+    }
+
+    #[test]
+    fn test_parse_accepts_addresses_and_ranges() {
+        assert_eq!(TrustedProxies::parse("").unwrap().0.len(), 0);
+        assert_eq!(TrustedProxies::parse("10.0.0.1, 172.16.0.0/12,::1").unwrap().0.len(), 3);
+        assert!(TrustedProxies::parse("10.0.0.1,proxy.internal").is_err());
+    }
+
+    #[test]
+    fn test_header_is_ignored_from_an_untrusted_peer() {
+        let proxies = TrustedProxies::parse("10.0.0.0/8").unwrap();
+
+        let client_ip = proxies.client_ip(ip("203.0.113.7"), &forwarded_for("198.51.100.1"));
+
+        assert_eq!(client_ip, ip("203.0.113.7"));
+    }
+
+    #[test]
+    fn test_chain_is_read_until_the_first_untrusted_hop() {
+        let proxies = TrustedProxies::parse("10.0.0.0/8").unwrap();
+
+        let client_ip = proxies.client_ip(ip("10.0.0.2"), &forwarded_for("198.51.100.1, 203.0.113.7, 10.0.0.1"));
+
+        assert_eq!(client_ip, ip("203.0.113.7"));
+    }
+
+    #[test]
+    fn test_chain_of_proxies_only_gives_the_first_one() {
+        let proxies = TrustedProxies::parse("10.0.0.0/8").unwrap();
+
+        assert_eq!(
+            proxies.client_ip(ip("10.0.0.2"), &forwarded_for("10.0.0.3, 10.0.0.1")),
+            ip("10.0.0.3")
+        );
+        assert_eq!(proxies.client_ip(ip("10.0.0.2"), &HeaderMap::new()), ip("10.0.0.2"));
+        assert_eq!(
+            proxies.client_ip(ip("10.0.0.2"), &forwarded_for("bogus, 10.0.0.1")),
+            ip("10.0.0.1")
+        );
+    }
+}
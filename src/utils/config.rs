@@ -1,12 +1,24 @@
+use super::client_ip::TrustedProxies;
+use super::jwt_keys::{JwtKeyError, JwtKeyRing};
+use super::totp_cipher::TotpCipher;
+use crate::domain::PasswordPolicy;
 use config::{Config, ConfigError, Environment, File};
 use lazy_static::lazy_static;
 use secrecy::SecretBox;
 use serde::{Deserialize, Serialize};
-use std::sync::{LazyLock, OnceLock};
+use std::sync::{Arc, LazyLock, OnceLock, PoisonError, RwLock};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct AppConfig {
     pub jwt_secret: String,
+    pub jwt_algorithm: String,
+    pub jwt_key_id: String,
+    pub jwt_private_key_path: String,
+    pub jwt_public_key_path: String,
+    #[serde(default)]
+    pub jwt_retired_keys: Vec<JwtRetiredKeyConfig>,
+    pub jwt_issuer: String,
+    pub jwt_audience: String,
     pub cookie_domain: String,
     pub database_url: String,
     pub redis_host_name: String,
@@ -16,12 +28,98 @@ pub struct AppConfig {
     pub postgres_password: String,
     pub token_ttl_seconds: i64,
     pub refresh_token_ttl_seconds: i64,
+    pub service_token_ttl_seconds: i64,
+    pub password_reset_token_ttl_seconds: i64,
+    pub password_reset_url: String,
+    pub email_verification_token_ttl_seconds: i64,
+    pub email_verification_url: String,
+    pub email_change_token_ttl_seconds: i64,
+    pub email_change_url: String,
+    pub allow_unverified_login: bool,
+    pub password_min_length: usize,
+    pub password_max_length: usize,
+    pub password_reject_email: bool,
+    pub password_blocklist_size: usize,
+    pub password_min_entropy_bits: u32,
+    pub http_address: String,
+    pub grpc_address: String,
+    pub tls_cert_path: String,
+    pub tls_key_path: String,
+    pub totp_encryption_key: String,
+    pub totp_issuer: String,
+    pub totp_drift_steps: u64,
+    pub two_fa_code_ttl_seconds: u64,
+    pub two_fa_max_attempts: u32,
+    pub lockout_account_threshold: u32,
+    pub lockout_ip_threshold: u32,
+    pub lockout_failure_window_seconds: u64,
+    pub lockout_base_seconds: u64,
+    pub lockout_max_seconds: u64,
+    pub lockout_reset_seconds: u64,
+    pub trusted_proxies: String,
+    #[serde(default)]
+    pub rate_limits: Vec<RateLimitRule>,
+    pub webauthn_rp_id: String,
+    pub webauthn_rp_name: String,
+    pub webauthn_origin: String,
+}
+
+// A token bucket of `capacity` requests refilled with `refill_per_minute` tokens, one bucket per
+// client address or per email of the body. `path` is a route, `*` covers the routes without a rule.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct RateLimitRule {
+    pub path: String,
+    pub key: RateLimitKey,
+    pub capacity: u32,
+    pub refill_per_minute: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RateLimitKey {
+    Ip,
+    Email,
+}
+
+impl RateLimitRule {
+    fn new(
+        path: &str,
+        key: RateLimitKey,
+        capacity: u32,
+        refill_per_minute: u32,
+    ) -> Self {
+        Self {
+            path: path.to_string(),
+            key,
+            capacity,
+            refill_per_minute,
+        }
+    }
+}
+
+// A key that no longer signs tokens but still verifies the ones it signed before a rotation.
+// Asymmetric keys need `public_key_path`, HMAC keys need `secret`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct JwtRetiredKeyConfig {
+    pub kid: String,
+    pub algorithm: String,
+    #[serde(default)]
+    pub public_key_path: String,
+    #[serde(default)]
+    pub secret: String,
 }
 
 impl Default for AppConfig {
     fn default() -> Self {
         Self {
             jwt_secret: String::new(),
+            jwt_algorithm: "HS256".to_string(),
+            jwt_key_id: "primary".to_string(),
+            jwt_private_key_path: String::new(),
+            jwt_public_key_path: String::new(),
+            jwt_retired_keys: Vec::new(),
+            jwt_issuer: "lgrb-auth-service".to_string(),
+            jwt_audience: "lgrb".to_string(),
             cookie_domain: String::new(),
             database_url: String::new(),
             redis_host_name: "127.0.0.1".to_string(),
@@ -31,6 +129,53 @@ impl Default for AppConfig {
             postgres_password: String::new(),
             token_ttl_seconds: 600,
             refresh_token_ttl_seconds: 3600,
+            service_token_ttl_seconds: 300,
+            password_reset_token_ttl_seconds: 1800,
+            password_reset_url: "http://localhost:3000/reset-password".to_string(),
+            email_verification_token_ttl_seconds: 86400,
+            email_verification_url: "http://localhost:3000/verify-email".to_string(),
+            email_change_token_ttl_seconds: 86400,
+            email_change_url: "http://localhost:3000/change-email/confirm".to_string(),
+            allow_unverified_login: true,
+            password_min_length: 8,
+            password_max_length: 128,
+            password_reject_email: true,
+            password_blocklist_size: PasswordPolicy::bundled_blocklist_size(),
+            password_min_entropy_bits: 30,
+            http_address: "0.0.0.0:3000".to_string(),
+            grpc_address: "0.0.0.0:50051".to_string(),
+            tls_cert_path: String::new(),
+            tls_key_path: String::new(),
+            totp_encryption_key: String::new(),
+            totp_issuer: "LGRB".to_string(),
+            totp_drift_steps: 1,
+            two_fa_code_ttl_seconds: 300,
+            two_fa_max_attempts: 5,
+            lockout_account_threshold: 5,
+            lockout_ip_threshold: 50,
+            lockout_failure_window_seconds: 900,
+            lockout_base_seconds: 60,
+            lockout_max_seconds: 3600,
+            lockout_reset_seconds: 86400,
+            trusted_proxies: String::new(),
+            rate_limits: vec![
+                RateLimitRule::new("*", RateLimitKey::Ip, 120, 120),
+                RateLimitRule::new("/signup", RateLimitKey::Ip, 10, 10),
+                RateLimitRule::new("/login", RateLimitKey::Ip, 60, 30),
+                RateLimitRule::new("/login", RateLimitKey::Email, 20, 10),
+                RateLimitRule::new("/verify-2fa", RateLimitKey::Ip, 30, 30),
+                RateLimitRule::new("/passkeys/login/finish", RateLimitKey::Ip, 30, 30),
+                RateLimitRule::new("/password-reset/request", RateLimitKey::Ip, 10, 10),
+                RateLimitRule::new("/password-reset/request", RateLimitKey::Email, 3, 1),
+                RateLimitRule::new("/password-reset/confirm", RateLimitKey::Ip, 10, 10),
+                RateLimitRule::new("/change-password", RateLimitKey::Ip, 10, 10),
+                RateLimitRule::new("/verify-email/resend", RateLimitKey::Ip, 10, 10),
+                RateLimitRule::new("/verify-email/resend", RateLimitKey::Email, 3, 1),
+                RateLimitRule::new("/change-email", RateLimitKey::Ip, 10, 10),
+            ],
+            webauthn_rp_id: "localhost".to_string(),
+            webauthn_rp_name: "LGRB".to_string(),
+            webauthn_origin: "http://localhost:3000".to_string(),
         }
     }
 }
@@ -45,10 +190,18 @@ impl AppConfig {
 
         let app_config: AppConfig = config.try_deserialize()?;
 
-        if app_config.jwt_secret.is_empty() {
+        if app_config.jwt_algorithm.starts_with("HS") && app_config.jwt_secret.is_empty() {
             return Err(ConfigError::Message("JWT_SECRET must be set and not empty".to_string()));
         }
 
+        if !app_config.jwt_algorithm.starts_with("HS")
+            && (app_config.jwt_private_key_path.is_empty() || app_config.jwt_public_key_path.is_empty())
+        {
+            return Err(ConfigError::Message(
+                "JWT_PRIVATE_KEY_PATH and JWT_PUBLIC_KEY_PATH must be set for asymmetric algorithms".to_string(),
+            ));
+        }
+
         if app_config.cookie_domain.is_empty() {
             return Err(ConfigError::Message(
                 "COOKIE_DOMAIN must be set and not empty".to_string(),
@@ -61,6 +214,72 @@ impl AppConfig {
             ));
         }
 
+        if app_config.tls_cert_path.is_empty() != app_config.tls_key_path.is_empty() {
+            return Err(ConfigError::Message(
+                "TLS_CERT_PATH and TLS_KEY_PATH must be set together".to_string(),
+            ));
+        }
+
+        if !app_config.totp_encryption_key.is_empty() && TotpCipher::from_config(&app_config).is_err() {
+            return Err(ConfigError::Message(
+                "TOTP_ENCRYPTION_KEY must be 32 bytes encoded in base64".to_string(),
+            ));
+        }
+
+        if TrustedProxies::parse(&app_config.trusted_proxies).is_err() {
+            return Err(ConfigError::Message(
+                "TRUSTED_PROXIES must be a comma-separated list of IP addresses or CIDR ranges".to_string(),
+            ));
+        }
+
+        if app_config
+            .rate_limits
+            .iter()
+            .any(|rule| rule.capacity == 0 || rule.refill_per_minute == 0)
+        {
+            return Err(ConfigError::Message(
+                "RATE_LIMITS capacity and refill_per_minute must be at least 1".to_string(),
+            ));
+        }
+
+        if app_config.two_fa_max_attempts == 0 {
+            return Err(ConfigError::Message(
+                "TWO_FA_MAX_ATTEMPTS must be at least 1".to_string(),
+            ));
+        }
+
+        if app_config.password_reset_token_ttl_seconds <= 0
+            || app_config.email_verification_token_ttl_seconds <= 0
+            || app_config.email_change_token_ttl_seconds <= 0
+        {
+            return Err(ConfigError::Message(
+                "PASSWORD_RESET_TOKEN_TTL_SECONDS, EMAIL_VERIFICATION_TOKEN_TTL_SECONDS and EMAIL_CHANGE_TOKEN_TTL_SECONDS must be at least 1"
+                    .to_string(),
+            ));
+        }
+
+        if app_config.password_min_length < 8
+            || app_config.password_min_length > app_config.password_max_length
+            || app_config.password_max_length > 128
+        {
+            return Err(ConfigError::Message(
+                "PASSWORD_MIN_LENGTH and PASSWORD_MAX_LENGTH must satisfy 8 <= min <= max <= 128".to_string(),
+            ));
+        }
+
+        if app_config.password_blocklist_size > PasswordPolicy::bundled_blocklist_size() {
+            return Err(ConfigError::Message(format!(
+                "PASSWORD_BLOCKLIST_SIZE must be at most {}, the size of the bundled list",
+                PasswordPolicy::bundled_blocklist_size()
+            )));
+        }
+
+        if app_config.lockout_account_threshold == 0 || app_config.lockout_ip_threshold == 0 {
+            return Err(ConfigError::Message(
+                "LOCKOUT_ACCOUNT_THRESHOLD and LOCKOUT_IP_THRESHOLD must be at least 1".to_string(),
+            ));
+        }
+
         Ok(app_config)
     }
 }
@@ -71,8 +290,36 @@ pub fn get_config() -> &'static AppConfig {
     CONFIG.get_or_init(|| AppConfig::from_env().expect("Failed to load configuration"))
 }
 
-pub static JWT_SECRET: LazyLock<SecretBox<String>> =
-    LazyLock::new(|| SecretBox::new(Box::from(get_config().jwt_secret.clone())));
+static JWT_KEY_RING: LazyLock<RwLock<Arc<JwtKeyRing>>> = LazyLock::new(|| {
+    RwLock::new(Arc::new(
+        JwtKeyRing::from_config(get_config()).expect("Failed to load the JWT key ring"),
+    ))
+});
+
+#[derive(Debug, thiserror::Error)]
+pub enum ReloadKeyRingError {
+    #[error("Failed to load configuration")]
+    Config(#[from] ConfigError),
+
+    #[error("Failed to load the JWT key ring")]
+    Key(#[from] JwtKeyError),
+}
+
+// Returns a snapshot of the key ring, callers keep using it even if a reload happens meanwhile
+pub fn jwt_key_ring() -> Arc<JwtKeyRing> {
+    JWT_KEY_RING.read().unwrap_or_else(PoisonError::into_inner).clone()
+}
+
+// Re-reads the configuration sources and swaps the key ring, the old ring is kept if anything fails
+pub fn reload_jwt_key_ring() -> Result<(), ReloadKeyRingError> {
+    let key_ring = JwtKeyRing::from_config(&AppConfig::from_env()?)?;
+    *JWT_KEY_RING.write().unwrap_or_else(PoisonError::into_inner) = Arc::new(key_ring);
+    Ok(())
+}
+
+pub static JWT_ISSUER: LazyLock<String> = LazyLock::new(|| get_config().jwt_issuer.clone());
+
+pub static JWT_AUDIENCE: LazyLock<String> = LazyLock::new(|| get_config().jwt_audience.clone());
 
 pub static COOKIE_DOMAIN: LazyLock<String> = LazyLock::new(|| get_config().cookie_domain.clone());
 
@@ -90,3 +337,70 @@ pub static CAPTCHA_SECRET_KEY: LazyLock<String> = LazyLock::new(|| get_config().
 pub static TOKEN_TTL_SECONDS: LazyLock<i64> = LazyLock::new(|| get_config().token_ttl_seconds);
 
 pub static REFRESH_TOKEN_TTL_SECONDS: LazyLock<i64> = LazyLock::new(|| get_config().refresh_token_ttl_seconds);
+
+pub static SERVICE_TOKEN_TTL_SECONDS: LazyLock<i64> = LazyLock::new(|| get_config().service_token_ttl_seconds);
+
+pub static PASSWORD_RESET_TOKEN_TTL_SECONDS: LazyLock<i64> =
+    LazyLock::new(|| get_config().password_reset_token_ttl_seconds);
+
+pub static PASSWORD_RESET_URL: LazyLock<String> = LazyLock::new(|| get_config().password_reset_url.clone());
+
+pub static EMAIL_VERIFICATION_TOKEN_TTL_SECONDS: LazyLock<i64> =
+    LazyLock::new(|| get_config().email_verification_token_ttl_seconds);
+
+pub static EMAIL_VERIFICATION_URL: LazyLock<String> = LazyLock::new(|| get_config().email_verification_url.clone());
+
+pub static EMAIL_CHANGE_TOKEN_TTL_SECONDS: LazyLock<i64> =
+    LazyLock::new(|| get_config().email_change_token_ttl_seconds);
+
+pub static EMAIL_CHANGE_URL: LazyLock<String> = LazyLock::new(|| get_config().email_change_url.clone());
+
+pub static ALLOW_UNVERIFIED_LOGIN: LazyLock<bool> = LazyLock::new(|| get_config().allow_unverified_login);
+
+pub static PASSWORD_POLICY: LazyLock<PasswordPolicy> = LazyLock::new(|| {
+    let config = get_config();
+    PasswordPolicy::new(
+        config.password_min_length,
+        config.password_max_length,
+        config.password_reject_email,
+        config.password_blocklist_size,
+        config.password_min_entropy_bits,
+    )
+});
+
+pub static HTTP_ADDRESS: LazyLock<String> = LazyLock::new(|| get_config().http_address.clone());
+
+pub static GRPC_ADDRESS: LazyLock<String> = LazyLock::new(|| get_config().grpc_address.clone());
+
+pub static TOTP_ISSUER: LazyLock<String> = LazyLock::new(|| get_config().totp_issuer.clone());
+
+pub static TOTP_DRIFT_STEPS: LazyLock<u64> = LazyLock::new(|| get_config().totp_drift_steps);
+
+pub static TWO_FA_CODE_TTL_SECONDS: LazyLock<u64> = LazyLock::new(|| get_config().two_fa_code_ttl_seconds);
+
+pub static TWO_FA_MAX_ATTEMPTS: LazyLock<u32> = LazyLock::new(|| get_config().two_fa_max_attempts);
+
+pub static LOCKOUT_ACCOUNT_THRESHOLD: LazyLock<u32> = LazyLock::new(|| get_config().lockout_account_threshold);
+
+pub static LOCKOUT_IP_THRESHOLD: LazyLock<u32> = LazyLock::new(|| get_config().lockout_ip_threshold);
+
+pub static LOCKOUT_FAILURE_WINDOW_SECONDS: LazyLock<u64> =
+    LazyLock::new(|| get_config().lockout_failure_window_seconds);
+
+pub static LOCKOUT_BASE_SECONDS: LazyLock<u64> = LazyLock::new(|| get_config().lockout_base_seconds);
+
+pub static LOCKOUT_MAX_SECONDS: LazyLock<u64> = LazyLock::new(|| get_config().lockout_max_seconds);
+
+pub static LOCKOUT_RESET_SECONDS: LazyLock<u64> = LazyLock::new(|| get_config().lockout_reset_seconds);
+
+pub static TRUSTED_PROXIES: LazyLock<TrustedProxies> = LazyLock::new(|| {
+    TrustedProxies::parse(&get_config().trusted_proxies).expect("Failed to parse the trusted proxies")
+});
+
+pub static RATE_LIMITS: LazyLock<Vec<RateLimitRule>> = LazyLock::new(|| get_config().rate_limits.clone());
+
+pub static WEBAUTHN_RP_ID: LazyLock<String> = LazyLock::new(|| get_config().webauthn_rp_id.clone());
+
+pub static WEBAUTHN_RP_NAME: LazyLock<String> = LazyLock::new(|| get_config().webauthn_rp_name.clone());
+
+pub static WEBAUTHN_ORIGIN: LazyLock<String> = LazyLock::new(|| get_config().webauthn_origin.clone());
@@ -1,10 +1,7 @@
 pub const JWT_COOKIE_NAME: &str = "jwt";
 pub const JWT_REFRESH_COOKIE_NAME: &str = "jwt-refresh";
 pub const PGSQL_MAX_CONNECTIONS: u32 = 10;
-
-pub mod prod {
-    pub const APP_ADDRESS: &str = "0.0.0.0:3000";
-}
+pub const PASSKEY_CHALLENGE_TTL_SECONDS: u64 = 300;
 
 pub mod test {
     pub const APP_ADDRESS: &str = "127.0.0.1:0";
@@ -12,9 +9,26 @@ pub mod test {
 
 pub mod email {
     pub const SUBJECT: &str = "Let's get Rusty Bootcamp code";
+    pub const LOCKOUT_SUBJECT: &str = "Let's get Rusty Bootcamp account locked";
+    pub const PASSWORD_RESET_SUBJECT: &str = "Let's get Rusty Bootcamp password reset";
+    pub const PASSWORD_CHANGED_SUBJECT: &str = "Let's get Rusty Bootcamp password changed";
+    pub const VERIFICATION_SUBJECT: &str = "Let's get Rusty Bootcamp email verification";
+    pub const EMAIL_CHANGE_SUBJECT: &str = "Let's get Rusty Bootcamp email change";
+    pub const EMAIL_CHANGE_NOTICE_SUBJECT: &str = "Let's get Rusty Bootcamp email change requested";
+    pub const PASSKEY_ADDED_SUBJECT: &str = "Let's get Rusty Bootcamp passkey added";
+    pub const RECOVERY_CODES_SUBJECT: &str = "Let's get Rusty Bootcamp recovery codes regenerated";
 }
 
 pub mod redis_env {
     pub const BANNED_TOKEN_KEY_PREFIX: &str = "banned_token:";
     pub const TWO_FA_CODE_PREFIX: &str = "two_fa_code:";
+    pub const REFRESH_TOKEN_KEY_PREFIX: &str = "refresh_token:";
+    pub const REVOKED_REFRESH_FAMILY_KEY_PREFIX: &str = "revoked_refresh_family:";
+    pub const SESSION_EPOCH_KEY_PREFIX: &str = "session_epoch:";
+    pub const REVOCATIONS_CHANNEL: &str = "revocations";
+    pub const PASSKEY_CHALLENGE_KEY_PREFIX: &str = "passkey_challenge:";
+    pub const LOGIN_FAILURES_KEY_PREFIX: &str = "login_failures:";
+    pub const LOGIN_LOCK_KEY_PREFIX: &str = "login_lock:";
+    pub const LOGIN_LOCKOUT_STREAK_KEY_PREFIX: &str = "login_lockout_streak:";
+    pub const RATE_LIMIT_KEY_PREFIX: &str = "rate_limit:";
 }
@@ -0,0 +1,529 @@
+use super::config::{AppConfig, JwtRetiredKeyConfig};
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use ed25519_dalek::VerifyingKey;
+use jsonwebtoken::errors::ErrorKind;
+use jsonwebtoken::jwk::{
+    AlgorithmParameters, CommonParameters, EllipticCurve, Jwk, JwkSet, KeyAlgorithm, OctetKeyPairParameters,
+    OctetKeyPairType, PublicKeyUse, RSAKeyParameters, RSAKeyType,
+};
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey, Header, Validation, decode_header};
+use rsa::RsaPublicKey;
+use rsa::pkcs8::DecodePublicKey;
+use rsa::traits::PublicKeyParts;
+use std::collections::HashSet;
+use std::str::FromStr;
+
+#[derive(Debug, thiserror::Error)]
+pub enum JwtKeyError {
+    #[error("Unsupported JWT algorithm: {0}")]
+    UnsupportedAlgorithm(String),
+
+    #[error("Failed to read the key file {0}")]
+    KeyFile(String, #[source] std::io::Error),
+
+    #[error("Invalid key material")]
+    InvalidKey(#[source] jsonwebtoken::errors::Error),
+
+    #[error("Invalid public key: {0}")]
+    InvalidPublicKey(String),
+
+    #[error("The current key {0} cannot sign tokens")]
+    MissingSigningKey(String),
+
+    #[error("The key id {0} is used by more than one key")]
+    DuplicateKeyId(String),
+}
+
+// The family of keys an algorithm belongs to, it decides how the key material is loaded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum KeyFamily {
+    Hmac,
+    Rsa,
+    Ed25519,
+}
+
+impl KeyFamily {
+    fn of(algorithm: Algorithm) -> Option<Self> {
+        match algorithm {
+            Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512 => Some(KeyFamily::Hmac),
+            Algorithm::RS256
+            | Algorithm::RS384
+            | Algorithm::RS512
+            | Algorithm::PS256
+            | Algorithm::PS384
+            | Algorithm::PS512 => Some(KeyFamily::Rsa),
+            Algorithm::EdDSA => Some(KeyFamily::Ed25519),
+            _ => None,
+        }
+    }
+}
+
+/// A JWT key identified by its `kid`. Symmetric (HMAC) keys are never published, asymmetric keys
+/// expose their public half as a JWK so other services can verify tokens without our secrets.
+/// Retired keys only keep the verification half.
+pub struct JwtKey {
+    kid: String,
+    algorithm: Algorithm,
+    encoding_key: Option<EncodingKey>,
+    decoding_key: DecodingKey,
+    jwk: Option<Jwk>,
+}
+
+impl JwtKey {
+    pub fn from_config(config: &AppConfig) -> Result<Self, JwtKeyError> {
+        let (algorithm, family) = parse_algorithm(&config.jwt_algorithm)?;
+
+        if family == KeyFamily::Hmac {
+            return Ok(Self::from_secret(
+                &config.jwt_key_id,
+                algorithm,
+                config.jwt_secret.as_bytes(),
+            ));
+        }
+
+        let private_pem = read_key_file(&config.jwt_private_key_path)?;
+        let encoding_key = match family {
+            KeyFamily::Rsa => EncodingKey::from_rsa_pem(&private_pem),
+            _ => EncodingKey::from_ed_pem(&private_pem),
+        }
+        .map_err(JwtKeyError::InvalidKey)?;
+
+        let mut key = Self::public(&config.jwt_key_id, algorithm, family, &config.jwt_public_key_path)?;
+        key.encoding_key = Some(encoding_key);
+        Ok(key)
+    }
+
+    // A retired key can no longer sign, it only verifies the tokens it signed while it was current
+    pub fn from_retired_config(config: &JwtRetiredKeyConfig) -> Result<Self, JwtKeyError> {
+        let (algorithm, family) = parse_algorithm(&config.algorithm)?;
+
+        if family == KeyFamily::Hmac {
+            let mut key = Self::from_secret(&config.kid, algorithm, config.secret.as_bytes());
+            key.encoding_key = None;
+            return Ok(key);
+        }
+
+        Self::public(&config.kid, algorithm, family, &config.public_key_path)
+    }
+
+    pub fn from_secret(
+        kid: &str,
+        algorithm: Algorithm,
+        secret: &[u8],
+    ) -> Self {
+        Self {
+            kid: kid.to_string(),
+            algorithm,
+            encoding_key: Some(EncodingKey::from_secret(secret)),
+            decoding_key: DecodingKey::from_secret(secret),
+            jwk: None,
+        }
+    }
+
+    fn public(
+        kid: &str,
+        algorithm: Algorithm,
+        family: KeyFamily,
+        public_key_path: &str,
+    ) -> Result<Self, JwtKeyError> {
+        let public_pem = read_key_file(public_key_path)?;
+        let decoding_key = match family {
+            KeyFamily::Rsa => DecodingKey::from_rsa_pem(&public_pem),
+            _ => DecodingKey::from_ed_pem(&public_pem),
+        }
+        .map_err(JwtKeyError::InvalidKey)?;
+
+        let public_pem = String::from_utf8(public_pem).map_err(|e| JwtKeyError::InvalidPublicKey(e.to_string()))?;
+        let jwk = public_jwk(kid, algorithm, family, &public_pem)?;
+
+        Ok(Self {
+            kid: kid.to_string(),
+            algorithm,
+            encoding_key: None,
+            decoding_key,
+            jwk: Some(jwk),
+        })
+    }
+
+    pub fn kid(&self) -> &str {
+        &self.kid
+    }
+
+    pub fn algorithm(&self) -> Algorithm {
+        self.algorithm
+    }
+
+    pub fn encoding_key(&self) -> Option<&EncodingKey> {
+        self.encoding_key.as_ref()
+    }
+
+    pub fn decoding_key(&self) -> &DecodingKey {
+        &self.decoding_key
+    }
+
+    // Header for tokens signed with this key, it always carries the `kid`
+    pub fn header(&self) -> Header {
+        let mut header = Header::new(self.algorithm);
+        header.kid = Some(self.kid.clone());
+        header
+    }
+
+    // Validation that only accepts tokens signed with this key's algorithm
+    pub fn validation(&self) -> Validation {
+        Validation::new(self.algorithm)
+    }
+
+    pub fn jwk(&self) -> Option<&Jwk> {
+        self.jwk.as_ref()
+    }
+}
+
+/// The current signing key plus the retired keys that still verify tokens issued before a rotation.
+/// Keys are selected by the `kid` of the token header.
+pub struct JwtKeyRing {
+    current: JwtKey,
+    retired: Vec<JwtKey>,
+}
+
+impl JwtKeyRing {
+    pub fn new(
+        current: JwtKey,
+        retired: Vec<JwtKey>,
+    ) -> Result<Self, JwtKeyError> {
+        if current.encoding_key.is_none() {
+            return Err(JwtKeyError::MissingSigningKey(current.kid));
+        }
+
+        let mut kids = HashSet::from([current.kid.as_str()]);
+        if let Some(duplicate) = retired.iter().find(|key| !kids.insert(key.kid.as_str())) {
+            return Err(JwtKeyError::DuplicateKeyId(duplicate.kid.clone()));
+        }
+
+        Ok(Self { current, retired })
+    }
+
+    pub fn from_config(config: &AppConfig) -> Result<Self, JwtKeyError> {
+        let current = JwtKey::from_config(config)?;
+        let retired = config
+            .jwt_retired_keys
+            .iter()
+            .map(JwtKey::from_retired_config)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Self::new(current, retired)
+    }
+
+    pub fn current(&self) -> &JwtKey {
+        &self.current
+    }
+
+    pub fn find(
+        &self,
+        kid: &str,
+    ) -> Option<&JwtKey> {
+        std::iter::once(&self.current)
+            .chain(self.retired.iter())
+            .find(|key| key.kid == kid)
+    }
+
+    // Picks the key named by the token header, tokens without a known `kid` are rejected
+    pub fn verification_key(
+        &self,
+        token: &str,
+    ) -> Result<&JwtKey, jsonwebtoken::errors::Error> {
+        let header = decode_header(token)?;
+        header
+            .kid
+            .as_deref()
+            .and_then(|kid| self.find(kid))
+            .filter(|key| key.algorithm == header.alg)
+            .ok_or_else(|| ErrorKind::InvalidToken.into())
+    }
+
+    pub fn jwks(&self) -> JwkSet {
+        JwkSet {
+            keys: std::iter::once(&self.current)
+                .chain(self.retired.iter())
+                .filter_map(|key| key.jwk.clone())
+                .collect(),
+        }
+    }
+}
+
+fn parse_algorithm(algorithm: &str) -> Result<(Algorithm, KeyFamily), JwtKeyError> {
+    let parsed =
+        Algorithm::from_str(algorithm).map_err(|_| JwtKeyError::UnsupportedAlgorithm(algorithm.to_string()))?;
+    let family = KeyFamily::of(parsed).ok_or_else(|| JwtKeyError::UnsupportedAlgorithm(algorithm.to_string()))?;
+    Ok((parsed, family))
+}
+
+fn read_key_file(path: &str) -> Result<Vec<u8>, JwtKeyError> {
+    std::fs::read(path).map_err(|e| JwtKeyError::KeyFile(path.to_string(), e))
+}
+
+fn public_jwk(
+    kid: &str,
+    algorithm: Algorithm,
+    family: KeyFamily,
+    public_pem: &str,
+) -> Result<Jwk, JwtKeyError> {
+    let algorithm_parameters = match family {
+        KeyFamily::Rsa => {
+            let public_key = RsaPublicKey::from_public_key_pem(public_pem)
+                .map_err(|e| JwtKeyError::InvalidPublicKey(e.to_string()))?;
+            AlgorithmParameters::RSA(RSAKeyParameters {
+                key_type: RSAKeyType::RSA,
+                n: URL_SAFE_NO_PAD.encode(public_key.n().to_bytes_be()),
+                e: URL_SAFE_NO_PAD.encode(public_key.e().to_bytes_be()),
+            })
+        }
+        KeyFamily::Ed25519 => {
+            let public_key = VerifyingKey::from_public_key_pem(public_pem)
+                .map_err(|e| JwtKeyError::InvalidPublicKey(e.to_string()))?;
+            AlgorithmParameters::OctetKeyPair(OctetKeyPairParameters {
+                key_type: OctetKeyPairType::OctetKeyPair,
+                curve: EllipticCurve::Ed25519,
+                x: URL_SAFE_NO_PAD.encode(public_key.to_bytes()),
+            })
+        }
+        KeyFamily::Hmac => {
+            return Err(JwtKeyError::InvalidPublicKey(
+                "HMAC keys have no public part".to_string(),
+            ));
+        }
+    };
+
+    Ok(Jwk {
+        common: CommonParameters {
+            public_key_use: Some(PublicKeyUse::Signature),
+            key_algorithm: KeyAlgorithm::from_str(&format!("{:?}", algorithm)).ok(),
+            key_id: Some(kid.to_string()),
+            ..Default::default()
+        },
+        algorithm: algorithm_parameters,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonwebtoken::{decode, encode};
+    use serde::{Deserialize, Serialize};
+
+    const RSA_PRIVATE_KEY: &str = "tests/fixtures/jwt/rsa_private.pem";
+    const RSA_PUBLIC_KEY: &str = "tests/fixtures/jwt/rsa_public.pem";
+    const ED25519_PRIVATE_KEY: &str = "tests/fixtures/jwt/ed25519_private.pem";
+    const ED25519_PUBLIC_KEY: &str = "tests/fixtures/jwt/ed25519_public.pem";
+
+    #[derive(Debug, Serialize, Deserialize)]
+    struct TestClaims {
+        sub: String,
+        exp: usize,
+    }
+
+    fn config_for(
+        algorithm: &str,
+        private_key_path: &str,
+        public_key_path: &str,
+    ) -> AppConfig {
+        AppConfig {
+            jwt_secret: "secret".to_string(),
+            jwt_algorithm: algorithm.to_string(),
+            jwt_key_id: "test-key".to_string(),
+            jwt_private_key_path: private_key_path.to_string(),
+            jwt_public_key_path: public_key_path.to_string(),
+            ..AppConfig::default()
+        }
+    }
+
+    fn sign_test_token(key: &JwtKey) -> String {
+        let claims = TestClaims {
+            sub: "user@example.com".to_string(),
+            exp: (chrono::Utc::now().timestamp() + 60) as usize,
+        };
+        encode(&key.header(), &claims, key.encoding_key().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_rsa_key_signs_and_verifies() {
+        let key = JwtKey::from_config(&config_for("RS256", RSA_PRIVATE_KEY, RSA_PUBLIC_KEY)).unwrap();
+        let token = sign_test_token(&key);
+
+        let header = jsonwebtoken::decode_header(&token).unwrap();
+        assert_eq!(header.alg, Algorithm::RS256);
+        assert_eq!(header.kid.as_deref(), Some("test-key"));
+
+        let claims = decode::<TestClaims>(&token, key.decoding_key(), &key.validation()).unwrap();
+        assert_eq!(claims.claims.sub, "user@example.com");
+    }
+
+    #[test]
+    fn test_ed25519_key_signs_and_verifies() {
+        let key = JwtKey::from_config(&config_for("EdDSA", ED25519_PRIVATE_KEY, ED25519_PUBLIC_KEY)).unwrap();
+        let token = sign_test_token(&key);
+
+        let header = jsonwebtoken::decode_header(&token).unwrap();
+        assert_eq!(header.alg, Algorithm::EdDSA);
+
+        assert!(decode::<TestClaims>(&token, key.decoding_key(), &key.validation()).is_ok());
+    }
+
+    #[test]
+    fn test_published_rsa_jwk_verifies_tokens() {
+        let key = JwtKey::from_config(&config_for("RS256", RSA_PRIVATE_KEY, RSA_PUBLIC_KEY)).unwrap();
+        let token = sign_test_token(&key);
+
+        let jwks = JwtKeyRing::new(key, vec![]).unwrap().jwks();
+        assert_eq!(jwks.keys.len(), 1);
+        let jwk = jwks.find("test-key").expect("JWK not found by kid");
+        assert_eq!(jwk.common.key_algorithm, Some(KeyAlgorithm::RS256));
+
+        let decoding_key = DecodingKey::from_jwk(jwk).unwrap();
+        assert!(decode::<TestClaims>(&token, &decoding_key, &Validation::new(Algorithm::RS256)).is_ok());
+    }
+
+    #[test]
+    fn test_published_ed25519_jwk_verifies_tokens() {
+        let key = JwtKey::from_config(&config_for("EdDSA", ED25519_PRIVATE_KEY, ED25519_PUBLIC_KEY)).unwrap();
+        let token = sign_test_token(&key);
+
+        let jwk = key.jwk().expect("Ed25519 keys must be published");
+        assert!(matches!(jwk.algorithm, AlgorithmParameters::OctetKeyPair(_)));
+
+        let decoding_key = DecodingKey::from_jwk(jwk).unwrap();
+        assert!(decode::<TestClaims>(&token, &decoding_key, &Validation::new(Algorithm::EdDSA)).is_ok());
+    }
+
+    #[test]
+    fn test_hmac_key_is_never_published() {
+        let key = JwtKey::from_config(&config_for("HS256", "", "")).unwrap();
+        assert!(key.jwk().is_none());
+        assert!(JwtKeyRing::new(key, vec![]).unwrap().jwks().keys.is_empty());
+    }
+
+    #[test]
+    fn test_token_signed_by_other_algorithm_is_rejected() {
+        let rsa_key = JwtKey::from_config(&config_for("RS256", RSA_PRIVATE_KEY, RSA_PUBLIC_KEY)).unwrap();
+        let hmac_key = JwtKey::from_secret("test-key", Algorithm::HS256, b"secret");
+        let token = sign_test_token(&hmac_key);
+
+        assert!(decode::<TestClaims>(&token, rsa_key.decoding_key(), &rsa_key.validation()).is_err());
+    }
+
+    #[test]
+    fn test_unsupported_algorithm() {
+        let result = JwtKey::from_config(&config_for("ES256", RSA_PRIVATE_KEY, RSA_PUBLIC_KEY));
+        assert!(matches!(result, Err(JwtKeyError::UnsupportedAlgorithm(_))));
+
+        let result = JwtKey::from_config(&config_for("none", RSA_PRIVATE_KEY, RSA_PUBLIC_KEY));
+        assert!(matches!(result, Err(JwtKeyError::UnsupportedAlgorithm(_))));
+    }
+
+    #[test]
+    fn test_missing_key_file() {
+        let result = JwtKey::from_config(&config_for("RS256", "does/not/exist.pem", RSA_PUBLIC_KEY));
+        assert!(matches!(result, Err(JwtKeyError::KeyFile(_, _))));
+    }
+
+    #[test]
+    fn test_mismatched_key_type() {
+        let result = JwtKey::from_config(&config_for("RS256", ED25519_PRIVATE_KEY, ED25519_PUBLIC_KEY));
+        assert!(result.is_err());
+    }
+
+    fn retired_config(
+        kid: &str,
+        algorithm: &str,
+        public_key_path: &str,
+        secret: &str,
+    ) -> JwtRetiredKeyConfig {
+        JwtRetiredKeyConfig {
+            kid: kid.to_string(),
+            algorithm: algorithm.to_string(),
+            public_key_path: public_key_path.to_string(),
+            secret: secret.to_string(),
+        }
+    }
+
+    fn rotated_ring() -> JwtKeyRing {
+        let mut config = config_for("RS256", RSA_PRIVATE_KEY, RSA_PUBLIC_KEY);
+        config.jwt_key_id = "new".to_string();
+        config.jwt_retired_keys = vec![
+            retired_config("old-ed25519", "EdDSA", ED25519_PUBLIC_KEY, ""),
+            retired_config("old-hmac", "HS256", "", "old-secret"),
+        ];
+        JwtKeyRing::from_config(&config).unwrap()
+    }
+
+    #[test]
+    fn test_ring_selects_key_by_kid() {
+        let ring = rotated_ring();
+
+        assert_eq!(ring.current().kid(), "new");
+        assert!(ring.find("old-ed25519").is_some());
+        assert!(ring.find("old-hmac").is_some());
+        assert!(ring.find("unknown").is_none());
+    }
+
+    #[test]
+    fn test_ring_verifies_tokens_signed_before_rotation() {
+        let ring = rotated_ring();
+
+        let mut old_config = config_for("EdDSA", ED25519_PRIVATE_KEY, ED25519_PUBLIC_KEY);
+        old_config.jwt_key_id = "old-ed25519".to_string();
+        let old_key = JwtKey::from_config(&old_config).unwrap();
+        let old_token = sign_test_token(&old_key);
+
+        let key = ring.verification_key(&old_token).unwrap();
+        assert_eq!(key.kid(), "old-ed25519");
+        assert!(decode::<TestClaims>(&old_token, key.decoding_key(), &key.validation()).is_ok());
+
+        let old_hmac_token = sign_test_token(&JwtKey::from_secret("old-hmac", Algorithm::HS256, b"old-secret"));
+        let key = ring.verification_key(&old_hmac_token).unwrap();
+        assert!(decode::<TestClaims>(&old_hmac_token, key.decoding_key(), &key.validation()).is_ok());
+
+        let new_token = sign_test_token(ring.current());
+        assert_eq!(ring.verification_key(&new_token).unwrap().kid(), "new");
+    }
+
+    #[test]
+    fn test_ring_rejects_unknown_kid_and_algorithm_mismatch() {
+        let ring = rotated_ring();
+
+        let unknown = sign_test_token(&JwtKey::from_secret("unknown", Algorithm::HS256, b"secret"));
+        assert!(ring.verification_key(&unknown).is_err());
+
+        let confused = sign_test_token(&JwtKey::from_secret("new", Algorithm::HS256, b"secret"));
+        assert!(ring.verification_key(&confused).is_err());
+
+        assert!(ring.verification_key("not-a-token").is_err());
+    }
+
+    #[test]
+    fn test_retired_keys_cannot_sign() {
+        let ring = rotated_ring();
+        assert!(ring.current().encoding_key().is_some());
+        assert!(ring.find("old-ed25519").unwrap().encoding_key().is_none());
+        assert!(ring.find("old-hmac").unwrap().encoding_key().is_none());
+    }
+
+    #[test]
+    fn test_ring_publishes_current_and_retired_public_keys() {
+        let jwks = rotated_ring().jwks();
+
+        assert_eq!(jwks.keys.len(), 2);
+        assert!(jwks.find("new").is_some());
+        assert!(jwks.find("old-ed25519").is_some());
+        assert!(jwks.find("old-hmac").is_none());
+    }
+
+    #[test]
+    fn test_ring_rejects_duplicate_kid() {
+        let mut config = config_for("HS256", "", "");
+        config.jwt_retired_keys = vec![retired_config("test-key", "HS256", "", "old-secret")];
+
+        let result = JwtKeyRing::from_config(&config);
+        assert!(matches!(result, Err(JwtKeyError::DuplicateKeyId(_))));
+    }
+}
@@ -0,0 +1,149 @@
+use crate::app_state::AppState;
+use crate::domain::data_stores::LoginLockoutStoreError;
+use crate::domain::{AuthAPIError, Email, LockoutSubject};
+use crate::utils::{LOCKOUT_ACCOUNT_THRESHOLD, LOCKOUT_BASE_SECONDS, LOCKOUT_IP_THRESHOLD, LOCKOUT_MAX_SECONDS, email};
+use std::net::IpAddr;
+
+// Refuse a login while the account or the address it comes from is locked, even with the right password
+pub async fn ensure_login_allowed(
+    state: &AppState,
+    email: &Email,
+    client_ip: Option<IpAddr>,
+) -> Result<(), AuthAPIError> {
+    let store = state.login_lockout_store.read().await;
+
+    if let Some(seconds) = store
+        .locked_for(&LockoutSubject::Account(email.clone()))
+        .await
+        .map_err(unexpected)?
+    {
+        return Err(AuthAPIError::AccountLocked(seconds));
+    }
+
+    if let Some(ip) = client_ip
+        && let Some(seconds) = store.locked_for(&LockoutSubject::Ip(ip)).await.map_err(unexpected)?
+    {
+        return Err(AuthAPIError::TooManyLoginAttempts(seconds));
+    }
+
+    Ok(())
+}
+
+// Count a wrong password against the account and the address, and lock the one that reached its
+// threshold. Unknown accounts are counted the same way, only existing owners get the email.
+// Returns the error the attempt ends with.
+pub async fn record_failed_login(
+    state: &AppState,
+    email: &Email,
+    client_ip: Option<IpAddr>,
+    account_exists: bool,
+) -> AuthAPIError {
+    let account = LockoutSubject::Account(email.clone());
+    let account_lock = match record_failure(state, &account, *LOCKOUT_ACCOUNT_THRESHOLD).await {
+        Ok(account_lock) => account_lock,
+        Err(e) => return unexpected(e),
+    };
+    let ip_lock = match client_ip {
+        Some(ip) => match record_failure(state, &LockoutSubject::Ip(ip), *LOCKOUT_IP_THRESHOLD).await {
+            Ok(ip_lock) => ip_lock,
+            Err(e) => return unexpected(e),
+        },
+        None => None,
+    };
+
+    if let Some(seconds) = account_lock {
+        tracing::warn!("Too many failed logins, the account is locked for {} seconds", seconds);
+        if account_exists {
+            notify_lockout(state, email, seconds).await;
+        }
+        return AuthAPIError::AccountLocked(seconds);
+    }
+
+    if let Some(seconds) = ip_lock {
+        tracing::warn!(
+            "Too many failed logins from one address, locked for {} seconds",
+            seconds
+        );
+        return AuthAPIError::TooManyLoginAttempts(seconds);
+    }
+
+    AuthAPIError::IncorrectCredentials
+}
+
+// A successful login starts the account over, the counters of its address are left alone
+pub async fn clear_failed_logins(
+    state: &AppState,
+    email: &Email,
+) -> Result<(), AuthAPIError> {
+    state
+        .login_lockout_store
+        .write()
+        .await
+        .reset(&LockoutSubject::Account(email.clone()))
+        .await
+        .map_err(unexpected)
+}
+
+// Each lockout within `LOCKOUT_RESET_SECONDS` of the previous one lasts twice as long
+pub fn lockout_seconds(streak: u32) -> u64 {
+    LOCKOUT_BASE_SECONDS
+        .saturating_mul(2u64.saturating_pow(streak))
+        .min(*LOCKOUT_MAX_SECONDS)
+}
+
+// Returns the lock duration when this failure reached the threshold
+async fn record_failure(
+    state: &AppState,
+    subject: &LockoutSubject,
+    threshold: u32,
+) -> Result<Option<u64>, LoginLockoutStoreError> {
+    let mut store = state.login_lockout_store.write().await;
+
+    if store.record_failure(subject).await? < threshold {
+        return Ok(None);
+    }
+
+    let seconds = lockout_seconds(store.lockout_streak(subject).await?);
+    store.lock(subject, seconds).await?;
+    Ok(Some(seconds))
+}
+
+// The lock is already in place, a failed email doesn't change the answer
+async fn notify_lockout(
+    state: &AppState,
+    email: &Email,
+    seconds: u64,
+) {
+    let content = format!(
+        "There were too many failed logins to your account, it is locked for {} minutes. \
+        If it wasn't you, change your password once the lock is over.",
+        seconds.div_ceil(60)
+    );
+
+    if let Err(e) = state
+        .email_client
+        .read()
+        .await
+        .send_email(email, email::LOCKOUT_SUBJECT, &content)
+        .await
+    {
+        tracing::warn!("Failed to send the lockout notification: {:?}", e);
+    }
+}
+
+fn unexpected(error: LoginLockoutStoreError) -> AuthAPIError {
+    AuthAPIError::UnexpectedError(error.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lockout_seconds_doubles_up_to_the_max() {
+        assert_eq!(lockout_seconds(0), *LOCKOUT_BASE_SECONDS);
+        assert_eq!(lockout_seconds(1), *LOCKOUT_BASE_SECONDS * 2);
+        assert_eq!(lockout_seconds(2), *LOCKOUT_BASE_SECONDS * 4);
+        assert_eq!(lockout_seconds(u32::MAX), *LOCKOUT_MAX_SECONDS);
+    }
+}
@@ -1,9 +1,25 @@
 mod auth;
+mod authenticated_user;
+mod client_ip;
 mod config;
 mod constants;
+mod jwt_keys;
+mod lockout;
+mod rate_limit;
+mod tls;
+mod totp_cipher;
 mod tracing;
+mod webauthn;
 
 pub use auth::*;
+pub use authenticated_user::*;
+pub use client_ip::*;
 pub use config::*;
 pub use constants::*;
+pub use jwt_keys::*;
+pub use lockout::*;
+pub use rate_limit::*;
+pub use tls::*;
+pub use totp_cipher::*;
 pub use tracing::*;
+pub use webauthn::*;
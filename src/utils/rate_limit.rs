@@ -0,0 +1,172 @@
+use crate::app_state::AppState;
+use crate::domain::data_stores::RateLimitStoreError;
+use crate::domain::{AuthAPIError, RateLimitDecision};
+use crate::utils::{ClientIp, RATE_LIMITS, RateLimitKey, RateLimitRule};
+use axum::body::{Body, to_bytes};
+use axum::extract::{Request, State};
+use axum::http::header::HeaderValue;
+use axum::http::{HeaderMap, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use serde::Deserialize;
+use std::net::IpAddr;
+
+const DEFAULT_RULE_PATH: &str = "*";
+const RATE_LIMIT_LIMIT: &str = "ratelimit-limit";
+const RATE_LIMIT_REMAINING: &str = "ratelimit-remaining";
+const RATE_LIMIT_RESET: &str = "ratelimit-reset";
+// Bodies read to find the email, anything bigger isn't a form of this service
+const MAX_BODY_BYTES: usize = 64 * 1024;
+
+#[derive(Deserialize)]
+struct EmailField {
+    email: Option<String>,
+}
+
+// Tower layer, via `axum::middleware::from_fn_with_state`, that takes a token from the buckets of
+// the request before it reaches the route. Over the limit the answer is a 429 with `Retry-After`,
+// every answer of a limited route carries the `RateLimit-*` headers of its tightest bucket.
+// A store failure lets the request through, the lockouts still protect the logins.
+pub async fn rate_limit(
+    State(state): State<AppState>,
+    ClientIp(client_ip): ClientIp,
+    request: Request,
+    next: Next,
+) -> Response {
+    let rules = rules_for(request.uri().path());
+    if rules.is_empty() {
+        return next.run(request).await;
+    }
+
+    let (request, email) = if rules.iter().any(|rule| rule.key == RateLimitKey::Email) {
+        match read_email(request).await {
+            Ok(read) => read,
+            Err(response) => return response,
+        }
+    } else {
+        (request, None)
+    };
+
+    let tightest = match take_tokens(&state, &rules, client_ip, email.as_deref()).await {
+        Ok(tightest) => tightest,
+        Err(e) => {
+            tracing::error!("Rate limit store failed, the request goes through: {:?}", e);
+            return next.run(request).await;
+        }
+    };
+
+    let Some(decision) = tightest else {
+        return next.run(request).await;
+    };
+    let mut response = if decision.allowed {
+        next.run(request).await
+    } else {
+        AuthAPIError::RateLimited(decision.retry_after_seconds).into_response()
+    };
+    add_rate_limit_headers(response.headers_mut(), &decision);
+    response
+}
+
+// The gRPC calls have no middleware of their own, they take from the buckets of the HTTP route doing the same
+// thing (`/login` for Login) so switching transport doesn't double the budget. The peer address is the key, gRPC
+// clients don't come through the HTTP proxies.
+pub async fn check_rate_limit(
+    state: &AppState,
+    path: &str,
+    client_ip: Option<IpAddr>,
+    email: Option<&str>,
+) -> Result<(), AuthAPIError> {
+    let Some(client_ip) = client_ip else {
+        return Ok(());
+    };
+    let email = email
+        .map(|email| email.trim().to_lowercase())
+        .filter(|email| !email.is_empty());
+
+    match take_tokens(state, &rules_for(path), client_ip, email.as_deref()).await {
+        Ok(Some(decision)) if !decision.allowed => Err(AuthAPIError::RateLimited(decision.retry_after_seconds)),
+        Ok(_) => Ok(()),
+        Err(e) => {
+            tracing::error!("Rate limit store failed, the request goes through: {:?}", e);
+            Ok(())
+        }
+    }
+}
+
+// Takes a token from the bucket of every rule, stopping at the first empty one. The answer is the decision of that
+// bucket, or of the one with the fewest tokens left, None when no rule applies.
+async fn take_tokens(
+    state: &AppState,
+    rules: &[&RateLimitRule],
+    client_ip: IpAddr,
+    email: Option<&str>,
+) -> Result<Option<RateLimitDecision>, RateLimitStoreError> {
+    let mut tightest: Option<RateLimitDecision> = None;
+    for rule in rules {
+        let subject = match rule.key {
+            RateLimitKey::Ip => format!("ip:{}", client_ip),
+            RateLimitKey::Email => match email {
+                Some(email) => format!("email:{}", email),
+                None => continue,
+            },
+        };
+
+        let decision = state
+            .rate_limit_store
+            .read()
+            .await
+            .take_token(
+                &format!("{}:{}", rule.path, subject),
+                rule.capacity,
+                rule.refill_per_minute,
+            )
+            .await?;
+
+        if !decision.allowed {
+            return Ok(Some(decision));
+        }
+        if tightest.is_none_or(|tightest| decision.remaining < tightest.remaining) {
+            tightest = Some(decision);
+        }
+    }
+
+    Ok(tightest)
+}
+
+// The rules of the route, or the default ones when it has none
+fn rules_for(path: &str) -> Vec<&'static RateLimitRule> {
+    let rules = RATE_LIMITS.iter().filter(|rule| rule.path == path).collect::<Vec<_>>();
+    if !rules.is_empty() {
+        return rules;
+    }
+
+    RATE_LIMITS
+        .iter()
+        .filter(|rule| rule.path == DEFAULT_RULE_PATH)
+        .collect()
+}
+
+// The body is read to find its `email` and put back for the route
+async fn read_email(request: Request) -> Result<(Request, Option<String>), Response> {
+    let (parts, body) = request.into_parts();
+    let bytes = to_bytes(body, MAX_BODY_BYTES)
+        .await
+        .map_err(|_| StatusCode::PAYLOAD_TOO_LARGE.into_response())?;
+
+    let email = serde_json::from_slice::<EmailField>(&bytes)
+        .ok()
+        .and_then(|field| field.email)
+        .map(|email| email.trim().to_lowercase())
+        .filter(|email| !email.is_empty());
+
+    Ok((Request::from_parts(parts, Body::from(bytes)), email))
+}
+
+fn add_rate_limit_headers(
+    headers: &mut HeaderMap,
+    decision: &RateLimitDecision,
+) {
+    headers.insert(RATE_LIMIT_LIMIT, HeaderValue::from(decision.limit));
+    headers.insert(RATE_LIMIT_REMAINING, HeaderValue::from(decision.remaining));
+    headers.insert(RATE_LIMIT_RESET, HeaderValue::from(decision.reset_seconds));
+}
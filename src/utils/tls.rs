@@ -0,0 +1,42 @@
+use super::config::{AppConfig, get_config};
+use axum_server::tls_rustls::RustlsConfig;
+use std::sync::LazyLock;
+use tonic::transport::{Identity, ServerTlsConfig};
+
+// PEM certificate chain and private key, both the HTTP and the gRPC servers use them when configured
+#[derive(Debug, Clone)]
+pub struct TlsConfig {
+    pub cert_path: String,
+    pub key_path: String,
+}
+
+impl TlsConfig {
+    // TLS is off unless both paths are set, `AppConfig::from_env` rejects only one of them
+    pub fn from_config(config: &AppConfig) -> Option<Self> {
+        if config.tls_cert_path.is_empty() || config.tls_key_path.is_empty() {
+            return None;
+        }
+
+        Some(Self {
+            cert_path: config.tls_cert_path.clone(),
+            key_path: config.tls_key_path.clone(),
+        })
+    }
+
+    pub async fn http_config(&self) -> std::io::Result<RustlsConfig> {
+        // Several dependencies enable rustls, the process wide provider has to be picked explicitly.
+        // Installing fails when it is already set, which is fine.
+        let _ = rustls::crypto::ring::default_provider().install_default();
+
+        RustlsConfig::from_pem_file(&self.cert_path, &self.key_path).await
+    }
+
+    pub async fn grpc_config(&self) -> std::io::Result<ServerTlsConfig> {
+        let cert = tokio::fs::read(&self.cert_path).await?;
+        let key = tokio::fs::read(&self.key_path).await?;
+
+        Ok(ServerTlsConfig::new().identity(Identity::from_pem(cert, key)))
+    }
+}
+
+pub static TLS_CONFIG: LazyLock<Option<TlsConfig>> = LazyLock::new(|| TlsConfig::from_config(get_config()));
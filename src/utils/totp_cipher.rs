@@ -0,0 +1,143 @@
+use super::config::{AppConfig, get_config};
+use crate::domain::TotpSecret;
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use std::sync::LazyLock;
+
+const NONCE_LENGTH: usize = 12;
+
+#[derive(Debug, thiserror::Error)]
+pub enum TotpCipherError {
+    #[error("TOTP_ENCRYPTION_KEY must be 32 bytes encoded in base64")]
+    InvalidKey,
+
+    #[error("TOTP_ENCRYPTION_KEY is not set")]
+    MissingKey,
+
+    #[error("Failed to encrypt the TOTP secret")]
+    Encryption,
+
+    #[error("Failed to decrypt the TOTP secret")]
+    Decryption,
+}
+
+// AES-256-GCM over the TOTP secrets, stored as base64 of nonce + ciphertext. The email of the
+// owner is authenticated with it, so a secret copied onto another account doesn't decrypt.
+pub struct TotpCipher {
+    cipher: Aes256Gcm,
+}
+
+impl TotpCipher {
+    pub fn new(key: &[u8]) -> Result<Self, TotpCipherError> {
+        if key.len() != 32 {
+            return Err(TotpCipherError::InvalidKey);
+        }
+
+        Ok(Self {
+            cipher: Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key)),
+        })
+    }
+
+    // TOTP enrollment is unavailable until a key is configured
+    pub fn from_config(config: &AppConfig) -> Result<Self, TotpCipherError> {
+        if config.totp_encryption_key.is_empty() {
+            return Err(TotpCipherError::MissingKey);
+        }
+
+        let key = STANDARD
+            .decode(&config.totp_encryption_key)
+            .map_err(|_| TotpCipherError::InvalidKey)?;
+        Self::new(&key)
+    }
+
+    pub fn encrypt(
+        &self,
+        secret: &TotpSecret,
+        owner: &str,
+    ) -> Result<String, TotpCipherError> {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let payload = Payload {
+            msg: secret.expose_bytes(),
+            aad: owner.as_bytes(),
+        };
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce, payload)
+            .map_err(|_| TotpCipherError::Encryption)?;
+
+        let mut sealed = nonce.to_vec();
+        sealed.extend_from_slice(&ciphertext);
+        Ok(STANDARD.encode(sealed))
+    }
+
+    pub fn decrypt(
+        &self,
+        sealed: &str,
+        owner: &str,
+    ) -> Result<TotpSecret, TotpCipherError> {
+        let sealed = STANDARD.decode(sealed).map_err(|_| TotpCipherError::Decryption)?;
+        if sealed.len() <= NONCE_LENGTH {
+            return Err(TotpCipherError::Decryption);
+        }
+
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LENGTH);
+        let payload = Payload {
+            msg: ciphertext,
+            aad: owner.as_bytes(),
+        };
+        let secret = self
+            .cipher
+            .decrypt(Nonce::from_slice(nonce), payload)
+            .map_err(|_| TotpCipherError::Decryption)?;
+
+        TotpSecret::new(secret).map_err(|_| TotpCipherError::Decryption)
+    }
+}
+
+pub static TOTP_CIPHER: LazyLock<Result<TotpCipher, TotpCipherError>> =
+    LazyLock::new(|| TotpCipher::from_config(get_config()));
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cipher() -> TotpCipher {
+        TotpCipher::new(&[7u8; 32]).unwrap()
+    }
+
+    #[test]
+    fn test_encrypt_decrypt_round_trip() {
+        let cipher = cipher();
+        let secret = TotpSecret::default();
+
+        let sealed = cipher.encrypt(&secret, "user@example.com").unwrap();
+        let decrypted = cipher.decrypt(&sealed, "user@example.com").unwrap();
+
+        assert_eq!(decrypted.expose_bytes(), secret.expose_bytes());
+        assert_ne!(
+            sealed,
+            cipher.encrypt(&secret, "user@example.com").unwrap(),
+            "every encryption uses a fresh nonce"
+        );
+    }
+
+    #[test]
+    fn test_reject_other_owner_and_tampering() {
+        let cipher = cipher();
+        let sealed = cipher.encrypt(&TotpSecret::default(), "user@example.com").unwrap();
+
+        assert!(cipher.decrypt(&sealed, "other@example.com").is_err());
+
+        let mut tampered = STANDARD.decode(&sealed).unwrap();
+        let last = tampered.len() - 1;
+        tampered[last] ^= 1;
+        assert!(cipher.decrypt(&STANDARD.encode(tampered), "user@example.com").is_err());
+    }
+
+    #[test]
+    fn test_reject_wrong_key_length() {
+        assert!(matches!(TotpCipher::new(&[7u8; 16]), Err(TotpCipherError::InvalidKey)));
+    }
+}
@@ -0,0 +1,523 @@
+use super::config::{WEBAUTHN_ORIGIN, WEBAUTHN_RP_ID};
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use ciborium::Value;
+use p256::ecdsa::signature::Verifier;
+use p256::ecdsa::{Signature, VerifyingKey};
+use rand::RngCore;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::sync::LazyLock;
+
+pub const COSE_ALG_ES256: i64 = -7;
+
+const FLAG_USER_PRESENT: u8 = 0x01;
+const FLAG_USER_VERIFIED: u8 = 0x04;
+const FLAG_ATTESTED_CREDENTIAL: u8 = 0x40;
+const AAGUID_LENGTH: usize = 16;
+const CHALLENGE_LENGTH: usize = 32;
+const USER_HANDLE_LENGTH: usize = 16;
+
+#[derive(Debug, thiserror::Error)]
+pub enum WebauthnError {
+    #[error("Malformed {0}")]
+    Malformed(&'static str),
+
+    #[error("Unsupported attestation format: {0}")]
+    UnsupportedAttestation(String),
+
+    #[error("Only ES256 credentials are supported")]
+    UnsupportedAlgorithm,
+
+    #[error("Unexpected client data type")]
+    CeremonyMismatch,
+
+    #[error("Challenge mismatch")]
+    ChallengeMismatch,
+
+    #[error("Origin mismatch")]
+    OriginMismatch,
+
+    #[error("Relying party ID mismatch")]
+    RpIdMismatch,
+
+    #[error("User presence not asserted")]
+    UserNotPresent,
+
+    #[error("User not verified")]
+    UserNotVerified,
+
+    #[error("Invalid signature")]
+    InvalidSignature,
+}
+
+impl WebauthnError {
+    // Data that can't be a browser response, as opposed to a response failing the checks
+    pub fn is_malformed(&self) -> bool {
+        matches!(
+            self,
+            Self::Malformed(_) | Self::UnsupportedAttestation(_) | Self::UnsupportedAlgorithm
+        )
+    }
+}
+
+// What a registration ceremony leaves to store: the public key is SEC1 encoded
+#[derive(Debug)]
+pub struct RegisteredCredential {
+    pub credential_id: Vec<u8>,
+    pub public_key: Vec<u8>,
+    pub sign_count: u32,
+}
+
+#[derive(Deserialize)]
+struct ClientData {
+    #[serde(rename = "type")]
+    ceremony: String,
+    challenge: String,
+    origin: String,
+}
+
+struct AuthenticatorData<'a> {
+    rp_id_hash: &'a [u8],
+    flags: u8,
+    sign_count: u32,
+    attested_credential: Option<RegisteredCredential>,
+}
+
+// Server side of the WebAuthn ceremonies, limited to ES256 credentials and to the `none` and
+// self `packed` attestations: the authenticator model isn't checked, only its signatures.
+pub struct RelyingParty {
+    pub id: String,
+    pub origin: String,
+}
+
+impl RelyingParty {
+    pub fn new(
+        id: &str,
+        origin: &str,
+    ) -> Self {
+        Self {
+            id: id.to_string(),
+            origin: origin.to_string(),
+        }
+    }
+
+    pub fn verify_registration(
+        &self,
+        challenge: &str,
+        client_data_json: &[u8],
+        attestation_object: &[u8],
+    ) -> Result<RegisteredCredential, WebauthnError> {
+        self.verify_client_data(client_data_json, "webauthn.create", challenge)?;
+
+        let attestation: Value =
+            ciborium::from_reader(attestation_object).map_err(|_| WebauthnError::Malformed("attestation object"))?;
+        let attestation = attestation
+            .as_map()
+            .ok_or(WebauthnError::Malformed("attestation object"))?;
+        let format = map_get(attestation, "fmt")
+            .and_then(Value::as_text)
+            .ok_or(WebauthnError::Malformed("attestation format"))?;
+        let statement = map_get(attestation, "attStmt")
+            .and_then(Value::as_map)
+            .ok_or(WebauthnError::Malformed("attestation statement"))?;
+        let raw_authenticator_data = map_get(attestation, "authData")
+            .and_then(Value::as_bytes)
+            .ok_or(WebauthnError::Malformed("authenticator data"))?;
+
+        let authenticator_data = parse_authenticator_data(raw_authenticator_data)?;
+        self.verify_authenticator_data(&authenticator_data, false)?;
+        let credential = authenticator_data
+            .attested_credential
+            .ok_or(WebauthnError::Malformed("attested credential"))?;
+
+        match format {
+            "none" if statement.is_empty() => {}
+            "packed" if map_get(statement, "x5c").is_none() => {
+                if map_get(statement, "alg").and_then(integer) != Some(COSE_ALG_ES256) {
+                    return Err(WebauthnError::UnsupportedAlgorithm);
+                }
+                let signature = map_get(statement, "sig")
+                    .and_then(Value::as_bytes)
+                    .ok_or(WebauthnError::Malformed("attestation signature"))?;
+                verify_signature(
+                    &credential.public_key,
+                    raw_authenticator_data,
+                    client_data_json,
+                    signature,
+                )?;
+            }
+            other => return Err(WebauthnError::UnsupportedAttestation(other.to_string())),
+        }
+
+        Ok(credential)
+    }
+
+    // Returns the signature counter reported by the authenticator
+    pub fn verify_assertion(
+        &self,
+        challenge: &str,
+        client_data_json: &[u8],
+        raw_authenticator_data: &[u8],
+        signature: &[u8],
+        public_key: &[u8],
+        user_verification_required: bool,
+    ) -> Result<u32, WebauthnError> {
+        self.verify_client_data(client_data_json, "webauthn.get", challenge)?;
+
+        let authenticator_data = parse_authenticator_data(raw_authenticator_data)?;
+        self.verify_authenticator_data(&authenticator_data, user_verification_required)?;
+        verify_signature(public_key, raw_authenticator_data, client_data_json, signature)?;
+
+        Ok(authenticator_data.sign_count)
+    }
+
+    fn verify_client_data(
+        &self,
+        client_data_json: &[u8],
+        ceremony: &str,
+        challenge: &str,
+    ) -> Result<(), WebauthnError> {
+        let client_data: ClientData =
+            serde_json::from_slice(client_data_json).map_err(|_| WebauthnError::Malformed("client data"))?;
+
+        if client_data.ceremony != ceremony {
+            return Err(WebauthnError::CeremonyMismatch);
+        }
+        if client_data.challenge != challenge {
+            return Err(WebauthnError::ChallengeMismatch);
+        }
+        if client_data.origin != self.origin {
+            return Err(WebauthnError::OriginMismatch);
+        }
+        Ok(())
+    }
+
+    fn verify_authenticator_data(
+        &self,
+        authenticator_data: &AuthenticatorData,
+        user_verification_required: bool,
+    ) -> Result<(), WebauthnError> {
+        if authenticator_data.rp_id_hash != Sha256::digest(self.id.as_bytes()).as_slice() {
+            return Err(WebauthnError::RpIdMismatch);
+        }
+        if authenticator_data.flags & FLAG_USER_PRESENT == 0 {
+            return Err(WebauthnError::UserNotPresent);
+        }
+        if user_verification_required && authenticator_data.flags & FLAG_USER_VERIFIED == 0 {
+            return Err(WebauthnError::UserNotVerified);
+        }
+        Ok(())
+    }
+}
+
+pub static RELYING_PARTY: LazyLock<RelyingParty> =
+    LazyLock::new(|| RelyingParty::new(&WEBAUTHN_RP_ID, &WEBAUTHN_ORIGIN));
+
+// Random challenge, base64url encoded the way it comes back in the client data
+pub fn new_challenge() -> String {
+    random_base64url(CHALLENGE_LENGTH)
+}
+
+// Opaque id the authenticators keep for the account, unlike the email it reveals nothing
+pub fn new_user_handle() -> String {
+    random_base64url(USER_HANDLE_LENGTH)
+}
+
+fn random_base64url(length: usize) -> String {
+    let mut bytes = vec![0u8; length];
+    rand::rng().fill_bytes(&mut bytes);
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+// Layout: rpIdHash (32) | flags (1) | signCount (4) | [aaguid (16) | idLength (2) | id | COSE key]
+fn parse_authenticator_data(data: &[u8]) -> Result<AuthenticatorData<'_>, WebauthnError> {
+    if data.len() < 37 {
+        return Err(WebauthnError::Malformed("authenticator data"));
+    }
+
+    let (rp_id_hash, rest) = data.split_at(32);
+    let flags = rest[0];
+    let sign_count = u32::from_be_bytes([rest[1], rest[2], rest[3], rest[4]]);
+
+    let attested_credential = if flags & FLAG_ATTESTED_CREDENTIAL != 0 {
+        let rest = &rest[5..];
+        if rest.len() < AAGUID_LENGTH + 2 {
+            return Err(WebauthnError::Malformed("attested credential"));
+        }
+        let id_length = u16::from_be_bytes([rest[AAGUID_LENGTH], rest[AAGUID_LENGTH + 1]]) as usize;
+        let rest = &rest[AAGUID_LENGTH + 2..];
+        if rest.len() < id_length {
+            return Err(WebauthnError::Malformed("credential id"));
+        }
+        let (credential_id, cose_key) = rest.split_at(id_length);
+        let cose_key: Value = ciborium::from_reader(cose_key).map_err(|_| WebauthnError::Malformed("public key"))?;
+
+        Some(RegisteredCredential {
+            credential_id: credential_id.to_vec(),
+            public_key: parse_cose_key(&cose_key)?,
+            sign_count,
+        })
+    } else {
+        None
+    };
+
+    Ok(AuthenticatorData {
+        rp_id_hash,
+        flags,
+        sign_count,
+        attested_credential,
+    })
+}
+
+// COSE_Key of an EC2 P-256 key: kty (1) = 2, alg (3) = -7, crv (-1) = 1, x (-2), y (-3)
+fn parse_cose_key(key: &Value) -> Result<Vec<u8>, WebauthnError> {
+    let key = key.as_map().ok_or(WebauthnError::Malformed("public key"))?;
+    let field = |label: i64| key.iter().find(|(k, _)| integer(k) == Some(label)).map(|(_, v)| v);
+
+    if field(1).and_then(integer) != Some(2)
+        || field(3).and_then(integer) != Some(COSE_ALG_ES256)
+        || field(-1).and_then(integer) != Some(1)
+    {
+        return Err(WebauthnError::UnsupportedAlgorithm);
+    }
+
+    let x = field(-2).and_then(Value::as_bytes);
+    let y = field(-3).and_then(Value::as_bytes);
+    let (Some(x), Some(y)) = (x, y) else {
+        return Err(WebauthnError::Malformed("public key"));
+    };
+
+    let mut public_key = vec![0x04];
+    public_key.extend_from_slice(x);
+    public_key.extend_from_slice(y);
+    VerifyingKey::from_sec1_bytes(&public_key).map_err(|_| WebauthnError::Malformed("public key"))?;
+    Ok(public_key)
+}
+
+// Both ceremonies sign authenticatorData | SHA-256(clientDataJSON)
+fn verify_signature(
+    public_key: &[u8],
+    authenticator_data: &[u8],
+    client_data_json: &[u8],
+    signature: &[u8],
+) -> Result<(), WebauthnError> {
+    let key = VerifyingKey::from_sec1_bytes(public_key).map_err(|_| WebauthnError::Malformed("public key"))?;
+    let signature = Signature::from_der(signature).map_err(|_| WebauthnError::Malformed("signature"))?;
+
+    let mut signed = authenticator_data.to_vec();
+    signed.extend_from_slice(&Sha256::digest(client_data_json));
+    key.verify(&signed, &signature)
+        .map_err(|_| WebauthnError::InvalidSignature)
+}
+
+fn map_get<'a>(
+    map: &'a [(Value, Value)],
+    key: &str,
+) -> Option<&'a Value> {
+    map.iter().find(|(k, _)| k.as_text() == Some(key)).map(|(_, v)| v)
+}
+
+fn integer(value: &Value) -> Option<i64> {
+    value.as_integer().and_then(|i| i64::try_from(i).ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use p256::ecdsa::SigningKey;
+    use p256::ecdsa::signature::Signer;
+
+    const RP_ID: &str = "localhost";
+    const ORIGIN: &str = "http://localhost:3000";
+
+    fn relying_party() -> RelyingParty {
+        RelyingParty::new(RP_ID, ORIGIN)
+    }
+
+    fn client_data(
+        ceremony: &str,
+        challenge: &str,
+        origin: &str,
+    ) -> Vec<u8> {
+        serde_json::to_vec(&serde_json::json!({
+            "type": ceremony,
+            "challenge": challenge,
+            "origin": origin,
+        }))
+        .unwrap()
+    }
+
+    fn authenticator_data(
+        rp_id: &str,
+        flags: u8,
+        sign_count: u32,
+    ) -> Vec<u8> {
+        let mut data = Sha256::digest(rp_id.as_bytes()).to_vec();
+        data.push(flags);
+        data.extend_from_slice(&sign_count.to_be_bytes());
+        data
+    }
+
+    fn attestation_object(
+        key: &SigningKey,
+        credential_id: &[u8],
+    ) -> Vec<u8> {
+        let point = key.verifying_key().to_encoded_point(false);
+        let cose_key = Value::Map(vec![
+            (Value::from(1), Value::from(2)),
+            (Value::from(3), Value::from(COSE_ALG_ES256)),
+            (Value::from(-1), Value::from(1)),
+            (Value::from(-2), Value::Bytes(point.x().unwrap().to_vec())),
+            (Value::from(-3), Value::Bytes(point.y().unwrap().to_vec())),
+        ]);
+
+        let mut data = authenticator_data(RP_ID, FLAG_USER_PRESENT | FLAG_ATTESTED_CREDENTIAL, 0);
+        data.extend_from_slice(&[0u8; AAGUID_LENGTH]);
+        data.extend_from_slice(&(credential_id.len() as u16).to_be_bytes());
+        data.extend_from_slice(credential_id);
+        ciborium::into_writer(&cose_key, &mut data).unwrap();
+
+        let mut object = Vec::new();
+        let attestation = Value::Map(vec![
+            (Value::from("fmt"), Value::from("none")),
+            (Value::from("attStmt"), Value::Map(Vec::new())),
+            (Value::from("authData"), Value::Bytes(data)),
+        ]);
+        ciborium::into_writer(&attestation, &mut object).unwrap();
+        object
+    }
+
+    fn sign(
+        key: &SigningKey,
+        authenticator_data: &[u8],
+        client_data_json: &[u8],
+    ) -> Vec<u8> {
+        let mut signed = authenticator_data.to_vec();
+        signed.extend_from_slice(&Sha256::digest(client_data_json));
+        let signature: Signature = key.sign(&signed);
+        signature.to_der().as_bytes().to_vec()
+    }
+
+    fn registered_key(key: &SigningKey) -> Vec<u8> {
+        let challenge = new_challenge();
+        relying_party()
+            .verify_registration(
+                &challenge,
+                &client_data("webauthn.create", &challenge, ORIGIN),
+                &attestation_object(key, b"credential"),
+            )
+            .unwrap()
+            .public_key
+    }
+
+    #[test]
+    fn test_verify_registration() {
+        let key = SigningKey::random(&mut p256::elliptic_curve::rand_core::OsRng);
+        let challenge = new_challenge();
+
+        let credential = relying_party()
+            .verify_registration(
+                &challenge,
+                &client_data("webauthn.create", &challenge, ORIGIN),
+                &attestation_object(&key, b"credential"),
+            )
+            .unwrap();
+
+        assert_eq!(credential.credential_id, b"credential");
+        assert_eq!(
+            credential.public_key,
+            key.verifying_key().to_encoded_point(false).as_bytes()
+        );
+    }
+
+    #[test]
+    fn test_reject_registration_for_another_challenge_or_origin() {
+        let key = SigningKey::random(&mut p256::elliptic_curve::rand_core::OsRng);
+        let challenge = new_challenge();
+        let attestation = attestation_object(&key, b"credential");
+
+        let result = relying_party().verify_registration(
+            &challenge,
+            &client_data("webauthn.create", &new_challenge(), ORIGIN),
+            &attestation,
+        );
+        assert!(matches!(result, Err(WebauthnError::ChallengeMismatch)));
+
+        let result = relying_party().verify_registration(
+            &challenge,
+            &client_data("webauthn.create", &challenge, "https://evil.example"),
+            &attestation,
+        );
+        assert!(matches!(result, Err(WebauthnError::OriginMismatch)));
+
+        let result = RelyingParty::new("example.com", ORIGIN).verify_registration(
+            &challenge,
+            &client_data("webauthn.create", &challenge, ORIGIN),
+            &attestation,
+        );
+        assert!(matches!(result, Err(WebauthnError::RpIdMismatch)));
+    }
+
+    #[test]
+    fn test_verify_assertion() {
+        let key = SigningKey::random(&mut p256::elliptic_curve::rand_core::OsRng);
+        let public_key = registered_key(&key);
+        let challenge = new_challenge();
+        let client_data_json = client_data("webauthn.get", &challenge, ORIGIN);
+        let data = authenticator_data(RP_ID, FLAG_USER_PRESENT | FLAG_USER_VERIFIED, 7);
+        let signature = sign(&key, &data, &client_data_json);
+
+        let sign_count = relying_party()
+            .verify_assertion(&challenge, &client_data_json, &data, &signature, &public_key, true)
+            .unwrap();
+
+        assert_eq!(sign_count, 7);
+    }
+
+    #[test]
+    fn test_reject_tampered_or_unverified_assertion() {
+        let key = SigningKey::random(&mut p256::elliptic_curve::rand_core::OsRng);
+        let public_key = registered_key(&key);
+        let challenge = new_challenge();
+        let client_data_json = client_data("webauthn.get", &challenge, ORIGIN);
+        let data = authenticator_data(RP_ID, FLAG_USER_PRESENT, 1);
+        let signature = sign(&key, &data, &client_data_json);
+
+        let result =
+            relying_party().verify_assertion(&challenge, &client_data_json, &data, &signature, &public_key, true);
+        assert!(matches!(result, Err(WebauthnError::UserNotVerified)));
+
+        let mut tampered = data.clone();
+        tampered[36] = 2;
+        let result =
+            relying_party().verify_assertion(&challenge, &client_data_json, &tampered, &signature, &public_key, false);
+        assert!(matches!(result, Err(WebauthnError::InvalidSignature)));
+
+        let other_key = SigningKey::random(&mut p256::elliptic_curve::rand_core::OsRng);
+        let signature = sign(&other_key, &data, &client_data_json);
+        let result =
+            relying_party().verify_assertion(&challenge, &client_data_json, &data, &signature, &public_key, false);
+        assert!(matches!(result, Err(WebauthnError::InvalidSignature)));
+    }
+
+    #[test]
+    fn test_reject_unsupported_attestation() {
+        let key = SigningKey::random(&mut p256::elliptic_curve::rand_core::OsRng);
+        let challenge = new_challenge();
+        let attestation: Value = ciborium::from_reader(attestation_object(&key, b"credential").as_slice()).unwrap();
+        let mut fields = attestation.into_map().unwrap();
+        fields[0].1 = Value::from("fido-u2f");
+        let mut object = Vec::new();
+        ciborium::into_writer(&Value::Map(fields), &mut object).unwrap();
+
+        let result = relying_party().verify_registration(
+            &challenge,
+            &client_data("webauthn.create", &challenge, ORIGIN),
+            &object,
+        );
+
+        assert!(matches!(result, Err(WebauthnError::UnsupportedAttestation(_))));
+        assert!(result.unwrap_err().is_malformed());
+    }
+}
@@ -0,0 +1,259 @@
+use crate::helpers::{LoggedInUser, TestApp};
+use auth_service::domain::TotpSecret;
+use auth_service::routes::{EnrollTotpResponse, VerifyTokenResponse};
+use auth_service::utils::{LOCKOUT_ACCOUNT_THRESHOLD, email};
+use fake::Fake;
+use fake::faker::internet::en::{Password as FakePassword, SafeEmail};
+use reqwest::StatusCode;
+
+// The token is the `token` query parameter of the link sent to the new address
+async fn change_token(
+    app: &TestApp,
+    new_email: &str,
+) -> String {
+    let sent = app
+        .wait_for_email(new_email, email::EMAIL_CHANGE_SUBJECT)
+        .await
+        .expect("No email change link sent");
+    sent.content
+        .split("token=")
+        .nth(1)
+        .and_then(|rest| rest.split_whitespace().next())
+        .expect("No token in the email change link")
+        .to_string()
+}
+
+#[tokio::test]
+async fn should_change_the_email_once_the_new_address_confirms() {
+    let mut app = TestApp::new().await;
+    let LoggedInUser {
+        email: old_email,
+        password,
+        access_token,
+        ..
+    } = app.signup_and_login(false).await;
+    let new_email: String = SafeEmail().fake();
+    let user_id = app.user_id(&old_email).await;
+
+    let response = app
+        .post_change_email(&serde_json::json!({ "newEmail": new_email, "password": password }))
+        .await;
+    assert_eq!(response.status().as_u16(), StatusCode::ACCEPTED);
+    assert!(
+        app.wait_for_email(&old_email, email::EMAIL_CHANGE_NOTICE_SUBJECT)
+            .await
+            .is_some()
+    );
+
+    // Nothing changes before the new address confirms
+    let response = app
+        .post_login(&serde_json::json!({ "email": new_email, "password": password }))
+        .await;
+    assert_eq!(response.status().as_u16(), StatusCode::UNAUTHORIZED);
+
+    let token = change_token(&app, &new_email).await;
+    let response = app.get_change_email_confirm(&token).await;
+    assert_eq!(response.status().as_u16(), StatusCode::OK);
+    assert_eq!(app.user_id(&new_email).await, user_id);
+
+    let response = app
+        .post_login(&serde_json::json!({ "email": old_email, "password": password }))
+        .await;
+    assert_eq!(response.status().as_u16(), StatusCode::UNAUTHORIZED);
+    let response = app
+        .post_login(&serde_json::json!({ "email": new_email, "password": password }))
+        .await;
+    assert_eq!(response.status().as_u16(), StatusCode::OK);
+
+    // The tokens issued before the change still name the same user
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": access_token }))
+        .await;
+    assert_eq!(response.status().as_u16(), StatusCode::OK);
+    let body = response
+        .json::<VerifyTokenResponse>()
+        .await
+        .expect("Could not deserialize response body to VerifyTokenResponse");
+    assert_eq!(body.subject, user_id.to_string());
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_reject_a_wrong_password_or_a_taken_address() {
+    let mut app = TestApp::new().await;
+    let other_email: String = SafeEmail().fake();
+    let body = serde_json::json!({
+        "email": other_email,
+        "password": FakePassword(8..20).fake::<String>(),
+        "requires2FA": false,
+    });
+    assert_eq!(app.post_signup(&body).await.status().as_u16(), StatusCode::CREATED);
+    let LoggedInUser { password, .. } = app.signup_and_login(false).await;
+
+    let response = app
+        .post_change_email(&serde_json::json!({
+            "newEmail": SafeEmail().fake::<String>(),
+            "password": FakePassword(8..20).fake::<String>(),
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), StatusCode::UNAUTHORIZED);
+
+    let response = app
+        .post_change_email(&serde_json::json!({ "newEmail": other_email, "password": password }))
+        .await;
+    assert_eq!(response.status().as_u16(), StatusCode::CONFLICT);
+
+    // The address can be taken between the request and the confirmation
+    let new_email: String = SafeEmail().fake();
+    let response = app
+        .post_change_email(&serde_json::json!({ "newEmail": new_email, "password": password }))
+        .await;
+    assert_eq!(response.status().as_u16(), StatusCode::ACCEPTED);
+    let token = change_token(&app, &new_email).await;
+    let body = serde_json::json!({
+        "email": new_email,
+        "password": FakePassword(8..20).fake::<String>(),
+        "requires2FA": false,
+    });
+    assert_eq!(app.post_signup(&body).await.status().as_u16(), StatusCode::CREATED);
+
+    let response = app.get_change_email_confirm(&token).await;
+    assert_eq!(response.status().as_u16(), StatusCode::CONFLICT);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_lock_the_account_after_too_many_wrong_passwords() {
+    let mut app = TestApp::new().await;
+    let LoggedInUser { email, password, .. } = app.signup_and_login(false).await;
+    let wrong_body = serde_json::json!({
+        "newEmail": SafeEmail().fake::<String>(),
+        "password": format!("{}-wrong", password),
+    });
+
+    for _ in 1..*LOCKOUT_ACCOUNT_THRESHOLD {
+        let response = app.post_change_email(&wrong_body).await;
+        assert_eq!(response.status().as_u16(), StatusCode::UNAUTHORIZED);
+    }
+    let response = app.post_change_email(&wrong_body).await;
+    assert_eq!(response.status().as_u16(), StatusCode::LOCKED);
+
+    // The right password waits for the lock to end as well
+    let response = app
+        .post_change_email(&serde_json::json!({ "newEmail": SafeEmail().fake::<String>(), "password": password }))
+        .await;
+    assert_eq!(response.status().as_u16(), StatusCode::LOCKED);
+    let response = app
+        .post_login(&serde_json::json!({ "email": email, "password": password }))
+        .await;
+    assert_eq!(response.status().as_u16(), StatusCode::LOCKED);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_reject_invalid_links_and_links_sent_before_logout_all() {
+    let mut app = TestApp::new().await;
+    let LoggedInUser {
+        email: old_email,
+        password,
+        ..
+    } = app.signup_and_login(false).await;
+    let new_email: String = SafeEmail().fake();
+
+    let response = app.get_change_email_confirm("invalid").await;
+    assert_eq!(response.status().as_u16(), StatusCode::UNAUTHORIZED);
+
+    let response = app
+        .post_change_email(&serde_json::json!({ "newEmail": new_email, "password": password }))
+        .await;
+    assert_eq!(response.status().as_u16(), StatusCode::ACCEPTED);
+    let token = change_token(&app, &new_email).await;
+
+    assert_eq!(app.post_logout_all().await.status().as_u16(), StatusCode::OK);
+    let response = app.get_change_email_confirm(&token).await;
+    assert_eq!(response.status().as_u16(), StatusCode::UNAUTHORIZED);
+
+    let response = app
+        .post_login(&serde_json::json!({ "email": old_email, "password": password }))
+        .await;
+    assert_eq!(response.status().as_u16(), StatusCode::OK);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_accept_a_link_only_once() {
+    let mut app = TestApp::new().await;
+    let LoggedInUser { password, .. } = app.signup_and_login(false).await;
+    let first_email: String = SafeEmail().fake();
+    let second_email: String = SafeEmail().fake();
+
+    let response = app
+        .post_change_email(&serde_json::json!({ "newEmail": first_email, "password": password }))
+        .await;
+    assert_eq!(response.status().as_u16(), StatusCode::ACCEPTED);
+    let first_token = change_token(&app, &first_email).await;
+    let response = app.get_change_email_confirm(&first_token).await;
+    assert_eq!(response.status().as_u16(), StatusCode::OK);
+
+    let response = app
+        .post_change_email(&serde_json::json!({ "newEmail": second_email, "password": password }))
+        .await;
+    assert_eq!(response.status().as_u16(), StatusCode::ACCEPTED);
+    let second_token = change_token(&app, &second_email).await;
+    let response = app.get_change_email_confirm(&second_token).await;
+    assert_eq!(response.status().as_u16(), StatusCode::OK);
+
+    // Replaying the first link doesn't move the account back
+    let response = app.get_change_email_confirm(&first_token).await;
+    assert_eq!(response.status().as_u16(), StatusCode::UNAUTHORIZED);
+    let response = app
+        .post_login(&serde_json::json!({ "email": second_email, "password": password }))
+        .await;
+    assert_eq!(response.status().as_u16(), StatusCode::OK);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_keep_totp_working_after_the_email_change() {
+    let mut app = TestApp::new().await;
+    let LoggedInUser { password, .. } = app.signup_and_login(false).await;
+    let new_email: String = SafeEmail().fake();
+    let now = chrono::Utc::now().timestamp() as u64;
+
+    let response = app.post_enroll_totp().await;
+    assert_eq!(response.status().as_u16(), StatusCode::OK);
+    let enrollment = response
+        .json::<EnrollTotpResponse>()
+        .await
+        .expect("Could not deserialize the response body to EnrollTotpResponse");
+    let secret = TotpSecret::from_base32(&enrollment.secret).expect("Invalid TOTP secret");
+    let response = app
+        .post_confirm_totp(&serde_json::json!({ "code": secret.code_at(now) }))
+        .await;
+    assert_eq!(response.status().as_u16(), StatusCode::OK);
+
+    let response = app
+        .post_change_email(&serde_json::json!({ "newEmail": new_email, "password": password }))
+        .await;
+    assert_eq!(response.status().as_u16(), StatusCode::ACCEPTED);
+    let token = change_token(&app, &new_email).await;
+    let response = app.get_change_email_confirm(&token).await;
+    assert_eq!(response.status().as_u16(), StatusCode::OK);
+
+    // The step of the confirmation code is used, the next one is still in the drift window
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": new_email,
+            "password": password,
+            "totpCode": secret.code_at(now + 30),
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), StatusCode::OK);
+
+    app.clean_up().await;
+}
@@ -0,0 +1,108 @@
+use crate::helpers::{LoggedInUser, TestApp};
+use auth_service::ErrorResponse;
+use auth_service::utils::{LOCKOUT_ACCOUNT_THRESHOLD, email};
+use fake::Fake;
+use fake::faker::internet::en::Password as FakePassword;
+use reqwest::StatusCode;
+
+#[tokio::test]
+async fn should_change_the_password_and_notify_the_user() {
+    let mut app = TestApp::new().await;
+    let LoggedInUser { email, password, .. } = app.signup_and_login(false).await;
+    let new_password: String = FakePassword(8..20).fake();
+
+    let response = app
+        .post_change_password(&serde_json::json!({ "currentPassword": password, "newPassword": new_password }))
+        .await;
+    assert_eq!(response.status().as_u16(), StatusCode::OK);
+    assert!(
+        app.wait_for_email(&email, email::PASSWORD_CHANGED_SUBJECT)
+            .await
+            .is_some()
+    );
+
+    let response = app
+        .post_login(&serde_json::json!({ "email": email, "password": password }))
+        .await;
+    assert_eq!(response.status().as_u16(), StatusCode::UNAUTHORIZED);
+    let response = app
+        .post_login(&serde_json::json!({ "email": email, "password": new_password }))
+        .await;
+    assert_eq!(response.status().as_u16(), StatusCode::OK);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_reject_a_wrong_current_password_or_a_reused_one() {
+    let mut app = TestApp::new().await;
+    let LoggedInUser { password, .. } = app.signup_and_login(false).await;
+
+    let response = app
+        .post_change_password(&serde_json::json!({
+            "currentPassword": FakePassword(8..20).fake::<String>(),
+            "newPassword": FakePassword(8..20).fake::<String>(),
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), StatusCode::UNAUTHORIZED);
+
+    let response = app
+        .post_change_password(&serde_json::json!({ "currentPassword": password, "newPassword": password }))
+        .await;
+    assert_eq!(response.status().as_u16(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error_message,
+        "New password must differ from the current one".to_owned()
+    );
+
+    let response = app
+        .post_change_password(&serde_json::json!({ "currentPassword": password, "newPassword": "short" }))
+        .await;
+    assert_eq!(response.status().as_u16(), StatusCode::BAD_REQUEST);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_lock_the_account_after_too_many_wrong_current_passwords() {
+    let mut app = TestApp::new().await;
+    let LoggedInUser { email, password, .. } = app.signup_and_login(false).await;
+    let wrong_body = serde_json::json!({
+        "currentPassword": format!("{}-wrong", password),
+        "newPassword": FakePassword(8..20).fake::<String>(),
+    });
+
+    for _ in 1..*LOCKOUT_ACCOUNT_THRESHOLD {
+        let response = app.post_change_password(&wrong_body).await;
+        assert_eq!(response.status().as_u16(), StatusCode::UNAUTHORIZED);
+    }
+    let response = app.post_change_password(&wrong_body).await;
+    assert_eq!(response.status().as_u16(), StatusCode::LOCKED);
+
+    // The lock is the one of the login
+    let response = app
+        .post_login(&serde_json::json!({ "email": email, "password": password }))
+        .await;
+    assert_eq!(response.status().as_u16(), StatusCode::LOCKED);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_jwt_cookie_missing() {
+    let mut app = TestApp::new().await;
+
+    let response = app
+        .post_change_password(&serde_json::json!({
+            "currentPassword": FakePassword(8..20).fake::<String>(),
+            "newPassword": FakePassword(8..20).fake::<String>(),
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), StatusCode::BAD_REQUEST);
+
+    app.clean_up().await;
+}
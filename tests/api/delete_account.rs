@@ -1,29 +1,163 @@
-use crate::helpers::TestApp;
+use crate::helpers::{LoggedInUser, TestApp};
+use auth_service::domain::Email;
+use auth_service::utils::JWT_REFRESH_COOKIE_NAME;
 use fake::Fake;
 use fake::faker::internet::en::{Password as FakePassword, SafeEmail};
-use reqwest::StatusCode;
+use reqwest::{StatusCode, Url};
+use secrecy::SecretBox;
+
+#[tokio::test]
+async fn should_return_400_if_jwt_cookie_missing() {
+    let mut app = TestApp::new().await;
+
+    let response = app
+        .delete_account(&serde_json::json!({
+            "email": SafeEmail().fake::<String>(),
+            "password": FakePassword(8..20).fake::<String>(),
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), StatusCode::BAD_REQUEST);
+
+    app.clean_up().await;
+}
 
 #[tokio::test]
 async fn should_return_204_if_deleted_successfully() {
     let mut app = TestApp::new().await;
-    let fake_email: String = SafeEmail().fake();
-    let fake_password: String = FakePassword(8..20).fake();
+    let LoggedInUser { email, password, .. } = app.signup_and_login(false).await;
+
+    let response = app
+        .delete_account(&serde_json::json!({
+            "email": email.clone(),
+            "password": password.clone(),
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), StatusCode::NO_CONTENT);
+    assert_eq!(response.content_length(), Some(0));
+
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": email,
+            "password": password,
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), StatusCode::UNAUTHORIZED);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_revoke_every_token_of_the_deleted_account() {
+    let mut app = TestApp::new().await;
+    let LoggedInUser { email, password, .. } = app.signup_and_login(false).await;
+
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": email.clone(),
+            "password": password.clone(),
+        }))
+        .await;
+    let other_refresh_token = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_REFRESH_COOKIE_NAME)
+        .expect("No refresh cookie found")
+        .value()
+        .to_string();
+
+    let response = app
+        .delete_account(&serde_json::json!({
+            "email": email,
+            "password": password,
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), StatusCode::NO_CONTENT);
+
+    app.cookie_jar.add_cookie_str(
+        &format!(
+            "{}={}; HttpOnly; SameSite=Lax; Path=/",
+            JWT_REFRESH_COOKIE_NAME, other_refresh_token
+        ),
+        &Url::parse("http://127.0.0.1").expect("Failed to parse URL"),
+    );
+    let response = app.post_refresh_token().await;
+    assert_eq!(response.status().as_u16(), StatusCode::UNAUTHORIZED);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_password_is_wrong() {
+    let mut app = TestApp::new().await;
+    let LoggedInUser { email, .. } = app.signup_and_login(false).await;
+
+    let response = app
+        .delete_account(&serde_json::json!({
+            "email": email,
+            "password": "wrong-password",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), StatusCode::UNAUTHORIZED);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_403_if_deleting_another_account() {
+    let mut app = TestApp::new().await;
+    let LoggedInUser { password, .. } = app.signup_and_login(false).await;
+
+    let response = app
+        .delete_account(&serde_json::json!({
+            "email": SafeEmail().fake::<String>(),
+            "password": password,
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), StatusCode::FORBIDDEN);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_require_a_2fa_code_if_2fa_is_enabled() {
+    let mut app = TestApp::new().await;
+    let LoggedInUser { email, password, .. } = app.signup_and_login(true).await;
+
+    let response = app
+        .delete_account(&serde_json::json!({
+            "email": email.clone(),
+            "password": password.clone(),
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), StatusCode::PARTIAL_CONTENT);
 
-    let tc = serde_json::json!({
-        "email": fake_email.clone(),
-        "password": fake_password,
-        "requires2FA": true
-    });
+    let (login_attempt_id, two_fa_code) = app.get_two_fa_code(&email).await;
+    let wrong_code = format!(
+        "{:06}",
+        (two_fa_code.parse::<u32>().expect("2FA code is not numeric") + 1) % 1_000_000
+    );
 
-    let response1 = app.post_signup(&tc).await;
-    assert_eq!(response1.status().as_u16(), StatusCode::CREATED);
+    let response = app
+        .delete_account(&serde_json::json!({
+            "email": email.clone(),
+            "password": password.clone(),
+            "loginAttemptId": login_attempt_id.clone(),
+            "2FACode": wrong_code,
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), StatusCode::UNAUTHORIZED);
 
-    let rc = serde_json::json!({
-        "email": fake_email,
-    });
+    let response = app
+        .delete_account(&serde_json::json!({
+            "email": email.clone(),
+            "password": password,
+            "loginAttemptId": login_attempt_id,
+            "2FACode": two_fa_code,
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), StatusCode::NO_CONTENT);
 
-    let delete_response = app.delete_account(&rc).await;
-    assert_eq!(delete_response.status().as_u16(), StatusCode::NO_CONTENT);
+    let email = Email::new(SecretBox::new(Box::from(email))).unwrap();
+    assert!(app.two_fa_code.read().await.get_code(&email).await.is_err());
 
     app.clean_up().await;
 }
@@ -0,0 +1,212 @@
+use crate::helpers::{TestApp, with_bearer};
+use auth_service::grpc::auth_service::auth_service::{
+    GetUserRequest, IssueServiceTokenRequest, LoginRequest, RevokeUserSessionsRequest, SignupRequest,
+    VerifyTokenRequest, login_response,
+};
+use fake::Fake;
+use fake::faker::internet::en::{Password as FakePassword, SafeEmail};
+use tonic::{Code, Request};
+
+const SERVICE_ID: &str = "billing";
+const SERVICE_SECRET: &str = "billing-service-secret";
+
+#[tokio::test]
+async fn test_service_credentials_are_checked() {
+    let mut app = TestApp::new().await;
+    let (server_handle, mut client) = app.spawn_grpc_server(50058).await;
+    app.register_service(SERVICE_ID, SERVICE_SECRET).await;
+
+    let status = client
+        .issue_service_token(Request::new(IssueServiceTokenRequest {
+            service_id: SERVICE_ID.to_string(),
+            service_secret: "wrong-secret".to_string(),
+        }))
+        .await
+        .expect_err("Wrong secret accepted");
+    assert_eq!(status.code(), Code::Unauthenticated);
+
+    let status = client
+        .issue_service_token(Request::new(IssueServiceTokenRequest {
+            service_id: "unknown".to_string(),
+            service_secret: SERVICE_SECRET.to_string(),
+        }))
+        .await
+        .expect_err("Unknown service accepted");
+    assert_eq!(status.code(), Code::Unauthenticated);
+
+    let response = client
+        .issue_service_token(Request::new(IssueServiceTokenRequest {
+            service_id: SERVICE_ID.to_string(),
+            service_secret: SERVICE_SECRET.to_string(),
+        }))
+        .await
+        .expect("Failed to issue a service token")
+        .into_inner();
+    assert!(!response.access_token.is_empty());
+    assert!(response.expires_in > 0);
+
+    server_handle.abort();
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn test_admin_calls_require_a_service_token() {
+    let mut app = TestApp::new().await;
+    let (server_handle, mut client) = app.spawn_grpc_server(50059).await;
+    let mut admin_client = app.connect_grpc_admin_client(50059).await;
+    let fake_email: String = SafeEmail().fake();
+    let fake_password: String = FakePassword(8..20).fake();
+
+    client
+        .signup(Request::new(SignupRequest {
+            email: fake_email.clone(),
+            password: fake_password.clone(),
+            requires_2fa: true,
+        }))
+        .await
+        .expect("Signup failed");
+
+    let status = admin_client
+        .get_user(Request::new(GetUserRequest {
+            email: fake_email.clone(),
+        }))
+        .await
+        .expect_err("Admin call without a token succeeded");
+    assert_eq!(status.code(), Code::Unauthenticated);
+
+    // A user access token is not a service token
+    let response = client
+        .login(Request::new(LoginRequest {
+            email: fake_email.clone(),
+            password: fake_password,
+            totp_code: String::new(),
+        }))
+        .await
+        .expect("Login failed")
+        .into_inner();
+    assert!(matches!(
+        response.result,
+        Some(login_response::Result::TwoFactorChallenge(_))
+    ));
+    let status = admin_client
+        .get_user(with_bearer(
+            GetUserRequest {
+                email: fake_email.clone(),
+            },
+            "not-a-token",
+        ))
+        .await
+        .expect_err("Admin call with an invalid token succeeded");
+    assert_eq!(status.code(), Code::Unauthenticated);
+
+    app.register_service(SERVICE_ID, SERVICE_SECRET).await;
+    let service_token = client
+        .issue_service_token(Request::new(IssueServiceTokenRequest {
+            service_id: SERVICE_ID.to_string(),
+            service_secret: SERVICE_SECRET.to_string(),
+        }))
+        .await
+        .expect("Failed to issue a service token")
+        .into_inner()
+        .access_token;
+
+    let user = admin_client
+        .get_user(with_bearer(
+            GetUserRequest {
+                email: fake_email.clone(),
+            },
+            &service_token,
+        ))
+        .await
+        .expect("Admin GetUser failed")
+        .into_inner();
+    assert_eq!(user.email, fake_email);
+    assert!(user.requires_2fa);
+    assert_eq!(user.user_id, app.user_id(&fake_email).await.to_string());
+
+    let status = admin_client
+        .get_user(with_bearer(
+            GetUserRequest {
+                email: SafeEmail().fake(),
+            },
+            &service_token,
+        ))
+        .await
+        .expect_err("Unknown user found");
+    assert_eq!(status.code(), Code::NotFound);
+
+    // The service token is rejected by the user facing token check
+    let response = client
+        .verify_token(Request::new(VerifyTokenRequest { token: service_token }))
+        .await
+        .expect("Request failed")
+        .into_inner();
+    assert!(!response.valid);
+
+    server_handle.abort();
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn test_revoke_user_sessions() {
+    let mut app = TestApp::new().await;
+    let (server_handle, mut client) = app.spawn_grpc_server(50060).await;
+    let mut admin_client = app.connect_grpc_admin_client(50060).await;
+    app.register_service(SERVICE_ID, SERVICE_SECRET).await;
+    let fake_email: String = SafeEmail().fake();
+    let fake_password: String = FakePassword(8..20).fake();
+
+    client
+        .signup(Request::new(SignupRequest {
+            email: fake_email.clone(),
+            password: fake_password.clone(),
+            requires_2fa: false,
+        }))
+        .await
+        .expect("Signup failed");
+    let response = client
+        .login(Request::new(LoginRequest {
+            email: fake_email.clone(),
+            password: fake_password,
+            totp_code: String::new(),
+        }))
+        .await
+        .expect("Login failed")
+        .into_inner();
+    let tokens = match response.result {
+        Some(login_response::Result::Tokens(tokens)) => tokens,
+        other => panic!("Expected tokens, got {:?}", other),
+    };
+
+    let service_token = client
+        .issue_service_token(Request::new(IssueServiceTokenRequest {
+            service_id: SERVICE_ID.to_string(),
+            service_secret: SERVICE_SECRET.to_string(),
+        }))
+        .await
+        .expect("Failed to issue a service token")
+        .into_inner()
+        .access_token;
+
+    let response = admin_client
+        .revoke_user_sessions(with_bearer(
+            RevokeUserSessionsRequest { email: fake_email },
+            &service_token,
+        ))
+        .await
+        .expect("Admin RevokeUserSessions failed")
+        .into_inner();
+    assert_eq!(response.session_epoch, 1);
+
+    let response = client
+        .verify_token(Request::new(VerifyTokenRequest {
+            token: tokens.access_token,
+        }))
+        .await
+        .expect("Request failed")
+        .into_inner();
+    assert!(!response.valid);
+
+    server_handle.abort();
+    app.clean_up().await;
+}
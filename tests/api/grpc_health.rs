@@ -0,0 +1,47 @@
+use crate::helpers::TestApp;
+use auth_service::grpc::health::{EMAIL_HEALTH_SERVICE, POSTGRES_HEALTH_SERVICE, REDIS_HEALTH_SERVICE};
+use tonic::transport::Channel;
+use tonic::{Code, Request};
+use tonic_health::pb::HealthCheckRequest;
+use tonic_health::pb::health_check_response::ServingStatus;
+use tonic_health::pb::health_client::HealthClient;
+
+#[tokio::test]
+async fn test_health_reports_every_dependency() {
+    let mut app = TestApp::new().await;
+    let (server_handle, _) = app.spawn_grpc_server(50061).await;
+    let channel = Channel::from_static("http://127.0.0.1:50061")
+        .connect()
+        .await
+        .expect("Failed to connect to the gRPC server");
+    let mut client = HealthClient::new(channel);
+
+    for service in [
+        "",
+        "auth_service.AuthService",
+        "auth_service.AuthAdminService",
+        POSTGRES_HEALTH_SERVICE,
+        REDIS_HEALTH_SERVICE,
+        EMAIL_HEALTH_SERVICE,
+    ] {
+        let response = client
+            .check(Request::new(HealthCheckRequest {
+                service: service.to_string(),
+            }))
+            .await
+            .expect("Health check failed")
+            .into_inner();
+        assert_eq!(response.status(), ServingStatus::Serving, "{}", service);
+    }
+
+    let status = client
+        .check(Request::new(HealthCheckRequest {
+            service: "unknown".to_string(),
+        }))
+        .await
+        .expect_err("Unknown service reported");
+    assert_eq!(status.code(), Code::NotFound);
+
+    server_handle.abort();
+    app.clean_up().await;
+}
@@ -0,0 +1,220 @@
+use crate::helpers::TestApp;
+use auth_service::grpc::auth_service::auth_service::{
+    DeleteAccountRequest, LoginRequest, LogoutRequest, RefreshTokenRequest, SignupRequest, TokenPair, Verify2FaRequest,
+    VerifyTokenRequest, delete_account_response, login_response,
+};
+use fake::Fake;
+use fake::faker::internet::en::{Password as FakePassword, SafeEmail};
+use tonic::{Code, Request};
+
+#[tokio::test]
+async fn test_signup_login_refresh_and_logout() {
+    let mut app = TestApp::new().await;
+    let (server_handle, mut client) = app.spawn_grpc_server(50055).await;
+    let fake_email: String = SafeEmail().fake();
+    let fake_password: String = FakePassword(8..20).fake();
+
+    client
+        .signup(Request::new(SignupRequest {
+            email: fake_email.clone(),
+            password: fake_password.clone(),
+            requires_2fa: false,
+        }))
+        .await
+        .expect("Signup failed");
+
+    let status = client
+        .signup(Request::new(SignupRequest {
+            email: fake_email.clone(),
+            password: fake_password.clone(),
+            requires_2fa: false,
+        }))
+        .await
+        .expect_err("Duplicated signup succeeded");
+    assert_eq!(status.code(), Code::AlreadyExists);
+
+    let response = client
+        .login(Request::new(LoginRequest {
+            email: fake_email.clone(),
+            password: fake_password.clone(),
+            totp_code: String::new(),
+        }))
+        .await
+        .expect("Login failed")
+        .into_inner();
+    let tokens = match response.result {
+        Some(login_response::Result::Tokens(tokens)) => tokens,
+        other => panic!("Expected tokens, got {:?}", other),
+    };
+
+    let refreshed = client
+        .refresh_token(Request::new(RefreshTokenRequest {
+            refresh_token: tokens.refresh_token.clone(),
+        }))
+        .await
+        .expect("Refresh failed")
+        .into_inner()
+        .tokens
+        .expect("No tokens in the response");
+    assert_ne!(refreshed.refresh_token, tokens.refresh_token);
+
+    client
+        .logout(Request::new(LogoutRequest {
+            access_token: refreshed.access_token.clone(),
+            refresh_token: refreshed.refresh_token.clone(),
+        }))
+        .await
+        .expect("Logout failed");
+
+    let response = client
+        .verify_token(Request::new(VerifyTokenRequest {
+            token: refreshed.access_token,
+        }))
+        .await
+        .expect("Request failed")
+        .into_inner();
+    assert!(!response.valid);
+
+    let status = client
+        .refresh_token(Request::new(RefreshTokenRequest {
+            refresh_token: refreshed.refresh_token,
+        }))
+        .await
+        .expect_err("Refresh after logout succeeded");
+    assert_eq!(status.code(), Code::Unauthenticated);
+
+    server_handle.abort();
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn test_login_with_2fa_and_wrong_password() {
+    let mut app = TestApp::new().await;
+    let (server_handle, mut client) = app.spawn_grpc_server(50056).await;
+    let fake_email: String = SafeEmail().fake();
+    let fake_password: String = FakePassword(8..20).fake();
+
+    client
+        .signup(Request::new(SignupRequest {
+            email: fake_email.clone(),
+            password: fake_password.clone(),
+            requires_2fa: true,
+        }))
+        .await
+        .expect("Signup failed");
+
+    let status = client
+        .login(Request::new(LoginRequest {
+            email: fake_email.clone(),
+            password: "wrong-password".to_string(),
+            totp_code: String::new(),
+        }))
+        .await
+        .expect_err("Login with a wrong password succeeded");
+    assert_eq!(status.code(), Code::Unauthenticated);
+
+    let response = client
+        .login(Request::new(LoginRequest {
+            email: fake_email.clone(),
+            password: fake_password,
+            totp_code: String::new(),
+        }))
+        .await
+        .expect("Login failed")
+        .into_inner();
+    let challenge = match response.result {
+        Some(login_response::Result::TwoFactorChallenge(challenge)) => challenge,
+        other => panic!("Expected a 2FA challenge, got {:?}", other),
+    };
+
+    let (_, two_fa_code) = app.get_two_fa_code(&fake_email).await;
+    let tokens: TokenPair = client
+        .verify2_fa(Request::new(Verify2FaRequest {
+            email: fake_email,
+            login_attempt_id: challenge.login_attempt_id,
+            two_fa_code,
+            recovery_code: String::new(),
+        }))
+        .await
+        .expect("Verify2FA failed")
+        .into_inner()
+        .tokens
+        .expect("No tokens in the response");
+
+    let response = client
+        .verify_token(Request::new(VerifyTokenRequest {
+            token: tokens.access_token,
+        }))
+        .await
+        .expect("Request failed")
+        .into_inner();
+    assert!(response.valid);
+
+    server_handle.abort();
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn test_delete_account() {
+    let mut app = TestApp::new().await;
+    let (server_handle, mut client) = app.spawn_grpc_server(50057).await;
+    let fake_email: String = SafeEmail().fake();
+    let fake_password: String = FakePassword(8..20).fake();
+
+    client
+        .signup(Request::new(SignupRequest {
+            email: fake_email.clone(),
+            password: fake_password.clone(),
+            requires_2fa: false,
+        }))
+        .await
+        .expect("Signup failed");
+
+    let response = client
+        .login(Request::new(LoginRequest {
+            email: fake_email.clone(),
+            password: fake_password.clone(),
+            totp_code: String::new(),
+        }))
+        .await
+        .expect("Login failed")
+        .into_inner();
+    let tokens = match response.result {
+        Some(login_response::Result::Tokens(tokens)) => tokens,
+        other => panic!("Expected tokens, got {:?}", other),
+    };
+
+    let status = client
+        .delete_account(Request::new(DeleteAccountRequest {
+            access_token: tokens.access_token.clone(),
+            password: "wrong-password".to_string(),
+            ..Default::default()
+        }))
+        .await
+        .expect_err("Delete with a wrong password succeeded");
+    assert_eq!(status.code(), Code::Unauthenticated);
+
+    let response = client
+        .delete_account(Request::new(DeleteAccountRequest {
+            access_token: tokens.access_token,
+            password: fake_password.clone(),
+            ..Default::default()
+        }))
+        .await
+        .expect("Delete failed")
+        .into_inner();
+    assert_eq!(response.result, Some(delete_account_response::Result::Deleted(true)));
+
+    let status = client
+        .login(Request::new(LoginRequest {
+            email: fake_email,
+            password: fake_password,
+            totp_code: String::new(),
+        }))
+        .await
+        .expect_err("Login to a deleted account succeeded");
+    assert_eq!(status.code(), Code::Unauthenticated);
+
+    server_handle.abort();
+    app.clean_up().await;
+}
@@ -0,0 +1,124 @@
+use crate::helpers::{TestApp, with_bearer};
+use auth_service::grpc::auth_service::auth_service::{
+    IssueServiceTokenRequest, LoginRequest, LogoutRequest, RevocationEvent, RevokeUserSessionsRequest, SignupRequest,
+    TokenRevoked, UserSessionsRevoked, WatchRevocationsRequest, login_response, revocation_event,
+};
+use fake::Fake;
+use fake::faker::internet::en::{Password as FakePassword, SafeEmail};
+use std::time::Duration;
+use tonic::{Code, Request, Streaming};
+
+const SERVICE_ID: &str = "token-cache";
+const SERVICE_SECRET: &str = "token-cache-secret";
+
+// The revocations channel is shared by every test running at the same time, the events of the others are skipped
+async fn wait_for_event(
+    stream: &mut Streaming<RevocationEvent>,
+    expected: revocation_event::Revocation,
+) {
+    tokio::time::timeout(Duration::from_secs(5), async {
+        loop {
+            let revocation = stream
+                .message()
+                .await
+                .expect("Stream failed")
+                .expect("Stream ended")
+                .revocation
+                .expect("Empty revocation");
+            if revocation == expected {
+                return;
+            }
+        }
+    })
+    .await
+    .unwrap_or_else(|_| panic!("No revocation {:?} received", expected));
+}
+
+#[tokio::test]
+async fn test_watch_revocations() {
+    let mut app = TestApp::new().await;
+    let (server_handle, mut client) = app.spawn_grpc_server(50062).await;
+    let mut admin_client = app.connect_grpc_admin_client(50062).await;
+    app.register_service(SERVICE_ID, SERVICE_SECRET).await;
+    let fake_email: String = SafeEmail().fake();
+    let fake_password: String = FakePassword(8..20).fake();
+
+    let status = admin_client
+        .watch_revocations(Request::new(WatchRevocationsRequest {}))
+        .await
+        .expect_err("Watching without a service token succeeded");
+    assert_eq!(status.code(), Code::Unauthenticated);
+
+    let service_token = client
+        .issue_service_token(Request::new(IssueServiceTokenRequest {
+            service_id: SERVICE_ID.to_string(),
+            service_secret: SERVICE_SECRET.to_string(),
+        }))
+        .await
+        .expect("Failed to issue a service token")
+        .into_inner()
+        .access_token;
+    let mut revocations = admin_client
+        .watch_revocations(with_bearer(WatchRevocationsRequest {}, &service_token))
+        .await
+        .expect("Failed to watch the revocations")
+        .into_inner();
+
+    client
+        .signup(Request::new(SignupRequest {
+            email: fake_email.clone(),
+            password: fake_password.clone(),
+            requires_2fa: false,
+        }))
+        .await
+        .expect("Signup failed");
+    let response = client
+        .login(Request::new(LoginRequest {
+            email: fake_email.clone(),
+            password: fake_password,
+            totp_code: String::new(),
+        }))
+        .await
+        .expect("Login failed")
+        .into_inner();
+    let tokens = match response.result {
+        Some(login_response::Result::Tokens(tokens)) => tokens,
+        other => panic!("Expected tokens, got {:?}", other),
+    };
+
+    client
+        .logout(Request::new(LogoutRequest {
+            access_token: tokens.access_token.clone(),
+            refresh_token: tokens.refresh_token,
+        }))
+        .await
+        .expect("Logout failed");
+    wait_for_event(
+        &mut revocations,
+        revocation_event::Revocation::Token(TokenRevoked {
+            token: tokens.access_token,
+        }),
+    )
+    .await;
+
+    admin_client
+        .revoke_user_sessions(with_bearer(
+            RevokeUserSessionsRequest {
+                email: fake_email.clone(),
+            },
+            &service_token,
+        ))
+        .await
+        .expect("Admin RevokeUserSessions failed");
+    wait_for_event(
+        &mut revocations,
+        revocation_event::Revocation::UserSessions(UserSessionsRevoked {
+            subject: app.user_id(&fake_email).await.to_string(),
+            session_epoch: 1,
+        }),
+    )
+    .await;
+
+    server_handle.abort();
+    app.clean_up().await;
+}
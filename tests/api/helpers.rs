@@ -1,8 +1,25 @@
-use auth_service::app_state::{AppState, BannedTokenStoreType, TwoFACodeStoreType};
-use auth_service::services::data_stores::{PostgresUserStore, RedisBannedTokenStore, RedisTwoFACodeStore};
-use auth_service::services::email::MockEmailClient;
-use auth_service::utils::{DATABASE_URL, REDIS_HOST_NAME, test};
+use auth_service::app_state::{AppState, BannedTokenStoreType, RefreshTokenStoreType, TwoFACodeStoreType};
+use auth_service::domain::{Email, UserId};
+use auth_service::grpc::admin_service::create_admin_grpc_service;
+use auth_service::grpc::auth_service::auth_service::{
+    auth_admin_service_client::AuthAdminServiceClient, auth_service_client::AuthServiceClient,
+};
+use auth_service::grpc::auth_service::create_grpc_service;
+use auth_service::grpc::health::DependencyHealth;
+use auth_service::routes::SignupResponse;
+use auth_service::services::data_stores::{
+    HashmapLoginLockoutStore, HashmapRateLimitStore, PostgresPasskeyStore, PostgresRecoveryCodeStore,
+    PostgresServiceCredentialStore, PostgresSessionEpochStore, PostgresTotpStore, PostgresUserStore,
+    RedisBannedTokenStore, RedisPasskeyChallengeStore, RedisRefreshTokenStore, RedisSessionEpochCache,
+    RedisTwoFACodeStore,
+};
+use auth_service::services::email::{MockEmailClient, SentEmail};
+use auth_service::utils::{DATABASE_URL, JWT_COOKIE_NAME, JWT_REFRESH_COOKIE_NAME, REDIS_HOST_NAME, test};
 use auth_service::{Application, get_postgres_pool, get_redis_client};
+use fake::Fake;
+use fake::faker::internet::en::{Password as FakePassword, SafeEmail};
+use redis::aio::MultiplexedConnection;
+use reqwest::StatusCode;
 use reqwest::cookie::Jar;
 use secrecy::{ExposeSecret, SecretBox};
 use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
@@ -10,14 +27,22 @@ use sqlx::{Connection, Executor, PgConnection, PgPool};
 use std::str::FromStr;
 use std::sync::Arc;
 use tokio::sync::RwLock;
+use tokio::task::JoinHandle;
+use tonic::Request;
+use tonic::transport::{Channel, Server};
 use uuid::Uuid;
 
 pub struct TestApp {
     pub address: String,
+    pub app_state: AppState,
     pub http_client: reqwest::Client,
     pub cookie_jar: Arc<Jar>,
     pub banned_tokens: BannedTokenStoreType,
+    pub refresh_tokens: RefreshTokenStoreType,
     pub two_fa_code: TwoFACodeStoreType,
+    pub email_client: Arc<RwLock<MockEmailClient>>,
+    pub pg_pool: PgPool,
+    pub redis_conn: MultiplexedConnection,
     pub clean_up_called: bool,
     pub db_name: String,
 }
@@ -26,19 +51,46 @@ impl TestApp {
     pub async fn new() -> Self {
         let db_name = Uuid::new_v4().to_string();
         let pg_pool = configure_postgresql(db_name.as_str()).await;
-        let redis_conn = configure_redis().await;
+        let (redis_client, redis_conn) = configure_redis().await;
 
-        let user_store = Arc::new(RwLock::new(PostgresUserStore::new(pg_pool)));
-        let banned_tokens: BannedTokenStoreType = Arc::new(RwLock::new(RedisBannedTokenStore::new(redis_conn.clone())));
-        let two_fa_code = Arc::new(RwLock::new(RedisTwoFACodeStore::new(redis_conn)));
+        let user_store = Arc::new(RwLock::new(PostgresUserStore::new(pg_pool.clone())));
+        let banned_tokens: BannedTokenStoreType = Arc::new(RwLock::new(RedisBannedTokenStore::new(
+            redis_conn.clone(),
+            redis_client,
+        )));
+        let refresh_tokens: RefreshTokenStoreType =
+            Arc::new(RwLock::new(RedisRefreshTokenStore::new(redis_conn.clone())));
+        let session_epochs = Arc::new(RwLock::new(RedisSessionEpochCache::new(
+            redis_conn.clone(),
+            PostgresSessionEpochStore::new(pg_pool.clone()),
+        )));
+        let two_fa_code = Arc::new(RwLock::new(RedisTwoFACodeStore::new(redis_conn.clone())));
         let email_service = Arc::new(RwLock::new(MockEmailClient::new()));
+        let service_credentials = Arc::new(RwLock::new(PostgresServiceCredentialStore::new(pg_pool.clone())));
+        let totp_secrets = Arc::new(RwLock::new(PostgresTotpStore::new(pg_pool.clone())));
+        let recovery_codes = Arc::new(RwLock::new(PostgresRecoveryCodeStore::new(pg_pool.clone())));
+        let passkeys = Arc::new(RwLock::new(PostgresPasskeyStore::new(pg_pool.clone())));
+        let passkey_challenges = Arc::new(RwLock::new(RedisPasskeyChallengeStore::new(redis_conn.clone())));
+        // Every test app logs in from 127.0.0.1, a shared Redis counter would lock them out of each other
+        let login_lockouts = Arc::new(RwLock::new(HashmapLoginLockoutStore::default()));
+        // Same for the rate limit buckets keyed by address
+        let rate_limits = Arc::new(RwLock::new(HashmapRateLimitStore::default()));
         let app_state = AppState::new(
             user_store,
             banned_tokens.clone(),
+            refresh_tokens.clone(),
+            session_epochs,
             two_fa_code.clone(),
             email_service.clone(),
+            service_credentials,
+            totp_secrets,
+            recovery_codes,
+            passkeys,
+            passkey_challenges,
+            login_lockouts,
+            rate_limits,
         );
-        let app = Application::build(app_state, test::APP_ADDRESS)
+        let app = Application::build(app_state.clone(), test::APP_ADDRESS)
             .await
             .expect("Failed to build app");
 
@@ -54,10 +106,15 @@ impl TestApp {
 
         TestApp {
             address,
+            app_state,
             http_client,
             cookie_jar,
             banned_tokens,
+            refresh_tokens,
             two_fa_code,
+            email_client: email_service,
+            pg_pool,
+            redis_conn,
             clean_up_called,
             db_name,
         }
@@ -86,6 +143,14 @@ impl TestApp {
             .expect("Failed to execute the request.")
     }
 
+    pub async fn get_jwks(&self) -> reqwest::Response {
+        self.http_client
+            .get(&format!("{}/.well-known/jwks.json", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute the request.")
+    }
+
     pub async fn post_login<Body>(
         &self,
         body: &Body,
@@ -109,6 +174,37 @@ impl TestApp {
             .expect("Failed to execute the request.")
     }
 
+    pub async fn post_logout_all(&self) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/logout-all", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute the request.")
+    }
+
+    pub async fn post_refresh_token(&self) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/refresh-token", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute the request.")
+    }
+
+    pub async fn post_verify_token<Body>(
+        &self,
+        body: &Body,
+    ) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/verify-token", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute the request.")
+    }
+
     pub async fn post_verify_2fa<Body>(
         &self,
         body: &Body,
@@ -124,6 +220,349 @@ impl TestApp {
             .expect("Failed to execute the request.")
     }
 
+    pub async fn post_enroll_totp(&self) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/enroll-totp", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute the request.")
+    }
+
+    pub async fn post_confirm_totp<Body>(
+        &self,
+        body: &Body,
+    ) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/confirm-totp", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute the request.")
+    }
+
+    pub async fn get_recovery_codes(&self) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/recovery-codes", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute the request.")
+    }
+
+    pub async fn post_recovery_codes<Body>(
+        &self,
+        body: &Body,
+    ) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/recovery-codes", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute the request.")
+    }
+
+    pub async fn post_passkey_register_start<Body>(
+        &self,
+        body: &Body,
+    ) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/passkeys/register/start", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute the request.")
+    }
+
+    pub async fn post_passkey_register_finish<Body>(
+        &self,
+        body: &Body,
+    ) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/passkeys/register/finish", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute the request.")
+    }
+
+    pub async fn post_passkey_login_start(&self) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/passkeys/login/start", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute the request.")
+    }
+
+    pub async fn post_passkey_login_finish<Body>(
+        &self,
+        body: &Body,
+    ) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/passkeys/login/finish", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute the request.")
+    }
+
+    pub async fn post_passkey_two_fa_start<Body>(
+        &self,
+        body: &Body,
+    ) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/passkeys/2fa/start", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute the request.")
+    }
+
+    pub async fn get_verify_email(
+        &self,
+        token: &str,
+    ) -> reqwest::Response {
+        self.http_client
+            .get(&format!("{}/verify-email", &self.address))
+            .query(&[("token", token)])
+            .send()
+            .await
+            .expect("Failed to execute the request.")
+    }
+
+    pub async fn post_verify_email_resend<Body>(
+        &self,
+        body: &Body,
+    ) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(&format!("{}/verify-email/resend", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute the request.")
+    }
+
+    pub async fn post_change_password<Body>(
+        &self,
+        body: &Body,
+    ) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(&format!("{}/change-password", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute the request.")
+    }
+
+    pub async fn post_change_email<Body>(
+        &self,
+        body: &Body,
+    ) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(&format!("{}/change-email", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute the request.")
+    }
+
+    pub async fn get_change_email_confirm(
+        &self,
+        token: &str,
+    ) -> reqwest::Response {
+        self.http_client
+            .get(&format!("{}/change-email/confirm", &self.address))
+            .query(&[("token", token)])
+            .send()
+            .await
+            .expect("Failed to execute the request.")
+    }
+
+    pub async fn post_password_reset_request<Body>(
+        &self,
+        body: &Body,
+    ) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(&format!("{}/password-reset/request", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute the request.")
+    }
+
+    pub async fn post_password_reset_confirm<Body>(
+        &self,
+        body: &Body,
+    ) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(&format!("{}/password-reset/confirm", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute the request.")
+    }
+
+    // The stable id of a signed up account, the `sub` of its tokens
+    pub async fn user_id(
+        &self,
+        email: &str,
+    ) -> UserId {
+        let email = Email::new(SecretBox::new(Box::from(email.to_owned()))).expect("Invalid email");
+        *self
+            .app_state
+            .user_store
+            .read()
+            .await
+            .get_user(&email)
+            .await
+            .expect("User not found")
+            .id()
+    }
+
+    // Signs up an account with a fake email and password, returned with the recovery codes of the
+    // signup response (there are none without `requires_2fa`)
+    pub async fn signup_with_recovery_codes(
+        &self,
+        requires_2fa: bool,
+    ) -> (String, String, Vec<String>) {
+        let email: String = SafeEmail().fake();
+        let password: String = FakePassword(8..20).fake();
+        let response = self
+            .post_signup(&serde_json::json!({
+                "email": email,
+                "password": password,
+                "requires2FA": requires_2fa
+            }))
+            .await;
+        assert_eq!(response.status().as_u16(), StatusCode::CREATED);
+
+        let recovery_codes = response
+            .json::<SignupResponse>()
+            .await
+            .expect("Could not deserialize the response body to SignupResponse")
+            .recovery_codes;
+        (email, password, recovery_codes)
+    }
+
+    // Signs up an account with a fake email and password and returns them
+    pub async fn signup(
+        &self,
+        requires_2fa: bool,
+    ) -> (String, String) {
+        let (email, password, _) = self.signup_with_recovery_codes(requires_2fa).await;
+        (email, password)
+    }
+
+    // Signs up an account with a fake email and password and logs it in, answering the 2FA challenge
+    // with the emailed code when `requires_2fa`. The client keeps the session cookies.
+    pub async fn signup_and_login(
+        &self,
+        requires_2fa: bool,
+    ) -> LoggedInUser {
+        let (email, password) = self.signup(requires_2fa).await;
+
+        let mut response = self
+            .post_login(&serde_json::json!({ "email": email, "password": password }))
+            .await;
+        if requires_2fa {
+            assert_eq!(response.status().as_u16(), StatusCode::PARTIAL_CONTENT);
+            let (login_attempt_id, two_fa_code) = self.get_two_fa_code(&email).await;
+            response = self
+                .post_verify_2fa(&serde_json::json!({
+                    "email": email,
+                    "loginAttemptId": login_attempt_id,
+                    "2FACode": two_fa_code,
+                }))
+                .await;
+        }
+        assert_eq!(response.status().as_u16(), StatusCode::OK);
+
+        let cookie_value = |name: &str| {
+            response
+                .cookies()
+                .find(|cookie| cookie.name() == name)
+                .expect("No auth cookie found")
+                .value()
+                .to_string()
+        };
+        LoggedInUser {
+            access_token: cookie_value(JWT_COOKIE_NAME),
+            refresh_token: cookie_value(JWT_REFRESH_COOKIE_NAME),
+            email,
+            password,
+        }
+    }
+
+    // The login attempt id and the code of the pending 2FA login of `email`
+    pub async fn get_two_fa_code(
+        &self,
+        email: &str,
+    ) -> (String, String) {
+        let email = Email::new(SecretBox::new(Box::from(email.to_owned()))).expect("Invalid email");
+        let (login_attempt_id, two_fa_code) = self
+            .two_fa_code
+            .read()
+            .await
+            .get_code(&email)
+            .await
+            .expect("No pending 2FA login");
+        (
+            login_attempt_id.id().expose_secret().clone(),
+            two_fa_code.code().expose_secret().clone(),
+        )
+    }
+
+    // Some emails are sent after the response, wait a little for them
+    pub async fn wait_for_email(
+        &self,
+        recipient: &str,
+        subject: &str,
+    ) -> Option<SentEmail> {
+        for _ in 0..50 {
+            let sent = self.email_client.read().await.sent_emails();
+            if let Some(email) = sent
+                .into_iter()
+                .rev()
+                .find(|email| email.recipient == recipient && email.subject == subject)
+            {
+                return Some(email);
+            }
+            tokio::time::sleep(tokio::time::Duration::from_millis(20)).await;
+        }
+        None
+    }
+
     pub async fn delete_account<Body>(
         &self,
         body: &Body,
@@ -139,12 +578,91 @@ impl TestApp {
             .expect("Failed to execute the request.")
     }
 
+    pub async fn spawn_grpc_server(
+        &self,
+        port: u16,
+    ) -> (JoinHandle<()>, AuthServiceClient<Channel>) {
+        let grpc_service = create_grpc_service(self.app_state.clone());
+        let admin_grpc_service = create_admin_grpc_service(self.app_state.clone());
+        let (health_reporter, health_service) = tonic_health::server::health_reporter();
+        DependencyHealth::new(
+            self.pg_pool.clone(),
+            self.redis_conn.clone(),
+            self.app_state.email_client.clone(),
+        )
+        .report(&health_reporter)
+        .await;
+        let addr = format!("127.0.0.1:{}", port).parse().unwrap();
+
+        let server_handle = tokio::spawn(async move {
+            Server::builder()
+                .add_service(health_service)
+                .add_service(grpc_service)
+                .add_service(admin_grpc_service)
+                .serve(addr)
+                .await
+                .expect("Failed to start the gRPC server")
+        });
+
+        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+
+        let client = AuthServiceClient::connect(format!("http://127.0.0.1:{}", port))
+            .await
+            .expect("Failed to connect to the gRPC server");
+
+        (server_handle, client)
+    }
+
+    pub async fn register_service(
+        &self,
+        service_id: &str,
+        secret: &str,
+    ) {
+        self.app_state
+            .service_credential_store
+            .write()
+            .await
+            .add_service(service_id, SecretBox::new(Box::from(secret.to_string())))
+            .await
+            .expect("Failed to register the service");
+    }
+
+    // The admin service is served next to the public one by `spawn_grpc_server`
+    pub async fn connect_grpc_admin_client(
+        &self,
+        port: u16,
+    ) -> AuthAdminServiceClient<Channel> {
+        AuthAdminServiceClient::connect(format!("http://127.0.0.1:{}", port))
+            .await
+            .expect("Failed to connect to the gRPC server")
+    }
+
     pub async fn clean_up(&mut self) {
         delete_database(&self.db_name).await;
         self.clean_up_called = true;
     }
 }
 
+// An account created by `TestApp::signup_and_login`, with the tokens of its session
+pub struct LoggedInUser {
+    pub email: String,
+    pub password: String,
+    pub access_token: String,
+    pub refresh_token: String,
+}
+
+// Admin calls carry the service token in the request metadata
+pub fn with_bearer<T>(
+    message: T,
+    token: &str,
+) -> Request<T> {
+    let mut request = Request::new(message);
+    request
+        .metadata_mut()
+        .insert("authorization", format!("Bearer {}", token).parse().unwrap());
+    request
+}
+
 impl Drop for TestApp {
     fn drop(&mut self) {
         if self.clean_up_called {
@@ -238,11 +756,13 @@ async fn delete_database(db_name: &str) {
         .expect("Failed to drop the database.");
 }
 
-async fn configure_redis() -> redis::aio::MultiplexedConnection {
+async fn configure_redis() -> (redis::Client, MultiplexedConnection) {
     let client = get_redis_client(REDIS_HOST_NAME.to_owned()).expect("Failed to get a Redis client");
 
-    client
+    let conn = client
         .get_multiplexed_async_connection()
         .await
-        .expect("Failed to create Redis connection manager")
+        .expect("Failed to create Redis connection manager");
+
+    (client, conn)
 }
@@ -0,0 +1,22 @@
+use crate::helpers::TestApp;
+use auth_service::utils::jwt_key_ring;
+use jsonwebtoken::jwk::JwkSet;
+use reqwest::StatusCode;
+
+#[tokio::test]
+async fn should_return_200_with_the_published_key_set() {
+    let mut app = TestApp::new().await;
+
+    let response = app.get_jwks().await;
+    assert_eq!(response.status().as_u16(), StatusCode::OK);
+    assert!(response.headers().get("cache-control").is_some());
+
+    let jwks = response.json::<JwkSet>().await.expect("Failed to parse the JWK set");
+    let key_ring = jwt_key_ring();
+    assert_eq!(jwks.keys, key_ring.jwks().keys);
+    if key_ring.current().jwk().is_some() {
+        assert!(jwks.find(key_ring.current().kid()).is_some());
+    }
+
+    app.clean_up().await;
+}
@@ -1,9 +1,22 @@
 use crate::helpers::TestApp;
+use argon2::password_hash::SaltString;
+use argon2::password_hash::rand_core::OsRng;
+use argon2::{Argon2, PasswordHasher};
+use auth_service::ErrorResponse;
+use auth_service::domain::data_stores::LoginLockoutStore;
+use auth_service::domain::{Email, LockoutSubject};
 use auth_service::routes::TwoFactorAuthResponse;
-use auth_service::utils::JWT_COOKIE_NAME;
+use auth_service::services::data_stores::RedisLoginLockoutStore;
+use auth_service::utils::redis_env::LOGIN_FAILURES_KEY_PREFIX;
+use auth_service::utils::{
+    JWT_COOKIE_NAME, LOCKOUT_ACCOUNT_THRESHOLD, LOCKOUT_BASE_SECONDS, LOCKOUT_FAILURE_WINDOW_SECONDS,
+    LOCKOUT_IP_THRESHOLD,
+};
 use fake::Fake;
 use fake::faker::internet::en::{Password as FakePassword, SafeEmail};
 use reqwest::StatusCode;
+use reqwest::header::RETRY_AFTER;
+use secrecy::SecretBox;
 
 #[tokio::test]
 async fn should_return_422_if_malformed_credentials() {
@@ -114,6 +127,79 @@ async fn should_return_200_if_valid_credentials_and_2fa_disabled() {
     app.clean_up().await;
 }
 
+#[tokio::test]
+async fn should_accept_a_password_hashed_before_normalization() {
+    let mut app = TestApp::new().await;
+
+    // Fullwidth digits, stored as typed before passwords were NFKC normalized
+    let fake_email: String = SafeEmail().fake();
+    let legacy_password = "Legacy-pass-１２３".to_string();
+    let response = app
+        .post_signup(&serde_json::json!({
+            "email": fake_email,
+            "password": "Placeholder-7#xq",
+            "requires2FA": false
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), StatusCode::CREATED);
+
+    let salt = SaltString::generate(&mut OsRng);
+    let legacy_hash = Argon2::default()
+        .hash_password(legacy_password.as_bytes(), &salt)
+        .expect("Could not hash the password")
+        .to_string();
+    sqlx::query("UPDATE users SET password_hash = $1 WHERE email = $2")
+        .bind(legacy_hash)
+        .bind(&fake_email)
+        .execute(&app.pg_pool)
+        .await
+        .expect("Could not store the legacy hash");
+
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": fake_email,
+            "password": legacy_password,
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), StatusCode::OK);
+
+    // The normalized form of a legacy password isn't what was hashed
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": fake_email,
+            "password": "Legacy-pass-123",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), StatusCode::UNAUTHORIZED);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_accept_the_password_typed_in_another_form() {
+    let mut app = TestApp::new().await;
+
+    let fake_email: String = SafeEmail().fake();
+    let response = app
+        .post_signup(&serde_json::json!({
+            "email": fake_email,
+            "password": "Normal-pass-１２３",
+            "requires2FA": false
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), StatusCode::CREATED);
+
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": fake_email,
+            "password": "Normal-pass-123",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), StatusCode::OK);
+
+    app.clean_up().await;
+}
+
 #[tokio::test]
 async fn should_return_206_if_valid_credentials_and_2fa_enabled() {
     let mut app = TestApp::new().await;
@@ -145,3 +231,165 @@ async fn should_return_206_if_valid_credentials_and_2fa_enabled() {
 
     app.clean_up().await;
 }
+
+#[tokio::test]
+async fn should_lock_the_account_after_too_many_wrong_passwords() {
+    let mut app = TestApp::new().await;
+    let fake_email: String = SafeEmail().fake();
+    let fake_password: String = FakePassword(8..20).fake();
+    let credentials = serde_json::json!({ "email": fake_email, "password": fake_password });
+    let wrong_credentials = serde_json::json!({ "email": fake_email, "password": format!("{}-wrong", fake_password) });
+
+    let response = app
+        .post_signup(&serde_json::json!({
+            "email": fake_email,
+            "password": fake_password,
+            "requires2FA": false
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), StatusCode::CREATED);
+
+    for _ in 1..*LOCKOUT_ACCOUNT_THRESHOLD {
+        let response = app.post_login(&wrong_credentials).await;
+        assert_eq!(response.status().as_u16(), StatusCode::UNAUTHORIZED);
+    }
+
+    let response = app.post_login(&wrong_credentials).await;
+    assert_eq!(response.status().as_u16(), StatusCode::LOCKED);
+    assert_eq!(
+        response.headers()[RETRY_AFTER].to_str().unwrap(),
+        LOCKOUT_BASE_SECONDS.to_string()
+    );
+
+    // The right password doesn't get through the lock either
+    let response = app.post_login(&credentials).await;
+    assert_eq!(response.status().as_u16(), StatusCode::LOCKED);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error_message,
+        "Account temporarily locked".to_owned()
+    );
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_start_the_redis_failure_window_with_the_first_failure() {
+    let mut app = TestApp::new().await;
+    let mut store = RedisLoginLockoutStore::new(app.redis_conn.clone());
+    let email = Email::new(SecretBox::new(Box::from(SafeEmail().fake::<String>()))).expect("Invalid email");
+    let subject = LockoutSubject::Account(email);
+    let key = format!("{}{}", LOGIN_FAILURES_KEY_PREFIX, subject.key());
+
+    assert_eq!(store.record_failure(&subject).await.unwrap(), 1);
+    let ttl = redis::cmd("TTL")
+        .arg(&key)
+        .query_async::<_, i64>(&mut app.redis_conn.clone())
+        .await
+        .unwrap();
+    assert!(ttl > 0 && ttl <= *LOCKOUT_FAILURE_WINDOW_SECONDS as i64);
+
+    // Later failures don't push the end of the window
+    redis::cmd("EXPIRE")
+        .arg(&key)
+        .arg(5)
+        .query_async::<_, ()>(&mut app.redis_conn.clone())
+        .await
+        .unwrap();
+    assert_eq!(store.record_failure(&subject).await.unwrap(), 2);
+    let ttl = redis::cmd("TTL")
+        .arg(&key)
+        .query_async::<_, i64>(&mut app.redis_conn.clone())
+        .await
+        .unwrap();
+    assert!(ttl > 0 && ttl <= 5);
+
+    store.reset(&subject).await.unwrap();
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_forget_the_failures_after_a_successful_login() {
+    let mut app = TestApp::new().await;
+    let fake_email: String = SafeEmail().fake();
+    let fake_password: String = FakePassword(8..20).fake();
+    let credentials = serde_json::json!({ "email": fake_email, "password": fake_password });
+    let wrong_credentials = serde_json::json!({ "email": fake_email, "password": format!("{}-wrong", fake_password) });
+
+    app.post_signup(&serde_json::json!({
+        "email": fake_email,
+        "password": fake_password,
+        "requires2FA": false
+    }))
+    .await;
+
+    for _ in 0..2 {
+        for _ in 1..*LOCKOUT_ACCOUNT_THRESHOLD {
+            let response = app.post_login(&wrong_credentials).await;
+            assert_eq!(response.status().as_u16(), StatusCode::UNAUTHORIZED);
+        }
+        let response = app.post_login(&credentials).await;
+        assert_eq!(response.status().as_u16(), StatusCode::OK);
+    }
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_keep_the_failures_until_the_second_factor_is_through() {
+    let mut app = TestApp::new().await;
+    let fake_email: String = SafeEmail().fake();
+    let fake_password: String = FakePassword(8..20).fake();
+    let credentials = serde_json::json!({ "email": fake_email, "password": fake_password });
+    let wrong_credentials = serde_json::json!({ "email": fake_email, "password": format!("{}-wrong", fake_password) });
+
+    app.post_signup(&serde_json::json!({
+        "email": fake_email,
+        "password": fake_password,
+        "requires2FA": true
+    }))
+    .await;
+
+    for _ in 1..*LOCKOUT_ACCOUNT_THRESHOLD {
+        let response = app.post_login(&wrong_credentials).await;
+        assert_eq!(response.status().as_u16(), StatusCode::UNAUTHORIZED);
+    }
+
+    // The right password alone doesn't reset the count
+    let response = app.post_login(&credentials).await;
+    assert_eq!(response.status().as_u16(), StatusCode::PARTIAL_CONTENT);
+
+    let response = app.post_login(&wrong_credentials).await;
+    assert_eq!(response.status().as_u16(), StatusCode::LOCKED);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_429_when_an_address_tries_too_many_accounts() {
+    let mut app = TestApp::new().await;
+
+    for _ in 1..*LOCKOUT_IP_THRESHOLD {
+        let response = app
+            .post_login(&serde_json::json!({
+                "email": SafeEmail().fake::<String>(),
+                "password": FakePassword(8..20).fake::<String>(),
+            }))
+            .await;
+        assert_eq!(response.status().as_u16(), StatusCode::UNAUTHORIZED);
+    }
+
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": SafeEmail().fake::<String>(),
+            "password": FakePassword(8..20).fake::<String>(),
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), StatusCode::TOO_MANY_REQUESTS);
+    assert!(response.headers().contains_key(RETRY_AFTER));
+
+    app.clean_up().await;
+}
@@ -1,5 +1,7 @@
-use crate::helpers::TestApp;
-use auth_service::utils::{JWT_COOKIE_NAME, TOKEN_TTL_SECONDS};
+use crate::helpers::{LoggedInUser, TestApp};
+use auth_service::utils::{
+    JWT_COOKIE_NAME, JWT_REFRESH_COOKIE_NAME, TOKEN_TTL_SECONDS, TokenType, jwt_key_ring, validate_token,
+};
 use fake::Fake;
 use fake::faker::internet::en::{Password as FakePassword, SafeEmail};
 use reqwest::{StatusCode, Url};
@@ -140,3 +142,113 @@ async fn test_redis_banned_token_ttl_integration() {
 
     app.clean_up().await;
 }
+
+#[tokio::test]
+async fn should_revoke_the_refresh_token_and_clear_both_cookies() {
+    let mut app = TestApp::new().await;
+    let fake_email: String = SafeEmail().fake();
+    let fake_password: String = FakePassword(8..20).fake();
+
+    app.post_signup(&serde_json::json!({
+        "email": fake_email.clone(),
+        "password": fake_password,
+        "requires2FA": false
+    }))
+    .await;
+
+    let login_response = app
+        .post_login(&serde_json::json!({
+            "email": fake_email,
+            "password": fake_password,
+        }))
+        .await;
+    assert_eq!(login_response.status().as_u16(), StatusCode::OK);
+
+    let refresh_cookie = login_response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_REFRESH_COOKIE_NAME)
+        .expect("No refresh cookie found");
+    let refresh_token = refresh_cookie.value().to_string();
+
+    let response = app.post_logout().await;
+    assert_eq!(response.status().as_u16(), StatusCode::OK);
+
+    let removed: Vec<_> = response.cookies().collect();
+    for name in [JWT_COOKIE_NAME, JWT_REFRESH_COOKIE_NAME] {
+        let cookie = removed
+            .iter()
+            .find(|cookie| cookie.name() == name)
+            .expect("Cookie was not removed");
+        assert!(cookie.value().is_empty());
+        assert_eq!(cookie.path(), Some("/"));
+    }
+
+    let response = app.post_refresh_token().await;
+    assert_eq!(response.status().as_u16(), StatusCode::BAD_REQUEST);
+
+    // A copy of the refresh token kept by the client is rejected as well
+    app.cookie_jar.add_cookie_str(
+        &format!(
+            "{}={}; HttpOnly; SameSite=Lax; Path=/",
+            JWT_REFRESH_COOKIE_NAME, refresh_token
+        ),
+        &Url::parse("http://127.0.0.1").expect("Failed to parse URL"),
+    );
+    let response = app.post_refresh_token().await;
+    assert_eq!(response.status().as_u16(), StatusCode::UNAUTHORIZED);
+
+    app.clean_up().await;
+}
+
+// The same access token, signed again as if it had expired an hour ago
+fn expire_access_token(token: &str) -> String {
+    let mut claims = validate_token(token, TokenType::Access).expect("Invalid access token");
+    let now = chrono::Utc::now().timestamp() as usize;
+    claims.iat = now - 7200;
+    claims.nbf = now - 7200;
+    claims.exp = now - 3600;
+
+    let key_ring = jwt_key_ring();
+    let key = key_ring.current();
+    jsonwebtoken::encode(&key.header(), &claims, key.encoding_key().expect("No signing key"))
+        .expect("Failed to sign the access token")
+}
+
+#[tokio::test]
+async fn should_revoke_the_refresh_token_once_the_access_token_expired() {
+    let mut app = TestApp::new().await;
+    let LoggedInUser {
+        access_token,
+        refresh_token,
+        ..
+    } = app.signup_and_login(false).await;
+
+    let expired_token = expire_access_token(&access_token);
+    app.cookie_jar.add_cookie_str(
+        &format!("{}={}; HttpOnly; SameSite=Lax; Path=/", JWT_COOKIE_NAME, expired_token),
+        &Url::parse("http://127.0.0.1").expect("Failed to parse URL"),
+    );
+
+    let response = app.post_logout().await;
+    assert_eq!(response.status().as_u16(), StatusCode::OK);
+    let removed: Vec<_> = response.cookies().collect();
+    for name in [JWT_COOKIE_NAME, JWT_REFRESH_COOKIE_NAME] {
+        let cookie = removed
+            .iter()
+            .find(|cookie| cookie.name() == name)
+            .expect("Cookie was not removed");
+        assert!(cookie.value().is_empty());
+    }
+
+    app.cookie_jar.add_cookie_str(
+        &format!(
+            "{}={}; HttpOnly; SameSite=Lax; Path=/",
+            JWT_REFRESH_COOKIE_NAME, refresh_token
+        ),
+        &Url::parse("http://127.0.0.1").expect("Failed to parse URL"),
+    );
+    let response = app.post_refresh_token().await;
+    assert_eq!(response.status().as_u16(), StatusCode::UNAUTHORIZED);
+
+    app.clean_up().await;
+}
@@ -0,0 +1,124 @@
+use crate::helpers::TestApp;
+use auth_service::utils::{JWT_COOKIE_NAME, JWT_REFRESH_COOKIE_NAME};
+use reqwest::{StatusCode, Url};
+
+async fn login(
+    app: &TestApp,
+    email: &str,
+    password: &str,
+) -> reqwest::Response {
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": email,
+            "password": password,
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), StatusCode::OK);
+    response
+}
+
+fn restore_cookie(
+    app: &TestApp,
+    name: &str,
+    value: &str,
+) {
+    app.cookie_jar.add_cookie_str(
+        &format!("{}={}; HttpOnly; SameSite=Lax; Path=/", name, value),
+        &Url::parse("http://127.0.0.1").expect("Failed to parse URL"),
+    );
+}
+
+#[tokio::test]
+async fn should_return_400_if_jwt_cookie_missing() {
+    let mut app = TestApp::new().await;
+    let response = app.post_logout_all().await;
+    assert_eq!(response.status().as_u16(), StatusCode::BAD_REQUEST);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_invalidate_every_session_of_the_user() {
+    let mut app = TestApp::new().await;
+    let (fake_email, fake_password) = app.signup(false).await;
+
+    // Two logins stand for two devices
+    let first_session = login(&app, &fake_email, &fake_password).await;
+    let first_access = first_session
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found")
+        .value()
+        .to_string();
+    let first_refresh = first_session
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_REFRESH_COOKIE_NAME)
+        .expect("No refresh cookie found")
+        .value()
+        .to_string();
+    login(&app, &fake_email, &fake_password).await;
+
+    let response = app.post_logout_all().await;
+    assert_eq!(response.status().as_u16(), StatusCode::OK);
+
+    restore_cookie(&app, JWT_REFRESH_COOKIE_NAME, &first_refresh);
+    let response = app.post_refresh_token().await;
+    assert_eq!(response.status().as_u16(), StatusCode::UNAUTHORIZED);
+
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": first_access }))
+        .await;
+    assert_eq!(response.status().as_u16(), StatusCode::UNAUTHORIZED);
+
+    // A new login gets a session with the new epoch
+    login(&app, &fake_email, &fake_password).await;
+    let response = app.post_refresh_token().await;
+    assert_eq!(response.status().as_u16(), StatusCode::OK);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_accept_a_bearer_token_and_reject_it_once_banned() {
+    let mut app = TestApp::new().await;
+    let (fake_email, fake_password) = app.signup(false).await;
+
+    let first_session = login(&app, &fake_email, &fake_password).await;
+    let first_access = first_session
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found")
+        .value()
+        .to_string();
+
+    let response = app.post_logout().await;
+    assert_eq!(response.status().as_u16(), StatusCode::OK);
+
+    // The logged out token is on the ban list
+    let response = app
+        .http_client
+        .post(format!("{}/logout-all", &app.address))
+        .bearer_auth(&first_access)
+        .send()
+        .await
+        .expect("Failed to execute the request.");
+    assert_eq!(response.status().as_u16(), StatusCode::UNAUTHORIZED);
+
+    let second_session = login(&app, &fake_email, &fake_password).await;
+    let second_access = second_session
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found")
+        .value()
+        .to_string();
+
+    // A client without the cookie jar only sends the header
+    let response = reqwest::Client::new()
+        .post(format!("{}/logout-all", &app.address))
+        .bearer_auth(&second_access)
+        .send()
+        .await
+        .expect("Failed to execute the request.");
+    assert_eq!(response.status().as_u16(), StatusCode::OK);
+
+    app.clean_up().await;
+}
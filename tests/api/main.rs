@@ -1,8 +1,23 @@
+mod change_email;
+mod change_password;
 mod delete_account;
+mod grpc_admin;
+mod grpc_health;
+mod grpc_lifecycle;
+mod grpc_revocations;
 mod helpers;
+mod jwks;
 mod login;
 mod logout;
+mod logout_all;
+mod passkeys;
+mod password_reset;
+mod rate_limit;
+mod recovery_codes;
+mod refresh_token;
 mod root;
 mod signup;
+mod totp;
 mod verify_2fa;
+mod verify_email;
 mod verify_token;
@@ -0,0 +1,384 @@
+use crate::helpers::{LoggedInUser, TestApp};
+use auth_service::ErrorResponse;
+use auth_service::routes::{
+    PasskeyOptionsResponse, PasskeyRegistrationResponse, SignupResponse, TwoFactorAuthResponse,
+};
+use auth_service::utils::{JWT_COOKIE_NAME, WEBAUTHN_ORIGIN, WEBAUTHN_RP_ID, email};
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use ciborium::Value;
+use fake::Fake;
+use fake::faker::internet::en::{Password as FakePassword, SafeEmail};
+use p256::ecdsa::signature::Signer;
+use p256::ecdsa::{Signature, SigningKey};
+use reqwest::StatusCode;
+use sha2::{Digest, Sha256};
+
+const USER_PRESENT: u8 = 0x01;
+const USER_VERIFIED: u8 = 0x04;
+const ATTESTED_CREDENTIAL: u8 = 0x40;
+
+// Stands in for a platform authenticator: an ES256 key answering the ceremonies with `none` attestation
+struct SoftAuthenticator {
+    key: SigningKey,
+    credential_id: Vec<u8>,
+    sign_count: u32,
+}
+
+impl SoftAuthenticator {
+    fn new() -> Self {
+        Self {
+            key: SigningKey::random(&mut p256::elliptic_curve::rand_core::OsRng),
+            credential_id: uuid::Uuid::new_v4().as_bytes().to_vec(),
+            sign_count: 0,
+        }
+    }
+
+    fn credential_id(&self) -> String {
+        URL_SAFE_NO_PAD.encode(&self.credential_id)
+    }
+
+    fn authenticator_data(
+        &self,
+        flags: u8,
+    ) -> Vec<u8> {
+        let mut data = Sha256::digest(WEBAUTHN_RP_ID.as_bytes()).to_vec();
+        data.push(flags);
+        data.extend_from_slice(&self.sign_count.to_be_bytes());
+        data
+    }
+
+    fn client_data(
+        ceremony: &str,
+        options: &PasskeyOptionsResponse,
+        origin: &str,
+    ) -> Vec<u8> {
+        serde_json::to_vec(&serde_json::json!({
+            "type": ceremony,
+            "challenge": options.public_key["challenge"],
+            "origin": origin,
+        }))
+        .unwrap()
+    }
+
+    fn register(
+        &self,
+        options: &PasskeyOptionsResponse,
+        origin: &str,
+    ) -> serde_json::Value {
+        let point = self.key.verifying_key().to_encoded_point(false);
+        let cose_key = Value::Map(vec![
+            (Value::from(1), Value::from(2)),
+            (Value::from(3), Value::from(-7)),
+            (Value::from(-1), Value::from(1)),
+            (Value::from(-2), Value::Bytes(point.x().unwrap().to_vec())),
+            (Value::from(-3), Value::Bytes(point.y().unwrap().to_vec())),
+        ]);
+
+        let mut authenticator_data = self.authenticator_data(USER_PRESENT | USER_VERIFIED | ATTESTED_CREDENTIAL);
+        authenticator_data.extend_from_slice(&[0u8; 16]);
+        authenticator_data.extend_from_slice(&(self.credential_id.len() as u16).to_be_bytes());
+        authenticator_data.extend_from_slice(&self.credential_id);
+        ciborium::into_writer(&cose_key, &mut authenticator_data).unwrap();
+
+        let mut attestation_object = Vec::new();
+        let attestation = Value::Map(vec![
+            (Value::from("fmt"), Value::from("none")),
+            (Value::from("attStmt"), Value::Map(Vec::new())),
+            (Value::from("authData"), Value::Bytes(authenticator_data)),
+        ]);
+        ciborium::into_writer(&attestation, &mut attestation_object).unwrap();
+
+        serde_json::json!({
+            "id": self.credential_id(),
+            "rawId": self.credential_id(),
+            "type": "public-key",
+            "response": {
+                "clientDataJSON": URL_SAFE_NO_PAD.encode(Self::client_data("webauthn.create", options, origin)),
+                "attestationObject": URL_SAFE_NO_PAD.encode(attestation_object),
+            },
+        })
+    }
+
+    fn assert(
+        &mut self,
+        options: &PasskeyOptionsResponse,
+        user_verified: bool,
+    ) -> serde_json::Value {
+        self.sign_count += 1;
+        let flags = if user_verified {
+            USER_PRESENT | USER_VERIFIED
+        } else {
+            USER_PRESENT
+        };
+        let authenticator_data = self.authenticator_data(flags);
+        let client_data_json = Self::client_data("webauthn.get", options, &WEBAUTHN_ORIGIN);
+
+        let mut signed = authenticator_data.clone();
+        signed.extend_from_slice(&Sha256::digest(&client_data_json));
+        let signature: Signature = self.key.sign(&signed);
+
+        serde_json::json!({
+            "challengeId": options.challenge_id,
+            "credential": {
+                "id": self.credential_id(),
+                "rawId": self.credential_id(),
+                "type": "public-key",
+                "response": {
+                    "clientDataJSON": URL_SAFE_NO_PAD.encode(client_data_json),
+                    "authenticatorData": URL_SAFE_NO_PAD.encode(authenticator_data),
+                    "signature": URL_SAFE_NO_PAD.encode(signature.to_der().as_bytes()),
+                },
+            },
+        })
+    }
+}
+
+async fn passkey_options(response: reqwest::Response) -> PasskeyOptionsResponse {
+    assert_eq!(response.status().as_u16(), StatusCode::OK);
+    response
+        .json::<PasskeyOptionsResponse>()
+        .await
+        .expect("Could not deserialize the response body to PasskeyOptionsResponse")
+}
+
+// The password is asked again, 2FA accounts answer the challenge with the code they are sent
+async fn register(
+    app: &TestApp,
+    authenticator: &SoftAuthenticator,
+    email: &str,
+    password: &str,
+) {
+    let mut response = app
+        .post_passkey_register_start(&serde_json::json!({ "password": password }))
+        .await;
+    if response.status() == StatusCode::PARTIAL_CONTENT {
+        let challenge = response
+            .json::<TwoFactorAuthResponse>()
+            .await
+            .expect("Could not deserialize the response body to TwoFactorAuthResponse");
+        let (_, two_fa_code) = app.get_two_fa_code(email).await;
+        response = app
+            .post_passkey_register_start(&serde_json::json!({
+                "password": password,
+                "loginAttemptId": challenge.login_attempt_id,
+                "2FACode": two_fa_code,
+            }))
+            .await;
+    }
+    let options = passkey_options(response).await;
+    assert_eq!(options.public_key["rp"]["id"], WEBAUTHN_RP_ID.as_str());
+
+    let response = app
+        .post_passkey_register_finish(&serde_json::json!({
+            "challengeId": options.challenge_id,
+            "name": "Laptop",
+            "credential": authenticator.register(&options, &WEBAUTHN_ORIGIN),
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), StatusCode::CREATED);
+    assert_eq!(
+        response
+            .json::<PasskeyRegistrationResponse>()
+            .await
+            .expect("Could not deserialize the response body to PasskeyRegistrationResponse")
+            .credential_id,
+        authenticator.credential_id()
+    );
+}
+
+#[tokio::test]
+async fn should_login_with_a_passkey_alone() {
+    let mut app = TestApp::new().await;
+    let mut authenticator = SoftAuthenticator::new();
+    let LoggedInUser { email, password, .. } = app.signup_and_login(false).await;
+    register(&app, &authenticator, &email, &password).await;
+    assert_eq!(app.post_logout().await.status().as_u16(), StatusCode::OK);
+
+    let options = passkey_options(app.post_passkey_login_start().await).await;
+    assert_eq!(options.public_key["userVerification"], "required");
+    let response = app
+        .post_passkey_login_finish(&authenticator.assert(&options, true))
+        .await;
+
+    assert_eq!(response.status().as_u16(), StatusCode::OK);
+    let auth_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found");
+    assert!(!auth_cookie.value().is_empty());
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_reject_unverified_replayed_or_cloned_passkey_logins() {
+    let mut app = TestApp::new().await;
+    let mut authenticator = SoftAuthenticator::new();
+    let LoggedInUser { email, password, .. } = app.signup_and_login(false).await;
+    register(&app, &authenticator, &email, &password).await;
+
+    // A first factor needs user verification
+    let options = passkey_options(app.post_passkey_login_start().await).await;
+    let response = app
+        .post_passkey_login_finish(&authenticator.assert(&options, false))
+        .await;
+    assert_eq!(response.status().as_u16(), StatusCode::UNAUTHORIZED);
+
+    // The challenge was consumed by the failed attempt
+    let response = app
+        .post_passkey_login_finish(&authenticator.assert(&options, true))
+        .await;
+    assert_eq!(response.status().as_u16(), StatusCode::UNAUTHORIZED);
+
+    let options = passkey_options(app.post_passkey_login_start().await).await;
+    let response = app
+        .post_passkey_login_finish(&authenticator.assert(&options, true))
+        .await;
+    assert_eq!(response.status().as_u16(), StatusCode::OK);
+
+    // A copy of the key still at an older signature counter
+    authenticator.sign_count -= 2;
+    let options = passkey_options(app.post_passkey_login_start().await).await;
+    let response = app
+        .post_passkey_login_finish(&authenticator.assert(&options, true))
+        .await;
+    assert_eq!(response.status().as_u16(), StatusCode::UNAUTHORIZED);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_reject_a_registration_from_another_origin() {
+    let mut app = TestApp::new().await;
+    let authenticator = SoftAuthenticator::new();
+    let LoggedInUser { password, .. } = app.signup_and_login(false).await;
+
+    let options = passkey_options(
+        app.post_passkey_register_start(&serde_json::json!({ "password": password }))
+            .await,
+    )
+    .await;
+    let response = app
+        .post_passkey_register_finish(&serde_json::json!({
+            "challengeId": options.challenge_id,
+            "credential": authenticator.register(&options, "https://phishing.example"),
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), StatusCode::UNAUTHORIZED);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_ask_the_password_again_and_notify_new_passkeys() {
+    let mut app = TestApp::new().await;
+    let authenticator = SoftAuthenticator::new();
+    let LoggedInUser { email, password, .. } = app.signup_and_login(false).await;
+
+    // The access token alone can't add a first factor
+    let response = app
+        .post_passkey_register_start(&serde_json::json!({ "password": format!("{}-wrong", password) }))
+        .await;
+    assert_eq!(response.status().as_u16(), StatusCode::UNAUTHORIZED);
+
+    register(&app, &authenticator, &email, &password).await;
+    assert!(app.wait_for_email(&email, email::PASSKEY_ADDED_SUBJECT).await.is_some());
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_registering_without_jwt() {
+    let mut app = TestApp::new().await;
+
+    let response = app
+        .post_passkey_register_start(&serde_json::json!({ "password": FakePassword(8..20).fake::<String>() }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), StatusCode::BAD_REQUEST);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_complete_a_2fa_login_with_a_passkey() {
+    let mut app = TestApp::new().await;
+    let mut authenticator = SoftAuthenticator::new();
+    let email: String = SafeEmail().fake();
+    let password: String = FakePassword(8..20).fake();
+    let credentials = serde_json::json!({ "email": email, "password": password });
+
+    let response = app
+        .post_signup(&serde_json::json!({ "email": email, "password": password, "requires2FA": true }))
+        .await;
+    let recovery_codes = response
+        .json::<SignupResponse>()
+        .await
+        .expect("Could not deserialize the response body to SignupResponse")
+        .recovery_codes;
+
+    let challenge = app
+        .post_login(&credentials)
+        .await
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize the response body to TwoFactorAuthResponse");
+
+    // No passkey yet: the first login goes through a recovery code
+    let response = app
+        .post_passkey_two_fa_start(&serde_json::json!({
+            "email": email,
+            "loginAttemptId": challenge.login_attempt_id,
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error_message,
+        "No passkey registered".to_owned()
+    );
+    let response = app
+        .post_verify_2fa(&serde_json::json!({
+            "email": email,
+            "loginAttemptId": challenge.login_attempt_id,
+            "recoveryCode": recovery_codes[0],
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), StatusCode::OK);
+    register(&app, &authenticator, &email, &password).await;
+
+    let challenge = app
+        .post_login(&credentials)
+        .await
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize the response body to TwoFactorAuthResponse");
+    let options = passkey_options(
+        app.post_passkey_two_fa_start(&serde_json::json!({
+            "email": email,
+            "loginAttemptId": challenge.login_attempt_id,
+        }))
+        .await,
+    )
+    .await;
+    assert_eq!(
+        options.public_key["allowCredentials"][0]["id"],
+        authenticator.credential_id()
+    );
+
+    // User presence is enough after the password step
+    let assertion = authenticator.assert(&options, false);
+    let response = app
+        .post_verify_2fa(&serde_json::json!({
+            "email": email,
+            "loginAttemptId": challenge.login_attempt_id,
+            "passkey": assertion,
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), StatusCode::OK);
+
+    app.clean_up().await;
+}
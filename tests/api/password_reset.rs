@@ -0,0 +1,147 @@
+use crate::helpers::TestApp;
+use auth_service::routes::PasswordResetResponse;
+use auth_service::utils::{JWT_COOKIE_NAME, email};
+use fake::Fake;
+use fake::faker::internet::en::{Password as FakePassword, SafeEmail};
+use reqwest::StatusCode;
+
+async fn request_reset(
+    app: &TestApp,
+    email: &str,
+) -> PasswordResetResponse {
+    let response = app
+        .post_password_reset_request(&serde_json::json!({ "email": email }))
+        .await;
+    assert_eq!(response.status().as_u16(), StatusCode::ACCEPTED);
+    response
+        .json::<PasswordResetResponse>()
+        .await
+        .expect("Could not deserialize the response body to PasswordResetResponse")
+}
+
+// The token is the `token` query parameter of the link in the email
+async fn reset_token(
+    app: &TestApp,
+    email: &str,
+) -> String {
+    let sent = app
+        .wait_for_email(email, email::PASSWORD_RESET_SUBJECT)
+        .await
+        .expect("No password reset email sent");
+    sent.content
+        .split("token=")
+        .nth(1)
+        .and_then(|rest| rest.split_whitespace().next())
+        .expect("No token in the password reset email")
+        .to_string()
+}
+
+#[tokio::test]
+async fn should_reset_the_password_and_revoke_the_sessions() {
+    let mut app = TestApp::new().await;
+    let (email, password) = app.signup(false).await;
+    let new_password: String = FakePassword(8..20).fake();
+
+    let response = app
+        .post_login(&serde_json::json!({ "email": email, "password": password }))
+        .await;
+    assert_eq!(response.status().as_u16(), StatusCode::OK);
+    let access_token = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found")
+        .value()
+        .to_string();
+
+    request_reset(&app, &email).await;
+    let token = reset_token(&app, &email).await;
+
+    let response = app
+        .post_password_reset_confirm(&serde_json::json!({ "token": token, "newPassword": new_password }))
+        .await;
+    assert_eq!(response.status().as_u16(), StatusCode::OK);
+
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": access_token }))
+        .await;
+    assert_eq!(response.status().as_u16(), StatusCode::UNAUTHORIZED);
+
+    let response = app
+        .post_login(&serde_json::json!({ "email": email, "password": password }))
+        .await;
+    assert_eq!(response.status().as_u16(), StatusCode::UNAUTHORIZED);
+    let response = app
+        .post_login(&serde_json::json!({ "email": email, "password": new_password }))
+        .await;
+    assert_eq!(response.status().as_u16(), StatusCode::OK);
+
+    // The token is spent
+    let response = app
+        .post_password_reset_confirm(&serde_json::json!({ "token": token, "newPassword": password }))
+        .await;
+    assert_eq!(response.status().as_u16(), StatusCode::UNAUTHORIZED);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_answer_alike_for_unknown_emails() {
+    let mut app = TestApp::new().await;
+    let (email, _) = app.signup(false).await;
+    let unknown_email: String = SafeEmail().fake();
+
+    let known = request_reset(&app, &email).await;
+    let unknown = request_reset(&app, &unknown_email).await;
+
+    assert_eq!(known, unknown);
+    assert!(
+        app.wait_for_email(&email, email::PASSWORD_RESET_SUBJECT)
+            .await
+            .is_some()
+    );
+    assert!(
+        app.wait_for_email(&unknown_email, email::PASSWORD_RESET_SUBJECT)
+            .await
+            .is_none()
+    );
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_reject_invalid_tokens_and_passwords() {
+    let mut app = TestApp::new().await;
+    let (email, password) = app.signup(false).await;
+
+    let response = app
+        .post_login(&serde_json::json!({ "email": email, "password": password }))
+        .await;
+    let access_token = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found")
+        .value()
+        .to_string();
+
+    // Session tokens aren't reset tokens
+    let response = app
+        .post_password_reset_confirm(&serde_json::json!({ "token": access_token, "newPassword": password }))
+        .await;
+    assert_eq!(response.status().as_u16(), StatusCode::UNAUTHORIZED);
+
+    request_reset(&app, &email).await;
+    let token = reset_token(&app, &email).await;
+
+    let response = app
+        .post_password_reset_confirm(&serde_json::json!({ "token": token, "newPassword": "short" }))
+        .await;
+    assert_eq!(response.status().as_u16(), StatusCode::BAD_REQUEST);
+
+    // A refused password doesn't spend the token
+    let response = app
+        .post_password_reset_confirm(&serde_json::json!({ "token": token, "newPassword": password }))
+        .await;
+    assert_eq!(response.status().as_u16(), StatusCode::OK);
+
+    app.clean_up().await;
+}
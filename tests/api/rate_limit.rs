@@ -0,0 +1,227 @@
+use crate::helpers::TestApp;
+use auth_service::ErrorResponse;
+use auth_service::grpc::auth_service::auth_service::{
+    IssueServiceTokenRequest, LoginRequest, RefreshTokenRequest, SignupRequest, Verify2FaRequest,
+};
+use auth_service::utils::{RATE_LIMITS, RateLimitKey};
+use fake::Fake;
+use fake::faker::internet::en::{Password as FakePassword, SafeEmail};
+use reqwest::StatusCode;
+use reqwest::header::RETRY_AFTER;
+use tonic::{Code, Request};
+
+fn capacity(
+    path: &str,
+    key: RateLimitKey,
+) -> u32 {
+    RATE_LIMITS
+        .iter()
+        .find(|rule| rule.path == path && rule.key == key)
+        .expect("No rate limit configured for the route")
+        .capacity
+}
+
+#[tokio::test]
+async fn should_return_429_once_the_signup_bucket_is_empty() {
+    let mut app = TestApp::new().await;
+    let capacity = capacity("/signup", RateLimitKey::Ip);
+
+    for remaining in (0..capacity).rev() {
+        let response = app.post_signup(&serde_json::json!({})).await;
+        assert_eq!(response.status().as_u16(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(response.headers()["ratelimit-limit"], capacity.to_string().as_str());
+        assert_eq!(
+            response.headers()["ratelimit-remaining"],
+            remaining.to_string().as_str()
+        );
+    }
+
+    let response = app.post_signup(&serde_json::json!({})).await;
+    assert_eq!(response.status().as_u16(), StatusCode::TOO_MANY_REQUESTS);
+    assert!(response.headers().contains_key(RETRY_AFTER));
+    assert_eq!(response.headers()["ratelimit-remaining"], "0");
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error_message,
+        "Too many requests".to_owned()
+    );
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_ignore_forwarded_addresses_from_untrusted_peers() {
+    let mut app = TestApp::new().await;
+
+    for i in 0..=capacity("/signup", RateLimitKey::Ip) {
+        let response = app
+            .http_client
+            .post(format!("{}/signup", &app.address))
+            .header("x-forwarded-for", format!("203.0.113.{}", i))
+            .json(&serde_json::json!({}))
+            .send()
+            .await
+            .expect("Failed to execute the request.");
+
+        if i == capacity("/signup", RateLimitKey::Ip) {
+            assert_eq!(response.status().as_u16(), StatusCode::TOO_MANY_REQUESTS);
+        } else {
+            assert_eq!(response.status().as_u16(), StatusCode::UNPROCESSABLE_ENTITY);
+        }
+    }
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_limit_logins_per_email_across_case() {
+    let mut app = TestApp::new().await;
+    let email: String = SafeEmail().fake();
+    let password: String = FakePassword(8..20).fake();
+
+    for i in 0..capacity("/login", RateLimitKey::Email) {
+        let email = if i % 2 == 0 {
+            email.to_uppercase()
+        } else {
+            email.clone()
+        };
+        let response = app
+            .post_login(&serde_json::json!({ "email": email, "password": password }))
+            .await;
+        assert_ne!(response.status().as_u16(), StatusCode::TOO_MANY_REQUESTS);
+    }
+
+    let response = app
+        .post_login(&serde_json::json!({ "email": email, "password": password }))
+        .await;
+    assert_eq!(response.status().as_u16(), StatusCode::TOO_MANY_REQUESTS);
+
+    // The address still has tokens for other accounts
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": SafeEmail().fake::<String>(),
+            "password": password,
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), StatusCode::UNAUTHORIZED);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_limit_the_grpc_calls_with_the_http_buckets() {
+    let mut app = TestApp::new().await;
+    let (server_handle, mut client) = app.spawn_grpc_server(50063).await;
+
+    for _ in 0..capacity("/signup", RateLimitKey::Ip) {
+        let status = client
+            .signup(Request::new(SignupRequest {
+                email: "not-an-email".to_string(),
+                password: String::new(),
+                requires_2fa: false,
+            }))
+            .await
+            .expect_err("Malformed signup succeeded");
+        assert_eq!(status.code(), Code::InvalidArgument);
+    }
+
+    let status = client
+        .signup(Request::new(SignupRequest {
+            email: "not-an-email".to_string(),
+            password: String::new(),
+            requires_2fa: false,
+        }))
+        .await
+        .expect_err("Rate limited signup succeeded");
+    assert_eq!(status.code(), Code::ResourceExhausted);
+    assert_eq!(status.message(), "Too many requests");
+
+    // Same address, same bucket
+    let response = app.post_signup(&serde_json::json!({})).await;
+    assert_eq!(response.status().as_u16(), StatusCode::TOO_MANY_REQUESTS);
+
+    // The Login bucket of an email is shared with /login as well
+    let email: String = SafeEmail().fake();
+    let password: String = FakePassword(8..20).fake();
+    for _ in 0..capacity("/login", RateLimitKey::Email) {
+        let response = app
+            .post_login(&serde_json::json!({ "email": email, "password": password }))
+            .await;
+        assert_ne!(response.status().as_u16(), StatusCode::TOO_MANY_REQUESTS);
+    }
+    let status = client
+        .login(Request::new(LoginRequest {
+            email: email.to_uppercase(),
+            password,
+            totp_code: String::new(),
+        }))
+        .await
+        .expect_err("Rate limited login succeeded");
+    assert_eq!(status.code(), Code::ResourceExhausted);
+    assert_eq!(status.message(), "Too many requests");
+
+    server_handle.abort();
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_limit_the_grpc_second_factor_refresh_and_service_token_calls() {
+    let mut app = TestApp::new().await;
+    let (server_handle, mut client) = app.spawn_grpc_server(50064).await;
+
+    let verify_2fa = || {
+        Request::new(Verify2FaRequest {
+            email: "not-an-email".to_string(),
+            login_attempt_id: String::new(),
+            two_fa_code: String::new(),
+            recovery_code: String::new(),
+        })
+    };
+    for _ in 0..capacity("/verify-2fa", RateLimitKey::Ip) {
+        let status = client
+            .verify2_fa(verify_2fa())
+            .await
+            .expect_err("Malformed 2FA succeeded");
+        assert_ne!(status.code(), Code::ResourceExhausted);
+    }
+    let status = client
+        .verify2_fa(verify_2fa())
+        .await
+        .expect_err("Rate limited 2FA succeeded");
+    assert_eq!(status.code(), Code::ResourceExhausted);
+    assert_eq!(status.message(), "Too many requests");
+
+    // RefreshToken has no rule of its own, it shares the `*` bucket with IssueServiceToken. The bucket refills
+    // while the calls are made, it takes at least its capacity to empty it
+    let capacity = capacity("*", RateLimitKey::Ip);
+    let mut calls = 0;
+    loop {
+        let status = client
+            .refresh_token(Request::new(RefreshTokenRequest {
+                refresh_token: String::new(),
+            }))
+            .await
+            .expect_err("Empty refresh token succeeded");
+        if status.code() == Code::ResourceExhausted {
+            break;
+        }
+        calls += 1;
+        assert!(calls <= 2 * capacity, "RefreshToken is not rate limited");
+    }
+    assert!(calls >= capacity);
+
+    let status = client
+        .issue_service_token(Request::new(IssueServiceTokenRequest {
+            service_id: "unknown-service".to_string(),
+            service_secret: "wrong-secret".to_string(),
+        }))
+        .await
+        .expect_err("Rate limited service token succeeded");
+    assert_eq!(status.code(), Code::ResourceExhausted);
+
+    server_handle.abort();
+    app.clean_up().await;
+}
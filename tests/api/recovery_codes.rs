@@ -0,0 +1,197 @@
+use crate::helpers::TestApp;
+use auth_service::ErrorResponse;
+use auth_service::domain::RECOVERY_CODE_COUNT;
+use auth_service::routes::{RecoveryCodesResponse, RemainingRecoveryCodesResponse, TwoFactorAuthResponse};
+use auth_service::utils::email;
+use reqwest::StatusCode;
+
+async fn login_with_recovery_code(
+    app: &TestApp,
+    email: &str,
+    password: &str,
+    recovery_code: &str,
+) -> reqwest::Response {
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": email,
+            "password": password,
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), StatusCode::PARTIAL_CONTENT);
+    let challenge = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize the response body to TwoFactorAuthResponse");
+
+    app.post_verify_2fa(&serde_json::json!({
+        "email": email,
+        "loginAttemptId": challenge.login_attempt_id,
+        "recoveryCode": recovery_code,
+    }))
+    .await
+}
+
+#[tokio::test]
+async fn should_return_recovery_codes_only_for_2fa_signups() {
+    let mut app = TestApp::new().await;
+    let (_, _, codes) = app.signup_with_recovery_codes(true).await;
+    assert_eq!(codes.len(), RECOVERY_CODE_COUNT);
+
+    let (_, _, codes) = app.signup_with_recovery_codes(false).await;
+    assert!(codes.is_empty());
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_login_once_with_each_recovery_code() {
+    let mut app = TestApp::new().await;
+    let (fake_email, fake_password, codes) = app.signup_with_recovery_codes(true).await;
+
+    let response = login_with_recovery_code(&app, &fake_email, &fake_password, &codes[0]).await;
+    assert_eq!(response.status().as_u16(), StatusCode::OK);
+
+    let response = app.get_recovery_codes().await;
+    assert_eq!(response.status().as_u16(), StatusCode::OK);
+    assert_eq!(
+        response
+            .json::<RemainingRecoveryCodesResponse>()
+            .await
+            .expect("Could not deserialize the response body to RemainingRecoveryCodesResponse"),
+        RemainingRecoveryCodesResponse {
+            remaining: RECOVERY_CODE_COUNT - 1
+        }
+    );
+
+    let response = login_with_recovery_code(&app, &fake_email, &fake_password, &codes[0]).await;
+    assert_eq!(response.status().as_u16(), StatusCode::UNAUTHORIZED);
+
+    // Typed back by hand, without the dash and in upper case
+    let typed = codes[1].replace('-', "").to_uppercase();
+    let response = login_with_recovery_code(&app, &fake_email, &fake_password, &typed).await;
+    assert_eq!(response.status().as_u16(), StatusCode::OK);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_both_or_no_second_factor_given() {
+    let mut app = TestApp::new().await;
+    let (fake_email, fake_password, codes) = app.signup_with_recovery_codes(true).await;
+
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": fake_email,
+            "password": fake_password,
+        }))
+        .await;
+    let challenge = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize the response body to TwoFactorAuthResponse");
+
+    let test_cases = [
+        serde_json::json!({
+            "email": fake_email,
+            "loginAttemptId": challenge.login_attempt_id,
+        }),
+        serde_json::json!({
+            "email": fake_email,
+            "loginAttemptId": challenge.login_attempt_id,
+            "2FACode": "123456",
+            "recoveryCode": codes[0],
+        }),
+    ];
+
+    for test_case in test_cases.iter() {
+        let response = app.post_verify_2fa(test_case).await;
+        assert_eq!(
+            response.status().as_u16(),
+            StatusCode::BAD_REQUEST,
+            "Failed for input: {:?}",
+            test_case
+        );
+    }
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_invalidate_old_codes_on_regeneration() {
+    let mut app = TestApp::new().await;
+    let (fake_email, fake_password, codes) = app.signup_with_recovery_codes(true).await;
+
+    let response = login_with_recovery_code(&app, &fake_email, &fake_password, &codes[0]).await;
+    assert_eq!(response.status().as_u16(), StatusCode::OK);
+
+    // The password and a second factor are asked again, here one of the current codes
+    let response = app
+        .post_recovery_codes(&serde_json::json!({ "password": format!("{}-wrong", fake_password) }))
+        .await;
+    assert_eq!(response.status().as_u16(), StatusCode::UNAUTHORIZED);
+    let response = app
+        .post_recovery_codes(&serde_json::json!({ "password": fake_password }))
+        .await;
+    assert_eq!(response.status().as_u16(), StatusCode::PARTIAL_CONTENT);
+    let challenge = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize the response body to TwoFactorAuthResponse");
+
+    let response = app
+        .post_recovery_codes(&serde_json::json!({
+            "password": fake_password,
+            "loginAttemptId": challenge.login_attempt_id,
+            "recoveryCode": codes[1],
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), StatusCode::OK);
+    let new_codes = response
+        .json::<RecoveryCodesResponse>()
+        .await
+        .expect("Could not deserialize the response body to RecoveryCodesResponse")
+        .recovery_codes;
+    assert_eq!(new_codes.len(), RECOVERY_CODE_COUNT);
+    assert!(
+        app.wait_for_email(&fake_email, email::RECOVERY_CODES_SUBJECT)
+            .await
+            .is_some()
+    );
+
+    let response = login_with_recovery_code(&app, &fake_email, &fake_password, &codes[2]).await;
+    assert_eq!(response.status().as_u16(), StatusCode::UNAUTHORIZED);
+
+    let response = login_with_recovery_code(&app, &fake_email, &fake_password, &new_codes[0]).await;
+    assert_eq!(response.status().as_u16(), StatusCode::OK);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_when_regenerating_without_2fa() {
+    let mut app = TestApp::new().await;
+    let (fake_email, fake_password) = app.signup(false).await;
+
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": fake_email,
+            "password": fake_password,
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), StatusCode::OK);
+
+    let response = app
+        .post_recovery_codes(&serde_json::json!({ "password": fake_password }))
+        .await;
+    assert_eq!(response.status().as_u16(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error_message,
+        "2FA is not enabled".to_owned()
+    );
+
+    app.clean_up().await;
+}
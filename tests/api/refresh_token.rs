@@ -0,0 +1,93 @@
+use crate::helpers::TestApp;
+use auth_service::utils::{JWT_REFRESH_COOKIE_NAME, TokenType, validate_token};
+use reqwest::{StatusCode, Url};
+
+fn cookie_value(
+    response: &reqwest::Response,
+    name: &str,
+) -> String {
+    response
+        .cookies()
+        .find(|cookie| cookie.name() == name)
+        .expect("No cookie found")
+        .value()
+        .to_string()
+}
+
+fn set_refresh_cookie(
+    app: &TestApp,
+    refresh_token: &str,
+) {
+    app.cookie_jar.add_cookie_str(
+        &format!(
+            "{}={}; HttpOnly; SameSite=Lax; Path=/",
+            JWT_REFRESH_COOKIE_NAME, refresh_token
+        ),
+        &Url::parse("http://127.0.0.1").expect("Failed to parse URL"),
+    );
+}
+
+#[tokio::test]
+async fn should_return_400_if_refresh_cookie_missing() {
+    let mut app = TestApp::new().await;
+    let response = app.post_refresh_token().await;
+    assert_eq!(response.status().as_u16(), StatusCode::BAD_REQUEST);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_access_token_is_used_as_refresh_token() {
+    let mut app = TestApp::new().await;
+    let access_token = app.signup_and_login(false).await.access_token;
+
+    set_refresh_cookie(&app, &access_token);
+    let response = app.post_refresh_token().await;
+    assert_eq!(response.status().as_u16(), StatusCode::UNAUTHORIZED);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_rotate_the_refresh_token() {
+    let mut app = TestApp::new().await;
+    let first_refresh_token = app.signup_and_login(false).await.refresh_token;
+
+    let response = app.post_refresh_token().await;
+    assert_eq!(response.status().as_u16(), StatusCode::OK);
+
+    let second_refresh_token = cookie_value(&response, JWT_REFRESH_COOKIE_NAME);
+    assert_ne!(first_refresh_token, second_refresh_token);
+
+    let response = app.post_refresh_token().await;
+    assert_eq!(response.status().as_u16(), StatusCode::OK);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_revoke_the_family_if_a_refresh_token_is_reused() {
+    let mut app = TestApp::new().await;
+    let first_refresh_token = app.signup_and_login(false).await.refresh_token;
+
+    let response = app.post_refresh_token().await;
+    assert_eq!(response.status().as_u16(), StatusCode::OK);
+    let second_refresh_token = cookie_value(&response, JWT_REFRESH_COOKIE_NAME);
+
+    set_refresh_cookie(&app, &first_refresh_token);
+    let response = app.post_refresh_token().await;
+    assert_eq!(response.status().as_u16(), StatusCode::UNAUTHORIZED);
+
+    // The legitimate latest token belongs to the revoked family as well
+    set_refresh_cookie(&app, &second_refresh_token);
+    let response = app.post_refresh_token().await;
+    assert_eq!(response.status().as_u16(), StatusCode::UNAUTHORIZED);
+
+    let claims =
+        validate_token(&second_refresh_token, TokenType::Refresh).expect("Failed to validate the refresh token");
+    {
+        let refresh_tokens = app.refresh_tokens.read().await;
+        assert!(refresh_tokens.is_family_revoked(&claims.fid).await.unwrap());
+    }
+
+    app.clean_up().await;
+}
@@ -1,5 +1,6 @@
 use crate::helpers::TestApp;
 use auth_service::ErrorResponse;
+use auth_service::domain::PasswordViolation;
 use auth_service::routes::SignupResponse;
 use fake::Fake;
 use fake::faker::internet::en::{Password as FakePassword, SafeEmail};
@@ -55,6 +56,7 @@ async fn should_return_201_if_valid_input() {
 
     let expected_response = SignupResponse {
         message: "User created successfully!".to_string(),
+        recovery_codes: Vec::new(),
     };
 
     assert_eq!(response.json::<SignupResponse>().await.unwrap(), expected_response);
@@ -99,6 +101,45 @@ async fn should_return_400_if_invalid_input() {
     app.clean_up().await;
 }
 
+#[tokio::test]
+async fn should_return_400_with_the_broken_password_rules() {
+    let mut app = TestApp::new().await;
+
+    let response = app
+        .post_signup(&serde_json::json!({
+            "email": "kenesparta@example.com",
+            "password": "Password123",
+            "requires2FA": false
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), StatusCode::BAD_REQUEST);
+    let body = response
+        .json::<ErrorResponse>()
+        .await
+        .expect("Could not deserialize response body to ErrorResponse");
+    assert_eq!(body.error_message, "Password does not meet the policy");
+    assert!(body.violations.contains(&PasswordViolation::CommonPassword));
+
+    let response = app
+        .post_signup(&serde_json::json!({
+            "email": "kenesparta@example.com",
+            "password": "Kenesparta-7#xq",
+            "requires2FA": false
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .violations,
+        vec![PasswordViolation::ContainsEmail]
+    );
+
+    app.clean_up().await;
+}
+
 #[tokio::test]
 async fn should_return_409_if_email_already_exists() {
     let mut app = TestApp::new().await;
@@ -0,0 +1,206 @@
+use crate::helpers::{LoggedInUser, TestApp};
+use auth_service::ErrorResponse;
+use auth_service::domain::{TotpSecret, TwoFAMethod};
+use auth_service::routes::{EnrollTotpResponse, TwoFactorAuthResponse};
+use auth_service::utils::LOCKOUT_ACCOUNT_THRESHOLD;
+use reqwest::StatusCode;
+
+const STEP_SECONDS: u64 = 30;
+
+fn now() -> u64 {
+    chrono::Utc::now().timestamp() as u64
+}
+
+async fn enroll(app: &TestApp) -> TotpSecret {
+    let response = app.post_enroll_totp().await;
+    assert_eq!(response.status().as_u16(), StatusCode::OK);
+
+    let enrollment = response
+        .json::<EnrollTotpResponse>()
+        .await
+        .expect("Could not deserialize the response body to EnrollTotpResponse");
+    assert!(enrollment.otpauth_uri.starts_with("otpauth://totp/"));
+    assert!(enrollment.otpauth_uri.contains(&enrollment.secret));
+
+    TotpSecret::from_base32(&enrollment.secret).expect("Invalid TOTP secret")
+}
+
+#[tokio::test]
+async fn should_return_400_if_confirming_without_enrollment() {
+    let mut app = TestApp::new().await;
+    app.signup_and_login(false).await;
+
+    let response = app.post_confirm_totp(&serde_json::json!({ "code": "123456" })).await;
+
+    assert_eq!(response.status().as_u16(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error_message,
+        "TOTP enrollment not started".to_owned()
+    );
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_require_a_valid_code_to_enable_totp() {
+    let mut app = TestApp::new().await;
+    let LoggedInUser {
+        email: fake_email,
+        password: fake_password,
+        ..
+    } = app.signup_and_login(false).await;
+    let secret = enroll(&app).await;
+
+    let wrong_code = secret.code_at(now() + 10 * STEP_SECONDS);
+    let response = app.post_confirm_totp(&serde_json::json!({ "code": wrong_code })).await;
+    assert_eq!(response.status().as_u16(), StatusCode::UNAUTHORIZED);
+
+    // Still pending, the logins don't ask for a code yet
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": fake_email,
+            "password": fake_password,
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), StatusCode::OK);
+
+    let response = app
+        .post_confirm_totp(&serde_json::json!({ "code": secret.code_at(now()) }))
+        .await;
+    assert_eq!(response.status().as_u16(), StatusCode::OK);
+
+    let response = app.post_enroll_totp().await;
+    assert_eq!(response.status().as_u16(), StatusCode::CONFLICT);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_login_with_totp_and_reject_replayed_codes() {
+    let mut app = TestApp::new().await;
+    let LoggedInUser {
+        email: fake_email,
+        password: fake_password,
+        ..
+    } = app.signup_and_login(false).await;
+    let secret = enroll(&app).await;
+
+    let confirmation_code = secret.code_at(now());
+    let response = app
+        .post_confirm_totp(&serde_json::json!({ "code": confirmation_code }))
+        .await;
+    assert_eq!(response.status().as_u16(), StatusCode::OK);
+
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": fake_email,
+            "password": fake_password,
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), StatusCode::PARTIAL_CONTENT);
+    let challenge = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize the response body to TwoFactorAuthResponse");
+    assert_eq!(challenge.method, TwoFAMethod::Totp);
+
+    // The code used to confirm the enrollment is still inside the drift window but already spent
+    let response = app
+        .post_verify_2fa(&serde_json::json!({
+            "email": fake_email,
+            "loginAttemptId": challenge.login_attempt_id,
+            "2FACode": confirmation_code,
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), StatusCode::UNAUTHORIZED);
+
+    let next_code = secret.code_at(now() + STEP_SECONDS);
+    let response = app
+        .post_verify_2fa(&serde_json::json!({
+            "email": fake_email,
+            "loginAttemptId": challenge.login_attempt_id,
+            "2FACode": next_code,
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), StatusCode::OK);
+
+    // Single call login with the code, spent as well
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": fake_email,
+            "password": fake_password,
+            "totpCode": next_code,
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), StatusCode::UNAUTHORIZED);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_login_in_one_call_with_a_totp_code() {
+    let mut app = TestApp::new().await;
+    let LoggedInUser {
+        email: fake_email,
+        password: fake_password,
+        ..
+    } = app.signup_and_login(false).await;
+    let secret = enroll(&app).await;
+
+    let response = app
+        .post_confirm_totp(&serde_json::json!({ "code": secret.code_at(now() - STEP_SECONDS) }))
+        .await;
+    assert_eq!(response.status().as_u16(), StatusCode::OK);
+
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": fake_email,
+            "password": fake_password,
+            "totpCode": secret.code_at(now() + STEP_SECONDS),
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), StatusCode::OK);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_lock_the_account_after_too_many_wrong_single_call_codes() {
+    let mut app = TestApp::new().await;
+    let LoggedInUser {
+        email: fake_email,
+        password: fake_password,
+        ..
+    } = app.signup_and_login(false).await;
+    let secret = enroll(&app).await;
+
+    let response = app
+        .post_confirm_totp(&serde_json::json!({ "code": secret.code_at(now()) }))
+        .await;
+    assert_eq!(response.status().as_u16(), StatusCode::OK);
+
+    let wrong_code = secret.code_at(now() + 10 * STEP_SECONDS);
+    let wrong_credentials = serde_json::json!({
+        "email": fake_email,
+        "password": fake_password,
+        "totpCode": wrong_code,
+    });
+    for _ in 1..*LOCKOUT_ACCOUNT_THRESHOLD {
+        let response = app.post_login(&wrong_credentials).await;
+        assert_eq!(response.status().as_u16(), StatusCode::UNAUTHORIZED);
+    }
+
+    let response = app.post_login(&wrong_credentials).await;
+    assert_eq!(response.status().as_u16(), StatusCode::LOCKED);
+
+    // The right code doesn't get through the lock either
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": fake_email,
+            "password": fake_password,
+            "totpCode": secret.code_at(now() + STEP_SECONDS),
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), StatusCode::LOCKED);
+    app.clean_up().await;
+}
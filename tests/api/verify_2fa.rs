@@ -1,6 +1,7 @@
 use crate::helpers::TestApp;
+use auth_service::ErrorResponse;
 use auth_service::domain::Email;
-use auth_service::utils::JWT_COOKIE_NAME;
+use auth_service::utils::{JWT_COOKIE_NAME, LOCKOUT_ACCOUNT_THRESHOLD, TWO_FA_MAX_ATTEMPTS};
 use fake::Fake;
 use fake::faker::internet::en::{Password as FakePassword, SafeEmail};
 use fake::faker::number::en::NumberWithFormat;
@@ -249,3 +250,127 @@ async fn should_return_401_if_same_code_twice() {
 
     app.clean_up().await;
 }
+
+#[tokio::test]
+async fn should_return_429_and_drop_the_code_after_too_many_failures() {
+    let mut app = TestApp::new().await;
+    let fake_email: String = SafeEmail().fake();
+    let fake_password: String = FakePassword(8..20).fake();
+
+    let response = app
+        .post_signup(&serde_json::json!({
+            "email": fake_email,
+            "password": fake_password,
+            "requires2FA": true
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), StatusCode::CREATED);
+
+    let first_response = app
+        .post_login(&serde_json::json!({
+            "email": fake_email,
+            "password": fake_password,
+        }))
+        .await;
+    assert_eq!(first_response.status().as_u16(), StatusCode::PARTIAL_CONTENT);
+
+    let email = &Email::new(SecretBox::new(Box::from(fake_email.clone()))).unwrap();
+    let (login_attempt_id, code) = {
+        let guard = app.two_fa_code.read().await;
+        guard.get_code(email).await.unwrap()
+    };
+    let login_attempt_id = login_attempt_id.id().expose_secret().clone();
+    let code = code.code().expose_secret().clone();
+    let wrong_code = if code == "000000" { "111111" } else { "000000" };
+
+    for _ in 1..*TWO_FA_MAX_ATTEMPTS {
+        let response = app
+            .post_verify_2fa(&serde_json::json!({
+                "email": fake_email,
+                "loginAttemptId": login_attempt_id,
+                "2FACode": wrong_code,
+            }))
+            .await;
+        assert_eq!(response.status().as_u16(), StatusCode::UNAUTHORIZED);
+    }
+
+    let response = app
+        .post_verify_2fa(&serde_json::json!({
+            "email": fake_email,
+            "loginAttemptId": login_attempt_id,
+            "2FACode": wrong_code,
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error_message,
+        "Too many 2FA attempts, log in again".to_owned()
+    );
+
+    // The right code no longer works, a new login is needed. The wrong codes count towards the
+    // login lockout too, the account is locked when they reached its threshold.
+    let response = app
+        .post_verify_2fa(&serde_json::json!({
+            "email": fake_email,
+            "loginAttemptId": login_attempt_id,
+            "2FACode": code,
+        }))
+        .await;
+    let expected = if *TWO_FA_MAX_ATTEMPTS >= *LOCKOUT_ACCOUNT_THRESHOLD {
+        StatusCode::LOCKED
+    } else {
+        StatusCode::UNAUTHORIZED
+    };
+    assert_eq!(response.status().as_u16(), expected);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_lock_the_account_after_wrong_codes_over_several_logins() {
+    let mut app = TestApp::new().await;
+    let fake_email: String = SafeEmail().fake();
+    let fake_password: String = FakePassword(8..20).fake();
+    let credentials = serde_json::json!({ "email": fake_email, "password": fake_password });
+
+    let response = app
+        .post_signup(&serde_json::json!({
+            "email": fake_email,
+            "password": fake_password,
+            "requires2FA": true
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), StatusCode::CREATED);
+
+    // A new login gives a fresh attempt budget, the lockout counts across them
+    for round in 1..=*LOCKOUT_ACCOUNT_THRESHOLD {
+        let response = app.post_login(&credentials).await;
+        assert_eq!(response.status().as_u16(), StatusCode::PARTIAL_CONTENT);
+
+        let (login_attempt_id, code) = app.get_two_fa_code(&fake_email).await;
+        let wrong_code = if code == "000000" { "111111" } else { "000000" };
+        let response = app
+            .post_verify_2fa(&serde_json::json!({
+                "email": fake_email,
+                "loginAttemptId": login_attempt_id,
+                "2FACode": wrong_code,
+            }))
+            .await;
+
+        let expected = if round < *LOCKOUT_ACCOUNT_THRESHOLD {
+            StatusCode::UNAUTHORIZED
+        } else {
+            StatusCode::LOCKED
+        };
+        assert_eq!(response.status().as_u16(), expected);
+    }
+
+    let response = app.post_login(&credentials).await;
+    assert_eq!(response.status().as_u16(), StatusCode::LOCKED);
+
+    app.clean_up().await;
+}
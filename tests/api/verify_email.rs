@@ -0,0 +1,155 @@
+use crate::helpers::TestApp;
+use auth_service::domain::Email;
+use auth_service::routes::VerifyEmailResponse;
+use auth_service::utils::{JWT_COOKIE_NAME, email};
+use fake::Fake;
+use fake::faker::internet::en::SafeEmail;
+use reqwest::StatusCode;
+use secrecy::SecretBox;
+
+async fn email_verified(
+    app: &TestApp,
+    email: &str,
+) -> bool {
+    let email = Email::new(SecretBox::new(Box::from(email.to_owned()))).unwrap();
+    app.app_state
+        .user_store
+        .read()
+        .await
+        .get_user(&email)
+        .await
+        .expect("User not found")
+        .email_verified()
+}
+
+async fn verification_emails(
+    app: &TestApp,
+    email: &str,
+) -> Vec<String> {
+    app.email_client
+        .read()
+        .await
+        .sent_emails()
+        .into_iter()
+        .filter(|sent| sent.recipient == email && sent.subject == email::VERIFICATION_SUBJECT)
+        .map(|sent| sent.content)
+        .collect()
+}
+
+// The token is the `token` query parameter of the link in the email
+fn verification_token(content: &str) -> String {
+    content
+        .split("token=")
+        .nth(1)
+        .and_then(|rest| rest.split_whitespace().next())
+        .expect("No token in the verification email")
+        .to_string()
+}
+
+#[tokio::test]
+async fn should_verify_the_email_with_the_signup_link() {
+    let mut app = TestApp::new().await;
+    let (email, _) = app.signup(false).await;
+    assert!(!email_verified(&app, &email).await);
+
+    let sent = verification_emails(&app, &email).await;
+    assert_eq!(sent.len(), 1);
+    let token = verification_token(&sent[0]);
+
+    let response = app.get_verify_email(&token).await;
+    assert_eq!(response.status().as_u16(), StatusCode::OK);
+    assert_eq!(
+        response
+            .json::<VerifyEmailResponse>()
+            .await
+            .expect("Could not deserialize the response body to VerifyEmailResponse")
+            .message,
+        "Email verified".to_owned()
+    );
+    assert!(email_verified(&app, &email).await);
+
+    // Opening the link again is harmless
+    let response = app.get_verify_email(&token).await;
+    assert_eq!(response.status().as_u16(), StatusCode::OK);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_reject_invalid_verification_tokens() {
+    let mut app = TestApp::new().await;
+    let (email, password) = app.signup(false).await;
+
+    let response = app.get_verify_email("not-a-token").await;
+    assert_eq!(response.status().as_u16(), StatusCode::UNAUTHORIZED);
+
+    // A session token isn't a verification token
+    let response = app
+        .post_login(&serde_json::json!({ "email": email, "password": password }))
+        .await;
+    let access_token = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found")
+        .value()
+        .to_string();
+    let response = app.get_verify_email(&access_token).await;
+    assert_eq!(response.status().as_u16(), StatusCode::UNAUTHORIZED);
+    assert!(!email_verified(&app, &email).await);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_resend_only_to_unverified_accounts() {
+    let mut app = TestApp::new().await;
+    let (email, _) = app.signup(false).await;
+    let unknown_email: String = SafeEmail().fake();
+
+    let response = app
+        .post_verify_email_resend(&serde_json::json!({ "email": email }))
+        .await;
+    assert_eq!(response.status().as_u16(), StatusCode::ACCEPTED);
+    let known = response
+        .json::<VerifyEmailResponse>()
+        .await
+        .expect("Could not deserialize the response body to VerifyEmailResponse");
+
+    let response = app
+        .post_verify_email_resend(&serde_json::json!({ "email": unknown_email }))
+        .await;
+    assert_eq!(response.status().as_u16(), StatusCode::ACCEPTED);
+    let unknown = response
+        .json::<VerifyEmailResponse>()
+        .await
+        .expect("Could not deserialize the response body to VerifyEmailResponse");
+    assert_eq!(known, unknown);
+
+    // The resent email comes after the response
+    for _ in 0..50 {
+        if verification_emails(&app, &email).await.len() == 2 {
+            break;
+        }
+        tokio::time::sleep(tokio::time::Duration::from_millis(20)).await;
+    }
+    let sent = verification_emails(&app, &email).await;
+    assert_eq!(sent.len(), 2);
+    assert!(
+        app.wait_for_email(&unknown_email, email::VERIFICATION_SUBJECT)
+            .await
+            .is_none()
+    );
+
+    let response = app.get_verify_email(&verification_token(&sent[1])).await;
+    assert_eq!(response.status().as_u16(), StatusCode::OK);
+
+    // Verified accounts get nothing more
+    let response = app
+        .post_verify_email_resend(&serde_json::json!({ "email": email }))
+        .await;
+    assert_eq!(response.status().as_u16(), StatusCode::ACCEPTED);
+    tokio::time::sleep(tokio::time::Duration::from_millis(200)).await;
+    assert_eq!(verification_emails(&app, &email).await.len(), 2);
+
+    app.clean_up().await;
+}
@@ -1,40 +1,32 @@
-use auth_service::domain::Email;
-use auth_service::grpc::auth_service::{
-    auth_service::{VerifyTokenRequest, auth_service_client::AuthServiceClient},
-    create_grpc_service,
-};
-use auth_service::utils::generate_auth_cookie;
+use crate::helpers::{LoggedInUser, TestApp};
+use auth_service::grpc::auth_service::auth_service::{RejectionReason, TokenType as GrpcTokenType, VerifyTokenRequest};
+use auth_service::routes::VerifyTokenResponse;
+use auth_service::utils::{TOKEN_TTL_SECONDS, TokenType, generate_token_pair};
 use fake::Fake;
-use fake::faker::internet::en::SafeEmail;
-use secrecy::SecretBox;
+use fake::faker::internet::en::{Password as FakePassword, SafeEmail};
+use reqwest::StatusCode;
 use tonic::Request;
-use tonic::transport::Server;
 
 #[tokio::test]
 async fn test_verify_token_valid() {
-    let grpc_service = create_grpc_service();
-    let addr = "127.0.0.1:50052".parse().unwrap();
-
-    let server_handle = tokio::spawn(async move {
-        Server::builder()
-            .add_service(grpc_service)
-            .serve(addr)
-            .await
-            .expect("Failed to start the gRPC server")
-    });
-
-    tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
-
-    let mut client = AuthServiceClient::connect("http://127.0.0.1:50052")
-        .await
-        .expect("Failed to connect to the gRPC server");
+    let mut app = TestApp::new().await;
+    let (server_handle, mut client) = app.spawn_grpc_server(50052).await;
 
     let fake_email: String = SafeEmail().fake();
-    let email = &Email::new(SecretBox::new(Box::from(fake_email))).expect("Failed to create email");
-    let valid_token = generate_auth_cookie(email).expect("Failed to generate a token");
+    let fake_password: String = FakePassword(8..20).fake();
+    app.post_signup(&serde_json::json!({
+        "email": fake_email.clone(),
+        "password": fake_password,
+        "requires2FA": false
+    }))
+    .await;
+
+    let user_id = app.user_id(&fake_email).await;
+    let family_id = uuid::Uuid::new_v4().to_string();
+    let token_pair = generate_token_pair(&user_id, &family_id, 0).expect("Failed to generate a token");
 
     let request = Request::new(VerifyTokenRequest {
-        token: valid_token.value().to_string(),
+        token: token_pair.access_token,
     });
 
     let response = client.verify_token(request).await.expect("Request failed");
@@ -42,6 +34,149 @@ async fn test_verify_token_valid() {
 
     assert!(response.valid);
     assert_eq!(response.message, "Token is valid");
+    assert_eq!(response.subject, user_id.to_string());
+    assert_eq!(response.token_type(), GrpcTokenType::Access);
+    assert!(response.expires_at > 0);
+    assert_eq!(response.reason(), RejectionReason::Unspecified);
+
+    server_handle.abort();
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn test_verify_token_invalid_after_logout_all() {
+    let mut app = TestApp::new().await;
+    let (server_handle, mut client) = app.spawn_grpc_server(50053).await;
+
+    let fake_email: String = SafeEmail().fake();
+    let fake_password: String = FakePassword(8..20).fake();
+    app.post_signup(&serde_json::json!({
+        "email": fake_email.clone(),
+        "password": fake_password,
+        "requires2FA": false
+    }))
+    .await;
+
+    let user_id = app.user_id(&fake_email).await;
+    let family_id = uuid::Uuid::new_v4().to_string();
+    let token_pair = generate_token_pair(&user_id, &family_id, 0).expect("Failed to generate a token");
+
+    app.app_state
+        .session_epoch_store
+        .write()
+        .await
+        .increment_epoch(&user_id)
+        .await
+        .expect("Failed to increment the session epoch");
+
+    let request = Request::new(VerifyTokenRequest {
+        token: token_pair.access_token,
+    });
+    let response = client.verify_token(request).await.expect("Request failed").into_inner();
+    assert!(!response.valid);
+    assert_eq!(response.reason(), RejectionReason::SessionRevoked);
+    assert!(response.subject.is_empty());
 
     server_handle.abort();
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn test_verify_token_rejection_reasons() {
+    let mut app = TestApp::new().await;
+    let (server_handle, mut client) = app.spawn_grpc_server(50054).await;
+    let LoggedInUser {
+        email, access_token, ..
+    } = app.signup_and_login(false).await;
+
+    let user_id = app.user_id(&email).await;
+    let family_id = uuid::Uuid::new_v4().to_string();
+    let token_pair = generate_token_pair(&user_id, &family_id, 0).expect("Failed to generate a token");
+
+    let cases = [
+        ("invalid".to_string(), RejectionReason::Malformed),
+        (token_pair.refresh_token, RejectionReason::WrongTokenType),
+    ];
+    for (token, reason) in cases {
+        let response = client
+            .verify_token(Request::new(VerifyTokenRequest { token }))
+            .await
+            .expect("Request failed")
+            .into_inner();
+        assert!(!response.valid);
+        assert_eq!(response.reason(), reason);
+    }
+
+    let response = app.post_logout().await;
+    assert_eq!(response.status().as_u16(), StatusCode::OK);
+
+    let response = client
+        .verify_token(Request::new(VerifyTokenRequest { token: access_token }))
+        .await
+        .expect("Request failed")
+        .into_inner();
+    assert!(!response.valid);
+    assert_eq!(response.reason(), RejectionReason::Banned);
+
+    server_handle.abort();
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_200_with_the_claims_if_token_is_valid() {
+    let mut app = TestApp::new().await;
+    let LoggedInUser {
+        email, access_token, ..
+    } = app.signup_and_login(false).await;
+
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": access_token }))
+        .await;
+    assert_eq!(response.status().as_u16(), StatusCode::OK);
+
+    let body = response
+        .json::<VerifyTokenResponse>()
+        .await
+        .expect("Could not deserialize response body to VerifyTokenResponse");
+    assert_eq!(body.subject, app.user_id(&email).await.to_string());
+    assert_eq!(body.token_type, TokenType::Access);
+    assert!(body.expires_in > 0 && body.expires_in <= *TOKEN_TTL_SECONDS as u64);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_token_is_invalid() {
+    let mut app = TestApp::new().await;
+
+    let response = app.post_verify_token(&serde_json::json!({ "token": "invalid" })).await;
+    assert_eq!(response.status().as_u16(), StatusCode::UNAUTHORIZED);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_token_is_banned() {
+    let mut app = TestApp::new().await;
+    let LoggedInUser { access_token, .. } = app.signup_and_login(false).await;
+
+    let response = app.post_logout().await;
+    assert_eq!(response.status().as_u16(), StatusCode::OK);
+
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": access_token }))
+        .await;
+    assert_eq!(response.status().as_u16(), StatusCode::UNAUTHORIZED);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_422_if_malformed_input() {
+    let mut app = TestApp::new().await;
+
+    let response = app.post_verify_token(&serde_json::json!({ "jwt": "invalid" })).await;
+    assert_eq!(response.status().as_u16(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    app.clean_up().await;
 }